@@ -0,0 +1,94 @@
+//! Live feed of permission decisions.
+//!
+//! Every permission check is recorded in the `PermissionGuard`'s audit log.
+//! The server exposes that log as the [`AUDIT_URI`] resource and, once a
+//! client subscribes to it, sends a `notifications/resources/updated`
+//! message for each new decision:
+//!
+//! ```json
+//! {"jsonrpc":"2.0","method":"notifications/resources/updated","params":{"uri":"webpuppet://audit","decision":{"operation":"Navigate","allowed":false,...}}}
+//! ```
+//!
+//! Reasons and URLs in decisions are redacted like everything else echoed to the client.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use webpuppet::permissions::AuditEntry;
+use webpuppet::PermissionGuard;
+
+use crate::protocol::Resource;
+use crate::redact::Redactor;
+
+/// URI of the permission audit resource.
+pub const AUDIT_URI: &str = "webpuppet://audit";
+
+/// The audit resource, as listed by `resources/list`.
+pub fn audit_resource() -> Resource {
+    Resource {
+        uri: AUDIT_URI.into(),
+        name: "Permission audit log".into(),
+        description: Some(
+            "Every permission decision made so far (operation, allowed, reason, URL, risk level). Subscribe to be notified of each new one.".into(),
+        ),
+        mime_type: Some("application/json".into()),
+    }
+}
+
+/// A decision as sent to the client, with its reason and URL redacted.
+pub fn decision_json(entry: &AuditEntry, redactor: &Redactor) -> serde_json::Value {
+    let entry = redactor.redact_audit_entry(entry);
+    serde_json::json!({
+        "timestamp": entry.timestamp,
+        "operation": entry.operation,
+        "allowed": entry.allowed,
+        "reason": entry.reason,
+        "url": entry.url,
+        "riskLevel": entry.risk_level,
+    })
+}
+
+/// Tracks the subscription to the audit resource and which decisions have
+/// been published.
+#[derive(Debug, Default)]
+pub struct AuditFeed {
+    subscribed: AtomicBool,
+    /// Number of audit log entries already published (or skipped).
+    published: Mutex<usize>,
+}
+
+impl AuditFeed {
+    /// Start publishing decisions made from now on.
+    pub fn subscribe(&self, guard: &PermissionGuard) {
+        let mut published = self.published.lock().unwrap();
+        *published = guard.audit_log().len();
+        self.subscribed.store(true, Ordering::SeqCst);
+    }
+
+    /// Stop publishing decisions.
+    pub fn unsubscribe(&self) {
+        self.subscribed.store(false, Ordering::SeqCst);
+    }
+
+    /// Whether a client is subscribed.
+    pub fn is_subscribed(&self) -> bool {
+        self.subscribed.load(Ordering::SeqCst)
+    }
+
+    /// Decisions made since the last call, if a client is subscribed.
+    pub fn take_new(&self, guard: &PermissionGuard) -> Vec<AuditEntry> {
+        if !self.is_subscribed() {
+            return Vec::new();
+        }
+
+        let mut published = self.published.lock().unwrap();
+        let log = guard.audit_log();
+        // The log was cleared; start over
+        if log.len() < *published {
+            *published = 0;
+        }
+        let new = log[*published..].to_vec();
+        *published = log.len();
+        new
+    }
+}
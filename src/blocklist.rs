@@ -0,0 +1,126 @@
+//! URLs and selectors tools refuse to act on.
+//!
+//! The permission policy decides which operations are allowed; the blocklist
+//! rules out specific targets whatever the policy says, as a guardrail around
+//! destructive controls (delete buttons, payment forms, logout links).
+//! `--blocklist <path>` loads it from a JSON file of regular expressions,
+//! matched case-insensitively anywhere in the URL or selector:
+//!
+//! ```json
+//! {
+//!   "urls": ["/account/delete", "^https://pay\\."],
+//!   "selectors": ["delete", "logout", "checkout|pay-now"]
+//! }
+//! ```
+//!
+//! URLs are checked before navigating, selectors before clicking or typing.
+//! Only the selector text is matched, not the element it selects.
+
+use std::path::Path;
+
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+
+/// Patterns of URLs and selectors that tools refuse.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(try_from = "BlocklistPatterns", into = "BlocklistPatterns")]
+pub struct Blocklist {
+    urls: Vec<Regex>,
+    selectors: Vec<Regex>,
+}
+
+/// The blocklist as written in its file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct BlocklistPatterns {
+    urls: Vec<String>,
+    selectors: Vec<String>,
+}
+
+impl TryFrom<BlocklistPatterns> for Blocklist {
+    type Error = String;
+
+    fn try_from(patterns: BlocklistPatterns) -> std::result::Result<Self, Self::Error> {
+        fn compile(patterns: &[String]) -> std::result::Result<Vec<Regex>, String> {
+            patterns
+                .iter()
+                .map(|pattern| {
+                    RegexBuilder::new(pattern)
+                        .case_insensitive(true)
+                        .build()
+                        .map_err(|e| format!("invalid pattern `{}`: {}", pattern, e))
+                })
+                .collect()
+        }
+
+        Ok(Self {
+            urls: compile(&patterns.urls)?,
+            selectors: compile(&patterns.selectors)?,
+        })
+    }
+}
+
+impl From<Blocklist> for BlocklistPatterns {
+    fn from(blocklist: Blocklist) -> Self {
+        let sources =
+            |patterns: &[Regex]| patterns.iter().map(|r| r.as_str().to_string()).collect();
+        Self {
+            urls: sources(&blocklist.urls),
+            selectors: sources(&blocklist.selectors),
+        }
+    }
+}
+
+impl Blocklist {
+    /// A blocklist of the given URL and selector patterns.
+    pub fn new<U, S>(urls: U, selectors: S) -> Result<Self>
+    where
+        U: IntoIterator,
+        U::Item: Into<String>,
+        S: IntoIterator,
+        S::Item: Into<String>,
+    {
+        Self::try_from(BlocklistPatterns {
+            urls: urls.into_iter().map(Into::into).collect(),
+            selectors: selectors.into_iter().map(Into::into).collect(),
+        })
+        .map_err(Error::InvalidParams)
+    }
+
+    /// Read a blocklist file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)?;
+        serde_json::from_str(&json)
+            .map_err(|e| Error::InvalidParams(format!("{}: {}", path.display(), e)))
+    }
+
+    /// Whether nothing is blocked.
+    pub fn is_empty(&self) -> bool {
+        self.urls.is_empty() && self.selectors.is_empty()
+    }
+
+    /// Refuse a URL matching a blocked pattern.
+    pub fn check_url(&self, url: &str) -> Result<()> {
+        check(&self.urls, "URL", url)
+    }
+
+    /// Refuse a selector matching a blocked pattern.
+    pub fn check_selector(&self, selector: &str) -> Result<()> {
+        check(&self.selectors, "selector", selector)
+    }
+}
+
+fn check(patterns: &[Regex], kind: &str, target: &str) -> Result<()> {
+    match patterns.iter().find(|pattern| pattern.is_match(target)) {
+        Some(pattern) => Err(Error::PermissionDenied(format!(
+            "{} `{}` is blocklisted (matches `{}`)",
+            kind,
+            target,
+            pattern.as_str()
+        ))),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,1427 @@
+//! Browser session management.
+//!
+//! Tools talk to the browser through the [`BrowserSession`] trait rather than
+//! `WebPuppet` directly. A session is launched lazily by a [`BrowserLauncher`]
+//! and kept alive between tool calls; both can be replaced with stubs in tests.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chromiumoxide::cdp::browser_protocol::page::CaptureScreenshotFormat;
+use futures::stream::{self, StreamExt};
+use futures::FutureExt;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use webpuppet::config::{
+    ChatGptConfig, ClaudeConfig, GeminiConfig, GrokConfig, NotebookLmConfig, PerplexityConfig,
+};
+use webpuppet::providers::{
+    ChatGptProvider, ClaudeProvider, GeminiProvider, GrokProvider, KaggleProvider,
+    NotebookLmProvider, PerplexityProvider, ProviderCapabilities,
+};
+use webpuppet::{
+    BrowserDetector, BrowserInstallation, BrowserType, Config, InterventionDetector,
+    InterventionReason, PromptRequest, PromptResponse, Provider, ProviderTrait, Session, WebPuppet,
+};
+
+use crate::config::ProxyConfig;
+use crate::console;
+use crate::error::{Error, Result};
+use crate::redact::REDACTED;
+
+/// Authentication state of a provider in the current browser profile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoginStatus {
+    /// An authenticated session exists.
+    LoggedIn,
+    /// No authenticated session; a login (and likely an intervention) is needed.
+    LoggedOut,
+    /// The state could not be determined.
+    Unknown,
+}
+
+impl LoginStatus {
+    /// Stable machine-readable name.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LoginStatus::LoggedIn => "logged_in",
+            LoginStatus::LoggedOut => "logged_out",
+            LoginStatus::Unknown => "unknown",
+        }
+    }
+}
+
+/// What a provider's page looked like when it was loaded just now.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderProbe {
+    /// Whether the provider's page loaded.
+    pub reachable: bool,
+    /// URL the browser ended up on (e.g. a login page after a redirect).
+    pub url: String,
+    /// Page title, if the page loaded.
+    pub title: Option<String>,
+    /// Whether the prompt input was found, i.e. the UI is ready for prompts.
+    pub input_ready: bool,
+    /// Why the page could not be loaded.
+    pub error: Option<String>,
+}
+
+/// Accounts signed in to a provider, as its page shows them.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProviderAccounts {
+    /// Accounts the page offers, by email (or name where it shows no
+    /// email).
+    pub accounts: Vec<String>,
+    /// Account in use, if the page shows it.
+    pub active: Option<String>,
+}
+
+/// The latest response on a provider's page, as far as it is rendered.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartialResponse {
+    /// Text rendered so far (raw, unscreened).
+    pub text: String,
+    /// Whether the provider has finished generating it.
+    pub complete: bool,
+}
+
+/// Rendering dimensions of the browser's pages.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Viewport {
+    /// Width in CSS pixels.
+    pub width: u32,
+    /// Height in CSS pixels.
+    pub height: u32,
+    /// Device pixel ratio (e.g. 2 or 3 for high-density phones); `None`
+    /// keeps the display's own ratio.
+    pub device_scale_factor: Option<f64>,
+    /// Present as a mobile device (mobile user agent, touch events).
+    pub mobile: bool,
+}
+
+impl std::fmt::Display for Viewport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}×{}", self.width, self.height)?;
+        if let Some(scale) = self.device_scale_factor {
+            write!(f, " @{}x", scale)?;
+        }
+        if self.mobile {
+            write!(f, " (mobile)")?;
+        }
+        Ok(())
+    }
+}
+
+/// How the browser presents itself to sites: user agent, language and
+/// timezone. `None` fields keep the browser's own value.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BrowserIdentity {
+    /// User-Agent header and `navigator.userAgent`.
+    pub user_agent: Option<String>,
+    /// Locale used for the UI and `Accept-Language`, e.g. `en-US`.
+    pub locale: Option<String>,
+    /// IANA timezone, e.g. `Europe/Berlin`.
+    pub timezone: Option<String>,
+}
+
+impl BrowserIdentity {
+    /// Check that every field set is well-formed.
+    pub fn validate(&self) -> Result<()> {
+        if let Some(ref user_agent) = self.user_agent {
+            if user_agent.trim().is_empty() || user_agent.chars().any(char::is_control) {
+                return Err(Error::InvalidParams(
+                    "user agent must be non-empty and must not contain control characters".into(),
+                ));
+            }
+        }
+        if let Some(ref locale) = self.locale {
+            validate_locale(locale)?;
+        }
+        if let Some(ref timezone) = self.timezone {
+            validate_timezone(timezone)?;
+        }
+        Ok(())
+    }
+
+    /// This identity with the fields set in `overrides` replaced.
+    pub fn merged(&self, overrides: &BrowserIdentity) -> Self {
+        Self {
+            user_agent: overrides
+                .user_agent
+                .clone()
+                .or_else(|| self.user_agent.clone()),
+            locale: overrides.locale.clone().or_else(|| self.locale.clone()),
+            timezone: overrides.timezone.clone().or_else(|| self.timezone.clone()),
+        }
+    }
+}
+
+/// Check a BCP 47 language tag such as `en`, `en-US` or `zh-Hant-TW`.
+pub fn validate_locale(locale: &str) -> Result<()> {
+    let mut subtags = locale.split('-');
+    let language = subtags.next().unwrap_or_default();
+    let valid = (2..=3).contains(&language.len())
+        && language.chars().all(|c| c.is_ascii_alphabetic())
+        && subtags.all(|subtag| {
+            (1..=8).contains(&subtag.len()) && subtag.chars().all(|c| c.is_ascii_alphanumeric())
+        });
+
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::InvalidParams(format!(
+            "invalid locale `{}`; expected a language tag like `en` or `en-US`",
+            locale
+        )))
+    }
+}
+
+/// Check an IANA timezone name such as `UTC` or `America/New_York`.
+pub fn validate_timezone(timezone: &str) -> Result<()> {
+    let valid = !timezone.is_empty()
+        && timezone.split('/').all(|part| {
+            part.chars().next().is_some_and(|c| c.is_ascii_alphabetic())
+                && part
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '+'))
+        });
+
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::InvalidParams(format!(
+            "invalid timezone `{}`; expected an IANA name like `UTC` or `Europe/Berlin`",
+            timezone
+        )))
+    }
+}
+
+/// Headers a page isn't allowed to set on its requests (the Fetch spec's
+/// forbidden request headers); `proxy-` and `sec-` prefixes are forbidden too.
+const FORBIDDEN_HEADERS: &[&str] = &[
+    "accept-charset",
+    "accept-encoding",
+    "access-control-request-headers",
+    "access-control-request-method",
+    "connection",
+    "content-length",
+    "cookie",
+    "cookie2",
+    "date",
+    "dnt",
+    "expect",
+    "host",
+    "keep-alive",
+    "origin",
+    "referer",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+    "via",
+];
+
+/// Extra HTTP headers added to the browsing tab's requests.
+///
+/// Values may be credentials, so they are left out of `Debug` output; use
+/// [`names`](Self::names) to show which headers are set.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct ExtraHeaders(BTreeMap<String, String>);
+
+impl ExtraHeaders {
+    /// Headers from name/value pairs, refusing malformed names and values and
+    /// headers the browser won't let a page set.
+    pub fn new(headers: BTreeMap<String, String>) -> Result<Self> {
+        for (name, value) in &headers {
+            let token = !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c));
+            if !token {
+                return Err(Error::InvalidParams(format!(
+                    "invalid header name `{}`",
+                    name
+                )));
+            }
+            let lower = name.to_ascii_lowercase();
+            if FORBIDDEN_HEADERS.contains(&lower.as_str())
+                || lower.starts_with("proxy-")
+                || lower.starts_with("sec-")
+            {
+                return Err(Error::InvalidParams(format!(
+                    "header `{}` is controlled by the browser and can't be set",
+                    name
+                )));
+            }
+            if value.chars().any(|c| c.is_control() && c != '\t') {
+                return Err(Error::InvalidParams(format!(
+                    "value of header `{}` must not contain control characters",
+                    name
+                )));
+            }
+        }
+        Ok(Self(headers))
+    }
+
+    /// Whether no headers are set.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Names of the headers set.
+    pub fn names(&self) -> Vec<&str> {
+        self.0.keys().map(String::as_str).collect()
+    }
+
+    /// The headers by name.
+    pub fn as_map(&self) -> &BTreeMap<String, String> {
+        &self.0
+    }
+
+    /// These headers with `other` added, replacing headers of the same name
+    /// (ignoring case).
+    pub fn merged(&self, other: &ExtraHeaders) -> Self {
+        let mut headers = self.0.clone();
+        for (name, value) in &other.0 {
+            headers.retain(|existing, _| !existing.eq_ignore_ascii_case(name));
+            headers.insert(name.clone(), value.clone());
+        }
+        Self(headers)
+    }
+}
+
+// Keep header values out of logs
+impl fmt::Debug for ExtraHeaders {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.0.keys().map(|name| (name, REDACTED)))
+            .finish()
+    }
+}
+
+/// A move through the browsing tab's session history.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryStep {
+    /// Go to the previous page.
+    Back,
+    /// Go to the next page.
+    Forward,
+    /// Load the current page again.
+    Reload {
+        /// Fetch the page from the network instead of the HTTP cache.
+        ignore_cache: bool,
+    },
+}
+
+/// Adds headers to the page's same-origin `fetch` and `XMLHttpRequest`
+/// requests; called again with new headers, it replaces them.
+///
+/// The session has no access to the DevTools network domain, so navigations
+/// and subresources go out without the headers. Cross-origin requests are
+/// left alone so credentials don't leak to third parties.
+const EXTRA_HEADERS_SCRIPT: &str = r#"((headers) => {
+    const state = window.__webpuppetExtraHeaders || (window.__webpuppetExtraHeaders = {});
+    state.headers = headers;
+    if (state.installed) {
+        return Object.keys(headers).length;
+    }
+    state.installed = true;
+
+    const sameOrigin = (url) => {
+        try {
+            return new URL(url, location.href).origin === location.origin;
+        } catch (e) {
+            return false;
+        }
+    };
+
+    const fetch = window.fetch;
+    window.fetch = function (input, init) {
+        const url = input instanceof Request ? input.url : String(input);
+        if (!sameOrigin(url) || Object.keys(state.headers).length === 0) {
+            return fetch.call(this, input, init);
+        }
+        const request = new Request(input, init);
+        for (const [name, value] of Object.entries(state.headers)) {
+            request.headers.set(name, value);
+        }
+        return fetch.call(this, request);
+    };
+
+    const open = XMLHttpRequest.prototype.open;
+    const send = XMLHttpRequest.prototype.send;
+    XMLHttpRequest.prototype.open = function (method, url, ...rest) {
+        this.__webpuppetSameOrigin = sameOrigin(url);
+        return open.call(this, method, url, ...rest);
+    };
+    XMLHttpRequest.prototype.send = function (body) {
+        if (this.__webpuppetSameOrigin) {
+            for (const [name, value] of Object.entries(state.headers)) {
+                this.setRequestHeader(name, value);
+            }
+        }
+        return send.call(this, body);
+    };
+    return Object.keys(headers).length;
+})"#;
+
+/// Finds the accounts a provider's page offers and, given the name of one,
+/// switches to it. Called with that name (or `null`) as a JSON literal;
+/// returns `{ accounts, active, switched }`.
+///
+/// Accounts come from account choosers (`data-email`/`data-identifier`),
+/// multi-login links (`authuser=`) and the account button, whose label
+/// also names the active account. Switching follows the account's link, or
+/// clicks its entry when it has none.
+const ACCOUNTS_SCRIPT: &str = r#"((target) => {
+    const email = /[\w.+-]+@[\w-]+(\.[\w-]+)+/;
+    const found = [];
+    const add = (name, el) => {
+        name = (name || '').trim();
+        if (name && !found.some(a => a.name.toLowerCase() === name.toLowerCase())) found.push({ name, el });
+    };
+    const label = (el) => (el.getAttribute('aria-label') || '') + ' ' + el.textContent;
+    document.querySelectorAll('[data-email], [data-identifier]').forEach(el =>
+        add(el.getAttribute('data-email') || el.getAttribute('data-identifier'), el));
+    document.querySelectorAll('a[href*="authuser="]').forEach(el => {
+        const match = label(el).match(email);
+        if (match) add(match[0], el);
+    });
+    let active = null;
+    const button = document.querySelector('a[aria-label^="Google Account"], [data-testid*="account"][aria-label*="@"], [aria-haspopup][aria-label*="@"]');
+    if (button) {
+        const match = label(button).match(email);
+        if (match) {
+            active = match[0];
+            add(active, null);
+        }
+    }
+    let switched = false;
+    if (target !== null) {
+        const entry = found.find(a => a.name.toLowerCase() === target.toLowerCase());
+        if (entry && entry.el && entry.name !== active) {
+            const href = entry.el.closest('a') && entry.el.closest('a').href;
+            if (href) {
+                setTimeout(() => { location.href = href; }, 0);
+            } else {
+                entry.el.click();
+            }
+            switched = true;
+        } else {
+            switched = !!entry && entry.name === active;
+        }
+    }
+    return { accounts: found.map(a => a.name), active, switched };
+})"#;
+
+/// What [`ACCOUNTS_SCRIPT`] returns.
+#[derive(Debug, Deserialize)]
+struct AccountsSnapshot {
+    #[serde(flatten)]
+    accounts: ProviderAccounts,
+    switched: bool,
+}
+
+/// Collects the text of the error notices a provider's page shows: alerts,
+/// error banners and toasts that are rendered. Returns up to ten distinct
+/// texts; long ones are left out, as they are page sections rather than
+/// notices.
+const PAGE_NOTICES_SCRIPT: &str = r#"(() => {
+    const selector = [
+        '[role="alert"]', '[role="alertdialog"]', '[aria-live="assertive"]',
+        '[class*="error" i]', '[class*="toast" i]', '[data-testid*="error" i]'
+    ].join(', ');
+    const notices = new Set();
+    for (const el of document.querySelectorAll(selector)) {
+        if (el.getClientRects().length === 0) continue;
+        const text = (el.innerText || '').trim().replace(/\s+/g, ' ');
+        if (text && text.length <= 300) notices.add(text);
+    }
+    return Array.from(notices).slice(0, 10);
+})()"#;
+
+/// Time given to a page to load after a history move, like `navigate` does.
+const HISTORY_SETTLE_TIME: Duration = Duration::from_millis(500);
+
+/// How long to wait for an element to capture to be attached.
+const ELEMENT_WAIT_TIME: Duration = Duration::from_secs(2);
+
+/// How often a streaming prompt reads the partial response from the page.
+const STREAM_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// User agent sent when emulating a mobile device.
+const MOBILE_USER_AGENT: &str = "Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/131.0.0.0 Mobile Safari/537.36";
+
+/// Options used when launching a browser session.
+#[derive(Debug, Clone)]
+pub struct LaunchOptions {
+    /// Whether to run the browser in headless mode.
+    pub headless: bool,
+    /// Provider whose tab is used for plain navigation.
+    pub default_provider: Provider,
+    /// Browser to launch; `None` prefers the system default (see
+    /// [`BrowserLauncher::preferred_browser`]).
+    pub browser: Option<BrowserType>,
+    /// Browser profile to use (e.g. `Profile 1`); `None` uses the default one.
+    pub profile: Option<String>,
+    /// Viewport to render pages at; `None` uses the browser's default.
+    pub viewport: Option<Viewport>,
+    /// Proxy to route traffic through; `None` connects directly.
+    pub proxy: Option<ProxyConfig>,
+    /// User agent and locale to present. The timezone is inherited from the
+    /// server process (see `--timezone`) since the browser has no flag for it.
+    pub identity: BrowserIdentity,
+    /// HTTP headers added to the browsing tab's requests; see
+    /// [`BrowserSession::set_extra_headers`].
+    pub extra_headers: ExtraHeaders,
+    /// Open every provider's session at launch, at most this many at once;
+    /// zero opens each on first use. See [`init_providers`].
+    pub provider_init_concurrency: usize,
+    /// Directory browser data persists in across restarts; see
+    /// [`session_data_dir`]. `None` uses the browser's own profile
+    /// directory if it has one.
+    pub session_dir: Option<PathBuf>,
+}
+
+/// Where a browser keeps its data under a `--session-dir`: a subdirectory
+/// per browser, since their profile formats don't mix. Profiles chosen with
+/// `--profile` live inside it as they would in the browser's own directory.
+pub fn session_data_dir(session_dir: &Path, browser: Option<BrowserType>) -> PathBuf {
+    let name = browser.map_or("default", |browser| browser.name());
+    session_dir.join(name.to_lowercase())
+}
+
+/// A live browser session shared by the tools.
+#[async_trait::async_trait]
+pub trait BrowserSession: Send + Sync {
+    /// Check whether the profile is logged in to a provider, without
+    /// starting a login flow.
+    async fn login_status(&self, provider: Provider) -> Result<LoginStatus>;
+
+    /// Authenticate with a provider if needed.
+    async fn authenticate(&self, provider: Provider) -> Result<()>;
+
+    /// What a human has to do on the provider's page (solve a captcha, enter
+    /// a 2FA code, log in) before prompts can go through, if anything.
+    ///
+    /// Defaults to nothing, for sessions that can't inspect the page.
+    async fn intervention_needed(&self, _provider: Provider) -> Result<Option<InterventionReason>> {
+        Ok(None)
+    }
+
+    /// Accounts signed in to a provider, as its page shows them.
+    ///
+    /// Defaults to none, for sessions that can't inspect the page.
+    async fn accounts(&self, _provider: Provider) -> Result<ProviderAccounts> {
+        Ok(ProviderAccounts::default())
+    }
+
+    /// Text of the error notices (alerts, error banners, toasts) the
+    /// provider's page shows.
+    ///
+    /// Defaults to none, for sessions that can't inspect the page.
+    async fn page_notices(&self, _provider: Provider) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    /// Make `account` (as listed by [`accounts`](Self::accounts)) the one
+    /// the provider's session uses, and return the accounts afterwards.
+    ///
+    /// Defaults to failing, for sessions that can't inspect the page.
+    async fn switch_account(&self, provider: Provider, account: &str) -> Result<ProviderAccounts> {
+        Err(Error::InvalidParams(format!(
+            "{} has no account {} to switch to",
+            provider, account
+        )))
+    }
+
+    /// Send a prompt to a provider and return the raw (unscreened) response.
+    async fn prompt(&self, provider: Provider, request: PromptRequest) -> Result<PromptResponse>;
+
+    /// Send a prompt like [`prompt`](Self::prompt), also sending the raw
+    /// response text to `chunks` piece by piece as the provider renders it.
+    ///
+    /// The returned response is always complete. Defaults to sending no
+    /// chunks, for sessions that can't read partial responses.
+    async fn prompt_streaming(
+        &self,
+        provider: Provider,
+        request: PromptRequest,
+        chunks: mpsc::UnboundedSender<String>,
+    ) -> Result<PromptResponse> {
+        drop(chunks);
+        self.prompt(provider, request).await
+    }
+
+    /// The latest response on a provider's page as rendered so far, without
+    /// waiting for it to finish; `None` if the page shows no response.
+    async fn read_response(&self, provider: Provider) -> Result<Option<PartialResponse>>;
+
+    /// Navigate the browsing tab to a URL.
+    async fn navigate(&self, url: &str) -> Result<()>;
+
+    /// Go back, forward or reload in the browsing tab.
+    async fn traverse(&self, step: HistoryStep) -> Result<()>;
+
+    /// Add `headers` to the browsing tab's future requests, replacing any
+    /// set before; empty headers stop adding any.
+    async fn set_extra_headers(&self, headers: &ExtraHeaders) -> Result<()>;
+
+    /// URL of the browsing tab.
+    async fn current_url(&self) -> Result<String>;
+
+    /// Title of the page in the browsing tab.
+    async fn title(&self) -> Result<String>;
+
+    /// Evaluate a JavaScript expression in the browsing tab and return its
+    /// JSON-serializable result.
+    async fn evaluate(&self, script: &str) -> Result<serde_json::Value>;
+
+    /// Capture the visible part of the browsing tab as a PNG image.
+    async fn screenshot(&self) -> Result<Vec<u8>>;
+
+    /// Scroll the first element matching `selector` into view and capture
+    /// just its bounding box as a PNG image.
+    async fn screenshot_element(&self, selector: &str) -> Result<Vec<u8>>;
+
+    /// Declared capabilities of a provider.
+    fn provider_capabilities(&self, provider: Provider) -> Option<ProviderCapabilities>;
+
+    /// Load a provider's page and check whether its prompt UI is present.
+    async fn probe(&self, provider: Provider) -> Result<ProviderProbe>;
+
+    /// Close the session and its browser.
+    async fn close(&self) -> Result<()>;
+
+    /// Providers that failed to open at launch, with the reason, and are
+    /// unavailable for the rest of the session.
+    ///
+    /// Defaults to none, for sessions that open providers on first use.
+    fn failed_providers(&self) -> Vec<(Provider, String)> {
+        Vec::new()
+    }
+}
+
+/// Outcome of [`init_providers`].
+#[derive(Debug)]
+pub struct ProviderInit<T> {
+    /// Providers that initialized, in the order they finished.
+    pub ready: Vec<(Provider, T)>,
+    /// Providers that failed, with the reason, in the order they finished.
+    pub failed: Vec<(Provider, String)>,
+}
+
+/// Run `init` for each provider, at most `concurrency` (at least one) at a
+/// time. A provider that fails doesn't stop the others; it is reported in
+/// [`ProviderInit::failed`] instead.
+pub async fn init_providers<T, F, Fut>(
+    providers: impl IntoIterator<Item = Provider>,
+    concurrency: usize,
+    init: F,
+) -> ProviderInit<T>
+where
+    F: Fn(Provider) -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let results: Vec<(Provider, Result<T>)> = stream::iter(providers)
+        .map(|provider| init(provider).map(move |result| (provider, result)))
+        .buffer_unordered(concurrency.max(1))
+        .collect()
+        .await;
+
+    let mut outcome = ProviderInit {
+        ready: Vec::new(),
+        failed: Vec::new(),
+    };
+    for (provider, result) in results {
+        match result {
+            Ok(value) => outcome.ready.push((provider, value)),
+            Err(e) => outcome.failed.push((provider, e.to_string())),
+        }
+    }
+    outcome
+}
+
+/// Launches browser sessions.
+#[async_trait::async_trait]
+pub trait BrowserLauncher: Send + Sync {
+    /// Launch a new session.
+    async fn launch(&self, options: &LaunchOptions) -> Result<Arc<dyn BrowserSession>>;
+
+    /// Browsers installed on this machine.
+    fn detect_browsers(&self) -> Vec<BrowserInstallation> {
+        BrowserDetector::detect_all()
+    }
+
+    /// The operating system's default web browser, if it can be told.
+    fn system_default_browser(&self) -> Option<BrowserType> {
+        system_default_browser()
+    }
+
+    /// The installed browser to launch; see [`choose_browser`].
+    fn preferred_browser(&self, requested: Option<BrowserType>) -> Option<BrowserInstallation> {
+        choose_browser(
+            &self.detect_browsers(),
+            requested,
+            self.system_default_browser(),
+        )
+    }
+
+    /// Profiles of the browser this launcher starts: `requested`, or the
+    /// [preferred](Self::preferred_browser) one.
+    fn profiles(&self, requested: Option<BrowserType>) -> Result<Vec<String>> {
+        let browser = self
+            .preferred_browser(requested)
+            .ok_or_else(|| Error::BrowserNotFound("No CDP-capable browser detected".into()))?;
+        Ok(browser.list_profiles()?)
+    }
+}
+
+/// Browsers in the order they are preferred in when the system default
+/// can't be automated (the order `WebPuppet` itself uses).
+pub const BROWSER_PRIORITY: &[BrowserType] = &[
+    BrowserType::Brave,
+    BrowserType::Chrome,
+    BrowserType::Chromium,
+    BrowserType::Edge,
+    BrowserType::Opera,
+    BrowserType::Vivaldi,
+];
+
+/// Parse a browser name (`brave`, `chrome`, `chromium`, `edge`, `opera` or
+/// `vivaldi`), refusing browsers that can't be automated.
+pub fn parse_browser(name: &str) -> Result<BrowserType> {
+    let browser: BrowserType = name
+        .parse()
+        .map_err(|_| Error::InvalidParams(format!("unknown browser `{}`", name)))?;
+    if !browser.supports_cdp() {
+        return Err(Error::InvalidParams(format!(
+            "{} can't be automated; use one of brave, chrome, chromium, edge, opera, vivaldi",
+            browser
+        )));
+    }
+    Ok(browser)
+}
+
+/// Pick the browser to launch among the installed ones: the `requested`
+/// type if given (`None` if it isn't installed), else the system default if
+/// it can be automated, else the first installed in [`BROWSER_PRIORITY`].
+pub fn choose_browser(
+    installed: &[BrowserInstallation],
+    requested: Option<BrowserType>,
+    system_default: Option<BrowserType>,
+) -> Option<BrowserInstallation> {
+    let find = |browser_type: BrowserType| {
+        installed
+            .iter()
+            .find(|b| b.browser_type == browser_type)
+            .cloned()
+    };
+    if let Some(requested) = requested {
+        return find(requested);
+    }
+    system_default
+        .filter(BrowserType::supports_cdp)
+        .and_then(find)
+        .or_else(|| BROWSER_PRIORITY.iter().find_map(|&b| find(b)))
+}
+
+/// The browser an OS identifier names: a `.desktop` file on Linux, a
+/// bundle id on macOS or a ProgId on Windows (e.g. `brave-browser.desktop`,
+/// `com.google.chrome`, `MSEdgeHTM`).
+pub fn browser_type_from_id(id: &str) -> Option<BrowserType> {
+    let id = id.to_lowercase();
+    [
+        ("brave", BrowserType::Brave),
+        ("chromium", BrowserType::Chromium),
+        ("chrome", BrowserType::Chrome),
+        ("edge", BrowserType::Edge),
+        ("msedge", BrowserType::Edge),
+        ("opera", BrowserType::Opera),
+        ("vivaldi", BrowserType::Vivaldi),
+        ("firefox", BrowserType::Firefox),
+        ("safari", BrowserType::Safari),
+    ]
+    .into_iter()
+    .find(|(name, _)| id.contains(name))
+    .map(|(_, browser_type)| browser_type)
+}
+
+/// Output of a command, if it ran successfully.
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = std::process::Command::new(program)
+        .args(args)
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Ask the operating system for its default web browser.
+#[cfg(target_os = "linux")]
+pub fn system_default_browser() -> Option<BrowserType> {
+    let desktop = command_output("xdg-settings", &["get", "default-web-browser"])?;
+    browser_type_from_id(desktop.trim())
+}
+
+/// Ask the operating system for its default web browser.
+#[cfg(target_os = "macos")]
+pub fn system_default_browser() -> Option<BrowserType> {
+    // The handler of the https scheme, as a bundle id
+    let handlers = command_output(
+        "defaults",
+        &[
+            "read",
+            "com.apple.LaunchServices/com.apple.launchservices.secure",
+            "LSHandlers",
+        ],
+    )?;
+    handlers
+        .split('}')
+        .find(|handler| handler.contains("LSHandlerURLScheme = https;"))
+        .and_then(|handler| {
+            handler
+                .lines()
+                .find(|line| line.contains("LSHandlerRoleAll"))
+        })
+        .and_then(|line| browser_type_from_id(line.split('=').nth(1)?))
+}
+
+/// Ask the operating system for its default web browser.
+#[cfg(target_os = "windows")]
+pub fn system_default_browser() -> Option<BrowserType> {
+    let choice = command_output(
+        "reg",
+        &[
+            "query",
+            r"HKCU\Software\Microsoft\Windows\Shell\Associations\UrlAssociations\https\UserChoice",
+            "/v",
+            "ProgId",
+        ],
+    )?;
+    choice
+        .lines()
+        .find(|line| line.contains("ProgId"))
+        .and_then(|line| browser_type_from_id(line.split_whitespace().last()?))
+}
+
+/// Ask the operating system for its default web browser.
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+pub fn system_default_browser() -> Option<BrowserType> {
+    None
+}
+
+/// Launcher backed by `WebPuppet`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct WebPuppetLauncher;
+
+#[async_trait::async_trait]
+impl BrowserLauncher for WebPuppetLauncher {
+    async fn launch(&self, options: &LaunchOptions) -> Result<Arc<dyn BrowserSession>> {
+        let mut config = browser_config(options);
+        match self.preferred_browser(options.browser) {
+            // Persisted data, even if the browser has its own
+            Some(browser) if options.session_dir.is_some() => {
+                config.browser.executable_path = Some(browser.executable_path);
+                config.browser.user_data_dir = options
+                    .session_dir
+                    .as_deref()
+                    .map(|dir| session_data_dir(dir, Some(browser.browser_type)));
+            }
+            // Its own profiles, where the user is logged in
+            Some(browser) if browser.user_data_dir.exists() => {
+                config.browser.executable_path = Some(browser.executable_path);
+                config.browser.user_data_dir = Some(browser.user_data_dir);
+            }
+            Some(_) => {}
+            None => {
+                if let Some(requested) = options.browser {
+                    return Err(Error::BrowserNotFound(format!(
+                        "{} is not installed",
+                        requested
+                    )));
+                }
+            }
+        }
+
+        if let Some(ref dir) = config.browser.user_data_dir {
+            if options.session_dir.is_some() {
+                create_private_dir(dir)?;
+            }
+        }
+
+        let puppet = WebPuppet::builder()
+            .with_config(config)
+            .with_all_providers()
+            .headless(options.headless)
+            .build()
+            .await
+            .map_err(Error::from_launch)?;
+
+        let mut session = WebPuppetSession::new(puppet)
+            .with_navigation_provider(options.default_provider)
+            .with_extra_headers(options.extra_headers.clone());
+        if options.provider_init_concurrency > 0 {
+            let puppet = &session.puppet;
+            let init = init_providers(
+                Provider::all(),
+                options.provider_init_concurrency,
+                |provider| async move { Ok(puppet.get_session(provider).await?) },
+            )
+            .await;
+            if init.ready.is_empty() {
+                let reasons: Vec<String> = init
+                    .failed
+                    .iter()
+                    .map(|(provider, reason)| format!("{}: {}", provider, reason))
+                    .collect();
+                return Err(Error::LaunchFailed(format!(
+                    "no provider could be initialized ({})",
+                    reasons.join("; ")
+                )));
+            }
+            for (provider, reason) in &init.failed {
+                tracing::warn!(
+                    "{} failed to initialize and is unavailable: {}",
+                    provider,
+                    reason
+                );
+            }
+            session = session.with_failed_providers(init.failed);
+        }
+
+        Ok(Arc::new(session))
+    }
+}
+
+/// Create `dir` (and its parents) readable by the current user only, since
+/// it holds session cookies.
+fn create_private_dir(dir: &Path) -> Result<()> {
+    std::fs::create_dir_all(dir)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))?;
+    }
+    Ok(())
+}
+
+/// `WebPuppet` configuration for a launch, with the browser arguments the
+/// options translate to.
+pub fn browser_config(options: &LaunchOptions) -> Config {
+    let mut config = Config::default();
+    if let Some(ref dir) = options.session_dir {
+        config.browser.user_data_dir = Some(session_data_dir(dir, options.browser));
+    }
+    if let Some(ref profile) = options.profile {
+        config
+            .browser
+            .args
+            .push(format!("--profile-directory={}", profile));
+    }
+    if let Some(viewport) = options.viewport {
+        config.browser.window_width = viewport.width;
+        config.browser.window_height = viewport.height;
+        if let Some(scale) = viewport.device_scale_factor {
+            config
+                .browser
+                .args
+                .push(format!("--force-device-scale-factor={}", scale));
+        }
+        if viewport.mobile {
+            config.browser.args.extend([
+                format!("--user-agent={}", MOBILE_USER_AGENT),
+                "--touch-events=enabled".to_string(),
+            ]);
+        }
+    }
+    // After the viewport, so an explicit user agent wins over the mobile one
+    if let Some(ref user_agent) = options.identity.user_agent {
+        config
+            .browser
+            .args
+            .push(format!("--user-agent={}", user_agent));
+    }
+    if let Some(ref locale) = options.identity.locale {
+        config.browser.args.extend([
+            format!("--lang={}", locale),
+            format!("--accept-lang={}", locale),
+        ]);
+    }
+    if let Some(ref proxy) = options.proxy {
+        config
+            .browser
+            .args
+            .push(format!("--proxy-server={}", proxy.url));
+        if !proxy.bypass.is_empty() {
+            config
+                .browser
+                .args
+                .push(format!("--proxy-bypass-list={}", proxy.bypass.join(";")));
+        }
+    }
+    config
+}
+
+/// [`BrowserSession`] backed by a `WebPuppet` instance.
+pub struct WebPuppetSession {
+    puppet: WebPuppet,
+    providers: HashMap<Provider, Arc<dyn ProviderTrait>>,
+    /// Provider whose browser tab is used for plain navigation.
+    navigation_provider: Provider,
+    /// Headers added to the browsing tab's requests, installed again after
+    /// every navigation.
+    extra_headers: Mutex<ExtraHeaders>,
+    /// Providers that failed to open at launch, with the reason.
+    failed: Vec<(Provider, String)>,
+}
+
+impl WebPuppetSession {
+    /// Wrap a `WebPuppet` instance.
+    pub fn new(puppet: WebPuppet) -> Self {
+        let providers = Provider::all()
+            .into_iter()
+            .map(|p| (p, provider_impl(p)))
+            .collect();
+
+        Self {
+            puppet,
+            providers,
+            navigation_provider: Provider::Grok,
+            extra_headers: Mutex::new(ExtraHeaders::default()),
+            failed: Vec::new(),
+        }
+    }
+
+    /// Use a different provider's tab for plain navigation.
+    pub fn with_navigation_provider(mut self, provider: Provider) -> Self {
+        self.navigation_provider = provider;
+        self
+    }
+
+    /// Add headers to the browsing tab's requests from the first navigation.
+    pub fn with_extra_headers(self, headers: ExtraHeaders) -> Self {
+        *self.extra_headers.lock().unwrap() = headers;
+        self
+    }
+
+    /// Leave out providers that failed to open; calls to them fail.
+    pub fn with_failed_providers(mut self, failed: Vec<(Provider, String)>) -> Self {
+        self.failed = failed;
+        self
+    }
+
+    /// Fail if `provider` is one of those that didn't open at launch.
+    fn check_available(&self, provider: Provider) -> Result<()> {
+        match self.failed.iter().find(|(p, _)| *p == provider) {
+            Some((_, reason)) => Err(Error::LaunchFailed(format!(
+                "{} failed to initialize ({}); call webpuppet_reset to retry",
+                provider, reason
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Run [`ACCOUNTS_SCRIPT`] on the provider's page, switching to
+    /// `target` if given.
+    async fn read_accounts(
+        &self,
+        provider: Provider,
+        target: Option<&str>,
+    ) -> Result<AccountsSnapshot> {
+        self.check_available(provider)?;
+        let session = self.puppet.get_session(provider).await?;
+        let on_provider_page = session
+            .current_url()
+            .await
+            .is_ok_and(|url| url.starts_with(provider.base_url()));
+        if !on_provider_page {
+            session.navigate(provider.base_url()).await?;
+        }
+
+        let script = format!(
+            "{}({})",
+            ACCOUNTS_SCRIPT,
+            serde_json::to_string(&target).unwrap_or_else(|_| "null".into())
+        );
+        let value: serde_json::Value = session.evaluate(&script).await?;
+        serde_json::from_value(value)
+            .map_err(|e| Error::Internal(format!("unexpected account lookup result: {}", e)))
+    }
+
+    /// Install the extra headers in the page of the browsing tab.
+    async fn install_extra_headers(&self, session: &Session) -> Result<()> {
+        let headers = self.extra_headers.lock().unwrap().clone();
+        let script = format!(
+            "{}({})",
+            EXTRA_HEADERS_SCRIPT,
+            serde_json::to_string(headers.as_map())?
+        );
+        let _: serde_json::Value = session.evaluate(&script).await?;
+        Ok(())
+    }
+}
+
+/// Start capturing the console of the page just loaded. A page that
+/// refuses the script only loses its console log, not the navigation.
+async fn install_console_capture(session: &Session) {
+    if let Err(e) = session
+        .evaluate::<serde_json::Value>(&console::capture_script())
+        .await
+    {
+        tracing::debug!("Console capture not installed: {}", e);
+    }
+}
+
+#[async_trait::async_trait]
+impl BrowserSession for WebPuppetSession {
+    async fn login_status(&self, provider: Provider) -> Result<LoginStatus> {
+        self.check_available(provider)?;
+        let Some(provider_impl) = self.providers.get(&provider) else {
+            return Ok(LoginStatus::Unknown);
+        };
+
+        let session = self.puppet.get_session(provider).await?;
+
+        match provider_impl.is_authenticated(&session).await {
+            Ok(true) => Ok(LoginStatus::LoggedIn),
+            Ok(false) => Ok(LoginStatus::LoggedOut),
+            Err(e) => {
+                tracing::debug!("Could not determine {} login state: {}", provider, e);
+                Ok(LoginStatus::Unknown)
+            }
+        }
+    }
+
+    async fn authenticate(&self, provider: Provider) -> Result<()> {
+        self.check_available(provider)?;
+        Ok(self.puppet.authenticate(provider).await?)
+    }
+
+    async fn intervention_needed(&self, provider: Provider) -> Result<Option<InterventionReason>> {
+        self.check_available(provider)?;
+        let session = self.puppet.get_session(provider).await?;
+        let on_provider_page = session
+            .current_url()
+            .await
+            .is_ok_and(|url| url.starts_with(provider.base_url()));
+        if !on_provider_page {
+            session.navigate(provider.base_url()).await?;
+        }
+
+        let url = session.current_url().await?;
+        let html = session.get_page_content().await?;
+        // Rate limits are handled separately and their phrases ("please
+        // wait") are too common to go by
+        let detector = InterventionDetector::new();
+        if let Some(reason) = detector
+            .detect_captcha(&html)
+            .or_else(|| detector.detect_2fa(&html))
+            .or_else(|| detector.detect_login_required(&html, &url))
+        {
+            return Ok(Some(reason));
+        }
+
+        let authenticated = match self.providers.get(&provider) {
+            Some(provider_impl) => provider_impl
+                .is_authenticated(&session)
+                .await
+                .unwrap_or(true),
+            None => true,
+        };
+        Ok((!authenticated).then_some(InterventionReason::LoginRequired))
+    }
+
+    async fn accounts(&self, provider: Provider) -> Result<ProviderAccounts> {
+        Ok(self.read_accounts(provider, None).await?.accounts)
+    }
+
+    async fn page_notices(&self, provider: Provider) -> Result<Vec<String>> {
+        self.check_available(provider)?;
+        let session = self.puppet.get_session(provider).await?;
+        let value: serde_json::Value = session.evaluate(PAGE_NOTICES_SCRIPT).await?;
+        serde_json::from_value(value)
+            .map_err(|e| Error::Internal(format!("unexpected page notice result: {}", e)))
+    }
+
+    async fn switch_account(&self, provider: Provider, account: &str) -> Result<ProviderAccounts> {
+        let snapshot = self.read_accounts(provider, Some(account)).await?;
+        if !snapshot.switched {
+            return Err(Error::InvalidParams(format!(
+                "{} shows no account {} (available: {})",
+                provider,
+                account,
+                if snapshot.accounts.accounts.is_empty() {
+                    "none".to_string()
+                } else {
+                    snapshot.accounts.accounts.join(", ")
+                }
+            )));
+        }
+        tokio::time::sleep(HISTORY_SETTLE_TIME).await;
+        let mut accounts = self.read_accounts(provider, None).await?.accounts;
+        // Pages that don't show the active account still switched to it
+        accounts.active.get_or_insert_with(|| account.to_string());
+        Ok(accounts)
+    }
+
+    async fn prompt(&self, provider: Provider, request: PromptRequest) -> Result<PromptResponse> {
+        self.check_available(provider)?;
+        Ok(self.puppet.prompt(provider, request).await?)
+    }
+
+    async fn prompt_streaming(
+        &self,
+        provider: Provider,
+        request: PromptRequest,
+        chunks: mpsc::UnboundedSender<String>,
+    ) -> Result<PromptResponse> {
+        let Some(selector) = response_selector(provider) else {
+            return self.prompt(provider, request).await;
+        };
+        self.check_available(provider)?;
+        let session = self.puppet.get_session(provider).await?;
+        let script = format!(
+            "(() => {{ const messages = document.querySelectorAll({}); \
+             const last = messages[messages.length - 1]; \
+             return {{ count: messages.length, text: last ? last.innerText : '' }}; }})()",
+            serde_json::to_string(&selector)?
+        );
+
+        // Whatever was on the page before is not part of this response
+        let before = response_snapshot(&session, &script).await;
+        let prompt = self.puppet.prompt(provider, request);
+        tokio::pin!(prompt);
+
+        let mut ticker = tokio::time::interval(STREAM_POLL_INTERVAL);
+        let mut streamed = String::new();
+        loop {
+            tokio::select! {
+                response = &mut prompt => return Ok(response?),
+                _ = ticker.tick() => {
+                    let Some(snapshot) = response_snapshot(&session, &script).await else {
+                        continue;
+                    };
+                    if snapshot.text.is_empty() || Some(&snapshot) == before.as_ref() {
+                        continue;
+                    }
+                    // Re-rendered text that no longer extends what was sent
+                    // only shows up in the final response
+                    if let Some(new) = snapshot.text.strip_prefix(streamed.as_str()) {
+                        if !new.is_empty() && chunks.send(new.to_string()).is_ok() {
+                            streamed = snapshot.text;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    async fn read_response(&self, provider: Provider) -> Result<Option<PartialResponse>> {
+        let (Some(selector), Some(generating)) =
+            (response_selector(provider), generating_selector(provider))
+        else {
+            return Err(Error::InvalidParams(format!(
+                "{} responses can't be read while they are generated",
+                provider
+            )));
+        };
+        self.check_available(provider)?;
+        let session = self.puppet.get_session(provider).await?;
+        let script = format!(
+            "(() => {{ const messages = document.querySelectorAll({}); \
+             const last = messages[messages.length - 1]; \
+             if (!last) return null; \
+             return {{ text: last.innerText, complete: !document.querySelector({}) }}; }})()",
+            serde_json::to_string(&selector)?,
+            serde_json::to_string(generating)?
+        );
+        Ok(session.evaluate(&script).await?)
+    }
+
+    async fn navigate(&self, url: &str) -> Result<()> {
+        let session = self.puppet.get_session(self.navigation_provider).await?;
+        session.navigate(url).await?;
+        if !self.extra_headers.lock().unwrap().is_empty() {
+            self.install_extra_headers(&session).await?;
+        }
+        install_console_capture(&session).await;
+        Ok(())
+    }
+
+    async fn traverse(&self, step: HistoryStep) -> Result<()> {
+        let session = self.puppet.get_session(self.navigation_provider).await?;
+        // The move is deferred so the script returns before the page unloads
+        let script = match step {
+            HistoryStep::Back => "setTimeout(() => history.back(), 0); null",
+            HistoryStep::Forward => "setTimeout(() => history.forward(), 0); null",
+            HistoryStep::Reload {
+                ignore_cache: false,
+            } => "setTimeout(() => location.reload(), 0); null",
+            // Refresh the cached copy of the document first
+            HistoryStep::Reload { ignore_cache: true } => {
+                "(async () => { await fetch(location.href, { cache: 'reload' }).catch(() => null); setTimeout(() => location.reload(), 0); return null; })()"
+            }
+        };
+        let _: serde_json::Value = session.evaluate(script).await?;
+        tokio::time::sleep(HISTORY_SETTLE_TIME).await;
+        if !self.extra_headers.lock().unwrap().is_empty() {
+            self.install_extra_headers(&session).await?;
+        }
+        install_console_capture(&session).await;
+        Ok(())
+    }
+
+    async fn set_extra_headers(&self, headers: &ExtraHeaders) -> Result<()> {
+        *self.extra_headers.lock().unwrap() = headers.clone();
+        let session = self.puppet.get_session(self.navigation_provider).await?;
+        self.install_extra_headers(&session).await
+    }
+
+    async fn current_url(&self) -> Result<String> {
+        let session = self.puppet.get_session(self.navigation_provider).await?;
+        Ok(session.current_url().await?)
+    }
+
+    async fn title(&self) -> Result<String> {
+        let session = self.puppet.get_session(self.navigation_provider).await?;
+        Ok(session.get_title().await?)
+    }
+
+    async fn evaluate(&self, script: &str) -> Result<serde_json::Value> {
+        let session = self.puppet.get_session(self.navigation_provider).await?;
+        Ok(session.evaluate(script).await?)
+    }
+
+    async fn screenshot(&self) -> Result<Vec<u8>> {
+        let session = self.puppet.get_session(self.navigation_provider).await?;
+        Ok(session.screenshot(None).await?)
+    }
+
+    async fn screenshot_element(&self, selector: &str) -> Result<Vec<u8>> {
+        let session = self.puppet.get_session(self.navigation_provider).await?;
+        let element = session
+            .wait_for_element(selector, ELEMENT_WAIT_TIME)
+            .await?;
+        element
+            .screenshot(CaptureScreenshotFormat::Png)
+            .await
+            .map_err(|e| {
+                webpuppet::Error::Browser(format!("Element screenshot failed: {}", e)).into()
+            })
+    }
+
+    fn provider_capabilities(&self, provider: Provider) -> Option<ProviderCapabilities> {
+        self.puppet.provider_capabilities(provider)
+    }
+
+    async fn probe(&self, provider: Provider) -> Result<ProviderProbe> {
+        self.check_available(provider)?;
+        let session = self.puppet.get_session(provider).await?;
+        if let Err(e) = session.navigate(provider.base_url()).await {
+            return Ok(ProviderProbe {
+                reachable: false,
+                url: provider.base_url().to_string(),
+                title: None,
+                input_ready: false,
+                error: Some(e.to_string()),
+            });
+        }
+
+        let url = session
+            .current_url()
+            .await
+            .unwrap_or_else(|_| provider.base_url().to_string());
+        let title = session.get_title().await.ok();
+        // Providers report "authenticated" when the prompt input is on the page
+        let input_ready = match self.providers.get(&provider) {
+            Some(provider_impl) => provider_impl
+                .is_authenticated(&session)
+                .await
+                .unwrap_or(false),
+            None => false,
+        };
+
+        Ok(ProviderProbe {
+            reachable: true,
+            url,
+            title,
+            input_ready,
+            error: None,
+        })
+    }
+
+    async fn close(&self) -> Result<()> {
+        Ok(self.puppet.close().await?)
+    }
+
+    fn failed_providers(&self) -> Vec<(Provider, String)> {
+        self.failed.clone()
+    }
+}
+
+/// The last response message on a provider's page.
+#[derive(Debug, PartialEq, Eq, Deserialize)]
+struct ResponseSnapshot {
+    /// Number of response messages on the page.
+    count: usize,
+    /// Text of the last one.
+    text: String,
+}
+
+async fn response_snapshot(session: &Session, script: &str) -> Option<ResponseSnapshot> {
+    session.evaluate(script).await.ok()
+}
+
+/// CSS selector of a provider's response messages, if its partial
+/// responses can be read while they are generated.
+fn response_selector(provider: Provider) -> Option<String> {
+    Some(match provider {
+        Provider::Grok => GrokConfig::default().response_selector,
+        Provider::Claude => ClaudeConfig::default().response_selector,
+        Provider::Gemini => GeminiConfig::default().response_selector,
+        Provider::ChatGpt => ChatGptConfig::default().response_selector,
+        Provider::Perplexity => PerplexityConfig::default().response_selector,
+        Provider::NotebookLm => NotebookLmConfig::default().response_selector,
+        Provider::Kaggle => return None,
+    })
+}
+
+/// CSS selector of what a provider's page shows while it is generating a
+/// response (the indicator webpuppet waits on to finish a prompt).
+fn generating_selector(provider: Provider) -> Option<&'static str> {
+    Some(match provider {
+        Provider::Grok => r#"div[data-testid="grokTypingIndicator"]"#,
+        Provider::Claude => "div.cursor-blink",
+        Provider::Gemini => r#"button[aria-label="Stop generating"]"#,
+        Provider::ChatGpt => r#"button[data-testid="stop-button"]"#,
+        Provider::Perplexity => r#"div[data-testid="loading-indicator"]"#,
+        Provider::NotebookLm => r#"div[data-testid="loading"]"#,
+        Provider::Kaggle => return None,
+    })
+}
+
+/// Declared capabilities of a provider, without launching a browser.
+pub fn declared_capabilities(provider: Provider) -> ProviderCapabilities {
+    provider_impl(provider).capabilities()
+}
+
+/// Provider implementation used for session checks outside of `WebPuppet`.
+fn provider_impl(provider: Provider) -> Arc<dyn ProviderTrait> {
+    match provider {
+        Provider::Grok => Arc::new(GrokProvider::new()),
+        Provider::Claude => Arc::new(ClaudeProvider::new()),
+        Provider::Gemini => Arc::new(GeminiProvider::new()),
+        Provider::ChatGpt => Arc::new(ChatGptProvider::new()),
+        Provider::Perplexity => Arc::new(PerplexityProvider::new()),
+        Provider::NotebookLm => Arc::new(NotebookLmProvider::new()),
+        Provider::Kaggle => Arc::new(KaggleProvider::new()),
+    }
+}
@@ -0,0 +1,43 @@
+//! Identity of the tool call running on the current task.
+//!
+//! The server runs every `tools/call` inside a [`CallInfo`] scope, so code
+//! deep inside a tool (e.g. the intervention handling in `send_screened`)
+//! can tell which request it is working for without threading the id
+//! through every signature.
+
+use std::future::Future;
+
+use crate::protocol::JsonRpcId;
+
+tokio::task_local! {
+    static CALL: CallInfo;
+}
+
+/// The request and tool of a running tool call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallInfo {
+    /// JSON-RPC id of the `tools/call` request, if it had one.
+    pub request_id: Option<JsonRpcId>,
+    /// Name of the tool being called.
+    pub tool: String,
+}
+
+impl CallInfo {
+    /// Describe a call to `tool` made by request `request_id`.
+    pub fn new(request_id: Option<JsonRpcId>, tool: impl Into<String>) -> Self {
+        Self {
+            request_id,
+            tool: tool.into(),
+        }
+    }
+
+    /// The call running on this task, if any.
+    pub fn current() -> Option<Self> {
+        CALL.try_with(Clone::clone).ok()
+    }
+
+    /// Run `future` as this call.
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        CALL.scope(self, future).await
+    }
+}
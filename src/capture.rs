@@ -0,0 +1,129 @@
+//! Images kept for clients that can't display them inline.
+//!
+//! MCP clients are expected to render image content, but text-only hosts
+//! can't. A client can say so in `initialize` by listing the content types
+//! it displays:
+//!
+//! ```json
+//! {"capabilities": {"experimental": {"contentTypes": ["text"]}}}
+//! ```
+//!
+//! Images in its tool results are then replaced by a text note pointing to
+//! a `webpuppet://capture/<n>` resource, which `resources/read` returns as a
+//! base64 blob. The most recent [`MAX_CAPTURES`] images of a session are
+//! kept, and `resources/templates/list` advertises the URI scheme as
+//! [`capture_template`].
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use crate::protocol::{ContentItem, Resource, ResourceTemplate, ToolCallResult};
+
+/// Prefix of the URIs captures are read at.
+pub const CAPTURE_URI_PREFIX: &str = "webpuppet://capture/";
+
+/// Number of captures kept per session; older ones are forgotten.
+pub const MAX_CAPTURES: usize = 16;
+
+/// The template of capture URIs, as listed by `resources/templates/list`.
+pub fn capture_template() -> ResourceTemplate {
+    ResourceTemplate {
+        uri_template: format!("{}{{id}}", CAPTURE_URI_PREFIX),
+        name: "Captured image".into(),
+        description: Some(format!(
+            "A screenshot or other image a tool returned to a client that can't display it inline; `id` is the number in the tool result's note. Only the last {} are kept.",
+            MAX_CAPTURES
+        )),
+        mime_type: None,
+    }
+}
+
+/// An image held back from a tool result.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capture {
+    /// URI the image is read at.
+    pub uri: String,
+    /// MIME type of the image.
+    pub mime_type: String,
+    /// Base64-encoded image data.
+    pub data: String,
+}
+
+/// The images held back in one session.
+#[derive(Debug, Default)]
+pub struct CaptureStore {
+    /// Number of the last capture.
+    last: AtomicU64,
+    captures: Mutex<VecDeque<Capture>>,
+}
+
+impl CaptureStore {
+    /// Create an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep an image, forgetting the oldest beyond [`MAX_CAPTURES`], and
+    /// return its URI.
+    pub fn store(&self, mime_type: &str, data: String) -> String {
+        let number = self.last.fetch_add(1, Ordering::SeqCst) + 1;
+        let uri = format!("{}{}", CAPTURE_URI_PREFIX, number);
+
+        let mut captures = self.captures.lock().unwrap();
+        if captures.len() == MAX_CAPTURES {
+            captures.pop_front();
+        }
+        captures.push_back(Capture {
+            uri: uri.clone(),
+            mime_type: mime_type.to_string(),
+            data,
+        });
+        uri
+    }
+
+    /// The capture at `uri`, if it is still kept.
+    pub fn get(&self, uri: &str) -> Option<Capture> {
+        self.captures
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|capture| capture.uri == uri)
+            .cloned()
+    }
+
+    /// The kept captures, as listed by `resources/list`.
+    pub fn resources(&self) -> Vec<Resource> {
+        self.captures
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|capture| Resource {
+                uri: capture.uri.clone(),
+                name: "Captured image".into(),
+                description: Some(
+                    "An image a tool returned to a client that can't display it inline.".into(),
+                ),
+                mime_type: Some(capture.mime_type.clone()),
+            })
+            .collect()
+    }
+
+    /// Replace the images in `result` with text notes pointing to where
+    /// they can be read.
+    pub fn hold_images(&self, result: &mut ToolCallResult) {
+        for item in &mut result.content {
+            if let ContentItem::Image {
+                data, mime_type, ..
+            } = item
+            {
+                let bytes = data.len() / 4 * 3;
+                let uri = self.store(mime_type, std::mem::take(data));
+                *item = ContentItem::text(format!(
+                    "[Image not shown: {} of about {} bytes. Read it with resources/read at {}]",
+                    mime_type, bytes, uri
+                ));
+            }
+        }
+    }
+}
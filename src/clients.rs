@@ -0,0 +1,118 @@
+//! Workarounds for the quirks of known MCP hosts.
+//!
+//! Hosts differ in what they can show: some print tool results without
+//! rendering markdown, some cut long results off. `initialize` names the
+//! client (`clientInfo.name`), and the server looks it up in a small
+//! built-in table, overridden by [`ServerConfig::client_workarounds`]
+//! (`--client-workaround`), to adjust the result format and size limit for
+//! the rest of the session. The workarounds applied are logged.
+//!
+//! [`ServerConfig::client_workarounds`]: crate::config::ServerConfig::client_workarounds
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::ResultFormat;
+
+/// Adjustments for a client.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClientWorkarounds {
+    /// Result format to use instead of `--result-format`.
+    pub result_format: Option<ResultFormat>,
+    /// Result size limit, in bytes; the smaller of this and
+    /// `--max-result-bytes` applies.
+    pub max_result_bytes: Option<usize>,
+}
+
+/// Clients with known quirks, by lowercase name.
+const KNOWN_CLIENTS: &[(&str, ClientWorkarounds)] = &[(
+    // The MCP Inspector shows tool result text as is, without rendering it
+    "mcp-inspector",
+    ClientWorkarounds {
+        result_format: Some(ResultFormat::Plain),
+        max_result_bytes: None,
+    },
+)];
+
+impl ClientWorkarounds {
+    /// Workarounds for the client named `name`: those in `overrides`, else
+    /// the built-in ones, else none. Names are matched ignoring case.
+    pub fn for_client(name: &str, overrides: &HashMap<String, ClientWorkarounds>) -> Self {
+        let name = name.trim().to_lowercase();
+        overrides
+            .iter()
+            .find(|(client, _)| client.trim().to_lowercase() == name)
+            .map(|(_, workarounds)| *workarounds)
+            .or_else(|| {
+                KNOWN_CLIENTS
+                    .iter()
+                    .find(|(client, _)| *client == name)
+                    .map(|(_, workarounds)| *workarounds)
+            })
+            .unwrap_or_default()
+    }
+
+    /// Whether there is nothing to adjust.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The result format to use when `--result-format` is `configured`.
+    pub fn result_format(&self, configured: ResultFormat) -> ResultFormat {
+        self.result_format.unwrap_or(configured)
+    }
+
+    /// The result size limit to use when `--max-result-bytes` is
+    /// `configured` (zero meaning none).
+    pub fn max_result_bytes(&self, configured: usize) -> usize {
+        match (self.max_result_bytes, configured) {
+            (None, configured) => configured,
+            (Some(limit), 0) => limit,
+            (Some(limit), configured) => limit.min(configured),
+        }
+    }
+}
+
+impl FromStr for ClientWorkarounds {
+    type Err = String;
+
+    /// Parse a comma-separated list of `plain`, `markdown` and
+    /// `max-result-bytes:N`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut workarounds = Self::default();
+        for option in s.split(',').map(str::trim).filter(|o| !o.is_empty()) {
+            match option.split_once(':') {
+                Some(("max-result-bytes", bytes)) => {
+                    workarounds.max_result_bytes = Some(
+                        bytes
+                            .trim()
+                            .parse()
+                            .map_err(|_| format!("'{}' is not a number of bytes", bytes.trim()))?,
+                    );
+                }
+                _ => workarounds.result_format = Some(option.parse()?),
+            }
+        }
+        Ok(workarounds)
+    }
+}
+
+impl fmt::Display for ClientWorkarounds {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut options = Vec::new();
+        if let Some(format) = self.result_format {
+            options.push(format.to_string());
+        }
+        if let Some(bytes) = self.max_result_bytes {
+            options.push(format!("max-result-bytes:{}", bytes));
+        }
+        if options.is_empty() {
+            return f.write_str("none");
+        }
+        f.write_str(&options.join(","))
+    }
+}
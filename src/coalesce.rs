@@ -0,0 +1,151 @@
+//! Coalescing of identical tool calls.
+//!
+//! Interactive assistants sometimes fire the same browser action twice in
+//! quick succession. [`RequestCoalescer`] lets an identical call (same key)
+//! that arrives while the first is in flight, or shortly after it completed,
+//! share the first call's result instead of driving the browser again.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tokio::sync::watch;
+
+use crate::error::Result;
+use crate::protocol::ToolCallResult;
+
+/// Outcome published by the leading call. Only successes are shared; when
+/// the leader fails, waiting callers run their own attempt.
+type Outcome = Option<std::result::Result<ToolCallResult, ()>>;
+
+struct Entry {
+    receiver: watch::Receiver<Outcome>,
+    completed_at: Option<Instant>,
+    ttl: Duration,
+}
+
+impl Entry {
+    fn is_live(&self, now: Instant) -> bool {
+        match self.completed_at {
+            None => true,
+            Some(at) => now.duration_since(at) < self.ttl,
+        }
+    }
+}
+
+/// Shares results between identical in-flight (or just-completed) calls.
+#[derive(Default)]
+pub struct RequestCoalescer {
+    entries: Mutex<HashMap<String, Entry>>,
+}
+
+impl RequestCoalescer {
+    /// Create an empty coalescer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build a coalescing key from a tool name and its arguments.
+    ///
+    /// Object keys are serialized in sorted order, so argument order does
+    /// not matter.
+    pub fn key(tool: &str, arguments: &serde_json::Value) -> String {
+        format!("{}:{}", tool, arguments)
+    }
+
+    /// Forget all in-flight and cached calls, returning how many were dropped.
+    ///
+    /// Callers already waiting on a dropped call still receive its outcome.
+    pub fn clear(&self) -> usize {
+        let mut entries = self.entries.lock().unwrap();
+        let count = entries.len();
+        entries.clear();
+        count
+    }
+
+    /// Run `work` unless an identical call is in flight or completed
+    /// successfully less than `ttl` ago, in which case its result is reused.
+    pub async fn run<F>(&self, key: String, ttl: Duration, work: F) -> Result<ToolCallResult>
+    where
+        F: Future<Output = Result<ToolCallResult>>,
+    {
+        let existing = {
+            let mut entries = self.entries.lock().unwrap();
+            let now = Instant::now();
+            entries.retain(|_, entry| entry.is_live(now));
+            entries.get(&key).map(|entry| entry.receiver.clone())
+        };
+
+        if let Some(mut receiver) = existing {
+            if let Ok(outcome) = receiver.wait_for(Option::is_some).await {
+                if let Some(Ok(result)) = outcome.clone() {
+                    tracing::debug!("Coalesced duplicate call {}", key);
+                    return Ok(result);
+                }
+            }
+            // The leader failed or was cancelled; fall through and run ourselves
+        }
+
+        let (sender, receiver) = watch::channel(None);
+        self.entries.lock().unwrap().insert(
+            key.clone(),
+            Entry {
+                receiver: receiver.clone(),
+                completed_at: None,
+                ttl,
+            },
+        );
+        let mut guard = LeaderGuard {
+            coalescer: self,
+            key: &key,
+            receiver,
+            completed: false,
+        };
+
+        let result = work.await;
+
+        match result {
+            Ok(ref value) => {
+                if let Some(entry) = self.entries.lock().unwrap().get_mut(&key) {
+                    if entry.receiver.same_channel(&guard.receiver) {
+                        entry.completed_at = Some(Instant::now());
+                    }
+                }
+                guard.completed = true;
+                sender.send_replace(Some(Ok(value.clone())));
+            }
+            Err(_) => {
+                sender.send_replace(Some(Err(())));
+            }
+        }
+
+        result
+    }
+}
+
+/// Removes the entry of a leading call that failed or was dropped before
+/// completing, so later calls don't wait on it.
+struct LeaderGuard<'a> {
+    coalescer: &'a RequestCoalescer,
+    key: &'a str,
+    receiver: watch::Receiver<Outcome>,
+    completed: bool,
+}
+
+impl Drop for LeaderGuard<'_> {
+    fn drop(&mut self) {
+        if self.completed {
+            return;
+        }
+        if let Ok(mut entries) = self.coalescer.entries.lock() {
+            // Only remove our own entry, not one a later leader inserted
+            let ours = entries
+                .get(self.key)
+                .is_some_and(|entry| entry.receiver.same_channel(&self.receiver));
+            if ours {
+                entries.remove(self.key);
+            }
+        }
+    }
+}
@@ -0,0 +1,479 @@
+//! Server configuration.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+use webpuppet::{BrowserType, Provider};
+
+use crate::blocklist::Blocklist;
+use crate::browser::BrowserIdentity;
+use crate::clients::ClientWorkarounds;
+use crate::error::{Error, Result};
+use crate::framing::DEFAULT_MAX_MESSAGE_BYTES;
+use crate::humanize::HumanizeConfig;
+use crate::outbound::DEFAULT_NOTIFICATION_CAPACITY;
+use crate::ratelimit::DEFAULT_MIN_INTERVAL;
+use crate::redact::RedactionConfig;
+
+/// Default maximum duration of a tool call.
+pub const DEFAULT_TOOL_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Default time answers of cacheable read-only tools are reused.
+pub const DEFAULT_READ_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Default time an unused browser is kept open.
+pub const DEFAULT_BROWSER_IDLE_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Default time the client gets to answer a server `ping`.
+pub const DEFAULT_PING_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default size limit of a file fetched by `webpuppet_download` (50 MiB).
+pub const DEFAULT_MAX_DOWNLOAD_BYTES: u64 = 50 * 1024 * 1024;
+
+/// Default time a provider gets to authenticate before a prompt asks for a
+/// human to log in instead.
+///
+/// Google's sign-in (Gemini, NotebookLM) is the slowest to settle; pages
+/// without an account wall (Perplexity, Kaggle) need the least.
+pub fn default_auth_timeout(provider: Provider) -> Duration {
+    match provider {
+        Provider::Gemini | Provider::NotebookLm => Duration::from_secs(45),
+        Provider::Perplexity | Provider::Kaggle => Duration::from_secs(20),
+        _ => Duration::from_secs(30),
+    }
+}
+
+/// What tools do with a provider response that fails screening.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScreeningMode {
+    /// Show the sanitized response after a warning (the default).
+    #[default]
+    Annotate,
+    /// Withhold the response and fail the call with only the warning.
+    Block,
+}
+
+impl FromStr for ScreeningMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "annotate" => Ok(Self::Annotate),
+            "block" => Ok(Self::Block),
+            other => Err(format!(
+                "unknown screening mode '{}' (expected block or annotate)",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ScreeningMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Annotate => "annotate",
+            Self::Block => "block",
+        })
+    }
+}
+
+/// How built-in tools format their human-readable output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResultFormat {
+    /// Markdown headings, bold and emoji (the default).
+    #[default]
+    Markdown,
+    /// The same text without markup, for hosts that show it raw. See
+    /// [`crate::plain`].
+    Plain,
+}
+
+impl FromStr for ResultFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "markdown" => Ok(Self::Markdown),
+            "plain" => Ok(Self::Plain),
+            other => Err(format!(
+                "unknown result format '{}' (expected markdown or plain)",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ResultFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Markdown => "markdown",
+            Self::Plain => "plain",
+        })
+    }
+}
+
+/// Runtime configuration shared by the server and its tools.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    /// Name of the permission policy given with `--policy`, for reporting
+    /// (default: `secure`). The policy itself is the tools'
+    /// `PermissionGuard`.
+    pub policy: String,
+    /// Whether to run the browser in headless mode (default: true).
+    pub headless: bool,
+    /// Provider used when a tool call doesn't name one (default: grok).
+    #[serde(with = "provider_name")]
+    pub default_provider: Provider,
+    /// Browser to launch (default: the system default browser if it can be
+    /// automated, else the first installed of Brave, Chrome, Chromium, Edge,
+    /// Opera and Vivaldi).
+    #[serde(with = "browser_name")]
+    pub browser: Option<BrowserType>,
+    /// Redaction applied to logged traffic and audit output.
+    pub redaction: RedactionConfig,
+    /// Notifications buffered for a slow client before the oldest are dropped.
+    pub notification_capacity: usize,
+    /// Minimum delay between navigations to each domain, keyed by domain
+    /// (covering its subdomains) or `*` for every other domain (default:
+    /// none). See [`crate::ratelimit::DomainDelays`].
+    #[serde(with = "domain_durations")]
+    pub domain_delays: HashMap<String, Duration>,
+    /// Minimum interval between prompts to the same provider (default: 2s).
+    #[serde(with = "humantime_serde")]
+    pub provider_min_interval: Duration,
+    /// Maximum time a single tool call may take (default: 5m).
+    #[serde(with = "humantime_serde")]
+    pub tool_timeout: Duration,
+    /// List tools whose operation the permission policy denies (default: false).
+    pub show_all_tools: bool,
+    /// Close the browser after it has been unused this long (default: 10m;
+    /// zero keeps it open).
+    #[serde(with = "humantime_serde")]
+    pub browser_idle_timeout: Duration,
+    /// Launch the browser as soon as the server starts serving, instead of
+    /// on the first tool call (default: false). Skipped when the permission
+    /// policy doesn't allow navigation.
+    pub prewarm: bool,
+    /// Open every provider's session when the browser launches, at most this
+    /// many at once (default: zero, i.e. each provider opens on first use).
+    /// Providers that fail to open are left out of the session.
+    pub provider_init_concurrency: usize,
+    /// Hold navigations, clicks and typing that look destructive (deleting,
+    /// paying, logging out) until a human confirms them through the
+    /// intervention flow (default: false). See [`crate::confirm`].
+    pub confirm_destructive: bool,
+    /// What to do with provider responses that fail screening (default:
+    /// annotate, i.e. show them after a warning).
+    pub screening_mode: ScreeningMode,
+    /// Pace typing and clicking like a human (default: off). See
+    /// [`crate::humanize`].
+    pub humanize: HumanizeConfig,
+    /// JSON-RPC methods clients may call (default: all). `initialize` is
+    /// always allowed.
+    pub allowed_methods: Option<Vec<String>>,
+    /// Tools that can never be called or listed, whatever the permission
+    /// policy says.
+    pub denied_tools: Vec<String>,
+    /// Send a heartbeat notification this often while a tool call or a human
+    /// intervention is in progress (default: zero, i.e. off).
+    #[serde(with = "humantime_serde")]
+    pub heartbeat_interval: Duration,
+    /// Send the client a `ping` request this often, and stop serving if it
+    /// isn't answered within [`ping_timeout`](Self::ping_timeout) (default:
+    /// zero, i.e. off).
+    #[serde(with = "humantime_serde")]
+    pub ping_interval: Duration,
+    /// How long the client gets to answer a `ping` (default: 30s).
+    #[serde(with = "humantime_serde")]
+    pub ping_timeout: Duration,
+    /// Format of the human-readable text of tool results (default:
+    /// markdown).
+    pub result_format: ResultFormat,
+    /// Truncate tool results (text, images and structured content) beyond
+    /// this many bytes of JSON (default: zero, i.e. no limit).
+    pub max_result_bytes: usize,
+    /// Workarounds by client name, overriding the built-in ones (default:
+    /// none). See [`crate::clients`].
+    pub client_workarounds: HashMap<String, ClientWorkarounds>,
+    /// Proxy the browser sends its traffic through (default: none, i.e. a
+    /// direct connection or the system settings).
+    pub proxy: Option<ProxyConfig>,
+    /// User agent, locale and timezone the browser presents (default:
+    /// the browser's own).
+    pub identity: BrowserIdentity,
+    /// Directory `webpuppet_download` saves files to (default:
+    /// `webpuppet-mcp-downloads` in the system temp directory).
+    pub download_dir: PathBuf,
+    /// Largest file `webpuppet_download` accepts (default: 50 MiB).
+    pub max_download_bytes: u64,
+    /// Keep the browser's data (cookies, storage, logins) in this directory
+    /// so they survive restarts, instead of using the browser's own profile
+    /// directory or a throwaway one (default: none). The directory holds
+    /// session tokens in clear; `webpuppet_clear_sessions` wipes it.
+    pub session_dir: Option<PathBuf>,
+    /// Per-provider authentication timeouts overriding
+    /// [`default_auth_timeout`].
+    #[serde(with = "provider_durations")]
+    pub auth_timeouts: HashMap<Provider, Duration>,
+    /// Largest incoming message accepted; longer lines are discarded
+    /// unread (default: 4 MiB; zero means no limit).
+    pub max_message_bytes: usize,
+    /// URLs and selectors tools refuse to act on, whatever the permission
+    /// policy allows (default: none).
+    pub blocklist: Blocklist,
+    /// How long answers of read-only tools that rarely change (detected
+    /// browsers, providers, declared capabilities) are reused (default: 60s;
+    /// zero disables caching).
+    #[serde(with = "humantime_serde")]
+    pub read_cache_ttl: Duration,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            policy: "secure".into(),
+            headless: true,
+            default_provider: Provider::Grok,
+            browser: None,
+            redaction: RedactionConfig::default(),
+            notification_capacity: DEFAULT_NOTIFICATION_CAPACITY,
+            domain_delays: HashMap::new(),
+            provider_min_interval: DEFAULT_MIN_INTERVAL,
+            tool_timeout: DEFAULT_TOOL_TIMEOUT,
+            show_all_tools: false,
+            browser_idle_timeout: DEFAULT_BROWSER_IDLE_TIMEOUT,
+            prewarm: false,
+            provider_init_concurrency: 0,
+            confirm_destructive: false,
+            screening_mode: ScreeningMode::Annotate,
+            humanize: HumanizeConfig::default(),
+            allowed_methods: None,
+            denied_tools: Vec::new(),
+            heartbeat_interval: Duration::ZERO,
+            ping_interval: Duration::ZERO,
+            ping_timeout: DEFAULT_PING_TIMEOUT,
+            result_format: ResultFormat::Markdown,
+            max_result_bytes: 0,
+            client_workarounds: HashMap::new(),
+            proxy: None,
+            identity: BrowserIdentity::default(),
+            download_dir: std::env::temp_dir().join("webpuppet-mcp-downloads"),
+            max_download_bytes: DEFAULT_MAX_DOWNLOAD_BYTES,
+            session_dir: None,
+            auth_timeouts: HashMap::new(),
+            max_message_bytes: DEFAULT_MAX_MESSAGE_BYTES,
+            blocklist: Blocklist::default(),
+            read_cache_ttl: DEFAULT_READ_CACHE_TTL,
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Whether clients may call a JSON-RPC method.
+    pub fn method_allowed(&self, method: &str) -> bool {
+        method == "initialize"
+            || self
+                .allowed_methods
+                .as_ref()
+                .is_none_or(|allowed| allowed.iter().any(|m| m == method))
+    }
+
+    /// Whether a tool is disabled outright.
+    pub fn tool_denied(&self, name: &str) -> bool {
+        self.denied_tools.iter().any(|tool| tool == name)
+    }
+
+    /// How long `provider` may take to authenticate.
+    pub fn auth_timeout(&self, provider: Provider) -> Duration {
+        self.auth_timeouts
+            .get(&provider)
+            .copied()
+            .unwrap_or_else(|| default_auth_timeout(provider))
+    }
+}
+
+/// Proxy schemes the browser supports.
+const PROXY_SCHEMES: &[&str] = &["http", "https", "socks4", "socks5"];
+
+/// Proxy the browser connects through.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProxyConfig {
+    /// Proxy URL, e.g. `http://proxy.corp:3128` or `socks5://127.0.0.1:1080`.
+    pub url: String,
+    /// Hosts reached directly rather than through the proxy (e.g.
+    /// `localhost`, `*.internal`).
+    #[serde(default)]
+    pub bypass: Vec<String>,
+}
+
+impl ProxyConfig {
+    /// Validate a proxy URL and its bypass list.
+    ///
+    /// Only HTTP(S) and SOCKS proxies with a host are accepted. Credentials
+    /// are rejected: the browser ignores them in the proxy URL, and they
+    /// would end up on its command line.
+    pub fn new(url: &str, bypass: Vec<String>) -> Result<Self> {
+        let parsed = Url::parse(url.trim())
+            .map_err(|e| Error::InvalidParams(format!("invalid proxy URL `{}`: {}", url, e)))?;
+
+        if !PROXY_SCHEMES.contains(&parsed.scheme()) {
+            return Err(Error::InvalidParams(format!(
+                "unsupported proxy scheme `{}:`; use one of {}",
+                parsed.scheme(),
+                PROXY_SCHEMES.join(", ")
+            )));
+        }
+        if parsed.host_str().is_none_or(str::is_empty) {
+            return Err(Error::InvalidParams(format!(
+                "proxy URL `{}` has no host",
+                url
+            )));
+        }
+        if !parsed.username().is_empty() || parsed.password().is_some() {
+            return Err(Error::InvalidParams(
+                "proxy credentials are not supported in the URL; the browser asks for them when the proxy requires them".into(),
+            ));
+        }
+
+        Ok(Self {
+            url: Self::server(&parsed),
+            bypass: bypass
+                .into_iter()
+                .map(|host| host.trim().to_string())
+                .filter(|host| !host.is_empty())
+                .collect(),
+        })
+    }
+
+    /// `scheme://host[:port]`, the form the browser expects.
+    fn server(url: &Url) -> String {
+        let host = url.host_str().unwrap_or_default();
+        match url.port() {
+            Some(port) => format!("{}://{}:{}", url.scheme(), host, port),
+            None => format!("{}://{}", url.scheme(), host),
+        }
+    }
+
+    /// Host (and port) of the proxy, for display.
+    pub fn host(&self) -> String {
+        Url::parse(&self.url)
+            .ok()
+            .and_then(|url| {
+                let host = url.host_str()?.to_string();
+                Some(match url.port() {
+                    Some(port) => format!("{}:{}", host, port),
+                    None => host,
+                })
+            })
+            .unwrap_or_else(|| self.url.clone())
+    }
+}
+
+/// (De)serializes a [`Provider`] by its short name.
+mod provider_name {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use webpuppet::Provider;
+
+    pub fn serialize<S: Serializer>(provider: &Provider, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(provider.name())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Provider, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        crate::tools::parse_provider(&name).map_err(serde::de::Error::custom)
+    }
+}
+
+/// (De)serializes an optional browser type as its name (`"brave"`).
+mod browser_name {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use webpuppet::BrowserType;
+
+    pub fn serialize<S: Serializer>(
+        browser: &Option<BrowserType>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match browser {
+            Some(browser) => serializer.serialize_str(&browser.name().to_lowercase()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<BrowserType>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|name| crate::browser::parse_browser(&name).map_err(serde::de::Error::custom))
+            .transpose()
+    }
+}
+
+/// (De)serializes per-provider durations as `{"claude": "1m", ...}`.
+mod domain_durations {
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        durations: &HashMap<String, Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        durations
+            .iter()
+            .map(|(domain, duration)| (domain.as_str(), humantime_serde::Serde::from(*duration)))
+            .collect::<std::collections::BTreeMap<_, _>>()
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<String, Duration>, D::Error> {
+        Ok(
+            HashMap::<String, humantime_serde::Serde<Duration>>::deserialize(deserializer)?
+                .into_iter()
+                .map(|(domain, duration)| (domain, duration.into_inner()))
+                .collect(),
+        )
+    }
+}
+
+mod provider_durations {
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use webpuppet::Provider;
+
+    pub fn serialize<S: Serializer>(
+        durations: &HashMap<Provider, Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        durations
+            .iter()
+            .map(|(provider, duration)| (provider.name(), humantime_serde::Serde::from(*duration)))
+            .collect::<std::collections::BTreeMap<_, _>>()
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<Provider, Duration>, D::Error> {
+        HashMap::<String, humantime_serde::Serde<Duration>>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(name, duration)| {
+                crate::tools::parse_provider(&name)
+                    .map(|provider| (provider, duration.into_inner()))
+                    .map_err(serde::de::Error::custom)
+            })
+            .collect()
+    }
+}
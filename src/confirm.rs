@@ -0,0 +1,237 @@
+//! Human confirmation of actions that look destructive.
+//!
+//! The blocklist refuses known targets outright; `--confirm-destructive`
+//! instead asks a human before going ahead with anything that merely looks
+//! irreversible, whatever the policy allows. An action looks destructive
+//! when:
+//!
+//! - a navigation URL's path mentions deleting, paying, logging out and the
+//!   like (`/account/delete`, `/checkout`),
+//! - a clicked element's label or link does, or it submits a form posting to
+//!   such an endpoint,
+//! - a typed-into field belongs to a form posting to such an endpoint.
+//!
+//! The action then waits, as a pending intervention, until the human calls
+//! `webpuppet_intervention_complete`: `success=true` lets it proceed, anything
+//! else (or no answer before the tool timeout) aborts it.
+
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+use serde_json::json;
+use url::Url;
+use webpuppet::{InterventionReason, Operation};
+
+use crate::error::{Error, Result};
+use crate::tools::{InterventionOrigin, InterventionOutcome, ToolContext};
+
+/// Words that make an action look destructive.
+const DESTRUCTIVE_WORDS: &str = r"\b(delete|remove|destroy|erase|wipe|pay|payment|purchase|buy|checkout|check[ -]out|place[ -]order|log[ -]?out|sign[ -]?out|unsubscribe|deactivate|close[ -]account|transfer)\b";
+
+/// Describes the first element matching a selector for the heuristics.
+/// Called with the selector and whether it is clicked; returns `null` if
+/// nothing matches, otherwise `{ label, href, formAction, formMethod }`. The
+/// form is only reported for fields and for buttons that submit it.
+const DESCRIBE_TARGET_SCRIPT: &str = r#"((selector, click) => {
+    const el = document.querySelector(selector);
+    if (!el) return null;
+    const label = [el.innerText, el.value, el.getAttribute('aria-label'), el.title]
+        .filter(part => typeof part === 'string' && part.trim())
+        .join(' ')
+        .slice(0, 200);
+    const link = el.closest('a[href]');
+    const submits = !click || el.type === 'submit' || (el.tagName === 'BUTTON' && !el.type);
+    const form = submits ? el.form || el.closest('form') : null;
+    return {
+        label: label,
+        href: link ? link.href : null,
+        formAction: form ? form.action : null,
+        formMethod: form ? (form.method || 'get').toLowerCase() : null
+    };
+})"#;
+
+/// An action to check before it runs.
+#[derive(Debug, Clone, Copy)]
+pub enum Action<'a> {
+    /// Opening a URL.
+    Navigate(&'a str),
+    /// Clicking the element matching a selector.
+    Click(&'a str),
+    /// Typing into the element matching a selector.
+    Type(&'a str),
+}
+
+impl Action<'_> {
+    fn operation(&self) -> Operation {
+        match self {
+            Action::Navigate(_) => Operation::Navigate,
+            Action::Click(_) => Operation::Click,
+            Action::Type(_) => Operation::TypeText,
+        }
+    }
+}
+
+impl std::fmt::Display for Action<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Action::Navigate(url) => write!(f, "navigating to {}", url),
+            Action::Click(selector) => write!(f, "clicking `{}`", selector),
+            Action::Type(selector) => write!(f, "typing into `{}`", selector),
+        }
+    }
+}
+
+/// What [`DESCRIBE_TARGET_SCRIPT`] reports.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TargetDescription {
+    #[serde(default)]
+    label: String,
+    href: Option<String>,
+    form_action: Option<String>,
+    form_method: Option<String>,
+}
+
+fn destructive_words() -> &'static Regex {
+    static WORDS: OnceLock<Regex> = OnceLock::new();
+    WORDS.get_or_init(|| {
+        RegexBuilder::new(DESTRUCTIVE_WORDS)
+            .case_insensitive(true)
+            .build()
+            .expect("destructive word pattern is valid")
+    })
+}
+
+/// The destructive-looking word in `text`, if any.
+pub fn destructive_word(text: &str) -> Option<String> {
+    destructive_words()
+        .find(text)
+        .map(|word| word.as_str().to_lowercase())
+}
+
+/// Why a URL looks destructive: a word in its path or query.
+fn destructive_url(url: &str) -> Option<String> {
+    let parsed = Url::parse(url).ok()?;
+    let target = format!(
+        "{} {}",
+        parsed.path().replace(['/', '-', '_'], " "),
+        parsed
+            .query()
+            .unwrap_or_default()
+            .replace(['&', '=', '_'], " ")
+    );
+    destructive_word(&target)
+}
+
+/// Why the action looks destructive, or `None` if it doesn't.
+async fn destructive_reason(context: &ToolContext, action: Action<'_>) -> Result<Option<String>> {
+    let (selector, click) = match action {
+        Action::Navigate(url) => {
+            return Ok(destructive_url(url).map(|word| format!("its URL mentions \"{}\"", word)))
+        }
+        Action::Click(selector) => (selector, true),
+        Action::Type(selector) => (selector, false),
+    };
+
+    let puppet = context.get_puppet().await?;
+    let script = format!("{}({}, {})", DESCRIBE_TARGET_SCRIPT, json!(selector), click);
+    // An element the script can't describe is left to the action to report
+    let Ok(target) =
+        serde_json::from_value::<Option<TargetDescription>>(puppet.evaluate(&script).await?)
+    else {
+        return Ok(None);
+    };
+    let Some(target) = target else {
+        return Ok(None);
+    };
+
+    if click {
+        if let Some(word) = destructive_word(&target.label) {
+            return Ok(Some(format!("the element reads \"{}\"", word)));
+        }
+        if let Some(word) = target.href.as_deref().and_then(destructive_url) {
+            return Ok(Some(format!("its link mentions \"{}\"", word)));
+        }
+    }
+    let posts = target.form_method.as_deref() == Some("post");
+    if let (true, Some(word)) = (
+        posts,
+        target.form_action.as_deref().and_then(destructive_url),
+    ) {
+        return Ok(Some(format!("its form posts to a \"{}\" endpoint", word)));
+    }
+    Ok(None)
+}
+
+/// Hold `action` until a human confirms it, if confirmation is on and it
+/// looks destructive. Fails with [`Error::PermissionDenied`] if the human
+/// declines, doesn't answer in time or another intervention is pending.
+pub async fn confirm(context: &ToolContext, action: Action<'_>) -> Result<()> {
+    if !context.config.confirm_destructive {
+        return Ok(());
+    }
+    let Some(why) = destructive_reason(context, action).await? else {
+        return Ok(());
+    };
+
+    let handler = context.intervention_handler.read().await.clone();
+    if handler.is_waiting() {
+        return Err(Error::PermissionDenied(format!(
+            "{} needs a human's confirmation ({}), but another intervention is pending",
+            action, why
+        )));
+    }
+
+    tracing::warn!("Holding {} for confirmation: {}", action, why);
+    let mut outcomes = context.intervention_outcome();
+    context
+        .request_intervention(
+            InterventionReason::Manual {
+                description: format!(
+                    "Confirm {}: {}. Call webpuppet_intervention_complete with success=true to proceed, or false to abort",
+                    action, why
+                ),
+            },
+            InterventionOrigin::current(None, action.operation()),
+        )
+        .await;
+
+    // Answer before the call itself times out
+    let limit = context
+        .config
+        .tool_timeout
+        .saturating_sub(Duration::from_secs(1));
+    let answer = tokio::time::timeout(limit, async {
+        loop {
+            if outcomes.changed().await.is_err() {
+                return None;
+            }
+            if let Some(outcome) = outcomes.borrow_and_update().clone() {
+                return Some(outcome);
+            }
+        }
+    })
+    .await;
+
+    match answer {
+        Ok(Some(InterventionOutcome::Completed { success: true, .. })) => {
+            tracing::info!("{} confirmed", action);
+            Ok(())
+        }
+        Ok(_) => Err(Error::PermissionDenied(format!(
+            "{} was not confirmed ({}); it was aborted",
+            action, why
+        ))),
+        Err(_) => {
+            handler.cancel();
+            Err(Error::PermissionDenied(format!(
+                "{} was not confirmed within {}s ({}); it was aborted",
+                action,
+                limit.as_secs(),
+                why
+            )))
+        }
+    }
+}
@@ -0,0 +1,81 @@
+//! Console messages of the page in the browsing tab.
+//!
+//! The session has no access to the DevTools runtime domain, so messages are
+//! captured in the page itself: [`CONSOLE_CAPTURE_SCRIPT`] wraps the
+//! `console` methods and listens for uncaught errors and rejections, keeping
+//! the last [`MAX_CONSOLE_MESSAGES`] in the page. The browser installs it
+//! after every navigation, so the buffer covers the current document only;
+//! messages logged while the page was still loading, before it could be
+//! installed, are missed.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Messages kept per page; older ones are dropped.
+pub const MAX_CONSOLE_MESSAGES: usize = 500;
+
+/// Installs the capture once per document and returns what it has captured:
+/// `{ messages: [{ level, text, timestamp }], dropped }`, with `timestamp`
+/// in milliseconds since the epoch and `dropped` counting messages pushed
+/// out by the cap.
+pub const CONSOLE_CAPTURE_SCRIPT: &str = r#"((cap) => {
+    let log = window.__webpuppetConsole;
+    if (!log) {
+        log = window.__webpuppetConsole = { messages: [], dropped: 0 };
+        const record = (level, parts) => {
+            const text = parts.map(part => {
+                if (typeof part === 'string') return part;
+                if (part instanceof Error) return part.stack || String(part);
+                try { return JSON.stringify(part); } catch (e) { return String(part); }
+            }).join(' ');
+            log.messages.push({ level, text: text.slice(0, 4000), timestamp: Date.now() });
+            if (log.messages.length > cap) {
+                log.messages.shift();
+                log.dropped++;
+            }
+        };
+        for (const level of ['log', 'info', 'warn', 'error', 'debug']) {
+            const original = console[level];
+            console[level] = function (...parts) {
+                record(level, parts);
+                return original.apply(this, parts);
+            };
+        }
+        window.addEventListener('error', (event) => {
+            record('error', [event.message + (event.filename ? ' (' + event.filename + ':' + event.lineno + ')' : '')]);
+        });
+        window.addEventListener('unhandledrejection', (event) => {
+            record('error', ['Unhandled rejection: ', event.reason]);
+        });
+    }
+    return { messages: log.messages, dropped: log.dropped };
+})"#;
+
+/// [`CONSOLE_CAPTURE_SCRIPT`] called with [`MAX_CONSOLE_MESSAGES`].
+pub fn capture_script() -> String {
+    format!("{}({})", CONSOLE_CAPTURE_SCRIPT, MAX_CONSOLE_MESSAGES)
+}
+
+/// A captured console message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConsoleMessage {
+    /// `log`, `info`, `warn`, `error` or `debug`; uncaught errors are
+    /// reported as `error`.
+    pub level: String,
+    /// The message's arguments, joined by spaces.
+    pub text: String,
+    /// When it was logged.
+    #[serde(deserialize_with = "chrono::serde::ts_milliseconds::deserialize")]
+    pub timestamp: DateTime<Utc>,
+}
+
+/// What [`CONSOLE_CAPTURE_SCRIPT`] returns.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ConsoleLog {
+    /// Captured messages, oldest first.
+    #[serde(default)]
+    pub messages: Vec<ConsoleMessage>,
+    /// Messages dropped because the buffer was full.
+    #[serde(default)]
+    pub dropped: usize,
+}
@@ -0,0 +1,105 @@
+//! Conversations a session has open with providers.
+//!
+//! A prompt answered with a conversation id is recorded in the session's
+//! [`ConversationLog`], so an assistant running a long session can list the
+//! threads it started, continue one by passing its id to `webpuppet_prompt`,
+//! and forget the ones it is done with. Snippets are redacted when they are
+//! recorded, like the action history.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use webpuppet::Provider;
+
+use crate::redact::Redactor;
+
+/// Characters of a conversation's first message kept as its snippet.
+const SNIPPET_CHARS: usize = 80;
+
+/// A conversation with a provider.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Conversation {
+    /// Id the provider gave the conversation.
+    pub id: String,
+    /// Provider name.
+    pub provider: String,
+    /// Start of the first message sent in it.
+    pub snippet: String,
+    /// When its first prompt was answered.
+    pub started_at: DateTime<Utc>,
+    /// When its last prompt was answered.
+    pub last_activity: DateTime<Utc>,
+    /// Prompts answered in it.
+    pub turns: u32,
+}
+
+/// The conversations of a session.
+#[derive(Debug, Default)]
+pub struct ConversationLog {
+    conversations: Mutex<HashMap<String, Conversation>>,
+}
+
+impl ConversationLog {
+    /// Create an empty log.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a prompt answered in conversation `id`, starting it with a
+    /// redacted snippet of `message` if it is new.
+    pub fn record(&self, id: &str, provider: Provider, message: &str, redactor: &Redactor) {
+        let now = Utc::now();
+        let mut conversations = self.conversations.lock().unwrap();
+        let conversation = conversations
+            .entry(id.to_string())
+            .or_insert_with(|| Conversation {
+                id: id.to_string(),
+                provider: provider.name().to_string(),
+                snippet: redactor.redact_text(&snippet(message)),
+                started_at: now,
+                last_activity: now,
+                turns: 0,
+            });
+        conversation.last_activity = now;
+        conversation.turns += 1;
+    }
+
+    /// Provider of conversation `id`, if it is open.
+    pub fn provider(&self, id: &str) -> Option<String> {
+        self.conversations
+            .lock()
+            .unwrap()
+            .get(id)
+            .map(|conversation| conversation.provider.clone())
+    }
+
+    /// Open conversations, most recently active first.
+    pub fn list(&self) -> Vec<Conversation> {
+        let mut conversations: Vec<_> = self
+            .conversations
+            .lock()
+            .unwrap()
+            .values()
+            .cloned()
+            .collect();
+        conversations.sort_by_key(|conversation| std::cmp::Reverse(conversation.last_activity));
+        conversations
+    }
+
+    /// Forget conversation `id`; returns whether it was open.
+    pub fn close(&self, id: &str) -> bool {
+        self.conversations.lock().unwrap().remove(id).is_some()
+    }
+}
+
+/// Shorten a first message to a one-line snippet.
+fn snippet(message: &str) -> String {
+    let line = message.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut snippet: String = line.chars().take(SNIPPET_CHARS).collect();
+    if line.chars().count() > SNIPPET_CHARS {
+        snippet.push('…');
+    }
+    snippet
+}
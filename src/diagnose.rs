@@ -0,0 +1,134 @@
+//! Diagnosis of prompts that came back without an answer.
+//!
+//! When a provider shows an error page, a login wall or nothing at all,
+//! the scraped "response" is empty or a short error notice. Relaying that
+//! as an answer leaves the assistant guessing, so `webpuppet_prompt` checks
+//! the provider's page after a short response and runs what it found
+//! through [`diagnose`], reporting a recognized failure as an error with its
+//! likely cause and what to do about it.
+//!
+//! The response text alone is not enough: a short answer about rate limits
+//! or logging in reads just like the notice it describes. A failure is only
+//! diagnosed from the page state: the provider reporting the session logged
+//! out, or an error notice (alert, banner, toast) whose text names the
+//! failure. An empty response is always a failure.
+
+use serde::Serialize;
+
+/// Longest response after which the page is checked for failures; longer
+/// ones are taken as answers without the extra page lookups.
+pub const MAX_NOTICE_CHARS: usize = 300;
+
+/// Phrases of login walls, in notices.
+const LOGIN_MARKERS: &[&str] = &[
+    "please log in",
+    "please sign in",
+    "log in to continue",
+    "sign in to continue",
+    "log in or sign up",
+    "sign in or sign up",
+    "session expired",
+    "session has expired",
+];
+
+/// Phrases of usage and rate limits, in notices.
+const RATE_LIMIT_MARKERS: &[&str] = &[
+    "too many requests",
+    "rate limit",
+    "usage limit",
+    "reached your limit",
+    "reached the limit",
+    "limit reached",
+    "try again later",
+];
+
+/// Phrases of provider errors and failed generations, in notices.
+const ERROR_MARKERS: &[&str] = &[
+    "something went wrong",
+    "an error occurred",
+    "internal server error",
+    "network error",
+    "error in message stream",
+    "service unavailable",
+    "bad gateway",
+    "we're experiencing",
+];
+
+/// Why a prompt probably failed, and what to do about it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnosis {
+    /// Stable machine-readable name: `empty`, `logged_out`,
+    /// `rate_limited` or `provider_error`.
+    pub category: &'static str,
+    /// Likely cause.
+    pub cause: &'static str,
+    /// What to try next.
+    pub suggestion: &'static str,
+}
+
+/// What the provider's page showed after a prompt.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PageState {
+    /// Whether the provider reports the session logged out.
+    pub logged_out: bool,
+    /// Text of the error notices (alerts, error banners, toasts) on the page.
+    pub notices: Vec<String>,
+}
+
+/// Whether a response is short enough to check the page for a failure
+/// notice before taking it as an answer.
+pub fn may_be_notice(text: &str) -> bool {
+    text.trim().chars().count() <= MAX_NOTICE_CHARS
+}
+
+/// Diagnose a prompt whose response is `text` and after which the
+/// provider's page showed `page`; `None` for an answer.
+pub fn diagnose(text: &str, page: &PageState) -> Option<Diagnosis> {
+    if page.logged_out {
+        return Some(logged_out());
+    }
+    if let Some(diagnosis) = page
+        .notices
+        .iter()
+        .find_map(|notice| diagnose_notice(notice))
+    {
+        return Some(diagnosis);
+    }
+    text.trim().is_empty().then_some(Diagnosis {
+        category: "empty",
+        cause: "The provider returned no text: the response didn't render, or the site changed and it can no longer be read.",
+        suggestion: "Check `webpuppet_login_status`, then retry. If it keeps happening, run `webpuppet_provider_capabilities` with probe=true to see whether the page still has its prompt input.",
+    })
+}
+
+/// Diagnose an error notice on the page by the failure it names; `None`
+/// for notices that name none (e.g. "Copied to clipboard").
+fn diagnose_notice(notice: &str) -> Option<Diagnosis> {
+    let notice = notice.to_lowercase();
+    let mentions = |markers: &[&str]| markers.iter().any(|marker| notice.contains(marker));
+    if mentions(LOGIN_MARKERS) {
+        Some(logged_out())
+    } else if mentions(RATE_LIMIT_MARKERS) {
+        Some(Diagnosis {
+            category: "rate_limited",
+            cause: "The provider refused the prompt with a usage or rate limit notice.",
+            suggestion: "Wait before retrying, or use another provider. A larger `--provider-min-interval-ms` spaces prompts further apart.",
+        })
+    } else if mentions(ERROR_MARKERS) {
+        Some(Diagnosis {
+            category: "provider_error",
+            cause: "The provider showed an error instead of an answer.",
+            suggestion: "Retry the prompt. If it keeps failing, check `webpuppet_login_status` and probe the page with `webpuppet_provider_capabilities`.",
+        })
+    } else {
+        None
+    }
+}
+
+fn logged_out() -> Diagnosis {
+    Diagnosis {
+        category: "logged_out",
+        cause: "The provider showed a login page instead of an answer: the session is likely logged out or expired.",
+        suggestion: "Check `webpuppet_login_status`, log in with a visible browser (`--visible`), then retry.",
+    }
+}
@@ -31,9 +31,68 @@ pub enum Error {
     #[error("permission denied: {0}")]
     PermissionDenied(String),
 
+    /// A URL with a scheme other than http(s) (`file:`, `data:`,
+    /// `chrome:`, ...), refused before any permission check.
+    #[error("disallowed URL scheme `{0}:`; only http and https URLs can be opened")]
+    DisallowedScheme(String),
+
+    /// No supported browser is installed (or the configured one is missing).
+    #[error("no usable browser found: {0}. Run `webpuppet_detect_browsers` to see which browsers are installed")]
+    BrowserNotFound(String),
+
+    /// A browser was found but failed to start.
+    #[error("browser failed to launch: {0}")]
+    LaunchFailed(String),
+
+    /// The provider session is not logged in (or expired).
+    #[error("authentication required for {provider}: {reason}. Log in using a visible browser (`--visible`), then retry")]
+    AuthRequired {
+        /// Provider name.
+        provider: String,
+        /// Why authentication is needed.
+        reason: String,
+    },
+
+    /// A prompt would have to wait too long for the provider rate limit.
+    #[error("rate limited: next prompt to {provider} is allowed in {wait_ms} ms, which exceeds the tool timeout")]
+    RateLimited {
+        /// Provider name.
+        provider: String,
+        /// How long the prompt would have had to wait.
+        wait_ms: u64,
+    },
+
+    /// A tool call did not finish within the tool timeout.
+    #[error("tool {tool} timed out after {timeout_secs}s")]
+    ToolTimeout {
+        /// Tool name.
+        tool: String,
+        /// Timeout that elapsed.
+        timeout_secs: u64,
+    },
+
+    /// The browser died or its DevTools connection dropped mid-operation.
+    /// The session has been discarded and is relaunched on next use.
+    #[error("browser crashed ({0}); it will be relaunched on the next call")]
+    BrowserCrashed(String),
+
+    /// A human has to act in the browser (captcha, 2FA, login) before the
+    /// call can succeed. The intervention handler is waiting for them.
+    #[error("human intervention required on {provider}: {reason}. Complete it in the browser, call `webpuppet_intervention_complete`, then retry")]
+    InterventionRequired {
+        /// Provider name.
+        provider: String,
+        /// What the human has to do.
+        reason: String,
+    },
+
+    /// A method or tool the server configuration disables outright.
+    #[error("{0} disabled by policy")]
+    Disabled(String),
+
     /// Webpuppet error.
     #[error("webpuppet error: {0}")]
-    Webpuppet(#[from] webpuppet::Error),
+    Webpuppet(webpuppet::Error),
 
     /// Serialization error.
     #[error("serialization error: {0}")]
@@ -56,20 +115,135 @@ impl Error {
             Error::ToolNotFound(_) => -32601,  // Method not found
             Error::InvalidParams(_) => -32602, // Invalid params
             Error::PermissionDenied(_) => -32000, // Server error
-            Error::Webpuppet(_) => -32001,
+            Error::Webpuppet(err) => webpuppet_class(err).0,
+            Error::BrowserNotFound(_) => -32003,
+            Error::LaunchFailed(_) => -32004,
+            Error::AuthRequired { .. } => -32005,
+            Error::RateLimited { .. } => -32006,
+            Error::ToolTimeout { .. } => -32007,
+            Error::BrowserCrashed(_) => -32008,
+            Error::Disabled(_) => -32014,
+            Error::InterventionRequired { .. } => -32015,
+            Error::DisallowedScheme(_) => -32016,
             Error::Serialization(_) => -32700, // Parse error
             Error::Io(_) => -32002,
             Error::Internal(_) => -32603, // Internal error
         }
     }
 
+    /// Stable machine-readable category, sent to clients as `data.category`
+    /// so they can branch without parsing messages.
+    pub fn category(&self) -> &'static str {
+        match self {
+            Error::JsonRpc { .. } => "protocol",
+            Error::ToolNotFound(_) => "tool_not_found",
+            Error::InvalidParams(_) => "invalid_params",
+            Error::PermissionDenied(_) => "permission_denied",
+            Error::Webpuppet(err) => webpuppet_class(err).1,
+            Error::BrowserNotFound(_) => "browser_not_found",
+            Error::LaunchFailed(_) => "launch_failed",
+            Error::AuthRequired { .. } => "auth_required",
+            Error::RateLimited { .. } => "rate_limited",
+            Error::ToolTimeout { .. } => "timeout",
+            Error::BrowserCrashed(_) => "browser_crashed",
+            Error::Disabled(_) => "disabled",
+            Error::InterventionRequired { .. } => "intervention_required",
+            Error::DisallowedScheme(_) => "disallowed_scheme",
+            Error::Serialization(_) => "serialization",
+            Error::Io(_) => "io",
+            Error::Internal(_) => "internal",
+        }
+    }
+
+    /// The JSON-RPC error `data` for this error.
+    pub fn data(&self) -> serde_json::Value {
+        serde_json::json!({ "category": self.category() })
+    }
+
     /// Convert to JSON-RPC error response.
     pub fn to_json_rpc_error(&self) -> serde_json::Value {
         serde_json::json!({
             "code": self.code(),
             "message": self.to_string(),
+            "data": self.data(),
         })
     }
+
+    /// Classify a failure to start the browser: a missing browser stays
+    /// [`Error::BrowserNotFound`], any other browser error is
+    /// [`Error::LaunchFailed`].
+    pub fn from_launch(err: webpuppet::Error) -> Self {
+        match Error::from(err) {
+            Error::Webpuppet(webpuppet::Error::Browser(message)) => Error::LaunchFailed(message),
+            other => other,
+        }
+    }
+}
+
+/// Code and category of a webpuppet error that has no dedicated variant.
+///
+/// Failures that mean the same thing as a server-side error share its code
+/// (e.g. a provider rate limit and our own rate limiter).
+fn webpuppet_class(err: &webpuppet::Error) -> (i32, &'static str) {
+    match err {
+        webpuppet::Error::Navigation(_) => (-32009, "navigation_failed"),
+        webpuppet::Error::ElementNotFound { .. } => (-32010, "element_not_found"),
+        webpuppet::Error::ExtractionFailed(_) => (-32011, "extraction_failed"),
+        webpuppet::Error::ProviderError { .. } | webpuppet::Error::UnsupportedProvider(_) => {
+            (-32012, "provider_error")
+        }
+        webpuppet::Error::InterventionCancelled | webpuppet::Error::InterventionTimeout(_) => {
+            (-32013, "intervention_failed")
+        }
+        webpuppet::Error::AuthenticationFailed { .. } | webpuppet::Error::SessionExpired(_) => {
+            (-32005, "auth_required")
+        }
+        webpuppet::Error::RateLimitExceeded { .. } => (-32006, "rate_limited"),
+        webpuppet::Error::Timeout(_) => (-32007, "timeout"),
+        webpuppet::Error::PermissionDenied { .. } => (-32000, "permission_denied"),
+        _ => (-32001, "webpuppet"),
+    }
+}
+
+impl From<webpuppet::Error> for Error {
+    /// Classify browser setup and authentication failures so tools can give
+    /// actionable guidance; everything else stays a generic webpuppet error.
+    fn from(err: webpuppet::Error) -> Self {
+        match err {
+            webpuppet::Error::BrowserNotFound(path) => Error::BrowserNotFound(path),
+            webpuppet::Error::Browser(message) if message.starts_with("No CDP-capable browser") => {
+                Error::BrowserNotFound(message)
+            }
+            webpuppet::Error::Browser(message) | webpuppet::Error::Navigation(message)
+                if is_disconnect_message(&message) =>
+            {
+                Error::BrowserCrashed(message)
+            }
+            webpuppet::Error::AuthenticationFailed { provider, reason } => {
+                Error::AuthRequired { provider, reason }
+            }
+            webpuppet::Error::SessionExpired(provider) => Error::AuthRequired {
+                provider,
+                reason: "session expired".into(),
+            },
+            other => Error::Webpuppet(other),
+        }
+    }
+}
+
+/// Whether a browser error message means the DevTools connection is gone,
+/// i.e. the browser process exited or crashed.
+fn is_disconnect_message(message: &str) -> bool {
+    const MARKERS: &[&str] = &[
+        "connection closed",
+        "closed connection",
+        "receiver is gone",
+        "oneshot canceled",
+        "broken pipe",
+        "connection reset",
+    ];
+    let message = message.to_lowercase();
+    MARKERS.iter().any(|marker| message.contains(marker))
 }
 
 /// Standard JSON-RPC error codes.
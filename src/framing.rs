@@ -0,0 +1,141 @@
+//! Newline-delimited message framing with a size limit.
+//!
+//! `AsyncBufReadExt::lines` buffers a whole line however long it is, so a
+//! client could exhaust the server's memory with a single line. A
+//! [`LineReader`] stops buffering once a line passes the limit, discards the
+//! rest of it as it arrives, and reports it as [`Inbound::Oversized`] with
+//! the request id if one could be found at the start of the line.
+//!
+//! Lines are trimmed of surrounding whitespace, including the `\r` of CRLF
+//! line endings, and of the UTF-8 byte order mark some Windows clients
+//! write before their first message.
+
+use std::io;
+
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+
+use crate::protocol::JsonRpcId;
+
+/// Default limit on the size of one incoming message (4 MiB).
+pub const DEFAULT_MAX_MESSAGE_BYTES: usize = 4 * 1024 * 1024;
+
+/// UTF-8 byte order mark, trimmed like whitespace.
+const BOM: char = '\u{feff}';
+
+/// Bytes of an oversized line kept to look for its request id.
+const ID_SCAN_BYTES: usize = 1024;
+
+/// A line read by a [`LineReader`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Inbound {
+    /// A complete line within the limit, trimmed of its line ending,
+    /// surrounding whitespace and any byte order mark.
+    Line(String),
+    /// A line over the limit, which was discarded.
+    Oversized {
+        /// Request id found at the start of the line, if any.
+        id: Option<JsonRpcId>,
+        /// Size of the whole line.
+        bytes: usize,
+    },
+}
+
+/// Reads newline-delimited lines of at most `max_bytes` each.
+#[derive(Debug)]
+pub struct LineReader<R> {
+    reader: R,
+    max_bytes: usize,
+    /// The line read so far (only its start once it is oversized).
+    buf: Vec<u8>,
+    /// Size of the line read so far.
+    size: usize,
+}
+
+impl<R: AsyncBufRead + Unpin> LineReader<R> {
+    /// Read lines from `reader`, discarding those longer than `max_bytes`
+    /// (zero means no limit).
+    pub fn new(reader: R, max_bytes: usize) -> Self {
+        Self {
+            reader,
+            max_bytes,
+            buf: Vec::new(),
+            size: 0,
+        }
+    }
+
+    /// Read the next line; `None` at the end of input.
+    ///
+    /// Cancel safe: a partially read line is kept for the next call.
+    pub async fn next_line(&mut self) -> io::Result<Option<Inbound>> {
+        loop {
+            let available = self.reader.fill_buf().await?;
+            if available.is_empty() {
+                if self.size == 0 {
+                    return Ok(None);
+                }
+                // The last line has no line ending
+                return self.take_line().map(Some);
+            }
+
+            let (chunk, consumed, complete) = match available.iter().position(|&b| b == b'\n') {
+                Some(end) => (&available[..end], end + 1, true),
+                None => (available, available.len(), false),
+            };
+            let oversized = self.max_bytes > 0 && self.size > self.max_bytes;
+            self.size += chunk.len();
+            if !oversized {
+                self.buf.extend_from_slice(chunk);
+                if self.max_bytes > 0 && self.size > self.max_bytes {
+                    // Keep just enough to find the id; drop the rest as it comes
+                    self.buf.truncate(ID_SCAN_BYTES);
+                    self.buf.shrink_to_fit();
+                }
+            }
+            self.reader.consume(consumed);
+
+            if complete {
+                return self.take_line().map(Some);
+            }
+        }
+    }
+
+    fn take_line(&mut self) -> io::Result<Inbound> {
+        let buf = std::mem::take(&mut self.buf);
+        let size = std::mem::take(&mut self.size);
+
+        if self.max_bytes > 0 && size > self.max_bytes {
+            return Ok(Inbound::Oversized {
+                id: find_id(&String::from_utf8_lossy(&buf)),
+                bytes: size,
+            });
+        }
+
+        let mut line = String::from_utf8(buf).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "stream did not contain valid UTF-8",
+            )
+        })?;
+        let trimmed = line.trim_matches(|c: char| c == BOM || c.is_whitespace());
+        if trimmed.len() != line.len() {
+            line = trimmed.to_string();
+        }
+        Ok(Inbound::Line(line))
+    }
+}
+
+/// The value of the first `"id"` member in the (possibly truncated) JSON
+/// text `start`.
+fn find_id(start: &str) -> Option<JsonRpcId> {
+    let mut rest = start;
+    while let Some(at) = rest.find("\"id\"") {
+        rest = &rest[at + 4..];
+        if let Some(value) = rest.trim_start().strip_prefix(':') {
+            return serde_json::Deserializer::from_str(value.trim_start())
+                .into_iter::<JsonRpcId>()
+                .next()
+                .and_then(|id| id.ok());
+        }
+    }
+    None
+}
@@ -0,0 +1,130 @@
+//! In-process client for driving an [`McpServer`] without a transport.
+//!
+//! [`InProcessClient`] sends requests straight to
+//! [`McpServer::handle_message`], so a full `initialize` → `tools/list` →
+//! `tools/call` exchange runs in the test process with no subprocess or
+//! stdio involved. Combined with [`McpServer::with_registry`] and mock tools
+//! this needs no browser either.
+//!
+//! ```no_run
+//! # async fn example() {
+//! use webpuppet_mcp::harness::InProcessClient;
+//! use webpuppet_mcp::McpServer;
+//!
+//! let server = McpServer::new();
+//! let client = InProcessClient::new(&server);
+//! client.initialize().await.unwrap();
+//! let tools = client.list_tools().await.unwrap();
+//! let result = client
+//!     .call_tool("webpuppet_list_providers", serde_json::json!({}))
+//!     .await
+//!     .unwrap();
+//! # }
+//! ```
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+use crate::error::codes;
+use crate::protocol::{
+    InitializeResult, JsonRpcError, ListToolsResult, ToolCallResult, ToolDefinition,
+};
+use crate::server::{McpServer, PROTOCOL_VERSION};
+
+/// Result of an in-process request: the `result` or the JSON-RPC `error`.
+pub type ClientResult<T> = std::result::Result<T, JsonRpcError>;
+
+/// Minimal MCP client talking to a server in the same process.
+pub struct InProcessClient<'a> {
+    server: &'a McpServer,
+    next_id: AtomicU64,
+}
+
+impl<'a> InProcessClient<'a> {
+    /// Create a client for `server`.
+    pub fn new(server: &'a McpServer) -> Self {
+        Self {
+            server,
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Send a request and return its raw result.
+    pub async fn request(&self, method: &str, params: Value) -> ClientResult<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let message = json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params
+        });
+
+        let response = self
+            .server
+            .handle_message(&message.to_string())
+            .await
+            .ok_or_else(|| local_error(format!("no response to {}", method)))?;
+
+        match (response.result, response.error) {
+            (_, Some(error)) => Err(error),
+            (Some(result), None) => Ok(result),
+            (None, None) => Err(local_error(format!("empty response to {}", method))),
+        }
+    }
+
+    /// Send a notification (no response is expected).
+    pub async fn notify(&self, method: &str, params: Value) {
+        let message = json!({
+            "jsonrpc": "2.0",
+            "method": method,
+            "params": params
+        });
+        self.server.handle_message(&message.to_string()).await;
+    }
+
+    /// Perform the `initialize` handshake, including the `initialized`
+    /// notification.
+    pub async fn initialize(&self) -> ClientResult<InitializeResult> {
+        let result = self
+            .request(
+                "initialize",
+                json!({
+                    "protocolVersion": PROTOCOL_VERSION,
+                    "capabilities": {},
+                    "clientInfo": {"name": "in-process", "version": env!("CARGO_PKG_VERSION")}
+                }),
+            )
+            .await?;
+        self.notify("notifications/initialized", json!({})).await;
+        parse(result)
+    }
+
+    /// List the server's tools.
+    pub async fn list_tools(&self) -> ClientResult<Vec<ToolDefinition>> {
+        let result: ListToolsResult = parse(self.request("tools/list", json!({})).await?)?;
+        Ok(result.tools)
+    }
+
+    /// Call a tool by name.
+    pub async fn call_tool(&self, name: &str, arguments: Value) -> ClientResult<ToolCallResult> {
+        parse(
+            self.request("tools/call", json!({"name": name, "arguments": arguments}))
+                .await?,
+        )
+    }
+}
+
+fn parse<T: DeserializeOwned>(value: Value) -> ClientResult<T> {
+    serde_json::from_value(value).map_err(|e| local_error(format!("unexpected result: {}", e)))
+}
+
+/// Error for problems detected on the client side.
+fn local_error(message: String) -> JsonRpcError {
+    JsonRpcError {
+        code: codes::INTERNAL_ERROR,
+        message,
+        data: None,
+    }
+}
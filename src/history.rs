@@ -0,0 +1,151 @@
+//! Bounded log of recent browser actions.
+//!
+//! Tools record what they did (navigations, prompts, screenshots) in the
+//! session's [`ActionHistory`] so an assistant can check what already
+//! happened instead of repeating steps. Entries are redacted when they are
+//! recorded, with the same rules as the audit log, so secrets in URLs or
+//! prompts are never kept.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use webpuppet::Provider;
+
+use crate::redact::Redactor;
+
+/// Default number of actions kept per session.
+pub const DEFAULT_HISTORY_CAPACITY: usize = 100;
+
+/// Characters of a prompt kept in its history entry.
+const PROMPT_PREVIEW_CHARS: usize = 80;
+
+/// Something a tool did in the browser.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Action {
+    /// The browsing tab was navigated.
+    Navigate {
+        /// URL navigated to.
+        url: String,
+    },
+    /// A prompt was sent to a provider.
+    Prompt {
+        /// Provider name.
+        provider: String,
+        /// Start of the prompt message.
+        message: String,
+    },
+    /// A screenshot was taken.
+    Screenshot {
+        /// URL of the captured page.
+        url: String,
+    },
+}
+
+impl Action {
+    /// A prompt, with its message shortened for the history.
+    pub fn prompt(provider: Provider, message: &str) -> Self {
+        let mut preview: String = message.chars().take(PROMPT_PREVIEW_CHARS).collect();
+        if message.chars().count() > PROMPT_PREVIEW_CHARS {
+            preview.push('…');
+        }
+        Action::Prompt {
+            provider: provider.name().to_string(),
+            message: preview,
+        }
+    }
+
+    /// Short human-readable description.
+    pub fn describe(&self) -> String {
+        match self {
+            Action::Navigate { url } => format!("navigated to {}", url),
+            Action::Prompt { provider, message } => {
+                format!("prompted {}: \"{}\"", provider, message)
+            }
+            Action::Screenshot { url } => format!("took a screenshot of {}", url),
+        }
+    }
+
+    fn redacted(self, redactor: &Redactor) -> Self {
+        match self {
+            Action::Navigate { url } => Action::Navigate {
+                url: redactor.redact_text(&url),
+            },
+            Action::Prompt { provider, message } => Action::Prompt {
+                provider,
+                message: redactor.redact_text(&message),
+            },
+            Action::Screenshot { url } => Action::Screenshot {
+                url: redactor.redact_text(&url),
+            },
+        }
+    }
+}
+
+/// A recorded action.
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryEntry {
+    /// When the action completed.
+    pub at: DateTime<Utc>,
+    /// What was done.
+    #[serde(flatten)]
+    pub action: Action,
+}
+
+/// The most recent actions of a session, oldest first.
+#[derive(Debug)]
+pub struct ActionHistory {
+    entries: Mutex<VecDeque<HistoryEntry>>,
+    capacity: usize,
+}
+
+impl ActionHistory {
+    /// Create a history keeping at most `capacity` actions.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: Mutex::new(VecDeque::new()),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Record an action, redacting it first and dropping the oldest entry
+    /// if the history is full.
+    pub fn record(&self, action: Action, redactor: &Redactor) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(HistoryEntry {
+            at: Utc::now(),
+            action: action.redacted(redactor),
+        });
+    }
+
+    /// Up to `limit` most recent entries, oldest first.
+    pub fn recent(&self, limit: usize) -> Vec<HistoryEntry> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .iter()
+            .skip(entries.len().saturating_sub(limit))
+            .cloned()
+            .collect()
+    }
+
+    /// Number of entries recorded (up to the capacity).
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    /// Whether nothing has been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for ActionHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_HISTORY_CAPACITY)
+    }
+}
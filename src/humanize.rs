@@ -0,0 +1,133 @@
+//! Human-like pacing of typing and clicking.
+//!
+//! Some provider sites flag text that appears in a field all at once, or
+//! clicks that land the instant a page is ready, as automation. With
+//! `--humanize`, `type` steps enter their text one key at a time with a
+//! random pause before each key, and `click` steps pause briefly before
+//! clicking. The pauses are drawn from configurable ranges and scaled down
+//! when they would take more than half the tool timeout, so a long text
+//! can't make the call time out. Off by default, since it slows every step.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Default pause before each typed key.
+pub const DEFAULT_KEYSTROKE_DELAY: DelayRange = DelayRange::from_millis(40, 140);
+
+/// Default pause before a click.
+pub const DEFAULT_CLICK_PAUSE: DelayRange = DelayRange::from_millis(150, 450);
+
+/// Bounds of a random pause.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DelayRange {
+    /// Shortest pause.
+    #[serde(with = "humantime_serde")]
+    pub min: Duration,
+    /// Longest pause.
+    #[serde(with = "humantime_serde")]
+    pub max: Duration,
+}
+
+impl DelayRange {
+    /// A range from `min` to `max` milliseconds.
+    pub const fn from_millis(min: u64, max: u64) -> Self {
+        Self {
+            min: Duration::from_millis(min),
+            max: Duration::from_millis(max),
+        }
+    }
+
+    /// A random pause within the range.
+    pub fn sample(&self) -> Duration {
+        if self.max <= self.min {
+            return self.min;
+        }
+        rand::thread_rng().gen_range(self.min..=self.max)
+    }
+}
+
+impl FromStr for DelayRange {
+    type Err = String;
+
+    /// Parse `MIN-MAX` (or a single value) in milliseconds.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let millis = |part: &str| {
+            part.trim()
+                .parse::<u64>()
+                .map_err(|_| format!("invalid delay '{}' (expected MIN-MAX milliseconds)", s))
+        };
+        let (min, max) = match s.split_once('-') {
+            Some((min, max)) => (millis(min)?, millis(max)?),
+            None => (millis(s)?, millis(s)?),
+        };
+        if min > max {
+            return Err(format!("invalid delay '{}': MIN is above MAX", s));
+        }
+        Ok(Self::from_millis(min, max))
+    }
+}
+
+impl fmt::Display for DelayRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.min.as_millis(), self.max.as_millis())
+    }
+}
+
+/// Whether and how to pace typing and clicking.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HumanizeConfig {
+    /// Pace `type` and `click` steps (default: false).
+    pub enabled: bool,
+    /// Pause before each typed key.
+    pub keystroke_delay: DelayRange,
+    /// Pause before a click.
+    pub click_pause: DelayRange,
+}
+
+impl Default for HumanizeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            keystroke_delay: DEFAULT_KEYSTROKE_DELAY,
+            click_pause: DEFAULT_CLICK_PAUSE,
+        }
+    }
+}
+
+impl HumanizeConfig {
+    /// Pauses before each of `keys` typed keys, scaled down to fit in
+    /// `budget` altogether. Empty when humanizing is off.
+    pub fn keystroke_delays(&self, keys: usize, budget: Duration) -> Vec<Duration> {
+        if !self.enabled {
+            return Vec::new();
+        }
+        let delays: Vec<Duration> = (0..keys).map(|_| self.keystroke_delay.sample()).collect();
+        fit(delays, budget)
+    }
+
+    /// Pause before a click, at most `budget`; zero when humanizing is off.
+    pub fn click_pause(&self, budget: Duration) -> Duration {
+        if !self.enabled {
+            return Duration::ZERO;
+        }
+        self.click_pause.sample().min(budget)
+    }
+}
+
+/// Scale `delays` down proportionally if together they exceed `budget`.
+fn fit(delays: Vec<Duration>, budget: Duration) -> Vec<Duration> {
+    let total: Duration = delays.iter().sum();
+    if total <= budget {
+        return delays;
+    }
+    let scale = budget.as_secs_f64() / total.as_secs_f64();
+    delays
+        .into_iter()
+        .map(|delay| delay.mul_f64(scale))
+        .collect()
+}
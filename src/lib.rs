@@ -19,6 +19,7 @@
 //!
 //! - `webpuppet_prompt`: Send prompts to AI providers (Claude, Grok, Gemini, ChatGPT, Perplexity, NotebookLM, Kaggle)
 //! - `webpuppet_screenshot`: Take screenshots of web pages
+//! - `webpuppet_screenshot_element`: Capture a single element of the current page
 //! - `webpuppet_navigate`: Navigate browser to a specific URL
 //! - `webpuppet_browser_status`: Get current browser session status and page info
 //! - `webpuppet_list_providers`: List available AI providers
@@ -27,8 +28,37 @@
 //! - `webpuppet_check_permission`: Check if an operation is allowed by permission policy
 //! - `webpuppet_intervention_status`: Check if human intervention is needed
 //! - `webpuppet_intervention_complete`: Signal completion of manual intervention
+//! - `webpuppet_wait_for_intervention`: Wait until a human finishes an intervention
 //! - `webpuppet_pause`: Pause automation for manual interaction
 //! - `webpuppet_resume`: Resume automation after pause
+//! - `webpuppet_screen_text`: Screen arbitrary text for prompt injections and hidden content
+//! - `webpuppet_login_status`: Check whether the browser profile is logged in to a provider
+//! - `webpuppet_reset`: Close the browser and clear intervention state
+//! - `webpuppet_fill_form`: Fill several form fields of the current page at once
+//! - `webpuppet_clear_storage`: Clear the current site's cookies and storage
+//! - `webpuppet_set_headers`: Add or clear HTTP headers on the page's requests
+//! - `webpuppet_run_sequence`: Run several primitive browser steps in one call
+//! - `webpuppet_config`: Report the configuration in effect
+//! - `webpuppet_provider_url`: Get the URL the browser opens for a provider
+//! - `webpuppet_read_response`: Read a provider's latest response while it is generated
+//! - `webpuppet_get_content`: Return the current page's content as text, markdown or HTML
+//! - `webpuppet_clear_sessions`: Delete the browser data persisted in `--session-dir`
+//! - `webpuppet_list_conversations`: List the provider conversations open in this session
+//! - `webpuppet_close_conversation`: Drop a conversation from the list
+//! - `webpuppet_console_log`: Console messages and uncaught errors of the current page
+//! - `webpuppet_export_audit`: Export the permission audit log
+//! - `webpuppet_list_accounts` / `webpuppet_switch_account`: List and switch a provider's signed-in accounts
+//! - `webpuppet_network_log`: List network requests made by the current page
+//! - `webpuppet_set_visibility`: Switch the browser between headless and visible mode
+//! - `webpuppet_find`: Find elements on the page by CSS selector or text
+//! - `webpuppet_use_profile`: Relaunch the browser with another of its profiles
+//! - `webpuppet_describe_page`: Describe the page as an accessibility tree
+//! - `webpuppet_compare_providers`: Send one prompt to several providers and compare the responses
+//! - `webpuppet_summarize_page`: Open a page and have a provider summarize it
+//! - `webpuppet_history`: List this session's recent navigations, prompts and screenshots
+//! - `webpuppet_set_viewport`: Set the viewport used for pages and screenshots
+//! - `webpuppet_back` / `webpuppet_forward` / `webpuppet_reload`: Move through the browsing tab's history
+//! - `webpuppet_download`: Download a linked file into the download directory
 //!
 //! ## Usage with VS Code
 //!
@@ -53,15 +83,45 @@
 //! - Only allowed domains can be accessed
 //! - Responses are screened for prompt injections
 //! - All operations are audit logged
+//! - Cookies, tokens, passwords and prompt bodies are redacted from logs
 
 #![warn(missing_docs)]
 #![warn(clippy::all)]
 
+pub mod audit;
+pub mod blocklist;
+pub mod browser;
+pub mod call;
+pub mod capture;
+pub mod clients;
+pub mod coalesce;
+pub mod config;
+pub mod confirm;
+pub mod console;
+pub mod conversations;
+pub mod diagnose;
 pub mod error;
+pub mod framing;
+pub mod harness;
+pub mod history;
+pub mod humanize;
+pub mod logging;
+pub mod manifest;
+pub mod navigation;
+pub mod notify;
+pub mod outbound;
+pub mod plain;
+pub mod progress;
 pub mod protocol;
+pub mod protolog;
+pub mod ratelimit;
+pub mod redact;
+pub mod render;
+pub mod roots;
 pub mod server;
 pub mod tools;
 
+pub use config::ServerConfig;
 pub use error::{Error, Result};
 pub use protocol::{JsonRpcRequest, JsonRpcResponse, McpMessage};
 pub use server::McpServer;
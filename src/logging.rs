@@ -0,0 +1,69 @@
+//! Formatting of the server's own diagnostics.
+//!
+//! Diagnostics go to stderr (stdout carries the protocol) either as
+//! human-readable lines or, with `--log-format json`, as one JSON object per
+//! line for log aggregators. Tool calls run inside a `tool_call` span with
+//! `request_id` and `tool` fields, which JSON lines carry under `span`:
+//!
+//! ```json
+//! {"timestamp":"...","level":"ERROR","fields":{"message":"Tool webpuppet_navigate failed: ..."},"target":"webpuppet_mcp::server","span":{"request_id":"4","tool":"webpuppet_navigate","name":"tool_call"},"spans":[...]}
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+
+use tracing::Subscriber;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// Output format of diagnostics.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human-readable lines (the default).
+    #[default]
+    Text,
+    /// One JSON object per line, including the fields of enclosing spans.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            other => Err(format!(
+                "unknown log format '{}' (expected text or json)",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Text => "text",
+            Self::Json => "json",
+        })
+    }
+}
+
+/// A `fmt` layer writing diagnostics in `format` to `writer`.
+pub fn fmt_layer<S, W>(format: LogFormat, writer: W) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    match format {
+        LogFormat::Text => tracing_subscriber::fmt::layer().with_writer(writer).boxed(),
+        LogFormat::Json => tracing_subscriber::fmt::layer()
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .with_writer(writer)
+            .boxed(),
+    }
+}
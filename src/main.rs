@@ -4,12 +4,24 @@
 //! to AI assistants like GitHub Copilot and Claude Desktop.
 
 use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Duration;
 
 use clap::Parser;
-use tracing_subscriber::{fmt, prelude::*, EnvFilter};
+use tracing_subscriber::{prelude::*, EnvFilter};
 
-use webpuppet::{PermissionGuard, PermissionPolicy};
-use webpuppet_mcp::McpServer;
+use webpuppet::{PermissionGuard, PermissionPolicy, Provider};
+use webpuppet_mcp::blocklist::Blocklist;
+use webpuppet_mcp::browser::{parse_browser, validate_timezone, BrowserIdentity};
+use webpuppet_mcp::clients::ClientWorkarounds;
+use webpuppet_mcp::config::{ProxyConfig, ResultFormat, ScreeningMode};
+use webpuppet_mcp::humanize::{DelayRange, DEFAULT_CLICK_PAUSE, DEFAULT_KEYSTROKE_DELAY};
+use webpuppet_mcp::logging::{self, LogFormat};
+use webpuppet_mcp::manifest::ToolsManifest;
+use webpuppet_mcp::protolog::ProtocolLog;
+use webpuppet_mcp::redact::{RedactingMakeWriter, Redactor};
+use webpuppet_mcp::tools::parse_provider;
+use webpuppet_mcp::{McpServer, ServerConfig, ToolRegistry};
 
 /// MCP server for webpuppet browser automation.
 #[derive(Parser, Debug)]
@@ -29,6 +41,188 @@ struct Args {
     #[arg(long)]
     visible: bool,
 
+    /// Provider used when a tool call doesn't specify one
+    /// (claude, grok, gemini, chatgpt, perplexity, notebooklm, kaggle).
+    #[arg(long, default_value = "grok")]
+    default_provider: String,
+
+    /// Browser to launch (brave, chrome, chromium, edge, opera, vivaldi).
+    /// Defaults to the system default browser, then the first installed of
+    /// Brave, Chrome, Chromium, Edge, Opera and Vivaldi.
+    #[arg(long)]
+    browser: Option<String>,
+
+    /// Notifications buffered for a slow client before the oldest are dropped.
+    #[arg(long, default_value_t = webpuppet_mcp::outbound::DEFAULT_NOTIFICATION_CAPACITY)]
+    notification_buffer: usize,
+
+    /// Minimum interval between prompts to the same provider, in milliseconds.
+    #[arg(long, default_value_t = 2000)]
+    provider_min_interval_ms: u64,
+
+    /// Minimum delay between navigations to the same site, as
+    /// `domain=millis` (comma-separated, e.g. `example.com=2000,*=500`). A
+    /// domain covers its subdomains; `*` applies to every other site.
+    #[arg(long, value_delimiter = ',', value_name = "DOMAIN=MS")]
+    domain_delay: Vec<String>,
+
+    /// Maximum time a single tool call may take, in seconds.
+    #[arg(long, default_value_t = 300)]
+    tool_timeout_secs: u64,
+
+    /// Don't reuse answers of read-only tools (detected browsers, providers,
+    /// declared capabilities) for a minute.
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Send a heartbeat notification this often, in seconds, during long tool
+    /// calls and human interventions (0 disables).
+    #[arg(long, default_value_t = 0)]
+    heartbeat_secs: u64,
+
+    /// Ping the client this often, in seconds, and stop serving if it doesn't
+    /// answer within `--ping-timeout-secs` (0 disables).
+    #[arg(long, default_value_t = 0)]
+    ping_secs: u64,
+
+    /// Seconds the client gets to answer a ping.
+    #[arg(long, default_value_t = 30)]
+    ping_timeout_secs: u64,
+
+    /// Format of the human-readable text of tool results: markdown, or plain
+    /// for hosts that don't render markdown (no headings, bold or emoji).
+    /// Defaults to markdown, and to plain with `--once`.
+    #[arg(long)]
+    result_format: Option<ResultFormat>,
+
+    /// Workarounds for a client, by the name it gives in `initialize`, as
+    /// `name=options` with options `plain`, `markdown` and
+    /// `max-result-bytes:N` (e.g. `acme-desktop=plain,max-result-bytes:20000`).
+    /// Overrides the built-in workarounds for that client. Repeatable.
+    #[arg(long, value_name = "NAME=OPTIONS")]
+    client_workaround: Vec<String>,
+
+    /// Truncate tool results (text, images and structured content) beyond this many bytes of JSON (0 disables).
+    #[arg(long, default_value_t = 0)]
+    max_result_bytes: usize,
+
+    /// Reject incoming messages larger than this many bytes without
+    /// buffering them (0 disables).
+    #[arg(long, default_value_t = webpuppet_mcp::framing::DEFAULT_MAX_MESSAGE_BYTES)]
+    max_message_bytes: usize,
+
+    /// Seconds a provider may take to authenticate before a prompt asks for
+    /// a manual login, as `provider=secs` (comma-separated, e.g.
+    /// `claude=60,gemini=90`). Unlisted providers keep their defaults.
+    #[arg(long, value_delimiter = ',', value_name = "PROVIDER=SECS")]
+    auth_timeout: Vec<String>,
+
+    /// Launch the browser at startup, in the background, so the first tool
+    /// call doesn't wait for it (skipped if the policy denies navigation).
+    #[arg(long)]
+    prewarm: bool,
+
+    /// Open every provider's session at launch, this many at once (0 opens
+    /// each on first use). Providers that fail to open are left out.
+    #[arg(long, default_value_t = 0)]
+    provider_init_concurrency: usize,
+
+    /// Ask a human to confirm navigations, clicks and typing that look
+    /// destructive (delete, pay, log out...) before they run.
+    #[arg(long)]
+    confirm_destructive: bool,
+
+    /// What to do with provider responses that fail screening: annotate
+    /// (show them after a warning) or block (withhold them and fail the call).
+    #[arg(long, default_value_t = ScreeningMode::Annotate)]
+    screening_mode: ScreeningMode,
+
+    /// Type one key at a time and pause before clicks, with random delays,
+    /// for sites that flag instant programmatic input.
+    #[arg(long)]
+    humanize: bool,
+
+    /// Range of the random pause before each typed key with `--humanize`,
+    /// in milliseconds.
+    #[arg(long, value_name = "MIN-MAX", default_value_t = DEFAULT_KEYSTROKE_DELAY)]
+    keystroke_delay_ms: DelayRange,
+
+    /// Range of the random pause before a click with `--humanize`, in
+    /// milliseconds.
+    #[arg(long, value_name = "MIN-MAX", default_value_t = DEFAULT_CLICK_PAUSE)]
+    click_pause_ms: DelayRange,
+
+    /// Close the browser after this many seconds without use (0 keeps it open).
+    #[arg(long, default_value_t = 600)]
+    browser_idle_timeout_secs: u64,
+
+    /// Only accept these JSON-RPC methods (comma-separated, e.g.
+    /// `tools/list,ping`); `initialize` is always accepted.
+    #[arg(long, value_delimiter = ',')]
+    allowed_methods: Vec<String>,
+
+    /// Disable these tools entirely, whatever the permission policy allows
+    /// (comma-separated).
+    #[arg(long, value_delimiter = ',')]
+    denied_tools: Vec<String>,
+
+    /// Handle this one JSON-RPC request, print the response and exit, instead
+    /// of serving stdio (`@path` reads the request from a file). `jsonrpc`
+    /// and `id` may be omitted.
+    #[arg(long, value_name = "REQUEST")]
+    once: Option<String>,
+
+    /// Route browser traffic through this proxy (`http://`, `https://`,
+    /// `socks4://` or `socks5://` URL, e.g. `socks5://127.0.0.1:1080`).
+    #[arg(long, value_name = "URL")]
+    proxy: Option<String>,
+
+    /// Hosts to reach directly instead of through `--proxy`
+    /// (comma-separated, e.g. `localhost,*.internal`).
+    #[arg(long, value_delimiter = ',', requires = "proxy")]
+    proxy_bypass: Vec<String>,
+
+    /// User agent the browser presents instead of its own.
+    #[arg(long)]
+    user_agent: Option<String>,
+
+    /// Locale the browser presents (UI language and `Accept-Language`),
+    /// e.g. `en-US`.
+    #[arg(long)]
+    locale: Option<String>,
+
+    /// IANA timezone the browser runs in, e.g. `Europe/Berlin`.
+    #[arg(long)]
+    timezone: Option<String>,
+
+    /// Directory `webpuppet_download` saves files to (default:
+    /// `webpuppet-mcp-downloads` in the system temp directory).
+    #[arg(long, value_name = "DIR")]
+    download_dir: Option<std::path::PathBuf>,
+
+    /// Persist browser data (cookies, storage, logins) in this directory so
+    /// logins survive restarts. It holds session tokens unencrypted; opt in
+    /// only on a machine you trust. `webpuppet_clear_sessions` wipes it.
+    #[arg(long, value_name = "DIR")]
+    session_dir: Option<std::path::PathBuf>,
+
+    /// Largest file `webpuppet_download` accepts, in bytes.
+    #[arg(long, default_value_t = webpuppet_mcp::config::DEFAULT_MAX_DOWNLOAD_BYTES)]
+    max_download_bytes: u64,
+
+    /// Refuse to navigate to URLs or click/type into selectors matching the
+    /// patterns in this JSON file, whatever the policy allows.
+    #[arg(long, value_name = "PATH")]
+    blocklist: Option<String>,
+
+    /// Register the declarative tools described in this JSON manifest.
+    #[arg(long, value_name = "PATH")]
+    tools_manifest: Option<String>,
+
+    /// List every tool, including those the permission policy denies.
+    #[arg(long)]
+    show_all_tools: bool,
+
     /// Enable verbose logging.
     #[arg(short, long)]
     verbose: bool,
@@ -36,12 +230,52 @@ struct Args {
     /// Log output file (default: stderr).
     #[arg(long)]
     log_file: Option<String>,
+
+    /// Diagnostics format: text or json (one object per line, with the
+    /// request id and tool name of the call being served).
+    #[arg(long, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+
+    /// Record every raw protocol message, in both directions, to this JSONL
+    /// file (not redacted).
+    #[arg(long)]
+    protocol_log: Option<String>,
+
+    /// Include full prompt bodies in debug logs (redacted by default).
+    #[arg(long)]
+    log_prompts: bool,
+
+    /// Additional field names to redact from logs (comma-separated).
+    #[arg(long, value_delimiter = ',')]
+    redact_fields: Vec<String>,
 }
 
-#[tokio::main]
-async fn main() -> ExitCode {
+fn main() -> ExitCode {
     let args = Args::parse();
 
+    // The browser has no timezone flag; it inherits TZ from this process.
+    // Set it before the runtime starts any threads. An invalid timezone is
+    // left out and reported once logging is set up.
+    if let Some(ref timezone) = args.timezone {
+        if validate_timezone(timezone).is_ok() {
+            std::env::set_var("TZ", timezone);
+        }
+    }
+
+    match tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+    {
+        Ok(runtime) => runtime.block_on(run(args)),
+        Err(e) => {
+            eprintln!("Cannot start the async runtime: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Configure and run the server as `args` ask.
+async fn run(args: Args) -> ExitCode {
     // Set up logging
     let filter = if args.verbose {
         EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("debug"))
@@ -49,10 +283,51 @@ async fn main() -> ExitCode {
         EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"))
     };
 
-    // Log to stderr (not stdout, which is used for MCP protocol)
+    let mut config = ServerConfig::default();
+    config.redaction.log_prompts = args.log_prompts;
+    config.redaction.fields.extend(args.redact_fields);
+    config.notification_capacity = args.notification_buffer;
+    config.provider_min_interval = Duration::from_millis(args.provider_min_interval_ms);
+    config.tool_timeout = Duration::from_secs(args.tool_timeout_secs);
+    config.show_all_tools = args.show_all_tools;
+    if args.no_cache {
+        config.read_cache_ttl = Duration::ZERO;
+    }
+    config.browser_idle_timeout = Duration::from_secs(args.browser_idle_timeout_secs);
+    config.prewarm = args.prewarm;
+    config.provider_init_concurrency = args.provider_init_concurrency;
+    config.confirm_destructive = args.confirm_destructive;
+    config.screening_mode = args.screening_mode;
+    config.humanize.enabled = args.humanize;
+    config.humanize.keystroke_delay = args.keystroke_delay_ms;
+    config.humanize.click_pause = args.click_pause_ms;
+    config.heartbeat_interval = Duration::from_secs(args.heartbeat_secs);
+    config.ping_interval = Duration::from_secs(args.ping_secs);
+    config.ping_timeout = Duration::from_secs(args.ping_timeout_secs);
+    config.result_format = args
+        .result_format
+        .unwrap_or_else(|| default_result_format(args.once.is_some()));
+    config.max_result_bytes = args.max_result_bytes;
+    config.max_message_bytes = args.max_message_bytes;
+    config.session_dir = args.session_dir;
+    if let Some(dir) = args.download_dir {
+        config.download_dir = dir;
+    }
+    config.max_download_bytes = args.max_download_bytes;
+    if !args.allowed_methods.is_empty() {
+        config.allowed_methods = Some(args.allowed_methods);
+    }
+    config.denied_tools = args.denied_tools;
+
+    // Log to stderr (not stdout, which is used for MCP protocol), scrubbing
+    // secrets from every line including those emitted by webpuppet itself
+    let redactor = Arc::new(Redactor::new(&config.redaction));
     tracing_subscriber::registry()
         .with(filter)
-        .with(fmt::layer().with_writer(std::io::stderr))
+        .with(logging::fmt_layer(
+            args.log_format,
+            RedactingMakeWriter::new(std::io::stderr, redactor),
+        ))
         .init();
 
     tracing::info!(
@@ -62,25 +337,165 @@ async fn main() -> ExitCode {
     );
 
     // Set up permissions
-    let permissions = match args.policy.to_lowercase().as_str() {
+    config.policy = args.policy.to_lowercase();
+    let permissions = match config.policy.as_str() {
         "secure" => PermissionGuard::new(PermissionPolicy::secure()),
         "permissive" => PermissionGuard::new(PermissionPolicy::permissive()),
         "readonly" => PermissionGuard::new(PermissionPolicy::read_only()),
         _ => {
             tracing::error!("Unknown policy: {}. Using 'secure'.", args.policy);
+            config.policy = "secure".into();
             PermissionGuard::secure()
         }
     };
 
-    tracing::info!("Using '{}' permission policy", args.policy);
+    tracing::info!("Using '{}' permission policy", config.policy);
 
-    // Create server with visible browser if requested
-    let server = if args.visible {
+    // Show the browser if requested
+    if args.visible {
         tracing::info!("Browser will be visible (non-headless mode)");
-        McpServer::with_visible_browser(permissions)
-    } else {
-        McpServer::with_permissions(permissions)
+        config.headless = false;
+    }
+
+    config.default_provider = match parse_provider(&args.default_provider) {
+        Ok(provider) => provider,
+        Err(e) => {
+            tracing::error!("Invalid --default-provider: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    tracing::info!("Default provider: {}", config.default_provider);
+
+    if let Some(ref browser) = args.browser {
+        config.browser = match parse_browser(browser) {
+            Ok(browser) => Some(browser),
+            Err(e) => {
+                tracing::error!("Invalid --browser: {}", e);
+                return ExitCode::FAILURE;
+            }
+        };
+    }
+
+    for spec in &args.client_workaround {
+        match parse_client_workaround(spec) {
+            Ok((client, workarounds)) => {
+                tracing::debug!("Workarounds for client {}: {}", client, workarounds);
+                config.client_workarounds.insert(client, workarounds);
+            }
+            Err(e) => {
+                tracing::error!("Invalid --client-workaround: {}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    for spec in &args.domain_delay {
+        match parse_domain_delay(spec) {
+            Ok((domain, delay)) => {
+                tracing::debug!("Navigation delay for {}: {:?}", domain, delay);
+                config.domain_delays.insert(domain, delay);
+            }
+            Err(e) => {
+                tracing::error!("Invalid --domain-delay: {}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    for spec in &args.auth_timeout {
+        match parse_auth_timeout(spec) {
+            Ok((provider, timeout)) => {
+                tracing::debug!("Authentication timeout for {}: {:?}", provider, timeout);
+                config.auth_timeouts.insert(provider, timeout);
+            }
+            Err(e) => {
+                tracing::error!("Invalid --auth-timeout: {}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    if let Some(ref proxy) = args.proxy {
+        match ProxyConfig::new(proxy, args.proxy_bypass.clone()) {
+            Ok(proxy) => {
+                tracing::info!("Browser proxy: {}", proxy.host());
+                config.proxy = Some(proxy);
+            }
+            Err(e) => {
+                tracing::error!("Invalid --proxy: {}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    let identity = BrowserIdentity {
+        user_agent: args.user_agent,
+        locale: args.locale,
+        timezone: args.timezone,
     };
+    if let Err(e) = identity.validate() {
+        tracing::error!("Invalid browser identity: {}", e);
+        return ExitCode::FAILURE;
+    }
+    if let Some(ref timezone) = identity.timezone {
+        // Already set as TZ by `main`, before the runtime started
+        tracing::info!("Browser timezone: {}", timezone);
+    }
+    config.identity = identity;
+
+    if let Some(ref path) = args.blocklist {
+        match Blocklist::load(path) {
+            Ok(blocklist) => {
+                tracing::info!("Loaded blocklist from {}", path);
+                config.blocklist = blocklist;
+            }
+            Err(e) => {
+                tracing::error!("Invalid --blocklist: {}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    if let Some(ref methods) = config.allowed_methods {
+        tracing::info!("Allowed methods: {}", methods.join(", "));
+    }
+    if !config.denied_tools.is_empty() {
+        tracing::info!("Disabled tools: {}", config.denied_tools.join(", "));
+    }
+
+    if args.log_prompts {
+        tracing::warn!("Prompt bodies will be included in debug logs");
+    }
+
+    let mut tools = ToolRegistry::with_config(permissions, config);
+    if let Some(ref path) = args.tools_manifest {
+        match ToolsManifest::load(path).and_then(|manifest| tools.register_manifest(manifest)) {
+            Ok(count) => tracing::info!("Registered {} tools from {}", count, path),
+            Err(e) => {
+                tracing::error!("Invalid --tools-manifest: {}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    let mut server = McpServer::with_registry(tools);
+
+    if let Some(ref path) = args.protocol_log {
+        match ProtocolLog::create(path) {
+            Ok(log) => {
+                tracing::warn!("Logging raw protocol traffic (unredacted) to {}", path);
+                server = server.with_protocol_log(log);
+            }
+            Err(e) => {
+                tracing::error!("Cannot open --protocol-log {}: {}", path, e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    if let Some(ref once) = args.once {
+        return run_once(&server, once).await;
+    }
 
     if args.stdio {
         match server.run_stdio().await {
@@ -98,3 +513,94 @@ async fn main() -> ExitCode {
         ExitCode::FAILURE
     }
 }
+
+/// Result format when `--result-format` isn't given: plain with `--once`,
+/// which prints its response to a terminal or script where markdown isn't
+/// rendered, markdown otherwise.
+fn default_result_format(once: bool) -> ResultFormat {
+    if once {
+        ResultFormat::Plain
+    } else {
+        ResultFormat::Markdown
+    }
+}
+
+/// Handle a single request given on the command line (or in a file, with
+/// `@path`) and print its response. Fails if the request did.
+///
+/// Tool results are plain text unless `--result-format` says otherwise
+/// (see [`default_result_format`]).
+async fn run_once(server: &McpServer, request: &str) -> ExitCode {
+    let request = match request.strip_prefix('@') {
+        Some(path) => match std::fs::read_to_string(path) {
+            Ok(request) => request,
+            Err(e) => {
+                tracing::error!("Cannot read request from {}: {}", path, e);
+                return ExitCode::FAILURE;
+            }
+        },
+        None => request.to_string(),
+    };
+
+    match server.handle_once(&request).await {
+        Ok(response) => {
+            let failed = response.error.is_some();
+            match serde_json::to_string(&response) {
+                Ok(json) => println!("{}", json),
+                Err(e) => {
+                    tracing::error!("Cannot serialize response: {}", e);
+                    return ExitCode::FAILURE;
+                }
+            }
+            if failed {
+                ExitCode::FAILURE
+            } else {
+                ExitCode::SUCCESS
+            }
+        }
+        Err(e) => {
+            tracing::error!("Invalid --once request: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Parse a `name=options` client workaround.
+fn parse_client_workaround(spec: &str) -> Result<(String, ClientWorkarounds), String> {
+    let (client, options) = spec
+        .split_once('=')
+        .ok_or_else(|| format!("expected name=options, got '{}'", spec))?;
+    if client.trim().is_empty() {
+        return Err(format!("'{}' names no client", spec));
+    }
+    Ok((client.trim().to_string(), options.parse()?))
+}
+
+/// Parse a `domain=millis` navigation delay.
+fn parse_domain_delay(spec: &str) -> Result<(String, Duration), String> {
+    let (domain, millis) = spec
+        .split_once('=')
+        .ok_or_else(|| format!("expected domain=millis, got '{}'", spec))?;
+    let domain = domain.trim().to_lowercase();
+    if domain.is_empty() || domain.contains(['/', ':']) {
+        return Err(format!("'{}' is not a domain", domain));
+    }
+    let millis: u64 = millis
+        .trim()
+        .parse()
+        .map_err(|_| format!("'{}' is not a number of milliseconds", millis))?;
+    Ok((domain, Duration::from_millis(millis)))
+}
+
+/// Parse a `provider=secs` authentication timeout.
+fn parse_auth_timeout(spec: &str) -> Result<(Provider, Duration), String> {
+    let (provider, secs) = spec
+        .split_once('=')
+        .ok_or_else(|| format!("expected provider=secs, got '{}'", spec))?;
+    let provider = parse_provider(provider.trim()).map_err(|e| e.to_string())?;
+    let secs: u64 = secs
+        .trim()
+        .parse()
+        .map_err(|_| format!("'{}' is not a number of seconds", secs))?;
+    Ok((provider, Duration::from_secs(secs)))
+}
@@ -0,0 +1,710 @@
+//! Declarative tools loaded from a manifest file.
+//!
+//! `--tools-manifest <path>` adds site-specific tools without forking the
+//! server. Each tool is a name, description and input schema plus a list of
+//! steps built from the primitives below; no code from the manifest is run.
+//! Arguments are substituted into step fields as `{{name}}`:
+//!
+//! ```json
+//! {
+//!   "tools": [{
+//!     "name": "crate_summary",
+//!     "description": "Read the summary of a crate on docs.rs",
+//!     "input_schema": {
+//!       "type": "object",
+//!       "properties": {"crate": {"type": "string"}},
+//!       "required": ["crate"]
+//!     },
+//!     "steps": [
+//!       {"action": "navigate", "url": "https://docs.rs/{{crate}}"},
+//!       {"action": "extract", "selector": ".docblock p", "as": "summary"}
+//!     ]
+//!   }]
+//! }
+//! ```
+//!
+//! Every step goes through the permission guard like the built-in tools do:
+//! `navigate` checks `Navigate` (with the URL), `click` checks `Click`,
+//! `type` checks `TypeText`, `wait_for`, `scroll` and `extract` check
+//! `ReadContent`, and `screenshot` checks `Screenshot`. The same steps can be
+//! run ad hoc with `webpuppet_run_sequence`.
+//!
+//! With `--humanize`, `type` steps enter their text one key at a time and
+//! `click` steps pause first; see [`crate::humanize`].
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use webpuppet::Operation;
+
+use crate::confirm::{confirm, Action as ConfirmAction};
+use crate::error::{Error, Result};
+use crate::history::Action;
+use crate::navigation::normalize_and_validate_url;
+use crate::protocol::{ContentItem, ToolCallResult, ToolDefinition};
+use crate::tools::{Tool, ToolContext};
+
+/// Longest tool name accepted (the limit most MCP hosts enforce).
+const MAX_TOOL_NAME_LEN: usize = 64;
+
+/// How long a `wait_for` step waits by default.
+const DEFAULT_WAIT_MS: u64 = 5_000;
+
+/// Longest a `wait_for` step may wait.
+const MAX_WAIT: Duration = Duration::from_secs(60);
+
+/// How often a `wait_for` step looks for its element.
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Runs a primitive against the first element matching a selector. Called
+/// with the selector, the action (`click`, `type` or `extract`) and its
+/// argument (text to type, or whether to extract every match) as JSON
+/// literals; `scroll` scrolls the element into view, and `keystroke` is a
+/// `type` that doesn't fire `change` (the text typed so far, when typing one
+/// key at a time). Returns `{ value }` or `{ error }`.
+const STEP_SCRIPT: &str = r#"((selector, action, arg) => {
+    let elements;
+    try {
+        elements = Array.from(document.querySelectorAll(selector));
+    } catch (e) {
+        return { error: 'invalid selector: ' + String(e.message || e) };
+    }
+    if (elements.length === 0) {
+        return { error: 'no element matches ' + selector };
+    }
+    const el = elements[0];
+    if (action === 'click') {
+        el.click();
+        return { value: null };
+    }
+    if (action === 'scroll') {
+        el.scrollIntoView({ block: 'center' });
+        return { value: null };
+    }
+    if (action === 'type' || action === 'keystroke') {
+        el.focus();
+        el.value = arg;
+        el.dispatchEvent(new Event('input', { bubbles: true }));
+        if (action === 'type') el.dispatchEvent(new Event('change', { bubbles: true }));
+        return { value: null };
+    }
+    const text = (node) => (node.innerText || node.textContent || '').trim();
+    return { value: arg ? elements.map(text) : text(el) };
+})"#;
+
+/// Additional tools described in a manifest file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ToolsManifest {
+    /// The tools to register.
+    pub tools: Vec<ManifestToolSpec>,
+}
+
+/// One declarative tool.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ManifestToolSpec {
+    /// Tool name, unique among all tools.
+    pub name: String,
+    /// Description shown to the client.
+    pub description: String,
+    /// JSON Schema of the arguments (default: no arguments).
+    #[serde(default = "empty_schema")]
+    pub input_schema: Value,
+    /// Steps run in order on each call.
+    pub steps: Vec<Step>,
+}
+
+/// A primitive browser action.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(tag = "action", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Step {
+    /// Open a URL in the browsing tab.
+    Navigate {
+        /// URL to open.
+        url: String,
+    },
+    /// Click the first element matching a selector.
+    Click {
+        /// CSS selector.
+        selector: String,
+    },
+    /// Fill the first element matching a selector with text.
+    Type {
+        /// CSS selector.
+        selector: String,
+        /// Text to enter.
+        text: String,
+    },
+    /// Read the text of the first (or every) element matching a selector.
+    Extract {
+        /// CSS selector.
+        selector: String,
+        /// Key of the extracted text in the result (default: the selector).
+        #[serde(rename = "as")]
+        key: Option<String>,
+        /// Extract every match as a list instead of the first one.
+        #[serde(default)]
+        all: bool,
+    },
+    /// Wait until an element matching a selector is on the page.
+    WaitFor {
+        /// CSS selector.
+        selector: String,
+        /// Milliseconds to wait before failing (default: 5000, at most 60000).
+        #[serde(default = "default_wait_ms")]
+        timeout_ms: u64,
+    },
+    /// Scroll an element into view, or the page by a number of pixels.
+    Scroll {
+        /// CSS selector of the element to scroll into view.
+        selector: Option<String>,
+        /// Pixels to scroll down (negative: up) when no selector is given.
+        #[serde(default)]
+        y: i64,
+    },
+    /// Capture the visible part of the page as a PNG image.
+    Screenshot,
+}
+
+/// What running a step produced.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StepOutput {
+    /// Nothing to report.
+    None,
+    /// Text read by an `extract` step, under its key.
+    Extracted {
+        /// Key of the text (`as`, or the selector).
+        key: String,
+        /// The text, or a list of texts.
+        value: Value,
+    },
+    /// PNG image captured by a `screenshot` step.
+    Screenshot(Vec<u8>),
+}
+
+fn default_wait_ms() -> u64 {
+    DEFAULT_WAIT_MS
+}
+
+fn empty_schema() -> Value {
+    json!({"type": "object", "properties": {}})
+}
+
+impl Step {
+    /// Operation the permission guard has to allow for this step.
+    pub fn operation(&self) -> Operation {
+        match self {
+            Step::Navigate { .. } => Operation::Navigate,
+            Step::Click { .. } => Operation::Click,
+            Step::Type { .. } => Operation::TypeText,
+            Step::Extract { .. } | Step::WaitFor { .. } | Step::Scroll { .. } => {
+                Operation::ReadContent
+            }
+            Step::Screenshot => Operation::Screenshot,
+        }
+    }
+
+    /// Name of the step's action, as written in JSON.
+    pub fn action(&self) -> &'static str {
+        match self {
+            Step::Navigate { .. } => "navigate",
+            Step::Click { .. } => "click",
+            Step::Type { .. } => "type",
+            Step::Extract { .. } => "extract",
+            Step::WaitFor { .. } => "wait_for",
+            Step::Scroll { .. } => "scroll",
+            Step::Screenshot => "screenshot",
+        }
+    }
+
+    /// Fields that may contain `{{argument}}` placeholders.
+    fn templates(&self) -> Vec<&str> {
+        match self {
+            Step::Navigate { url } => vec![url],
+            Step::Click { selector }
+            | Step::Extract { selector, .. }
+            | Step::WaitFor { selector, .. }
+            | Step::Scroll {
+                selector: Some(selector),
+                ..
+            } => vec![selector],
+            Step::Type { selector, text } => vec![selector, text],
+            Step::Scroll { selector: None, .. } | Step::Screenshot => vec![],
+        }
+    }
+
+    /// This step with the `{{placeholders}}` of its fields replaced by
+    /// argument values.
+    fn substituted(&self, arguments: &Value) -> Step {
+        let sub = |template: &String| substitute(template, arguments);
+        match self {
+            Step::Navigate { url } => Step::Navigate { url: sub(url) },
+            Step::Click { selector } => Step::Click {
+                selector: sub(selector),
+            },
+            Step::Type { selector, text } => Step::Type {
+                selector: sub(selector),
+                text: sub(text),
+            },
+            Step::Extract { selector, key, all } => Step::Extract {
+                selector: sub(selector),
+                key: key.clone(),
+                all: *all,
+            },
+            Step::WaitFor {
+                selector,
+                timeout_ms,
+            } => Step::WaitFor {
+                selector: sub(selector),
+                timeout_ms: *timeout_ms,
+            },
+            Step::Scroll { selector, y } => Step::Scroll {
+                selector: selector.as_ref().map(sub),
+                y: *y,
+            },
+            Step::Screenshot => Step::Screenshot,
+        }
+    }
+
+    /// Run the step in the browsing tab, checking its operation (and URL or
+    /// selector) first.
+    pub async fn run(&self, context: &ToolContext) -> Result<StepOutput> {
+        let operation = self.operation();
+        if let Step::Navigate { url } = self {
+            let url = normalize_and_validate_url(url)?;
+            context
+                .permissions
+                .require_with_url(operation, &url)
+                .map_err(|e| Error::PermissionDenied(e.to_string()))?;
+            context.config.blocklist.check_url(&url)?;
+            confirm(context, ConfirmAction::Navigate(&url)).await?;
+
+            let puppet = context.get_puppet().await?;
+            context.navigate(&puppet, &url).await?;
+            context.record(Action::Navigate { url });
+            return Ok(StepOutput::None);
+        }
+
+        context
+            .permissions
+            .require(operation)
+            .map_err(|e| Error::PermissionDenied(e.to_string()))?;
+
+        let (selector, arg, key) = match self {
+            Step::Click { selector } => (selector, Value::Null, None),
+            Step::Type { selector, text } => (selector, Value::String(text.clone()), None),
+            Step::Extract { selector, key, all } => (
+                selector,
+                Value::Bool(*all),
+                Some(key.clone().unwrap_or_else(|| selector.clone())),
+            ),
+            Step::Scroll {
+                selector: Some(selector),
+                ..
+            } => (selector, Value::Null, None),
+            Step::Scroll { selector: None, y } => {
+                let puppet = context.get_puppet().await?;
+                puppet
+                    .evaluate(&format!("window.scrollBy(0, {}); null", y))
+                    .await?;
+                return Ok(StepOutput::None);
+            }
+            Step::WaitFor {
+                selector,
+                timeout_ms,
+            } => {
+                wait_for(selector, *timeout_ms, context).await?;
+                return Ok(StepOutput::None);
+            }
+            Step::Screenshot => {
+                let puppet = context.get_puppet().await?;
+                let png = puppet.screenshot().await?;
+                let url = puppet.current_url().await.unwrap_or_default();
+                context.record(Action::Screenshot { url });
+                return Ok(StepOutput::Screenshot(png));
+            }
+            Step::Navigate { .. } => unreachable!("handled above"),
+        };
+        let action = self.action();
+        // Humanized pauses may take at most half the call's time
+        let humanize = &context.config.humanize;
+        let budget = context.config.tool_timeout / 2;
+        match self {
+            Step::Click { .. } => {
+                context.config.blocklist.check_selector(selector)?;
+                confirm(context, ConfirmAction::Click(selector)).await?;
+                tokio::time::sleep(humanize.click_pause(budget)).await;
+            }
+            Step::Type { text, .. } => {
+                context.config.blocklist.check_selector(selector)?;
+                confirm(context, ConfirmAction::Type(selector)).await?;
+                if humanize.enabled && !text.is_empty() {
+                    type_keys(context, selector, text, budget).await?;
+                    return Ok(StepOutput::None);
+                }
+            }
+            _ => {}
+        }
+
+        let value = run_script(context, selector, action, &arg).await?;
+        Ok(match key {
+            Some(key) => StepOutput::Extracted { key, value },
+            None => StepOutput::None,
+        })
+    }
+}
+
+/// Run [`STEP_SCRIPT`] with `action` against `selector`, returning its value.
+async fn run_script(
+    context: &ToolContext,
+    selector: &str,
+    action: &str,
+    arg: &Value,
+) -> Result<Value> {
+    let script = format!(
+        "{}({}, {}, {})",
+        STEP_SCRIPT,
+        json!(selector),
+        json!(action),
+        arg
+    );
+
+    let puppet = context.get_puppet().await?;
+    let outcome = puppet.evaluate(&script).await?;
+    if let Some(message) = outcome.get("error").and_then(Value::as_str) {
+        // Keystrokes are reported as the `type` step they belong to
+        let action = if action == "keystroke" {
+            "type"
+        } else {
+            action
+        };
+        return Err(Error::InvalidParams(format!(
+            "{} `{}` failed: {}",
+            action, selector, message
+        )));
+    }
+    Ok(outcome.get("value").cloned().unwrap_or(Value::Null))
+}
+
+/// Type `text` into `selector` one key at a time, pausing before each key
+/// as `--humanize` says. Only the last key fires `change`.
+async fn type_keys(
+    context: &ToolContext,
+    selector: &str,
+    text: &str,
+    budget: Duration,
+) -> Result<()> {
+    let keys = text.chars().count();
+    let delays = context.config.humanize.keystroke_delays(keys, budget);
+    let mut typed = String::with_capacity(text.len());
+    for (index, (key, delay)) in text.chars().zip(delays).enumerate() {
+        tokio::time::sleep(delay).await;
+        typed.push(key);
+        let action = if index + 1 == keys {
+            "type"
+        } else {
+            "keystroke"
+        };
+        run_script(context, selector, action, &Value::String(typed.clone())).await?;
+    }
+    Ok(())
+}
+
+/// Poll the page until an element matches `selector`, for at most
+/// `timeout_ms` (capped at [`MAX_WAIT`]).
+async fn wait_for(selector: &str, timeout_ms: u64, context: &ToolContext) -> Result<()> {
+    let timeout = Duration::from_millis(timeout_ms).min(MAX_WAIT);
+    let script = format!(
+        "(() => {{ try {{ return document.querySelector({}) !== null; }} catch (e) {{ return {{ error: 'invalid selector: ' + String(e.message || e) }}; }} }})()",
+        json!(selector)
+    );
+    let puppet = context.get_puppet().await?;
+    let started = Instant::now();
+    loop {
+        let found = puppet.evaluate(&script).await?;
+        if let Some(message) = found.get("error").and_then(Value::as_str) {
+            return Err(Error::InvalidParams(format!(
+                "wait_for `{}` failed: {}",
+                selector, message
+            )));
+        }
+        if found == Value::Bool(true) {
+            return Ok(());
+        }
+        if started.elapsed() >= timeout {
+            return Err(Error::InvalidParams(format!(
+                "wait_for `{}` failed: no element matched within {} ms",
+                selector,
+                timeout.as_millis()
+            )));
+        }
+        tokio::time::sleep(WAIT_POLL_INTERVAL).await;
+    }
+}
+
+/// Base64 PNG image content for a screenshot step.
+pub(crate) fn screenshot_content(png: &[u8]) -> ContentItem {
+    ContentItem::image(BASE64.encode(png), "image/png")
+}
+
+impl ToolsManifest {
+    /// Read and validate a manifest file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)?;
+        Self::parse(&json).map_err(|e| Error::InvalidParams(format!("{}: {}", path.display(), e)))
+    }
+
+    /// Parse and validate a manifest.
+    pub fn parse(json: &str) -> Result<Self> {
+        let manifest: ToolsManifest = serde_json::from_str(json)
+            .map_err(|e| Error::InvalidParams(format!("malformed tools manifest: {}", e)))?;
+
+        let mut names = HashSet::new();
+        for spec in &manifest.tools {
+            spec.validate().map_err(|reason| {
+                Error::InvalidParams(format!("tool `{}`: {}", spec.name, reason))
+            })?;
+            if !names.insert(spec.name.as_str()) {
+                return Err(Error::InvalidParams(format!(
+                    "tool `{}` is defined more than once",
+                    spec.name
+                )));
+            }
+        }
+        Ok(manifest)
+    }
+}
+
+impl ManifestToolSpec {
+    /// Check the name, schema and steps, returning why they are invalid.
+    fn validate(&self) -> std::result::Result<(), String> {
+        if self.name.is_empty()
+            || self.name.len() > MAX_TOOL_NAME_LEN
+            || !self
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-'))
+        {
+            return Err(format!(
+                "name must be 1-{} letters, digits, `_` or `-`",
+                MAX_TOOL_NAME_LEN
+            ));
+        }
+
+        if self.input_schema.get("type").and_then(Value::as_str) != Some("object") {
+            return Err("input_schema must be a JSON Schema with \"type\": \"object\"".into());
+        }
+        let properties: HashSet<&str> = match self.input_schema.get("properties") {
+            None => HashSet::new(),
+            Some(Value::Object(properties)) => properties.keys().map(String::as_str).collect(),
+            Some(_) => return Err("input_schema.properties must be an object".into()),
+        };
+        for required in self.required_arguments() {
+            if !properties.contains(required) {
+                return Err(format!(
+                    "required argument `{}` is not in input_schema.properties",
+                    required
+                ));
+            }
+        }
+
+        if self.steps.is_empty() {
+            return Err("steps must not be empty".into());
+        }
+        let mut keys = HashSet::new();
+        for (index, step) in self.steps.iter().enumerate() {
+            let step_error = |reason: String| format!("step {}: {}", index + 1, reason);
+            for template in step.templates() {
+                for placeholder in placeholders(template).map_err(step_error)? {
+                    if !properties.contains(placeholder) {
+                        return Err(step_error(format!(
+                            "`{{{{{}}}}}` is not an argument in input_schema.properties",
+                            placeholder
+                        )));
+                    }
+                }
+            }
+            match step {
+                Step::Navigate { url } if !url.contains("{{") => {
+                    normalize_and_validate_url(url).map_err(|e| step_error(e.to_string()))?;
+                }
+                Step::Extract { selector, key, .. } => {
+                    let key = key.as_deref().unwrap_or(selector);
+                    if !keys.insert(key) {
+                        return Err(step_error(format!("`{}` is extracted twice", key)));
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Names listed in the schema's `required`.
+    fn required_arguments(&self) -> Vec<&str> {
+        self.input_schema
+            .get("required")
+            .and_then(Value::as_array)
+            .map(|required| required.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Names of the `{{placeholders}}` in a template.
+fn placeholders(template: &str) -> std::result::Result<Vec<&str>, String> {
+    let mut names = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let end = after
+            .find("}}")
+            .ok_or_else(|| format!("unclosed `{{{{` in `{}`", template))?;
+        names.push(after[..end].trim());
+        rest = &after[end + 2..];
+    }
+    Ok(names)
+}
+
+/// Replace the `{{placeholders}}` in a template with argument values.
+/// Strings are inserted as-is, other values as JSON; missing optional
+/// arguments become empty.
+fn substitute(template: &str, arguments: &Value) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            break;
+        };
+        match arguments.get(after[..end].trim()) {
+            Some(Value::String(value)) => out.push_str(value),
+            Some(Value::Null) | None => {}
+            Some(value) => out.push_str(&value.to_string()),
+        }
+        rest = &after[end + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// A [`Tool`] that runs the steps of a manifest entry.
+pub struct ManifestTool {
+    spec: ManifestToolSpec,
+}
+
+impl ManifestTool {
+    /// Wrap a validated manifest entry.
+    pub fn new(spec: ManifestToolSpec) -> Self {
+        Self { spec }
+    }
+}
+
+#[async_trait::async_trait]
+impl Tool for ManifestTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: self.spec.name.clone(),
+            description: self.spec.description.clone(),
+            input_schema: self.spec.input_schema.clone(),
+        }
+    }
+
+    fn primary_operation(&self) -> Option<Operation> {
+        self.spec.steps.first().map(Step::operation)
+    }
+
+    fn required_operations(&self) -> Vec<Operation> {
+        let mut operations = Vec::new();
+        for operation in self.spec.steps.iter().map(Step::operation) {
+            if !operations.contains(&operation) {
+                operations.push(operation);
+            }
+        }
+        operations
+    }
+
+    /// A manifest can be rerun on a fresh browser if it loads its own page
+    /// first and never clicks or types.
+    fn idempotent(&self) -> bool {
+        matches!(self.spec.steps.first(), Some(Step::Navigate { .. }))
+            && self
+                .spec
+                .steps
+                .iter()
+                .all(|step| !matches!(step, Step::Click { .. } | Step::Type { .. }))
+    }
+
+    fn drives_page(&self) -> bool {
+        true
+    }
+
+    async fn execute(&self, arguments: Value, context: &ToolContext) -> Result<ToolCallResult> {
+        let arguments = match arguments {
+            Value::Null => json!({}),
+            Value::Object(_) => arguments,
+            _ => return Err(Error::InvalidParams("arguments must be an object".into())),
+        };
+        for required in self.spec.required_arguments() {
+            if arguments.get(required).is_none_or(Value::is_null) {
+                return Err(Error::InvalidParams(format!(
+                    "missing required argument `{}`",
+                    required
+                )));
+            }
+        }
+
+        let mut extracted = serde_json::Map::new();
+        let mut screenshots = Vec::new();
+        for step in &self.spec.steps {
+            match step.substituted(&arguments).run(context).await? {
+                StepOutput::Extracted { key, value } => {
+                    extracted.insert(key, value);
+                }
+                StepOutput::Screenshot(png) => screenshots.push(screenshot_content(&png)),
+                StepOutput::None => {}
+            }
+        }
+
+        let puppet = context.get_puppet().await?;
+        let url = puppet.current_url().await.ok();
+        let lines: Vec<String> = extracted
+            .iter()
+            .map(|(key, value)| match value {
+                Value::String(text) => format!("- **{}**: {}", key, text),
+                other => format!("- **{}**: {}", key, other),
+            })
+            .collect();
+        let text = if lines.is_empty() {
+            format!(
+                "# {}\n\nCompleted {} steps.",
+                self.spec.name,
+                self.spec.steps.len()
+            )
+        } else {
+            format!("# {}\n\n{}", self.spec.name, lines.join("\n"))
+        };
+
+        let structured = json!({
+            "url": url,
+            "extracted": extracted,
+        });
+
+        let mut content = vec![ContentItem::text(text), ContentItem::json(&structured)];
+        content.extend(screenshots);
+
+        Ok(ToolCallResult {
+            content,
+            is_error: false,
+            structured_content: Some(structured),
+        })
+    }
+}
@@ -0,0 +1,63 @@
+//! URL handling shared by the tools that open pages.
+//!
+//! Every URL a tool is asked to open goes through
+//! [`normalize_and_validate_url`] before the permission check, so the
+//! allowlist always sees the same canonical form that the browser loads:
+//! `example.com`, `HTTPS://Example.com` and `https://example.com/` are all
+//! checked (and opened) as `https://example.com/`.
+
+use url::Url;
+
+use crate::error::{Error, Result};
+
+/// Schemes tools may open.
+const ALLOWED_SCHEMES: &[&str] = &["http", "https"];
+
+/// Bring a URL into canonical form and reject anything but http(s).
+///
+/// - A missing scheme defaults to `https://` (`example.com/docs`).
+/// - Hosts are lowercased and internationalized names converted to
+///   punycode; an empty path becomes `/` and default ports are dropped.
+/// - `file:`, `javascript:`, `data:`, `chrome:` and other schemes are
+///   rejected with [`Error::DisallowedScheme`], whatever the allowlist says;
+///   URLs without a host with [`Error::InvalidParams`].
+pub fn normalize_and_validate_url(raw: &str) -> Result<String> {
+    let raw = raw.trim();
+    if raw.is_empty() {
+        return Err(Error::InvalidParams("URL must not be empty".into()));
+    }
+
+    let candidate = if has_scheme(raw) {
+        raw.to_string()
+    } else {
+        format!("https://{}", raw)
+    };
+
+    let url = Url::parse(&candidate)
+        .map_err(|e| Error::InvalidParams(format!("invalid URL `{}`: {}", raw, e)))?;
+
+    if !ALLOWED_SCHEMES.contains(&url.scheme()) {
+        return Err(Error::DisallowedScheme(url.scheme().to_string()));
+    }
+    if url.host_str().is_none_or(str::is_empty) {
+        return Err(Error::InvalidParams(format!("URL `{}` has no host", raw)));
+    }
+
+    Ok(url.to_string())
+}
+
+/// Whether `raw` starts with a scheme, as opposed to `host:port`.
+fn has_scheme(raw: &str) -> bool {
+    let Some((scheme, rest)) = raw.split_once(':') else {
+        return false;
+    };
+    let valid_scheme = scheme
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    // `example.com:8080/path` is a host and port, not a scheme
+    valid_scheme && !rest.starts_with(|c: char| c.is_ascii_digit())
+}
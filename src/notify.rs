@@ -0,0 +1,116 @@
+//! Server-to-client notifications from anywhere in the call stack.
+//!
+//! A [`NotificationSink`] is carried by the [`ToolContext`] so tools (and the
+//! server itself) can send progress, log and resource notifications without
+//! a handle on the server. The server connects the sink to its
+//! [`OutboundQueue`] when it is created, and the writer task of
+//! [`McpServer::serve`] writes whatever is queued:
+//!
+//! ```json
+//! {"jsonrpc":"2.0","method":"notifications/message","params":{"level":"info","logger":"webpuppet-mcp","data":"..."}}
+//! ```
+//!
+//! Until it is connected (e.g. a context used without a server, in tests or
+//! when embedding the tools), a sink silently discards what it is given.
+//!
+//! [`ToolContext`]: crate::tools::ToolContext
+//! [`McpServer::serve`]: crate::McpServer::serve
+
+use std::sync::{Arc, OnceLock};
+
+use crate::outbound::OutboundQueue;
+use crate::protocol::JsonRpcRequest;
+
+/// Sends notifications to the client through the server's outbound queue.
+///
+/// Clones share the connection, so a sink connected after being cloned
+/// into a session's context reaches the client from there too.
+#[derive(Debug, Clone, Default)]
+pub struct NotificationSink {
+    queue: Arc<OnceLock<Arc<OutboundQueue>>>,
+}
+
+impl NotificationSink {
+    /// A sink that discards everything until [`connect`](Self::connect)ed.
+    pub fn noop() -> Self {
+        Self::default()
+    }
+
+    /// A sink writing to `queue`.
+    pub fn connected(queue: Arc<OutboundQueue>) -> Self {
+        let sink = Self::default();
+        sink.connect(queue);
+        sink
+    }
+
+    /// Send notifications to `queue` from now on. A sink connects once;
+    /// returns whether this call connected it.
+    pub fn connect(&self, queue: Arc<OutboundQueue>) -> bool {
+        self.queue.set(queue).is_ok()
+    }
+
+    /// Whether notifications reach a client.
+    pub fn is_connected(&self) -> bool {
+        self.queue.get().is_some()
+    }
+
+    /// Queue a notification. Notifications may be dropped (oldest first) if
+    /// the client isn't reading; see [`OutboundQueue`].
+    pub fn send(&self, notification: JsonRpcRequest) {
+        let Some(queue) = self.queue.get() else {
+            return;
+        };
+        match serde_json::to_string(&JsonRpcRequest {
+            id: None,
+            ..notification
+        }) {
+            Ok(message) => queue.push_notification(message),
+            Err(e) => tracing::warn!("Cannot serialize notification: {}", e),
+        }
+    }
+
+    /// Send a notification with `method` and `params`.
+    pub fn notify(&self, method: &str, params: serde_json::Value) {
+        self.send(JsonRpcRequest {
+            jsonrpc: "2.0".into(),
+            id: None,
+            method: method.into(),
+            params: Some(params),
+        });
+    }
+
+    /// Send a `notifications/progress` for the call identified by `token`.
+    pub fn progress(&self, token: &serde_json::Value, progress: u64, message: &str) {
+        self.notify(
+            "notifications/progress",
+            serde_json::json!({
+                "progressToken": token,
+                "progress": progress,
+                "message": message,
+            }),
+        );
+    }
+
+    /// Send a `notifications/message` log entry at `level` (`debug`,
+    /// `info`, `warning`, `error`, ...).
+    pub fn log(&self, level: &str, logger: &str, data: serde_json::Value) {
+        self.notify(
+            "notifications/message",
+            serde_json::json!({
+                "level": level,
+                "logger": logger,
+                "data": data,
+            }),
+        );
+    }
+
+    /// Send a `notifications/resources/updated` for `uri`, with the fields
+    /// of `details` (an object) added to its params.
+    pub fn resource_updated(&self, uri: &str, details: serde_json::Value) {
+        let mut params = serde_json::json!({ "uri": uri });
+        if let serde_json::Value::Object(details) = details {
+            params.as_object_mut().unwrap().extend(details);
+        }
+        self.notify("notifications/resources/updated", params);
+    }
+}
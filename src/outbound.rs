@@ -0,0 +1,188 @@
+//! Bounded outbound message queue.
+//!
+//! Everything the server sends to the client goes through an
+//! [`OutboundQueue`] that a single writer task drains. A slow or stalled
+//! client then only ever blocks that writer, and the queue keeps memory
+//! bounded with a simple drop policy:
+//!
+//! - **Responses are never dropped.** They are bounded by the number of
+//!   requests in flight, so they don't count towards the capacity.
+//! - **Notifications are dropped oldest-first** once `capacity` of them are
+//!   waiting. Log and progress notifications are superseded by newer ones,
+//!   so losing the oldest is the least harmful choice.
+//!
+//! The writer owns the output and writes each message with its line ending
+//! in one `write_all`, flushed before the next is taken, so messages from
+//! concurrent senders never interleave.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::sync::Notify;
+
+use crate::protolog::{Direction, ProtocolLog};
+
+/// Default number of notifications buffered for a slow client.
+pub const DEFAULT_NOTIFICATION_CAPACITY: usize = 256;
+
+/// Kind of queued message, which decides whether it may be dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Response,
+    Notification,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    messages: VecDeque<(Kind, String)>,
+    notifications: usize,
+    closed: bool,
+}
+
+/// Queue of serialized messages waiting to be written to the client.
+#[derive(Debug)]
+pub struct OutboundQueue {
+    state: Mutex<State>,
+    ready: Notify,
+    capacity: usize,
+    dropped: AtomicU64,
+}
+
+impl OutboundQueue {
+    /// Create a queue buffering at most `capacity` notifications.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            state: Mutex::new(State::default()),
+            ready: Notify::new(),
+            capacity: capacity.max(1),
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    /// Queue a response. Responses are never dropped.
+    pub fn push_response(&self, message: String) {
+        self.push(Kind::Response, message);
+    }
+
+    /// Queue a notification, dropping the oldest waiting notification if
+    /// the queue is full.
+    pub fn push_notification(&self, message: String) {
+        self.push(Kind::Notification, message);
+    }
+
+    fn push(&self, kind: Kind, message: String) {
+        {
+            let mut state = self.state.lock().unwrap();
+            if state.closed {
+                return;
+            }
+
+            if kind == Kind::Notification && state.notifications >= self.capacity {
+                if let Some(oldest) = state
+                    .messages
+                    .iter()
+                    .position(|(kind, _)| *kind == Kind::Notification)
+                {
+                    state.messages.remove(oldest);
+                    state.notifications -= 1;
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+
+            if kind == Kind::Notification {
+                state.notifications += 1;
+            }
+            state.messages.push_back((kind, message));
+        }
+        self.ready.notify_one();
+    }
+
+    /// Wait for the next message. Returns `None` once the queue is closed
+    /// and drained.
+    pub async fn next(&self) -> Option<String> {
+        loop {
+            {
+                let mut state = self.state.lock().unwrap();
+                if let Some((kind, message)) = state.messages.pop_front() {
+                    if kind == Kind::Notification {
+                        state.notifications -= 1;
+                    }
+                    return Some(message);
+                }
+                if state.closed {
+                    return None;
+                }
+            }
+            self.ready.notified().await;
+        }
+    }
+
+    /// Stop accepting messages; the writer exits after draining the rest.
+    pub fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.ready.notify_one();
+    }
+
+    /// Number of messages waiting to be written.
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().messages.len()
+    }
+
+    /// Whether no messages are waiting.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of notifications dropped because the queue was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Write queued messages, one per line, until the queue is closed.
+    pub async fn drain<W>(&self, writer: W) -> std::io::Result<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        self.drain_logged(writer, None).await
+    }
+
+    /// Like [`drain`](Self::drain), also recording each written message in
+    /// a protocol log.
+    pub async fn drain_logged<W>(
+        &self,
+        mut writer: W,
+        log: Option<&ProtocolLog>,
+    ) -> std::io::Result<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let mut line = Vec::new();
+        while let Some(message) = self.next().await {
+            // A line break inside a message would split it in two
+            debug_assert!(!message.contains('\n'), "outbound message spans lines");
+            line.clear();
+            line.extend_from_slice(message.as_bytes());
+            line.push(b'\n');
+            writer.write_all(&line).await?;
+            writer.flush().await?;
+            if let Some(log) = log {
+                log.record(Direction::Out, &message);
+            }
+        }
+
+        let dropped = self.dropped();
+        if dropped > 0 {
+            tracing::warn!("Dropped {} notifications for a slow client", dropped);
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for OutboundQueue {
+    fn default() -> Self {
+        Self::new(DEFAULT_NOTIFICATION_CAPACITY)
+    }
+}
@@ -0,0 +1,80 @@
+//! Plain-text rendering of tool results for hosts that don't render
+//! markdown.
+//!
+//! Built-in tools format their human-readable output as markdown with a few
+//! emoji (`# Permission Check`, `**Status**: ✅ ALLOWED`). With
+//! `--result-format plain` the registry passes every result through
+//! [`plain_result`], which keeps the words and drops the markup: heading
+//! markers, bold, inline code, code fences and emoji are removed, and links
+//! become `text (url)`. JSON items and the text of tools that return page or
+//! provider content verbatim are left alone.
+
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+use crate::protocol::{ContentItem, ToolCallResult};
+
+/// Strip the markup from the text items of `result`, except those carrying
+/// JSON.
+pub fn plain_result(result: &mut ToolCallResult) {
+    for item in &mut result.content {
+        if let ContentItem::Text { text, .. } = item {
+            if !is_json(text) {
+                *text = to_plain(text);
+            }
+        }
+    }
+}
+
+/// Whether `text` is a JSON object or array, as [`ContentItem::json`] makes.
+fn is_json(text: &str) -> bool {
+    let trimmed = text.trim_start();
+    (trimmed.starts_with('{') || trimmed.starts_with('['))
+        && serde_json::from_str::<serde_json::Value>(text).is_ok()
+}
+
+fn links() -> &'static Regex {
+    static LINKS: OnceLock<Regex> = OnceLock::new();
+    LINKS.get_or_init(|| Regex::new(r"\[([^\]]*)\]\(([^)\s]+)\)").expect("link pattern is valid"))
+}
+
+/// Markdown `text` without its markup.
+pub fn to_plain(text: &str) -> String {
+    let lines: Vec<String> = text
+        .lines()
+        .filter(|line| !line.trim_start().starts_with("```"))
+        .map(|line| {
+            let line = strip_heading(line);
+            let line = links().replace_all(line, "$1 ($2)");
+            let line = line.replace("**", "").replace('`', "");
+            let line: String = line.chars().filter(|c| !is_emoji(*c)).collect();
+            // Emoji leave doubled or leading spaces behind
+            let indent = &line[..line.len() - line.trim_start().len()];
+            let words = line.split_whitespace().collect::<Vec<_>>().join(" ");
+            format!("{}{}", indent, words)
+        })
+        .collect();
+    lines.join("\n")
+}
+
+/// `line` without a leading `#` to `######` heading marker.
+fn strip_heading(line: &str) -> &str {
+    let hashes = line.chars().take_while(|c| *c == '#').count();
+    match line[hashes..].strip_prefix(' ') {
+        Some(rest) if (1..=6).contains(&hashes) => rest,
+        _ => line,
+    }
+}
+
+/// Whether `c` is an emoji or a character only used to build one.
+fn is_emoji(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x1F000..=0x1FAFF // pictographs, emoticons, symbols
+            | 0x2600..=0x27BF // miscellaneous symbols and dingbats
+            | 0x2B00..=0x2BFF // arrows and stars used as emoji
+            | 0xFE0F // emoji presentation selector
+            | 0x200D // zero-width joiner
+    )
+}
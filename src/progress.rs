@@ -0,0 +1,66 @@
+//! Progress notifications for long-running tool calls.
+//!
+//! A client that wants progress sends a `progressToken` in the `_meta` of its
+//! `tools/call` request. The server then runs the tool inside a
+//! [`ProgressReporter`] scope, and the tool reports partial results with
+//! [`ProgressReporter::current`] as `notifications/progress` messages:
+//!
+//! ```json
+//! {"jsonrpc":"2.0","method":"notifications/progress","params":{"progressToken":"t1","progress":3,"message":"partial text"}}
+//! ```
+//!
+//! Progress notifications go through the [`NotificationSink`] like any other
+//! notification, so a client that stops reading may miss some; the tool
+//! result is always complete.
+
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use crate::notify::NotificationSink;
+
+tokio::task_local! {
+    static REPORTER: ProgressReporter;
+}
+
+/// Sends progress notifications for one tool call.
+#[derive(Debug, Clone)]
+pub struct ProgressReporter {
+    token: serde_json::Value,
+    sink: NotificationSink,
+    sent: Arc<AtomicU64>,
+}
+
+impl ProgressReporter {
+    /// Create a reporter for the call identified by `token`.
+    pub fn new(token: serde_json::Value, sink: NotificationSink) -> Self {
+        Self {
+            token,
+            sink,
+            sent: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Reporter of the tool call running on this task, if its client asked
+    /// for progress.
+    pub fn current() -> Option<Self> {
+        REPORTER.try_with(Clone::clone).ok()
+    }
+
+    /// Run `future` with this reporter as the [`current`](Self::current) one.
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        REPORTER.scope(self, future).await
+    }
+
+    /// Send a progress notification carrying `message`. The progress value
+    /// counts the notifications sent so far.
+    pub fn report(&self, message: &str) {
+        let progress = self.sent.fetch_add(1, Ordering::SeqCst) + 1;
+        self.sink.progress(&self.token, progress, message);
+    }
+
+    /// Number of notifications sent.
+    pub fn sent(&self) -> u64 {
+        self.sent.load(Ordering::SeqCst)
+    }
+}
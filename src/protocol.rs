@@ -3,6 +3,7 @@
 //! Implements the Model Context Protocol (MCP) as specified at:
 //! https://spec.modelcontextprotocol.io/
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 /// JSON-RPC 2.0 request.
@@ -10,7 +11,8 @@ use serde::{Deserialize, Serialize};
 pub struct JsonRpcRequest {
     /// Protocol version (always "2.0").
     pub jsonrpc: String,
-    /// Request ID.
+    /// Request ID (absent for notifications).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub id: Option<JsonRpcId>,
     /// Method name.
     pub method: String,
@@ -101,6 +103,15 @@ pub enum JsonRpcId {
     Number(i64),
 }
 
+impl std::fmt::Display for JsonRpcId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JsonRpcId::String(id) => write!(f, "\"{}\"", id),
+            JsonRpcId::Number(id) => write!(f, "{}", id),
+        }
+    }
+}
+
 /// MCP message types.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
@@ -179,6 +190,23 @@ pub struct ClientCapabilities {
     /// Sampling capability.
     #[serde(default)]
     pub sampling: Option<serde_json::Value>,
+    /// Non-standard capabilities, such as the content types the client
+    /// displays (`{"contentTypes": ["text", "image"]}`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub experimental: Option<serde_json::Value>,
+}
+
+impl ClientCapabilities {
+    /// Whether the client displays image content: unless it lists the
+    /// content types it displays without `image`, it is assumed to.
+    pub fn accepts_images(&self) -> bool {
+        let types = self
+            .experimental
+            .as_ref()
+            .and_then(|experimental| experimental.get("contentTypes"))
+            .and_then(|types| types.as_array());
+        types.is_none_or(|types| types.iter().any(|t| t == "image"))
+    }
 }
 
 /// Roots capability.
@@ -204,6 +232,9 @@ pub struct ServerCapabilities {
     /// Logging capability.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub logging: Option<serde_json::Value>,
+    /// Non-standard capabilities, keyed by vendor.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub experimental: Option<serde_json::Value>,
 }
 
 /// Tools capability.
@@ -263,6 +294,48 @@ pub struct ToolDefinition {
     pub input_schema: serde_json::Value,
 }
 
+impl ToolDefinition {
+    /// Build a definition whose input schema is derived from the tool's
+    /// argument struct, so the advertised schema can't drift from what the
+    /// tool actually deserializes.
+    pub fn from_args<T: JsonSchema>(
+        name: impl Into<String>,
+        description: impl Into<String>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            description: description.into(),
+            input_schema: args_schema::<T>(),
+        }
+    }
+}
+
+/// JSON Schema for a tool argument struct.
+///
+/// Field doc comments become property descriptions; `Option` fields are
+/// left out of `required`.
+pub fn args_schema<T: JsonSchema>() -> serde_json::Value {
+    let mut schema = schemars::schema_for!(T).to_value();
+    if let Some(object) = schema.as_object_mut() {
+        // Not needed in an MCP input schema
+        object.remove("$schema");
+        object.remove("title");
+    }
+    schema
+}
+
+/// Parameters of `tools/list`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ListToolsParams {
+    /// Pagination cursor (ignored: every tool fits on one page).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    /// Only list the tool with this name (a webpuppet extension), e.g. to
+    /// fetch one tool's input schema.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
 /// Tool call request.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolCallParams {
@@ -271,6 +344,18 @@ pub struct ToolCallParams {
     /// Tool arguments.
     #[serde(default)]
     pub arguments: serde_json::Value,
+    /// Request metadata.
+    #[serde(rename = "_meta", default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<RequestMeta>,
+}
+
+/// Metadata a client may attach to a request.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestMeta {
+    /// Token to send `notifications/progress` for this request with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub progress_token: Option<serde_json::Value>,
 }
 
 /// Tool call result.
@@ -281,6 +366,35 @@ pub struct ToolCallResult {
     /// Whether the tool encountered an error.
     #[serde(rename = "isError", default)]
     pub is_error: bool,
+    /// Machine-readable result, for tools with a natural structured output.
+    /// `content` still carries the human-readable version.
+    #[serde(
+        rename = "structuredContent",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub structured_content: Option<serde_json::Value>,
+}
+
+/// Who a content item is intended for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// The human using the client.
+    User,
+    /// The model.
+    Assistant,
+}
+
+/// Hints for how a client should surface a content item.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Annotations {
+    /// Who the item is intended for; absent means everyone.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audience: Option<Vec<Role>>,
+    /// Importance from 0 (optional) to 1 (required).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<f64>,
 }
 
 /// Content item in tool results.
@@ -292,6 +406,9 @@ pub enum ContentItem {
     Text {
         /// Text value.
         text: String,
+        /// How to surface the item.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        annotations: Option<Annotations>,
     },
     /// Image content.
     #[serde(rename = "image")]
@@ -301,6 +418,9 @@ pub enum ContentItem {
         /// MIME type.
         #[serde(rename = "mimeType")]
         mime_type: String,
+        /// How to surface the item.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        annotations: Option<Annotations>,
     },
     /// Resource content.
     #[serde(rename = "resource")]
@@ -313,13 +433,24 @@ pub enum ContentItem {
         /// Resource text.
         #[serde(skip_serializing_if = "Option::is_none")]
         text: Option<String>,
+        /// How to surface the item.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        annotations: Option<Annotations>,
     },
 }
 
 impl ContentItem {
     /// Create a text content item.
     pub fn text(text: impl Into<String>) -> Self {
-        ContentItem::Text { text: text.into() }
+        ContentItem::Text {
+            text: text.into(),
+            annotations: None,
+        }
+    }
+
+    /// Create a text content item carrying a machine-readable JSON object.
+    pub fn json(value: &serde_json::Value) -> Self {
+        Self::text(value.to_string())
     }
 
     /// Create an image content item.
@@ -327,6 +458,38 @@ impl ContentItem {
         ContentItem::Image {
             data: data.into(),
             mime_type: mime_type.into(),
+            annotations: None,
+        }
+    }
+
+    /// Annotations of the item, if any.
+    pub fn annotations(&self) -> Option<&Annotations> {
+        match self {
+            ContentItem::Text { annotations, .. }
+            | ContentItem::Image { annotations, .. }
+            | ContentItem::Resource { annotations, .. } => annotations.as_ref(),
+        }
+    }
+
+    /// Mark who the item is intended for.
+    pub fn with_audience(mut self, audience: impl IntoIterator<Item = Role>) -> Self {
+        self.annotations_mut().audience = Some(audience.into_iter().collect());
+        self
+    }
+
+    /// Set the item's importance, clamped to 0 (optional) ..= 1 (required).
+    pub fn with_priority(mut self, priority: f64) -> Self {
+        self.annotations_mut().priority = Some(priority.clamp(0.0, 1.0));
+        self
+    }
+
+    fn annotations_mut(&mut self) -> &mut Annotations {
+        match self {
+            ContentItem::Text { annotations, .. }
+            | ContentItem::Image { annotations, .. }
+            | ContentItem::Resource { annotations, .. } => {
+                annotations.get_or_insert_with(Default::default)
+            }
         }
     }
 }
@@ -337,3 +500,144 @@ pub struct ListToolsResult {
     /// Available tools.
     pub tools: Vec<ToolDefinition>,
 }
+
+/// Completion request parameters (`completion/complete`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompleteParams {
+    /// What is being completed.
+    #[serde(rename = "ref")]
+    pub reference: CompletionReference,
+    /// Argument being completed.
+    pub argument: CompletionArgument,
+}
+
+/// Reference to the prompt, resource or tool whose argument is being completed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum CompletionReference {
+    /// A prompt template.
+    #[serde(rename = "ref/prompt")]
+    Prompt {
+        /// Prompt name.
+        name: String,
+    },
+    /// A resource template.
+    #[serde(rename = "ref/resource")]
+    Resource {
+        /// Resource URI or URI template.
+        uri: String,
+    },
+    /// A tool (not part of the 2024-11-05 spec, accepted for tool arguments).
+    #[serde(rename = "ref/tool")]
+    Tool {
+        /// Tool name.
+        name: String,
+    },
+}
+
+/// Argument being completed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompletionArgument {
+    /// Argument name.
+    pub name: String,
+    /// Value typed so far.
+    #[serde(default)]
+    pub value: String,
+}
+
+/// Completion result.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompleteResult {
+    /// Completion candidates.
+    pub completion: Completion,
+}
+
+/// Completion candidates.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Completion {
+    /// Candidate values (at most 100).
+    pub values: Vec<String>,
+    /// Total number of candidates, if known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<usize>,
+    /// Whether more candidates exist beyond `values`.
+    #[serde(rename = "hasMore", default)]
+    pub has_more: bool,
+}
+
+/// Resource advertised by `resources/list`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Resource {
+    /// Resource URI.
+    pub uri: String,
+    /// Human-readable name.
+    pub name: String,
+    /// What the resource holds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// MIME type of the contents.
+    #[serde(rename = "mimeType", skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+}
+
+/// Result of `resources/list`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListResourcesResult {
+    /// Available resources.
+    pub resources: Vec<Resource>,
+}
+
+/// Parameterized resource URI advertised by `resources/templates/list`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResourceTemplate {
+    /// RFC 6570 URI template, e.g. `webpuppet://capture/{id}`.
+    #[serde(rename = "uriTemplate")]
+    pub uri_template: String,
+    /// Human-readable name.
+    pub name: String,
+    /// What the resources hold and where their parameters come from.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// MIME type of the contents, if every match has the same.
+    #[serde(rename = "mimeType", skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+}
+
+/// Result of `resources/templates/list`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListResourceTemplatesResult {
+    /// Available URI templates.
+    #[serde(rename = "resourceTemplates")]
+    pub resource_templates: Vec<ResourceTemplate>,
+}
+
+/// Parameters of `resources/read`, `resources/subscribe` and
+/// `resources/unsubscribe`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResourceParams {
+    /// Resource URI.
+    pub uri: String,
+}
+
+/// Contents of a resource: text, or binary data as a base64 `blob`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResourceContents {
+    /// Resource URI.
+    pub uri: String,
+    /// MIME type.
+    #[serde(rename = "mimeType", skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    /// Resource text (empty for binary contents).
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub text: String,
+    /// Base64-encoded binary contents.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blob: Option<String>,
+}
+
+/// Result of `resources/read`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReadResourceResult {
+    /// Contents of the resource.
+    pub contents: Vec<ResourceContents>,
+}
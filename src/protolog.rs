@@ -0,0 +1,82 @@
+//! Raw protocol log for debugging client/server disagreements.
+//!
+//! A [`ProtocolLog`] records every line the server reads and every line it
+//! writes, exactly as exchanged, as JSONL:
+//!
+//! ```json
+//! {"ts":"2025-01-22T10:00:00.000Z","direction":"in","message":"{\"jsonrpc\":\"2.0\",...}"}
+//! ```
+//!
+//! Messages are stored as strings rather than parsed JSON so malformed input
+//! is kept verbatim. Unlike the tracing log, nothing is redacted: the file
+//! holds full prompts and responses and should be treated accordingly.
+
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use chrono::{SecondsFormat, Utc};
+use serde::Serialize;
+
+/// Direction of a logged message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    /// Read from the client.
+    In,
+    /// Written to the client.
+    Out,
+}
+
+#[derive(Serialize)]
+struct Record<'a> {
+    ts: String,
+    direction: Direction,
+    message: &'a str,
+}
+
+/// Append-only JSONL log of the raw protocol traffic.
+pub struct ProtocolLog {
+    writer: Mutex<Box<dyn Write + Send>>,
+}
+
+impl ProtocolLog {
+    /// Open (or create) a log file, appending to it.
+    pub fn create(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let file: File = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self::new(BufWriter::new(file)))
+    }
+
+    /// Log to an arbitrary writer.
+    pub fn new(writer: impl Write + Send + 'static) -> Self {
+        Self {
+            writer: Mutex::new(Box::new(writer)),
+        }
+    }
+
+    /// Record one message. Failures are logged and otherwise ignored so a
+    /// full disk never interrupts the session.
+    pub fn record(&self, direction: Direction, message: &str) {
+        let record = Record {
+            ts: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            direction,
+            message,
+        };
+
+        let mut writer = self.writer.lock().unwrap();
+        let result = serde_json::to_writer(&mut *writer, &record)
+            .map_err(std::io::Error::from)
+            .and_then(|()| writer.write_all(b"\n"))
+            .and_then(|()| writer.flush());
+        if let Err(e) = result {
+            tracing::warn!("Failed to write protocol log: {}", e);
+        }
+    }
+}
+
+impl std::fmt::Debug for ProtocolLog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProtocolLog").finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,175 @@
+//! Per-provider prompt rate limiting and per-domain navigation delays.
+//!
+//! Sending prompts to the same provider in quick succession through the
+//! browser can trip anti-bot throttles and get the user's session flagged.
+//! [`ProviderRateLimiter`] spaces prompts to each provider at least
+//! `min_interval` apart, queuing callers that arrive too fast.
+//!
+//! Browsing is spaced the same way, per site: [`DomainDelays`] keeps
+//! navigations to a configured domain (and its subdomains) a minimum delay
+//! apart, so research across many pages of one site doesn't hammer it.
+//! Domains are independent of each other and of the provider limits.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tokio::time::Instant;
+use url::Url;
+use webpuppet::Provider;
+
+use crate::error::{Error, Result};
+
+/// Default minimum interval between prompts to the same provider.
+pub const DEFAULT_MIN_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Enforces a minimum interval between prompts to each provider.
+#[derive(Debug)]
+pub struct ProviderRateLimiter {
+    min_interval: Duration,
+    /// Earliest time the next prompt to each provider may start.
+    next_slot: Mutex<HashMap<Provider, Instant>>,
+}
+
+impl ProviderRateLimiter {
+    /// Create a limiter spacing prompts `min_interval` apart.
+    pub fn new(min_interval: Duration) -> Self {
+        Self {
+            min_interval,
+            next_slot: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Minimum interval between prompts to the same provider.
+    pub fn min_interval(&self) -> Duration {
+        self.min_interval
+    }
+
+    /// Wait for this provider's next slot.
+    ///
+    /// Fails immediately, without taking a slot, if the wait would exceed
+    /// `max_wait`.
+    pub async fn acquire(&self, provider: Provider, max_wait: Duration) -> Result<()> {
+        let wait = self.reserve(provider, max_wait)?;
+
+        if !wait.is_zero() {
+            tracing::debug!(
+                "Delaying prompt to {} by {} ms (rate limit)",
+                provider,
+                wait.as_millis()
+            );
+            tokio::time::sleep(wait).await;
+        }
+
+        Ok(())
+    }
+
+    /// Reserve the next slot for a provider, returning how long to wait for it.
+    fn reserve(&self, provider: Provider, max_wait: Duration) -> Result<Duration> {
+        let now = Instant::now();
+        let mut slots = self.next_slot.lock().unwrap();
+
+        let start = slots
+            .get(&provider)
+            .copied()
+            .map_or(now, |slot| slot.max(now));
+        let wait = start - now;
+
+        if wait > max_wait {
+            return Err(Error::RateLimited {
+                provider: provider.to_string(),
+                wait_ms: wait.as_millis() as u64,
+            });
+        }
+
+        slots.insert(provider, start + self.min_interval);
+        Ok(wait)
+    }
+}
+
+impl Default for ProviderRateLimiter {
+    fn default() -> Self {
+        Self::new(DEFAULT_MIN_INTERVAL)
+    }
+}
+
+/// Domain in a delay map that applies to every domain without its own.
+pub const ANY_DOMAIN: &str = "*";
+
+/// Enforces a minimum delay between navigations to each configured domain.
+#[derive(Debug, Default)]
+pub struct DomainDelays {
+    /// Minimum delay per domain, or [`ANY_DOMAIN`].
+    delays: HashMap<String, Duration>,
+    /// Earliest time the next navigation to each domain may start.
+    next_slot: Mutex<HashMap<String, Instant>>,
+}
+
+impl DomainDelays {
+    /// Create a limiter with a minimum delay per domain. A domain covers
+    /// its subdomains; [`ANY_DOMAIN`] covers every domain not listed, each
+    /// on its own.
+    pub fn new(delays: HashMap<String, Duration>) -> Self {
+        Self {
+            delays: delays
+                .into_iter()
+                .map(|(domain, delay)| {
+                    (domain.trim().trim_start_matches('.').to_lowercase(), delay)
+                })
+                .collect(),
+            next_slot: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Wait for the next navigation slot of `url`'s domain.
+    pub async fn acquire(&self, url: &str) {
+        let Some((domain, wait)) = self.reserve(url) else {
+            return;
+        };
+
+        if !wait.is_zero() {
+            tracing::debug!(
+                "Delaying navigation to {} by {} ms (domain delay)",
+                domain,
+                wait.as_millis()
+            );
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Domain whose delay applies to `host`, and the delay.
+    fn rule(&self, host: &str) -> Option<(String, Duration)> {
+        let mut candidate = host;
+        loop {
+            if let Some(delay) = self.delays.get(candidate) {
+                return Some((candidate.to_string(), *delay));
+            }
+            match candidate.split_once('.') {
+                Some((_, parent)) => candidate = parent,
+                None => break,
+            }
+        }
+        self.delays
+            .get(ANY_DOMAIN)
+            .map(|delay| (host.to_string(), *delay))
+    }
+
+    /// Reserve the next slot for `url`'s domain, returning the domain and how
+    /// long to wait for it; `None` when no delay applies.
+    fn reserve(&self, url: &str) -> Option<(String, Duration)> {
+        let host = Url::parse(url).ok()?.host_str()?.to_lowercase();
+        let (domain, delay) = self.rule(&host)?;
+        if delay.is_zero() {
+            return None;
+        }
+
+        let now = Instant::now();
+        let mut slots = self.next_slot.lock().unwrap();
+        let start = slots
+            .get(&domain)
+            .copied()
+            .map_or(now, |slot| slot.max(now));
+        slots.insert(domain.clone(), start + delay);
+        Some((domain, start - now))
+    }
+}
@@ -0,0 +1,241 @@
+//! Redaction of secrets and personal data in logged traffic.
+//!
+//! Protocol traffic is logged at debug level and transcripts of those logs
+//! tend to get shared. The [`Redactor`] scrubs known-sensitive fields
+//! (cookies, tokens, passwords) and, unless explicitly enabled, prompt
+//! bodies before anything reaches the tracing output or the audit log.
+//!
+//! Field-aware redaction is applied to protocol messages by the server;
+//! [`RedactingMakeWriter`] additionally applies pattern-based redaction to
+//! every formatted log line, which covers events emitted by dependencies.
+
+use std::io::Write;
+use std::sync::Arc;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use webpuppet::permissions::AuditEntry;
+
+/// Replacement marker for redacted values.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Field names redacted by default (matched case-insensitively; `-` and `_` are equivalent).
+pub const DEFAULT_REDACTED_FIELDS: &[&str] = &[
+    "cookie",
+    "cookies",
+    "set_cookie",
+    "authorization",
+    "password",
+    "passwd",
+    "secret",
+    "client_secret",
+    "token",
+    "access_token",
+    "refresh_token",
+    "id_token",
+    "api_key",
+    "session_id",
+    "otp",
+    "headers",
+];
+
+/// Fields that carry prompt bodies; redacted unless prompt logging is enabled.
+pub const PROMPT_FIELDS: &[&str] = &["message", "context", "prompt"];
+
+/// Redaction settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RedactionConfig {
+    /// Field names whose values are always redacted.
+    pub fields: Vec<String>,
+    /// Log full prompt bodies instead of redacting them.
+    pub log_prompts: bool,
+}
+
+impl Default for RedactionConfig {
+    fn default() -> Self {
+        Self {
+            fields: DEFAULT_REDACTED_FIELDS
+                .iter()
+                .map(|f| f.to_string())
+                .collect(),
+            log_prompts: false,
+        }
+    }
+}
+
+/// Scrubs sensitive values from JSON messages, free text and audit entries.
+#[derive(Debug, Clone)]
+pub struct Redactor {
+    fields: Vec<String>,
+    log_prompts: bool,
+    patterns: Vec<(Regex, &'static str)>,
+}
+
+impl Redactor {
+    /// Create a redactor from configuration.
+    pub fn new(config: &RedactionConfig) -> Self {
+        let patterns = [
+            // Authorization headers, whatever the credential's length
+            (
+                r#"(?i)\b(authorization["']?\s*[:=]\s*["']?(?:bearer|basic)\s+)[A-Za-z0-9\-._~+/]+=*"#,
+                "$1[REDACTED]",
+            ),
+            // Bare credentials long enough to be tokens rather than prose
+            // ("basic usage")
+            (
+                r"(?i)\b(bearer|basic)\s+[A-Za-z0-9\-._~+/]{20,}=*",
+                "$1 [REDACTED]",
+            ),
+            // key=value / key: value pairs, including URL query parameters
+            (
+                r#"(?i)\b((?:access_|refresh_|id_)?token|password|passwd|pwd|secret|api[_-]?key|session[_-]?id|cookie|sid)(["']?\s*[:=]\s*)("[^"]*"|'[^']*'|[^\s&;,"']+)"#,
+                "$1$2[REDACTED]",
+            ),
+            // JSON Web Tokens
+            (
+                r"\beyJ[A-Za-z0-9_-]+\.[A-Za-z0-9_-]+\.[A-Za-z0-9_-]+",
+                REDACTED,
+            ),
+        ]
+        .into_iter()
+        .map(|(pattern, replacement)| {
+            (
+                Regex::new(pattern).expect("redaction pattern is valid"),
+                replacement,
+            )
+        })
+        .collect();
+
+        Self {
+            fields: config.fields.iter().map(|f| normalize_field(f)).collect(),
+            log_prompts: config.log_prompts,
+            patterns,
+        }
+    }
+
+    /// Redact a raw protocol line. Non-JSON input is treated as free text.
+    pub fn redact_json(&self, line: &str) -> String {
+        match serde_json::from_str::<Value>(line) {
+            Ok(value) => self.redact_value(&value).to_string(),
+            Err(_) => self.redact_text(line),
+        }
+    }
+
+    /// Redact a JSON value, returning a scrubbed copy.
+    pub fn redact_value(&self, value: &Value) -> Value {
+        match value {
+            Value::Object(map) => Value::Object(
+                map.iter()
+                    .map(|(key, value)| (key.clone(), self.redact_field(key, value)))
+                    .collect(),
+            ),
+            Value::Array(items) => {
+                Value::Array(items.iter().map(|v| self.redact_value(v)).collect())
+            }
+            Value::String(s) => Value::String(self.redact_text(s)),
+            other => other.clone(),
+        }
+    }
+
+    /// Redact secrets matching known token/password patterns in free text.
+    pub fn redact_text(&self, text: &str) -> String {
+        self.patterns
+            .iter()
+            .fold(text.to_string(), |acc, (pattern, replacement)| {
+                pattern.replace_all(&acc, *replacement).into_owned()
+            })
+    }
+
+    /// Redact an audit log entry (reason and URL).
+    pub fn redact_audit_entry(&self, entry: &AuditEntry) -> AuditEntry {
+        AuditEntry {
+            reason: self.redact_text(&entry.reason),
+            url: entry.url.as_deref().map(|url| self.redact_text(url)),
+            ..entry.clone()
+        }
+    }
+
+    fn redact_field(&self, key: &str, value: &Value) -> Value {
+        let key = normalize_field(key);
+
+        if value.is_null() {
+            return Value::Null;
+        }
+
+        if self.fields.contains(&key) {
+            return Value::String(REDACTED.into());
+        }
+
+        if !self.log_prompts && PROMPT_FIELDS.contains(&key.as_str()) {
+            if let Value::String(s) = value {
+                return Value::String(format!("[REDACTED {} chars]", s.chars().count()));
+            }
+        }
+
+        self.redact_value(value)
+    }
+}
+
+impl Default for Redactor {
+    fn default() -> Self {
+        Self::new(&RedactionConfig::default())
+    }
+}
+
+fn normalize_field(field: &str) -> String {
+    field.to_lowercase().replace('-', "_")
+}
+
+/// [`MakeWriter`](tracing_subscriber::fmt::MakeWriter) wrapper that redacts
+/// each formatted log event before it reaches the underlying writer.
+#[derive(Debug, Clone)]
+pub struct RedactingMakeWriter<M> {
+    inner: M,
+    redactor: Arc<Redactor>,
+}
+
+impl<M> RedactingMakeWriter<M> {
+    /// Wrap a writer factory.
+    pub fn new(inner: M, redactor: Arc<Redactor>) -> Self {
+        Self { inner, redactor }
+    }
+}
+
+impl<'a, M> tracing_subscriber::fmt::MakeWriter<'a> for RedactingMakeWriter<M>
+where
+    M: tracing_subscriber::fmt::MakeWriter<'a>,
+{
+    type Writer = RedactingWriter<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RedactingWriter {
+            inner: self.inner.make_writer(),
+            redactor: self.redactor.clone(),
+        }
+    }
+}
+
+/// Writer produced by [`RedactingMakeWriter`].
+#[derive(Debug)]
+pub struct RedactingWriter<W> {
+    inner: W,
+    redactor: Arc<Redactor>,
+}
+
+impl<W: Write> Write for RedactingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        // The fmt layer writes each event in a single call, so redacting per
+        // write sees whole lines.
+        let text = String::from_utf8_lossy(buf);
+        self.inner
+            .write_all(self.redactor.redact_text(&text).as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
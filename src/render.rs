@@ -0,0 +1,246 @@
+//! Page content in the shape the caller asked for.
+//!
+//! Tools that read a page's content share [`render_page`], which extracts
+//! the main content (`<main>`, `<article>` or the body) once and returns it
+//! as plain text, simplified markdown or raw HTML. Markdown keeps headings,
+//! list items, quotes, code blocks and links; everything else is flattened
+//! into paragraphs.
+
+use std::fmt;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::browser::BrowserSession;
+use crate::error::{Error, Result};
+
+/// Representation of a page's content.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PageFormat {
+    /// Rendered text, as a user would copy it.
+    Text,
+    /// Headings, lists, quotes, code and links as markdown.
+    #[default]
+    Markdown,
+    /// The content element's HTML, unchanged.
+    Html,
+}
+
+impl PageFormat {
+    /// Name used in arguments and results.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Text => "text",
+            Self::Markdown => "markdown",
+            Self::Html => "html",
+        }
+    }
+}
+
+impl fmt::Display for PageFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Extracts the page's main content. Called with the format as a JSON
+/// string; returns `{ title, text }` plus `blocks` for markdown or `html`
+/// for html. Blocks are the outermost headings, paragraphs, list items,
+/// quotes and `<pre>` elements, each with its text and links in order.
+pub const RENDER_PAGE_SCRIPT: &str = r#"((format) => {
+    const root = document.querySelector('main, article, [role="main"]') || document.body;
+    const text = root ? (root.innerText || root.textContent || '') : '';
+    const page = { title: document.title || '', text: text.replace(/\n{3,}/g, '\n\n').trim() };
+    if (!root) return page;
+    if (format === 'html') {
+        page.html = root.outerHTML;
+    } else if (format === 'markdown') {
+        const kinds = { P: 'paragraph', LI: 'item', BLOCKQUOTE: 'quote', PRE: 'code' };
+        const selector = 'h1, h2, h3, h4, h5, h6, p, li, blockquote, pre';
+        const inline = (node, out) => {
+            for (const child of node.childNodes) {
+                if (child.nodeType === Node.TEXT_NODE) {
+                    out.push({ text: child.textContent });
+                } else if (child.nodeType === Node.ELEMENT_NODE) {
+                    if (child.tagName === 'A' && child.href) {
+                        out.push({ text: child.innerText || child.textContent || '', href: child.href });
+                    } else if (!child.matches(selector)) {
+                        inline(child, out);
+                    }
+                }
+            }
+            return out;
+        };
+        page.blocks = Array.from(root.querySelectorAll(selector))
+            .filter(el => !el.parentElement.closest('p, blockquote, pre, h1, h2, h3, h4, h5, h6'))
+            .map(el => {
+                const heading = /^H([1-6])$/.exec(el.tagName);
+                if (heading) return { kind: 'heading', level: Number(heading[1]), inlines: inline(el, []) };
+                if (el.tagName === 'PRE') return { kind: 'code', inlines: [{ text: el.textContent || '' }] };
+                return { kind: kinds[el.tagName], inlines: inline(el, []) };
+            });
+    }
+    return page;
+})"#;
+
+/// A run of text inside a block, optionally linked.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Inline {
+    /// Text as it appears on the page.
+    #[serde(default)]
+    pub text: String,
+    /// Absolute link target, for the text of an `<a>`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub href: Option<String>,
+}
+
+/// Kind of a content block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BlockKind {
+    /// `<h1>` to `<h6>`.
+    Heading,
+    /// `<p>`.
+    Paragraph,
+    /// `<li>`.
+    Item,
+    /// `<blockquote>`.
+    Quote,
+    /// `<pre>`.
+    Code,
+}
+
+/// A block of the page's content, as reported by [`RENDER_PAGE_SCRIPT`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Block {
+    /// What the block is.
+    pub kind: BlockKind,
+    /// Heading level (1 to 6); ignored for other kinds.
+    #[serde(default)]
+    pub level: u8,
+    /// The block's text and links.
+    #[serde(default)]
+    pub inlines: Vec<Inline>,
+}
+
+/// What [`RENDER_PAGE_SCRIPT`] returns.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PageContent {
+    /// Document title.
+    #[serde(default)]
+    pub title: String,
+    /// Rendered text of the content element.
+    #[serde(default)]
+    pub text: String,
+    /// Content blocks (markdown only).
+    #[serde(default)]
+    pub blocks: Vec<Block>,
+    /// HTML of the content element (html only).
+    #[serde(default)]
+    pub html: Option<String>,
+}
+
+/// A page rendered in one format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderedPage {
+    /// Document title.
+    pub title: String,
+    /// Format of `content`.
+    pub format: PageFormat,
+    /// The content itself.
+    pub content: String,
+}
+
+/// Read the current page's main content and render it as `format`.
+pub async fn render_page(session: &dyn BrowserSession, format: PageFormat) -> Result<RenderedPage> {
+    let raw = session
+        .evaluate(&format!(
+            "{}({})",
+            RENDER_PAGE_SCRIPT,
+            serde_json::to_string(format.as_str())?
+        ))
+        .await?;
+    let page: PageContent = serde_json::from_value(raw)
+        .map_err(|e| Error::Internal(format!("unexpected page content format: {}", e)))?;
+    Ok(RenderedPage {
+        title: page.title.clone(),
+        format,
+        content: render(&page, format),
+    })
+}
+
+/// Render extracted content as `format`. Markdown falls back to the plain
+/// text when no blocks were found (pages built from bare `<div>`s), as does
+/// html when the page reported none.
+pub fn render(page: &PageContent, format: PageFormat) -> String {
+    match format {
+        PageFormat::Text => page.text.clone(),
+        PageFormat::Markdown if page.blocks.is_empty() => page.text.clone(),
+        PageFormat::Markdown => to_markdown(&page.blocks),
+        PageFormat::Html => page.html.clone().unwrap_or_else(|| page.text.clone()),
+    }
+}
+
+/// Render blocks as markdown: one blank line between blocks, none between
+/// consecutive list items.
+pub fn to_markdown(blocks: &[Block]) -> String {
+    let mut out = String::new();
+    let mut previous = None;
+    for block in blocks {
+        let rendered = match block.kind {
+            BlockKind::Code => {
+                let code: String = block.inlines.iter().map(|i| i.text.as_str()).collect();
+                format!("```\n{}\n```", code.trim_end_matches('\n'))
+            }
+            kind => {
+                let line = inline_markdown(&block.inlines);
+                if line.is_empty() {
+                    continue;
+                }
+                match kind {
+                    BlockKind::Heading => {
+                        format!("{} {}", "#".repeat(block.level.clamp(1, 6) as usize), line)
+                    }
+                    BlockKind::Item => format!("- {}", line),
+                    BlockKind::Quote => format!("> {}", line),
+                    _ => line,
+                }
+            }
+        };
+        if !out.is_empty() {
+            let in_list = previous == Some(BlockKind::Item) && block.kind == BlockKind::Item;
+            out.push_str(if in_list { "\n" } else { "\n\n" });
+        }
+        out.push_str(&rendered);
+        previous = Some(block.kind);
+    }
+    out
+}
+
+/// Join inline runs on one line, collapsing whitespace and writing links as
+/// `[text](href)`.
+fn inline_markdown(inlines: &[Inline]) -> String {
+    let mut line = String::new();
+    for inline in inlines {
+        let text = inline.text.split_whitespace().collect::<Vec<_>>().join(" ");
+        let piece = match &inline.href {
+            Some(href) if text.is_empty() => format!("<{}>", href),
+            Some(href) => format!("[{}]({})", text.replace(']', "\\]"), href),
+            None => text,
+        };
+        if piece.is_empty() {
+            continue;
+        }
+        // Keep the spacing between runs that the page had
+        if !line.is_empty() && inline.text.starts_with(char::is_whitespace) && !line.ends_with(' ')
+        {
+            line.push(' ');
+        }
+        line.push_str(&piece);
+        if inline.href.is_none() && inline.text.ends_with(char::is_whitespace) {
+            line.push(' ');
+        }
+    }
+    line.trim_end().to_string()
+}
@@ -0,0 +1,152 @@
+//! Client roots, scoping where tools may write files.
+//!
+//! A client that declares the `roots` capability in `initialize` is asked
+//! for its roots (`roots/list`) once it sends `notifications/initialized`,
+//! and again on `notifications/roots/list_changed`. From then on, files are
+//! only written inside one of the listed `file://` roots; until the list
+//! arrives, nothing is. A client without the capability leaves tools to
+//! the server's own directories (e.g. the download directory).
+
+use std::path::{Component, Path, PathBuf};
+use std::sync::RwLock;
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::error::{Error, Result};
+
+/// A root the client exposes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Root {
+    /// `file://` URI of the root directory.
+    pub uri: String,
+    /// Human-readable name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+impl Root {
+    /// The directory this root names, if it is a `file://` URI.
+    pub fn path(&self) -> Option<PathBuf> {
+        Url::parse(&self.uri).ok()?.to_file_path().ok()
+    }
+}
+
+/// The roots of the connected client (shared by all sessions).
+#[derive(Debug, Default)]
+pub struct ClientRoots {
+    /// `None` if the client doesn't declare roots.
+    roots: RwLock<Option<Vec<Root>>>,
+}
+
+impl ClientRoots {
+    /// Roots of a client that doesn't declare any.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The client declared the `roots` capability: refuse every path until
+    /// its roots are [`set`](Self::set).
+    pub fn declared(&self) {
+        let mut roots = self.roots.write().unwrap();
+        if roots.is_none() {
+            *roots = Some(Vec::new());
+        }
+    }
+
+    /// Whether the client declared roots.
+    pub fn is_declared(&self) -> bool {
+        self.roots.read().unwrap().is_some()
+    }
+
+    /// Replace the roots with those the client listed.
+    pub fn set(&self, roots: Vec<Root>) {
+        tracing::info!(
+            "Client roots: {}",
+            roots
+                .iter()
+                .map(|root| root.uri.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        *self.roots.write().unwrap() = Some(roots);
+    }
+
+    /// The client's roots, or `None` if it doesn't declare any.
+    pub fn list(&self) -> Option<Vec<Root>> {
+        self.roots.read().unwrap().clone()
+    }
+
+    /// Refuse `path` unless it is inside one of the client's roots. Every
+    /// path passes if the client doesn't declare roots.
+    pub fn check(&self, path: &Path) -> Result<()> {
+        let Some(roots) = self.list() else {
+            return Ok(());
+        };
+
+        let path = resolve(path);
+        if roots
+            .iter()
+            .filter_map(Root::path)
+            .any(|root| path.starts_with(resolve(&root)))
+        {
+            return Ok(());
+        }
+
+        let listed = if roots.is_empty() {
+            "none".to_string()
+        } else {
+            roots
+                .iter()
+                .map(|root| root.uri.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        Err(Error::PermissionDenied(format!(
+            "{} is outside the client's roots ({})",
+            path.display(),
+            listed
+        )))
+    }
+}
+
+/// `path` made absolute with `.` and `..` resolved, following symlinks as
+/// far as the path exists.
+fn resolve(path: &Path) -> PathBuf {
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir().unwrap_or_default().join(path)
+    };
+
+    let mut resolved = PathBuf::new();
+    for component in absolute.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            other => resolved.push(other),
+        }
+    }
+
+    // Resolve symlinks in the longest existing prefix
+    let mut existing = resolved.as_path();
+    let mut rest = Vec::new();
+    while !existing.exists() {
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name.to_owned());
+                existing = parent;
+            }
+            _ => return resolved,
+        }
+    }
+    match existing.canonicalize() {
+        Ok(mut canonical) => {
+            canonical.extend(rest.iter().rev());
+            canonical
+        }
+        Err(_) => resolved,
+    }
+}
@@ -1,19 +1,36 @@
 //! MCP server implementation.
 
-use std::io::{BufRead, BufReader, Write};
+use std::collections::{HashMap, VecDeque};
+use std::io::ErrorKind;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
-use tokio::sync::RwLock;
+use tokio::io::{AsyncBufRead, AsyncWrite, BufReader};
+use tokio::sync::{Notify, RwLock};
+use tokio::time::MissedTickBehavior;
+use tracing::Instrument;
 
-use webpuppet::PermissionGuard;
+use webpuppet::{Operation, PermissionGuard};
 
-use crate::error::{codes, Result};
+use crate::audit::{self, AuditFeed, AUDIT_URI};
+use crate::call::CallInfo;
+use crate::capture::{self, CAPTURE_URI_PREFIX};
+use crate::clients::ClientWorkarounds;
+use crate::config::ServerConfig;
+use crate::error::{codes, Error, Result};
+use crate::framing::{Inbound, LineReader};
+use crate::notify::NotificationSink;
+use crate::outbound::OutboundQueue;
+use crate::progress::ProgressReporter;
 use crate::protocol::{
-    ClientCapabilities, InitializeParams, InitializeResult, JsonRpcId, JsonRpcRequest,
-    JsonRpcResponse, ListToolsResult, McpMessage, ServerCapabilities, ServerInfo, ToolCallParams,
-    ToolsCapability,
+    ClientCapabilities, CompleteParams, CompleteResult, InitializeParams, InitializeResult,
+    JsonRpcId, JsonRpcRequest, JsonRpcResponse, ListResourceTemplatesResult, ListResourcesResult,
+    ListToolsParams, McpMessage, ReadResourceResult, ResourceContents, ResourceParams,
+    ResourcesCapability, ServerCapabilities, ServerInfo, ToolCallParams, ToolsCapability,
 };
-use crate::tools::ToolRegistry;
+use crate::protolog::{Direction, ProtocolLog};
+use crate::roots::Root;
+use crate::tools::{ToolContext, ToolRegistry};
 
 /// MCP protocol version.
 pub const PROTOCOL_VERSION: &str = "2024-11-05";
@@ -31,16 +48,31 @@ pub enum ServerState {
     Uninitialized,
     /// Server is initialized and ready.
     Ready,
-    /// Server is shutting down.
+    /// `shutdown` was received; only `exit` is accepted now.
     ShuttingDown,
+    /// `exit` was received; the server stops reading.
+    Exited,
 }
 
 /// MCP server for webpuppet.
 pub struct McpServer {
     state: Arc<RwLock<ServerState>>,
     tools: Arc<ToolRegistry>,
-    #[allow(dead_code)]
+    outbound: Arc<OutboundQueue>,
+    /// Notifications the server sends itself, through `outbound`.
+    notifications: NotificationSink,
+    /// Per-session tool contexts, keyed by session id.
+    sessions: RwLock<HashMap<String, Arc<ToolContext>>>,
+    /// Raw log of the traffic handled by [`serve`](Self::serve).
+    protocol_log: Option<Arc<ProtocolLog>>,
+    /// Subscription to the permission audit resource.
+    audit: AuditFeed,
+    /// Capabilities the client declared in `initialize`.
     client_capabilities: Arc<RwLock<Option<ClientCapabilities>>>,
+    /// Requests sent to the client and not answered yet: method by id.
+    client_requests: std::sync::Mutex<HashMap<String, String>>,
+    /// Sequence number of the next request sent to the client.
+    next_client_request: AtomicU64,
 }
 
 impl McpServer {
@@ -51,68 +83,583 @@ impl McpServer {
 
     /// Create a new MCP server with custom permissions.
     pub fn with_permissions(permissions: PermissionGuard) -> Self {
-        Self {
-            state: Arc::new(RwLock::new(ServerState::Uninitialized)),
-            tools: Arc::new(ToolRegistry::new(permissions)),
-            client_capabilities: Arc::new(RwLock::new(None)),
-        }
+        Self::with_registry(ToolRegistry::new(permissions))
     }
 
     /// Create a new MCP server with visible browser (non-headless).
     pub fn with_visible_browser(permissions: PermissionGuard) -> Self {
+        Self::with_registry(ToolRegistry::with_visible_browser(permissions))
+    }
+
+    /// Create a new MCP server from configuration.
+    pub fn with_config(permissions: PermissionGuard, config: ServerConfig) -> Self {
+        Self::with_registry(ToolRegistry::with_config(permissions, config))
+    }
+
+    /// Create a new MCP server around a pre-built tool registry.
+    ///
+    /// Use this to add custom tools or to supply a custom [`ToolContext`]
+    /// (e.g. one with a different browser launcher) via
+    /// [`ToolRegistry::with_context`].
+    pub fn with_registry(tools: ToolRegistry) -> Self {
+        let capacity = tools.context().config.notification_capacity;
+        let outbound = Arc::new(OutboundQueue::new(capacity));
+        // Let tools reach this server's client
+        if !tools.context().notifications.connect(outbound.clone()) {
+            tracing::warn!("Tool context already sends notifications to another server");
+        }
         Self {
             state: Arc::new(RwLock::new(ServerState::Uninitialized)),
-            tools: Arc::new(ToolRegistry::with_visible_browser(permissions)),
+            tools: Arc::new(tools),
+            notifications: NotificationSink::connected(outbound.clone()),
+            outbound,
+            sessions: RwLock::new(HashMap::new()),
+            protocol_log: None,
+            audit: AuditFeed::default(),
             client_capabilities: Arc::new(RwLock::new(None)),
+            client_requests: std::sync::Mutex::new(HashMap::new()),
+            next_client_request: AtomicU64::new(1),
         }
     }
 
+    /// Record every line read and written by [`serve`](Self::serve) in a
+    /// protocol log.
+    pub fn with_protocol_log(mut self, log: ProtocolLog) -> Self {
+        self.protocol_log = Some(Arc::new(log));
+        self
+    }
+
+    /// Queue of messages waiting to be written to the client.
+    pub fn outbound(&self) -> &Arc<OutboundQueue> {
+        &self.outbound
+    }
+
+    /// Send a notification to the client.
+    ///
+    /// Notifications are queued and may be dropped (oldest first) if the
+    /// client isn't reading; see [`OutboundQueue`].
+    pub fn notify(&self, method: &str, params: serde_json::Value) {
+        self.notifications.notify(method, params);
+    }
+
+    /// Send a request to the client; its response is handled by
+    /// [`handle_client_response`](Self::handle_client_response).
+    ///
+    /// Ids are strings (`webpuppet-<n>`) so they never collide with the
+    /// client's own request ids. Returns the id, unless the request could
+    /// not be sent.
+    fn request_client(&self, method: &str, params: Option<serde_json::Value>) -> Option<String> {
+        let id = format!(
+            "webpuppet-{}",
+            self.next_client_request.fetch_add(1, Ordering::Relaxed)
+        );
+        let request = JsonRpcRequest {
+            jsonrpc: "2.0".into(),
+            id: Some(JsonRpcId::String(id.clone())),
+            method: method.into(),
+            params,
+        };
+        match serde_json::to_string(&request) {
+            Ok(message) => {
+                self.client_requests
+                    .lock()
+                    .unwrap()
+                    .insert(id.clone(), method.to_string());
+                // Like responses, requests are never dropped
+                self.outbound.push_response(message);
+                Some(id)
+            }
+            Err(e) => {
+                tracing::warn!("Cannot serialize {} request: {}", method, e);
+                None
+            }
+        }
+    }
+
+    /// Handle the client's response to a request sent with
+    /// [`request_client`](Self::request_client).
+    fn handle_client_response(&self, response: JsonRpcResponse) {
+        let method = match response.id {
+            Some(JsonRpcId::String(ref id)) => self.client_requests.lock().unwrap().remove(id),
+            _ => None,
+        };
+        let Some(method) = method else {
+            tracing::debug!("Ignoring response to unknown request {:?}", response.id);
+            return;
+        };
+        if let Some(error) = response.error {
+            tracing::warn!("Client failed {}: {}", method, error.message);
+            return;
+        }
+
+        if method == "roots/list" {
+            let roots = response
+                .result
+                .and_then(|result| result.get("roots").cloned())
+                .and_then(|roots| serde_json::from_value::<Vec<Root>>(roots).ok());
+            match roots {
+                Some(roots) => self.tools.context().roots.set(roots),
+                None => tracing::warn!("Client sent malformed roots"),
+            }
+        }
+    }
+
+    /// Ask the client for its roots, if it declared any.
+    async fn refresh_roots(&self) {
+        let declared = self
+            .client_capabilities
+            .read()
+            .await
+            .as_ref()
+            .is_some_and(|capabilities| capabilities.roots.is_some());
+        if declared {
+            let _ = self.request_client("roots/list", None);
+        }
+    }
+
+    /// Whether the client displays image content (see
+    /// [`ClientCapabilities::accepts_images`]).
+    async fn client_accepts_images(&self) -> bool {
+        self.client_capabilities
+            .read()
+            .await
+            .as_ref()
+            .is_none_or(ClientCapabilities::accepts_images)
+    }
+
     /// Run the server on stdio.
     pub async fn run_stdio(&self) -> Result<()> {
-        let stdin = std::io::stdin();
-        let mut stdout = std::io::stdout();
-        let reader = BufReader::new(stdin.lock());
-
         tracing::info!("MCP server starting on stdio");
 
-        for line in reader.lines() {
-            let line = line?;
+        self.serve(BufReader::new(tokio::io::stdin()), tokio::io::stdout())
+            .await
+    }
 
-            if line.is_empty() {
+    /// Serve newline-delimited JSON-RPC messages from `reader`, writing
+    /// responses and notifications to `writer`.
+    ///
+    /// Writes go through the bounded [`OutboundQueue`], so a client that
+    /// stops reading never blocks request handling. A client that goes away
+    /// (EOF, broken pipe, reset connection) is a clean shutdown, not an error.
+    ///
+    /// A `shutdown` request is answered and leaves input open; later requests
+    /// are refused until the `exit` notification, which ends serving. Only
+    /// I/O failures make this return an error.
+    ///
+    /// With [`prewarm`](ServerConfig::prewarm) set, the browser is launched
+    /// alongside, without holding up `initialize`.
+    pub async fn serve<R, W>(&self, reader: R, writer: W) -> Result<()>
+    where
+        R: AsyncBufRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let writer_gone = Notify::new();
+
+        if self.tools.context().config.prewarm {
+            // In the background, so the client can initialize meanwhile
+            let context = self.tools.context().clone();
+            tokio::spawn(async move { context.prewarm().await });
+        }
+
+        let read_loop = async {
+            let result = tokio::select! {
+                result = self.read_loop(reader) => result,
+                _ = writer_gone.notified() => Ok(()),
+                _ = self.heartbeat() => Ok(()),
+                _ = self.liveness() => Ok(()),
+            };
+            self.outbound.close();
+            result
+        };
+
+        let write_loop = async {
+            let result = self
+                .outbound
+                .drain_logged(writer, self.protocol_log.as_deref())
+                .await;
+            if result.is_err() {
+                // Nobody is listening any more; stop handling requests
+                writer_gone.notify_one();
+            }
+            result
+        };
+
+        let (read_result, write_result) = tokio::join!(read_loop, write_loop);
+        let read_result = read_result.or_else(|e| match e {
+            Error::Io(ref io) if is_disconnect(io) => {
+                tracing::info!("Client closed input: {}", io);
+                Ok(())
+            }
+            other => Err(other),
+        });
+        let write_result = write_result.or_else(|e| {
+            if is_disconnect(&e) {
+                tracing::info!("Client stopped reading output: {}", e);
+                Ok(())
+            } else {
+                Err(e)
+            }
+        });
+
+        tracing::info!("MCP server shutting down");
+        if let Err(e) = self.tools.context().close_puppet().await {
+            tracing::warn!("Failed to close browser session: {}", e);
+        }
+        let sessions: Vec<_> = self.sessions.write().await.drain().collect();
+        for (id, context) in sessions {
+            if let Err(e) = context.close_puppet().await {
+                tracing::warn!("Failed to close browser of session {}: {}", id, e);
+            }
+        }
+
+        read_result?;
+        write_result?;
+        Ok(())
+    }
+
+    /// Ping the client every [`ping_interval`](ServerConfig::ping_interval)
+    /// once it is initialized, and return when a ping goes unanswered for
+    /// [`ping_timeout`](ServerConfig::ping_timeout), so a host that silently
+    /// went away doesn't keep the server (and its browser) alive. Never
+    /// returns if pings are off.
+    ///
+    /// Any response counts as an answer, even an error.
+    async fn liveness(&self) {
+        let config = &self.tools.context().config;
+        if config.ping_interval.is_zero() {
+            return std::future::pending().await;
+        }
+
+        loop {
+            tokio::time::sleep(config.ping_interval).await;
+            if *self.state.read().await != ServerState::Ready {
+                continue;
+            }
+            let Some(id) = self.request_client("ping", None) else {
                 continue;
+            };
+
+            tokio::time::sleep(config.ping_timeout).await;
+            if self.client_requests.lock().unwrap().remove(&id).is_some() {
+                tracing::warn!(
+                    "Client did not answer a ping within {}s; closing the connection",
+                    config.ping_timeout.as_secs_f64()
+                );
+                return;
             }
+        }
+    }
+
+    /// Send a heartbeat notification every
+    /// [`heartbeat_interval`](ServerConfig::heartbeat_interval) while a tool
+    /// call or a human intervention is in progress, so hosts that drop quiet
+    /// connections keep this one open. Never returns.
+    ///
+    /// Heartbeats are ordinary queued notifications written whole by the
+    /// outbound writer, so they can't interleave with a response.
+    async fn heartbeat(&self) {
+        let context = self.tools.context();
+        let period = context.config.heartbeat_interval;
+        if period.is_zero() {
+            return std::future::pending().await;
+        }
+
+        let mut ticker = tokio::time::interval(period);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        // The first tick completes immediately
+        ticker.tick().await;
+
+        let mut sequence = 0u64;
+        loop {
+            ticker.tick().await;
 
-            tracing::debug!("Received: {}", line);
+            let reason = if context.intervention_handler.read().await.is_waiting() {
+                "intervention"
+            } else if context.calls_in_flight() > 0 {
+                "tool_call"
+            } else {
+                continue;
+            };
+
+            sequence += 1;
+            self.notifications.log(
+                "debug",
+                SERVER_NAME,
+                serde_json::json!({"heartbeat": sequence, "reason": reason}),
+            );
+        }
+    }
 
-            let response = self.handle_message(&line).await;
+    /// Handle messages one at a time. While a request is being handled, the
+    /// next lines are still read: a `notifications/cancelled` for it stops
+    /// the request (see [`run_cancellable`](Self::run_cancellable)), anything
+    /// else waits its turn.
+    async fn read_loop<R: AsyncBufRead + Unpin>(&self, reader: R) -> Result<()> {
+        let mut lines = LineReader::new(reader, self.tools.context().config.max_message_bytes);
+        let mut pending = VecDeque::new();
+        let mut input_done = false;
+
+        loop {
+            let line = match pending.pop_front() {
+                Some(line) => line,
+                None if input_done => break,
+                None => match lines.next_line().await? {
+                    Some(inbound) => match self.receive(inbound)? {
+                        Some(line) => line,
+                        None => continue,
+                    },
+                    None => break,
+                },
+            };
+            if line.is_empty() {
+                continue;
+            }
+
+            let response = self
+                .run_cancellable(&line, &mut lines, &mut pending, &mut input_done)
+                .await?;
 
             if let Some(response) = response {
-                let json = serde_json::to_string(&response)?;
-                tracing::debug!("Sending: {}", json);
-                writeln!(stdout, "{}", json)?;
-                stdout.flush()?;
+                self.outbound
+                    .push_response(serde_json::to_string(&response)?);
             }
 
-            // Check if we should exit
-            if *self.state.read().await == ServerState::ShuttingDown {
+            if *self.state.read().await == ServerState::Exited {
                 break;
             }
         }
 
-        tracing::info!("MCP server shutting down");
         Ok(())
     }
 
-    /// Handle an incoming message.
+    /// Handle one message while watching the input for its cancellation.
+    ///
+    /// Lines read in the meantime are queued in `pending`, except calls to
+    /// the intervention tools, which are handled right away: the request may
+    /// be held until a human confirms it (see [`confirm`](crate::confirm)),
+    /// and that answer can't wait for the request to finish. If the client
+    /// cancels the request, handling is abandoned, no response is sent, and
+    /// a cancelled tool call also closes the browser, since it may have
+    /// been left mid-action (e.g. with a response still generating).
+    async fn run_cancellable<R: AsyncBufRead + Unpin>(
+        &self,
+        line: &str,
+        lines: &mut LineReader<R>,
+        pending: &mut VecDeque<String>,
+        input_done: &mut bool,
+    ) -> Result<Option<JsonRpcResponse>> {
+        let request = serde_json::from_str::<serde_json::Value>(line).ok();
+        let id = request.as_ref().and_then(|r| r.get("id")).cloned();
+        let is_tool_call = request
+            .as_ref()
+            .is_some_and(|r| r.get("method").and_then(|m| m.as_str()) == Some("tools/call"));
+
+        let handling = self.handle_message(line);
+        tokio::pin!(handling);
+
+        loop {
+            tokio::select! {
+                response = &mut handling => return Ok(response),
+                next = lines.next_line(), if !*input_done && id.is_some() => match next? {
+                    Some(next) => if let Some(next) = self.receive(next)? {
+                        if id.as_ref().is_some_and(|id| cancels(&next, id)) {
+                            break;
+                        }
+                        // Answers to our requests (e.g. pings) can't wait
+                        if let Ok(McpMessage::Response(response)) = McpMessage::parse(&next) {
+                            self.handle_client_response(response);
+                            continue;
+                        }
+                        // Nor can the human's answer to an intervention holding this request
+                        if answers_intervention(&next) {
+                            if let Some(response) = self.handle_message(&next).await {
+                                self.outbound
+                                    .push_response(serde_json::to_string(&response)?);
+                            }
+                            continue;
+                        }
+                        pending.push_back(next);
+                    },
+                    None => *input_done = true,
+                },
+            }
+        }
+
+        tracing::info!("Request {} cancelled by client", id.unwrap_or_default());
+        if is_tool_call {
+            if let Err(e) = self.tools.context().close_puppet().await {
+                tracing::warn!("Failed to close browser after cancellation: {}", e);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Take in a line read from the client: record it and return it, or
+    /// answer an oversized one with an `INVALID_REQUEST` error right away.
+    fn receive(&self, inbound: Inbound) -> Result<Option<String>> {
+        match inbound {
+            Inbound::Line(line) => {
+                self.log_inbound(&line);
+                Ok(Some(line))
+            }
+            Inbound::Oversized { id, bytes } => {
+                let limit = self.tools.context().config.max_message_bytes;
+                tracing::warn!("Discarded a {}-byte message (limit {} bytes)", bytes, limit);
+                let response = JsonRpcResponse::error(
+                    id,
+                    codes::INVALID_REQUEST,
+                    format!(
+                        "message of {} bytes exceeds the {}-byte limit",
+                        bytes, limit
+                    ),
+                );
+                self.outbound
+                    .push_response(serde_json::to_string(&response)?);
+                Ok(None)
+            }
+        }
+    }
+
+    fn log_inbound(&self, line: &str) {
+        if let Some(ref log) = self.protocol_log {
+            log.record(Direction::In, line);
+        }
+    }
+
+    /// Handle a single request without a client handshake, for scripts and
+    /// health checks.
+    ///
+    /// The server initializes itself first (unless `request` is an
+    /// `initialize`), then handles the request and closes the browser.
+    /// `jsonrpc` and `id` may be left out of the request. Method filters and
+    /// the permission policy apply as usual.
+    pub async fn handle_once(&self, request: &str) -> Result<JsonRpcResponse> {
+        let mut request: serde_json::Value = serde_json::from_str(request)?;
+        let Some(fields) = request.as_object_mut() else {
+            return Err(Error::InvalidParams("request must be a JSON object".into()));
+        };
+        fields.entry("jsonrpc").or_insert_with(|| "2.0".into());
+        fields.entry("id").or_insert_with(|| 1.into());
+
+        if fields.get("method").and_then(|m| m.as_str()) != Some("initialize") {
+            let initialize = serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "method": "initialize",
+                "params": {
+                    "protocolVersion": PROTOCOL_VERSION,
+                    "capabilities": {},
+                    "clientInfo": {"name": format!("{} --once", SERVER_NAME), "version": SERVER_VERSION}
+                }
+            });
+            self.handle_message(&initialize.to_string()).await;
+        }
+
+        let response = self.handle_message(&request.to_string()).await;
+
+        if let Err(e) = self.tools.context().close_puppet().await {
+            tracing::warn!("Failed to close browser session: {}", e);
+        }
+        response.ok_or_else(|| Error::Internal("request produced no response".into()))
+    }
+
+    /// Handle an incoming message in the default session.
+    ///
+    /// Both the message and the response are logged at debug level after
+    /// passing through the configured [`Redactor`](crate::redact::Redactor).
     pub async fn handle_message(&self, json: &str) -> Option<JsonRpcResponse> {
+        let context = self.tools.context().clone();
+        self.handle_message_in(&context, json).await
+    }
+
+    /// Handle an incoming message in an isolated session.
+    ///
+    /// Each session id gets its own browser session and intervention state,
+    /// so concurrent clients can't pause, resume or navigate each other's
+    /// browser. Tool definitions, permissions and configuration are shared.
+    pub async fn handle_session_message(
+        &self,
+        session_id: &str,
+        json: &str,
+    ) -> Option<JsonRpcResponse> {
+        let context = self.session_context(session_id).await;
+        self.handle_message_in(&context, json).await
+    }
+
+    /// Get the tool context of a session, creating it on first use.
+    pub async fn session_context(&self, session_id: &str) -> Arc<ToolContext> {
+        if let Some(context) = self.sessions.read().await.get(session_id) {
+            return context.clone();
+        }
+
+        self.sessions
+            .write()
+            .await
+            .entry(session_id.to_string())
+            .or_insert_with(|| {
+                tracing::debug!("Starting session {}", session_id);
+                Arc::new(self.tools.context().new_session())
+            })
+            .clone()
+    }
+
+    /// End a session, closing its browser.
+    pub async fn close_session(&self, session_id: &str) -> Result<()> {
+        let context = self.sessions.write().await.remove(session_id);
+        match context {
+            Some(context) => context.close_puppet().await,
+            None => Ok(()),
+        }
+    }
+
+    async fn handle_message_in(
+        &self,
+        context: &Arc<ToolContext>,
+        json: &str,
+    ) -> Option<JsonRpcResponse> {
+        let redactor = &context.redactor;
+        tracing::debug!("Received: {}", redactor.redact_json(json));
+
+        let response = self.dispatch_message(context, json).await;
+        self.publish_audit(context);
+
+        if let Some(ref response) = response {
+            if let Ok(value) = serde_json::to_value(response) {
+                tracing::debug!("Sending: {}", redactor.redact_value(&value));
+            }
+        }
+
+        response
+    }
+
+    /// Send a `notifications/resources/updated` for every permission decision
+    /// made since the last call, if the client subscribed to the audit
+    /// resource.
+    fn publish_audit(&self, context: &ToolContext) {
+        for entry in self.audit.take_new(&context.permissions) {
+            self.notifications.resource_updated(
+                AUDIT_URI,
+                serde_json::json!({
+                    "decision": audit::decision_json(&entry, &context.redactor),
+                }),
+            );
+        }
+    }
+
+    /// Route a parsed message to the request or notification handlers.
+    async fn dispatch_message(
+        &self,
+        context: &Arc<ToolContext>,
+        json: &str,
+    ) -> Option<JsonRpcResponse> {
         match McpMessage::parse(json) {
-            Ok(McpMessage::Request(request)) => Some(self.handle_request(request).await),
+            Ok(McpMessage::Request(request)) => Some(self.handle_request(context, request).await),
             Ok(McpMessage::Notification(notification)) => {
                 self.handle_notification(notification).await;
                 None
             }
-            Ok(McpMessage::Response(_)) => {
-                // We don't expect responses in this direction
+            Ok(McpMessage::Response(response)) => {
+                self.handle_client_response(response);
                 None
             }
             Err(e) => Some(JsonRpcResponse::error(
@@ -124,15 +671,56 @@ impl McpServer {
     }
 
     /// Handle a JSON-RPC request.
-    async fn handle_request(&self, request: JsonRpcRequest) -> JsonRpcResponse {
+    async fn handle_request(
+        &self,
+        context: &Arc<ToolContext>,
+        request: JsonRpcRequest,
+    ) -> JsonRpcResponse {
         let id = request.id.clone();
 
+        if !context.config.method_allowed(&request.method) {
+            let error = Error::Disabled(format!("method {}", request.method));
+            tracing::warn!("Rejected call: {}", error);
+            return JsonRpcResponse::error_with_data(
+                id,
+                error.code(),
+                error.to_string(),
+                error.data(),
+            );
+        }
+
+        // After `shutdown`, the client may only send `exit`
+        if *self.state.read().await == ServerState::ShuttingDown {
+            return JsonRpcResponse::error(
+                id,
+                codes::INVALID_REQUEST,
+                "server is shutting down; only the exit notification is accepted",
+            );
+        }
+
         match request.method.as_str() {
             "initialize" => self.handle_initialize(id, request.params).await,
-            "tools/list" => self.handle_tools_list(id).await,
-            "tools/call" => self.handle_tools_call(id, request.params).await,
+            "tools/list" => self.handle_tools_list(id, request.params).await,
+            "tools/call" => self.handle_tools_call(context, id, request.params).await,
+            "completion/complete" => self.handle_complete(id, request.params).await,
+            "resources/list" => {
+                let mut resources = vec![audit::audit_resource()];
+                resources.extend(context.captures.resources());
+                JsonRpcResponse::success(id, ListResourcesResult { resources })
+            }
+            "resources/templates/list" => JsonRpcResponse::success(
+                id,
+                ListResourceTemplatesResult {
+                    resource_templates: vec![capture::capture_template()],
+                },
+            ),
+            "resources/read" | "resources/subscribe" | "resources/unsubscribe" => {
+                self.handle_resource(context, id, &request.method, request.params)
+            }
             "ping" => JsonRpcResponse::success(id, serde_json::json!({})),
             "shutdown" => {
+                // Keep reading until `exit` (or the end of input)
+                tracing::info!("Shutdown requested; waiting for exit");
                 *self.state.write().await = ServerState::ShuttingDown;
                 JsonRpcResponse::success(id, serde_json::json!({}))
             }
@@ -149,12 +737,20 @@ impl McpServer {
         match notification.method.as_str() {
             "notifications/initialized" => {
                 tracing::info!("Client initialized");
+                self.refresh_roots().await;
+            }
+            "notifications/roots/list_changed" => {
+                self.refresh_roots().await;
             }
             "notifications/cancelled" => {
                 tracing::debug!("Request cancelled by client");
             }
             "exit" => {
-                *self.state.write().await = ServerState::ShuttingDown;
+                let mut state = self.state.write().await;
+                if *state != ServerState::ShuttingDown {
+                    tracing::info!("Exit received without a shutdown request");
+                }
+                *state = ServerState::Exited;
             }
             _ => {
                 tracing::debug!("Unknown notification: {}", notification.method);
@@ -168,8 +764,18 @@ impl McpServer {
         id: Option<JsonRpcId>,
         params: Option<serde_json::Value>,
     ) -> JsonRpcResponse {
+        // The first initialize is authoritative; a repeat must not reset
+        // what it negotiated
+        if *self.state.read().await == ServerState::Ready {
+            return JsonRpcResponse::error(
+                id,
+                codes::INVALID_REQUEST,
+                "server already initialized",
+            );
+        }
+
         // Parse params
-        let _params: InitializeParams = match params {
+        let params: InitializeParams = match params {
             Some(p) => match serde_json::from_value(p) {
                 Ok(params) => params,
                 Err(e) => {
@@ -189,6 +795,26 @@ impl McpServer {
             }
         };
 
+        if params.capabilities.roots.is_some() {
+            // Files stay unwritable until the roots are listed
+            self.tools.context().roots.declared();
+        }
+        *self.client_capabilities.write().await = Some(params.capabilities);
+
+        let context = self.tools.context();
+        let workarounds = ClientWorkarounds::for_client(
+            &params.client_info.name,
+            &context.config.client_workarounds,
+        );
+        if !workarounds.is_empty() {
+            tracing::info!(
+                "Applying workarounds for client {}: {}",
+                params.client_info.name,
+                workarounds
+            );
+        }
+        context.set_client_workarounds(workarounds);
+
         // Update state
         *self.state.write().await = ServerState::Ready;
 
@@ -199,9 +825,13 @@ impl McpServer {
                 tools: Some(ToolsCapability {
                     list_changed: false,
                 }),
-                resources: None,
+                resources: Some(ResourcesCapability {
+                    subscribe: true,
+                    list_changed: false,
+                }),
                 prompts: None,
                 logging: None,
+                experimental: Some(self.experimental_capabilities()),
             },
             server_info: ServerInfo {
                 name: SERVER_NAME.into(),
@@ -212,22 +842,264 @@ impl McpServer {
         JsonRpcResponse::success(id, result)
     }
 
-    /// Handle tools/list request.
-    async fn handle_tools_list(&self, id: Option<JsonRpcId>) -> JsonRpcResponse {
+    /// Optional features active in this build and configuration, advertised
+    /// under `capabilities.experimental.webpuppet` so clients can degrade
+    /// gracefully.
+    fn experimental_capabilities(&self) -> serde_json::Value {
+        let context = self.tools.context();
+
+        serde_json::json!({
+            "webpuppet": {
+                "features": {
+                    "realScreenshots": true,
+                    // No tool evaluates arbitrary JavaScript
+                    "jsEval": false,
+                    "httpTransport": cfg!(feature = "http"),
+                    "stdioTransport": cfg!(feature = "stdio"),
+                    "visibleBrowser": !context.is_headless(),
+                },
+                "defaultProvider": context.default_provider.name(),
+                "heartbeatMs": context.config.heartbeat_interval.as_millis() as u64,
+                "selfCheck": self.self_check(),
+            }
+        })
+    }
+
+    /// Check, without launching a browser, that the environment is usable:
+    /// which browsers are installed, whether the selected profile exists and
+    /// whether the default provider may be prompted. Problems are listed
+    /// under `warnings` so clients see them before the first tool call.
+    fn self_check(&self) -> serde_json::Value {
+        let context = self.tools.context();
+        let mut warnings = Vec::new();
+
+        let browsers: Vec<serde_json::Value> = context
+            .launcher
+            .detect_browsers()
+            .iter()
+            .map(|b| {
+                serde_json::json!({
+                    "type": b.browser_type.to_string(),
+                    "version": b.version,
+                })
+            })
+            .collect();
+        if browsers.is_empty() {
+            warnings
+                .push("no supported browser found; install Brave, Chrome or Chromium".to_string());
+        }
+
+        let profile = context.profile();
+        let profile_valid = match (&profile, context.launcher.profiles(context.config.browser)) {
+            (None, _) => true,
+            (Some(name), Ok(available)) => available.contains(name),
+            (Some(_), Err(_)) => false,
+        };
+        if !profile_valid {
+            warnings.push(format!(
+                "browser profile '{}' not found",
+                profile.as_deref().unwrap_or_default()
+            ));
+        }
+
+        let provider = context.default_provider;
+        let provider_allowed = context
+            .permissions
+            .require_with_url(Operation::SendPrompt, provider.base_url())
+            .is_ok();
+        if !provider_allowed {
+            warnings.push(format!(
+                "the permission policy doesn't allow prompting the default provider {}",
+                provider.name()
+            ));
+        }
+
+        serde_json::json!({
+            "browsers": browsers,
+            "browserCount": browsers.len(),
+            "profile": {
+                "name": profile,
+                "valid": profile_valid,
+            },
+            "defaultProvider": {
+                "name": provider.name(),
+                "allowed": provider_allowed,
+            },
+            "warnings": warnings,
+        })
+    }
+
+    /// Handle tools/list request, optionally for a single tool by name.
+    async fn handle_tools_list(
+        &self,
+        id: Option<JsonRpcId>,
+        params: Option<serde_json::Value>,
+    ) -> JsonRpcResponse {
         let state = *self.state.read().await;
         if state != ServerState::Ready {
             return JsonRpcResponse::error(id, codes::INTERNAL_ERROR, "server not initialized");
         }
+        let params: ListToolsParams = match params.map(serde_json::from_value) {
+            Some(Ok(params)) => params,
+            Some(Err(e)) => {
+                return JsonRpcResponse::error(
+                    id,
+                    codes::INVALID_PARAMS,
+                    format!("invalid tools/list params: {}", e),
+                );
+            }
+            None => ListToolsParams::default(),
+        };
+        let mut definitions = self.tools.list_tools();
+        if let Some(ref name) = params.name {
+            definitions.retain(|definition| definition.name == *name);
+            if definitions.is_empty() {
+                return JsonRpcResponse::error(
+                    id,
+                    codes::INVALID_PARAMS,
+                    format!("unknown tool: {}", name),
+                );
+            }
+        }
 
-        let tools = self.tools.list_tools();
-        let result = ListToolsResult { tools };
+        // Advertise each tool's required operations in its `_meta`
+        let tools: Vec<serde_json::Value> = definitions
+            .into_iter()
+            .map(|definition| {
+                let operations = self
+                    .tools
+                    .required_operations(&definition.name)
+                    .unwrap_or_default();
+                let mut value = serde_json::to_value(&definition).unwrap_or_default();
+                value["_meta"] = serde_json::json!({
+                    "webpuppet/requiredOperations": operations
+                });
+                value
+            })
+            .collect();
 
-        JsonRpcResponse::success(id, result)
+        JsonRpcResponse::success(id, serde_json::json!({ "tools": tools }))
+    }
+
+    /// Handle resources/read, resources/subscribe and resources/unsubscribe.
+    fn handle_resource(
+        &self,
+        context: &ToolContext,
+        id: Option<JsonRpcId>,
+        method: &str,
+        params: Option<serde_json::Value>,
+    ) -> JsonRpcResponse {
+        let params: ResourceParams = match params.map(serde_json::from_value) {
+            Some(Ok(params)) => params,
+            Some(Err(e)) => {
+                return JsonRpcResponse::error(
+                    id,
+                    codes::INVALID_PARAMS,
+                    format!("invalid resource params: {}", e),
+                );
+            }
+            None => {
+                return JsonRpcResponse::error(id, codes::INVALID_PARAMS, "resource uri required");
+            }
+        };
+        if params.uri.starts_with(CAPTURE_URI_PREFIX) && method == "resources/read" {
+            return match context.captures.get(&params.uri) {
+                Some(capture) => JsonRpcResponse::success(
+                    id,
+                    ReadResourceResult {
+                        contents: vec![ResourceContents {
+                            uri: capture.uri,
+                            mime_type: Some(capture.mime_type),
+                            text: String::new(),
+                            blob: Some(capture.data),
+                        }],
+                    },
+                ),
+                None => JsonRpcResponse::error(
+                    id,
+                    codes::INVALID_PARAMS,
+                    format!("unknown or expired capture: {}", params.uri),
+                ),
+            };
+        }
+        if params.uri != AUDIT_URI {
+            return JsonRpcResponse::error(
+                id,
+                codes::INVALID_PARAMS,
+                format!("unknown resource: {}", params.uri),
+            );
+        }
+
+        match method {
+            "resources/read" => {
+                let decisions: Vec<serde_json::Value> = context
+                    .permissions
+                    .audit_log()
+                    .iter()
+                    .map(|entry| audit::decision_json(entry, &context.redactor))
+                    .collect();
+                let text = serde_json::to_string_pretty(&decisions).unwrap_or_default();
+                JsonRpcResponse::success(
+                    id,
+                    ReadResourceResult {
+                        contents: vec![ResourceContents {
+                            uri: AUDIT_URI.into(),
+                            mime_type: Some("application/json".into()),
+                            text,
+                            blob: None,
+                        }],
+                    },
+                )
+            }
+            "resources/subscribe" => {
+                self.audit.subscribe(&context.permissions);
+                JsonRpcResponse::success(id, serde_json::json!({}))
+            }
+            _ => {
+                self.audit.unsubscribe();
+                JsonRpcResponse::success(id, serde_json::json!({}))
+            }
+        }
+    }
+
+    /// Handle completion/complete request.
+    async fn handle_complete(
+        &self,
+        id: Option<JsonRpcId>,
+        params: Option<serde_json::Value>,
+    ) -> JsonRpcResponse {
+        let state = *self.state.read().await;
+        if state != ServerState::Ready {
+            return JsonRpcResponse::error(id, codes::INTERNAL_ERROR, "server not initialized");
+        }
+
+        let params: CompleteParams = match params.map(serde_json::from_value) {
+            Some(Ok(params)) => params,
+            Some(Err(e)) => {
+                return JsonRpcResponse::error(
+                    id,
+                    codes::INVALID_PARAMS,
+                    format!("invalid completion params: {}", e),
+                );
+            }
+            None => {
+                return JsonRpcResponse::error(
+                    id,
+                    codes::INVALID_PARAMS,
+                    "completion params required",
+                );
+            }
+        };
+
+        let completion = self.tools.complete(&params.reference, &params.argument);
+
+        JsonRpcResponse::success(id, CompleteResult { completion })
     }
 
     /// Handle tools/call request.
     async fn handle_tools_call(
         &self,
+        context: &ToolContext,
         id: Option<JsonRpcId>,
         params: Option<serde_json::Value>,
     ) -> JsonRpcResponse {
@@ -257,17 +1129,89 @@ impl McpServer {
             }
         };
 
-        // Execute tool
-        match self.tools.execute(&params.name, params.arguments).await {
-            Ok(result) => JsonRpcResponse::success(id, result),
+        let span = tracing::info_span!(
+            "tool_call",
+            request_id = tracing::field::Empty,
+            tool = %params.name
+        );
+        if let Some(ref id) = id {
+            span.record("request_id", tracing::field::display(id));
+        }
+
+        // Execute tool, with progress notifications if the client asked for them
+        let execution = CallInfo::new(id.clone(), &params.name)
+            .scope(
+                self.tools
+                    .execute_in(context, &params.name, params.arguments),
+            )
+            .instrument(span.clone());
+        let progress_token = params.meta.and_then(|meta| meta.progress_token);
+        let result = match progress_token {
+            Some(token) => {
+                ProgressReporter::new(token, self.notifications.clone())
+                    .scope(execution)
+                    .await
+            }
+            None => execution.await,
+        };
+
+        match result {
+            Ok(mut result) => {
+                if !self.client_accepts_images().await {
+                    context.captures.hold_images(&mut result);
+                }
+                JsonRpcResponse::success(id, result)
+            }
             Err(e) => {
-                tracing::error!("Tool {} failed: {}", params.name, e);
-                JsonRpcResponse::error(id, e.code(), e.to_string())
+                span.in_scope(|| {
+                    tracing::error!(
+                        "Tool {} failed: {}",
+                        params.name,
+                        context.redactor.redact_text(&e.to_string())
+                    )
+                });
+                JsonRpcResponse::error_with_data(id, e.code(), e.to_string(), e.data())
             }
         }
     }
 }
 
+/// Whether `line` is a `notifications/cancelled` for the request `id`.
+fn cancels(line: &str, id: &serde_json::Value) -> bool {
+    serde_json::from_str::<serde_json::Value>(line).is_ok_and(|message| {
+        message["method"] == "notifications/cancelled" && message["params"]["requestId"] == *id
+    })
+}
+
+/// Tools answering or inspecting a pending intervention, handled while
+/// another request is still running.
+const INTERVENTION_TOOLS: &[&str] = &[
+    "webpuppet_intervention_status",
+    "webpuppet_intervention_complete",
+    "webpuppet_resume",
+];
+
+/// Whether `line` is a `tools/call` of one of the [`INTERVENTION_TOOLS`].
+fn answers_intervention(line: &str) -> bool {
+    serde_json::from_str::<serde_json::Value>(line).is_ok_and(|message| {
+        message["method"] == "tools/call"
+            && message["params"]["name"]
+                .as_str()
+                .is_some_and(|name| INTERVENTION_TOOLS.contains(&name))
+    })
+}
+
+/// Whether an I/O error means the client went away.
+fn is_disconnect(err: &std::io::Error) -> bool {
+    matches!(
+        err.kind(),
+        ErrorKind::BrokenPipe
+            | ErrorKind::UnexpectedEof
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+    )
+}
+
 impl Default for McpServer {
     fn default() -> Self {
         Self::new()
@@ -1,19 +1,142 @@
 //! Tool definitions and registry for MCP server.
 
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::any::Any;
+use std::collections::{BTreeMap, HashMap};
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-use serde::Deserialize;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use futures::FutureExt;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use serde_json::json;
-use tokio::sync::RwLock;
+use tokio::sync::{mpsc, watch, RwLock};
 
 use webpuppet::{
-    BrowserDetector, InterventionHandler, InterventionState, Operation, PermissionGuard,
-    PromptRequest, Provider, ScreeningConfig, WebPuppet,
+    ContentScreener, InterventionHandler, InterventionReason, InterventionState, Operation,
+    PermissionGuard, PromptRequest, Provider, ScreeningConfig, ScreeningResult, SecurityIssue,
 };
 
+use crate::browser::{
+    choose_browser, declared_capabilities, BrowserIdentity, BrowserLauncher, BrowserSession,
+    ExtraHeaders, HistoryStep, LaunchOptions, LoginStatus, ProviderAccounts, Viewport,
+    WebPuppetLauncher,
+};
+use crate::call::CallInfo;
+use crate::capture::CaptureStore;
+use crate::clients::ClientWorkarounds;
+use crate::coalesce::RequestCoalescer;
+use crate::config::{ProxyConfig, ResultFormat, ScreeningMode, ServerConfig};
+use crate::confirm::{confirm, Action as ConfirmAction};
+use crate::console::{self, ConsoleLog, ConsoleMessage, MAX_CONSOLE_MESSAGES};
+use crate::conversations::ConversationLog;
+use crate::diagnose::{self, diagnose, Diagnosis, PageState};
 use crate::error::{Error, Result};
-use crate::protocol::{ContentItem, ToolCallResult, ToolDefinition};
+use crate::history::{Action, ActionHistory, DEFAULT_HISTORY_CAPACITY};
+use crate::manifest::{screenshot_content, ManifestTool, Step, StepOutput, ToolsManifest};
+use crate::navigation::normalize_and_validate_url;
+use crate::notify::NotificationSink;
+use crate::plain::plain_result;
+use crate::progress::ProgressReporter;
+use crate::protocol::{
+    Completion, CompletionArgument, CompletionReference, ContentItem, JsonRpcId, Role,
+    ToolCallResult, ToolDefinition,
+};
+use crate::ratelimit::{DomainDelays, ProviderRateLimiter};
+use crate::redact::{Redactor, REDACTED};
+use crate::render::{render_page, PageFormat};
+use crate::roots::ClientRoots;
+
+/// Provider ids accepted by `provider` arguments, in the order of
+/// [`PROVIDERS`].
+pub const PROVIDER_IDS: &[&str] = &[
+    "claude",
+    "grok",
+    "gemini",
+    "chatgpt",
+    "perplexity",
+    "notebooklm",
+    "kaggle",
+];
+
+/// Display metadata of a provider.
+#[derive(Debug, Clone, Copy)]
+pub struct ProviderInfo {
+    /// The provider.
+    pub provider: Provider,
+    /// Display name.
+    pub name: &'static str,
+    /// What the provider is good at.
+    pub features: &'static str,
+}
+
+impl ProviderInfo {
+    /// Id accepted by `provider` arguments.
+    pub fn id(&self) -> &'static str {
+        self.provider.name()
+    }
+
+    /// Page the browser opens to reach the provider.
+    pub fn url(&self) -> &'static str {
+        self.provider.base_url()
+    }
+}
+
+/// Every supported provider, in listing order.
+pub const PROVIDERS: &[ProviderInfo] = &[
+    ProviderInfo {
+        provider: Provider::Claude,
+        name: "Claude (Anthropic)",
+        features: "Large context, artifacts, code",
+    },
+    ProviderInfo {
+        provider: Provider::Grok,
+        name: "Grok (X/xAI)",
+        features: "Real-time info, integrated with X",
+    },
+    ProviderInfo {
+        provider: Provider::Gemini,
+        name: "Gemini (Google)",
+        features: "Google integration, large context",
+    },
+    ProviderInfo {
+        provider: Provider::ChatGpt,
+        name: "ChatGPT (OpenAI)",
+        features: "GPT-4o, vision, code, web search",
+    },
+    ProviderInfo {
+        provider: Provider::Perplexity,
+        name: "Perplexity AI",
+        features: "Search-focused, sources cited",
+    },
+    ProviderInfo {
+        provider: Provider::NotebookLm,
+        name: "NotebookLM (Google)",
+        features: "Research assistant, 500k context",
+    },
+    ProviderInfo {
+        provider: Provider::Kaggle,
+        name: "Kaggle (Datasets)",
+        features: "Dataset search/catalog; returns dataset page links",
+    },
+];
+
+/// Metadata of `provider`.
+pub fn provider_info(provider: Provider) -> &'static ProviderInfo {
+    PROVIDERS
+        .iter()
+        .find(|info| info.provider == provider)
+        .expect("every provider is listed in PROVIDERS")
+}
+
+/// Maximum number of values in a completion result (per the MCP spec).
+const MAX_COMPLETIONS: usize = 100;
+
+/// How long a completed navigate/screenshot result is reused for identical calls.
+const COALESCE_TTL: Duration = Duration::from_millis(500);
 
 /// Tool trait for implementing MCP tools.
 #[async_trait::async_trait]
@@ -21,6 +144,51 @@ pub trait Tool: Send + Sync {
     /// Get the tool definition.
     fn definition(&self) -> ToolDefinition;
 
+    /// The operation this tool can't do without.
+    ///
+    /// Tools whose primary operation the permission policy denies outright
+    /// are left out of `tools/list`. `None` means the tool is always listed.
+    fn primary_operation(&self) -> Option<Operation> {
+        None
+    }
+
+    /// Every operation the tool checks against the permission policy, so
+    /// clients can predict denials without calling it. Some may only be
+    /// checked for certain arguments.
+    ///
+    /// Defaults to the primary operation, if any.
+    fn required_operations(&self) -> Vec<Operation> {
+        self.primary_operation().into_iter().collect()
+    }
+
+    /// Whether the call can safely be repeated.
+    ///
+    /// If the browser crashes during an idempotent call it is retried once
+    /// against a fresh browser; other calls fail with
+    /// [`Error::BrowserCrashed`] instead. Only tools whose arguments fully
+    /// describe what they load (a URL, a provider) qualify: a fresh browser
+    /// has lost the current page, so reading it again would succeed with
+    /// nothing.
+    fn idempotent(&self) -> bool {
+        false
+    }
+
+    /// Whether the tool changes or captures the browsing tab (navigates,
+    /// clicks, types, takes screenshots).
+    ///
+    /// Such calls in one session run one at a time so they don't interleave
+    /// on the page; other tools, including those only reading the page, run
+    /// alongside them.
+    fn drives_page(&self) -> bool {
+        false
+    }
+
+    /// Whether the tool's text is page or provider content to return as is,
+    /// even with `--result-format plain`, rather than a formatted report.
+    fn verbatim_output(&self) -> bool {
+        false
+    }
+
     /// Execute the tool with the given arguments.
     async fn execute(
         &self,
@@ -31,61 +199,772 @@ pub trait Tool: Send + Sync {
 
 /// Context passed to tools during execution.
 pub struct ToolContext {
-    /// WebPuppet instance (lazy-initialized).
-    pub puppet: Arc<RwLock<Option<WebPuppet>>>,
+    /// Browser session (lazy-initialized, kept alive between tool calls).
+    pub puppet: Arc<RwLock<Option<Arc<dyn BrowserSession>>>>,
+    /// Launcher used to start the browser session.
+    pub launcher: Arc<dyn BrowserLauncher>,
     /// Permission guard.
     pub permissions: Arc<PermissionGuard>,
     /// Screening configuration.
     pub screening_config: ScreeningConfig,
-    /// Intervention handler for human-in-the-loop.
-    pub intervention_handler: Arc<RwLock<InterventionHandler>>,
-    /// Whether to run browser in headless mode (default: true).
-    pub headless: bool,
+    /// Intervention handler for human-in-the-loop. Shared behind an `Arc`
+    /// so a pending intervention can be waited on without holding the lock.
+    pub intervention_handler: Arc<RwLock<Arc<InterventionHandler>>>,
+    /// What the pending intervention is blocking, if any.
+    intervention_origin: Arc<Mutex<Option<InterventionOrigin>>>,
+    /// How the last intervention requested through
+    /// [`ToolContext::request_intervention`] ended (`None` while pending).
+    intervention_outcome: Arc<watch::Sender<Option<InterventionOutcome>>>,
+    /// Whether to run browser in headless mode (default: true); can be
+    /// changed at runtime with [`ToolContext::set_headless`].
+    headless: AtomicBool,
+    /// Browser profile to launch with; changed with [`ToolContext::use_profile`].
+    profile: Mutex<Option<String>>,
+    /// Viewport to launch with; changed with [`ToolContext::set_viewport`].
+    viewport: Mutex<Option<Viewport>>,
+    /// User agent and locale to launch with; changed with
+    /// [`ToolContext::set_identity`].
+    identity: Mutex<BrowserIdentity>,
+    /// Headers added to the browsing tab's requests; changed with
+    /// [`ToolContext::set_extra_headers`].
+    extra_headers: Mutex<ExtraHeaders>,
+    /// Progress of launching the browser at startup; see
+    /// [`ToolContext::prewarm`].
+    prewarm: Mutex<Prewarm>,
+    /// Provider used when a tool call doesn't specify one.
+    pub default_provider: Provider,
+    /// Server configuration.
+    pub config: Arc<ServerConfig>,
+    /// Redactor for anything logged or echoed back from tool arguments.
+    pub redactor: Arc<Redactor>,
+    /// Coalesces identical in-flight browser actions.
+    pub coalescer: RequestCoalescer,
+    /// Spaces out prompts to each provider (shared by all sessions).
+    pub rate_limiter: Arc<ProviderRateLimiter>,
+    /// Spaces out navigations to each domain (shared by all sessions).
+    pub domain_delays: Arc<DomainDelays>,
+    /// Browser usage, for closing it when idle.
+    activity: Arc<Activity>,
+    /// Held by calls of tools that [drive the page](Tool::drives_page).
+    page_lock: Arc<tokio::sync::Mutex<()>>,
+    /// Recent actions of this session.
+    pub history: ActionHistory,
+    /// Conversations this session has open with providers.
+    pub conversations: ConversationLog,
+    /// Adjustments for the quirks of the connected client.
+    client_workarounds: Mutex<ClientWorkarounds>,
+    /// Account each provider was last seen using.
+    active_accounts: Mutex<BTreeMap<String, String>>,
+    /// Notifications to the client; connected by the server serving this
+    /// context (shared by all sessions).
+    pub notifications: NotificationSink,
+    /// Roots of the client, limiting where files are written (shared by all
+    /// sessions).
+    pub roots: Arc<ClientRoots>,
+    /// Images held back from a client that can't display them.
+    pub captures: CaptureStore,
+}
+
+/// The tool call an intervention is blocking.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InterventionOrigin {
+    /// JSON-RPC id of the blocked `tools/call` request.
+    pub request_id: Option<JsonRpcId>,
+    /// Tool that was called.
+    pub tool: Option<String>,
+    /// Provider whose page needs the human.
+    pub provider: Option<String>,
+    /// Operation that could not go ahead.
+    pub operation: Option<Operation>,
+}
+
+/// How a human intervention ended.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum InterventionOutcome {
+    /// The human signalled completion.
+    Completed {
+        /// Whether they succeeded.
+        success: bool,
+        /// What they said about it.
+        message: Option<String>,
+    },
+    /// Nobody completed it in time.
+    TimedOut,
+    /// It was cancelled (e.g. by `webpuppet_reset`).
+    Cancelled,
+}
+
+impl InterventionOrigin {
+    /// The call running on this task, blocked doing `operation` on `provider`.
+    pub fn current(provider: Option<Provider>, operation: Operation) -> Self {
+        let call = CallInfo::current();
+        Self {
+            request_id: call.as_ref().and_then(|call| call.request_id.clone()),
+            tool: call.map(|call| call.tool),
+            provider: provider.map(|p| p.name().to_string()),
+            operation: Some(operation),
+        }
+    }
+}
+
+impl std::fmt::Display for InterventionOrigin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.request_id {
+            Some(ref id) => write!(f, "request {}", id)?,
+            None => write!(f, "a request")?,
+        }
+        let mut details = Vec::new();
+        if let Some(ref tool) = self.tool {
+            details.push(format!("`{}`", tool));
+        }
+        match (&self.operation, &self.provider) {
+            (Some(operation), Some(provider)) => {
+                details.push(format!("{} on {}", operation, provider))
+            }
+            (Some(operation), None) => details.push(operation.to_string()),
+            (None, Some(provider)) => details.push(provider.clone()),
+            (None, None) => {}
+        }
+        if !details.is_empty() {
+            write!(f, " ({})", details.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Progress of launching the browser ahead of the first tool call.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "state", content = "error")]
+pub enum Prewarm {
+    /// Not requested.
+    Off,
+    /// Requested, but the permission policy doesn't allow navigation.
+    Skipped,
+    /// The browser is being launched.
+    Warming,
+    /// The browser was launched.
+    Ready,
+    /// Launching failed; the first tool call will try again.
+    Failed(String),
+}
+
+impl std::fmt::Display for Prewarm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Off => write!(f, "off"),
+            Self::Skipped => write!(f, "skipped (navigation not allowed)"),
+            Self::Warming => write!(f, "launching"),
+            Self::Ready => write!(f, "ready"),
+            Self::Failed(e) => write!(f, "failed ({})", e),
+        }
+    }
+}
+
+/// Tracks browser usage so an idle browser can be closed.
+#[derive(Debug)]
+struct Activity {
+    /// When a tool last used (or finished using) the browser.
+    last_used: Mutex<Instant>,
+    /// Tool calls currently running.
+    active_calls: AtomicUsize,
+    /// Incremented on every launch; an idle watcher stops once it changes.
+    generation: AtomicU64,
+    /// Set when the browser was closed for inactivity, until reported.
+    auto_closed: AtomicBool,
+}
+
+impl Activity {
+    fn new() -> Self {
+        Self {
+            last_used: Mutex::new(Instant::now()),
+            active_calls: AtomicUsize::new(0),
+            generation: AtomicU64::new(0),
+            auto_closed: AtomicBool::new(false),
+        }
+    }
+
+    fn touch(&self) {
+        *self.last_used.lock().unwrap() = Instant::now();
+    }
+
+    fn idle_for(&self) -> Duration {
+        self.last_used.lock().unwrap().elapsed()
+    }
+}
+
+/// Marks a tool call as running for as long as it is alive.
+struct ActiveCall<'a>(&'a Activity);
+
+impl<'a> ActiveCall<'a> {
+    fn start(activity: &'a Activity) -> Self {
+        activity.active_calls.fetch_add(1, Ordering::SeqCst);
+        Self(activity)
+    }
+}
+
+impl Drop for ActiveCall<'_> {
+    fn drop(&mut self) {
+        self.0.touch();
+        self.0.active_calls.fetch_sub(1, Ordering::SeqCst);
+    }
 }
 
 impl ToolContext {
     /// Create a new tool context.
     pub fn new(permissions: PermissionGuard) -> Self {
+        Self::with_config(permissions, ServerConfig::default())
+    }
+
+    /// Create a new tool context with visible browser (non-headless).
+    pub fn with_visible_browser(permissions: PermissionGuard) -> Self {
+        Self::with_config(
+            permissions,
+            ServerConfig {
+                headless: false,
+                ..ServerConfig::default()
+            },
+        )
+    }
+
+    /// Create a new tool context from server configuration.
+    pub fn with_config(permissions: PermissionGuard, config: ServerConfig) -> Self {
         Self {
             puppet: Arc::new(RwLock::new(None)),
+            launcher: Arc::new(WebPuppetLauncher),
             permissions: Arc::new(permissions),
             screening_config: ScreeningConfig::default(),
-            intervention_handler: Arc::new(RwLock::new(InterventionHandler::new())),
-            headless: true,
+            intervention_handler: Arc::new(RwLock::new(Arc::new(InterventionHandler::new()))),
+            intervention_origin: Arc::new(Mutex::new(None)),
+            intervention_outcome: Arc::new(watch::Sender::new(None)),
+            headless: AtomicBool::new(config.headless),
+            profile: Mutex::new(None),
+            viewport: Mutex::new(None),
+            identity: Mutex::new(config.identity.clone()),
+            extra_headers: Mutex::new(ExtraHeaders::default()),
+            prewarm: Mutex::new(Prewarm::Off),
+            default_provider: config.default_provider,
+            redactor: Arc::new(Redactor::new(&config.redaction)),
+            coalescer: RequestCoalescer::new(),
+            rate_limiter: Arc::new(ProviderRateLimiter::new(config.provider_min_interval)),
+            domain_delays: Arc::new(DomainDelays::new(config.domain_delays.clone())),
+            activity: Arc::new(Activity::new()),
+            page_lock: Arc::new(tokio::sync::Mutex::new(())),
+            history: ActionHistory::default(),
+            conversations: ConversationLog::new(),
+            client_workarounds: Mutex::new(ClientWorkarounds::default()),
+            active_accounts: Mutex::new(BTreeMap::new()),
+            notifications: NotificationSink::noop(),
+            roots: Arc::new(ClientRoots::new()),
+            captures: CaptureStore::new(),
+            config: Arc::new(config),
         }
     }
 
-    /// Create a new tool context with visible browser (non-headless).
-    pub fn with_visible_browser(permissions: PermissionGuard) -> Self {
+    /// Create a context for a new session.
+    ///
+    /// The new context shares permissions, configuration and the launcher
+    /// with this one, but has its own browser session, intervention state
+    /// and coalescing cache.
+    pub fn new_session(&self) -> Self {
         Self {
             puppet: Arc::new(RwLock::new(None)),
-            permissions: Arc::new(permissions),
-            screening_config: ScreeningConfig::default(),
-            intervention_handler: Arc::new(RwLock::new(InterventionHandler::new())),
-            headless: false,
+            launcher: self.launcher.clone(),
+            permissions: self.permissions.clone(),
+            screening_config: self.screening_config.clone(),
+            intervention_handler: Arc::new(RwLock::new(Arc::new(InterventionHandler::new()))),
+            intervention_origin: Arc::new(Mutex::new(None)),
+            intervention_outcome: Arc::new(watch::Sender::new(None)),
+            headless: AtomicBool::new(self.is_headless()),
+            profile: Mutex::new(self.profile()),
+            viewport: Mutex::new(self.viewport()),
+            identity: Mutex::new(self.identity()),
+            // Headers may carry credentials; they stay with their session
+            extra_headers: Mutex::new(ExtraHeaders::default()),
+            prewarm: Mutex::new(Prewarm::Off),
+            default_provider: self.default_provider,
+            config: self.config.clone(),
+            redactor: self.redactor.clone(),
+            coalescer: RequestCoalescer::new(),
+            rate_limiter: self.rate_limiter.clone(),
+            domain_delays: self.domain_delays.clone(),
+            activity: Arc::new(Activity::new()),
+            page_lock: Arc::new(tokio::sync::Mutex::new(())),
+            history: ActionHistory::default(),
+            conversations: ConversationLog::new(),
+            client_workarounds: Mutex::new(ClientWorkarounds::default()),
+            active_accounts: Mutex::new(BTreeMap::new()),
+            notifications: self.notifications.clone(),
+            roots: self.roots.clone(),
+            captures: CaptureStore::new(),
         }
     }
 
-    /// Get or create the WebPuppet instance.
-    pub async fn get_puppet(&self) -> Result<WebPuppet> {
-        let guard = self.puppet.read().await;
-        if let Some(ref _puppet) = *guard {
-            // Clone isn't implemented, so we'll need to recreate
-            drop(guard);
-        } else {
-            drop(guard);
+    /// Use a custom browser launcher.
+    pub fn with_launcher(mut self, launcher: Arc<dyn BrowserLauncher>) -> Self {
+        self.launcher = launcher;
+        self
+    }
+
+    /// Ask a human to step in without blocking the caller.
+    ///
+    /// The session reports `waiting_for_human` with `reason`, and `origin`
+    /// as what it is blocking, until `webpuppet_intervention_complete` is
+    /// called or the handler times out. Does nothing if an intervention is
+    /// already pending.
+    pub async fn request_intervention(
+        &self,
+        reason: InterventionReason,
+        origin: InterventionOrigin,
+    ) {
+        let handler = self.intervention_handler.read().await.clone();
+        if handler.is_waiting() {
+            return;
         }
 
-        // Create new puppet with headless setting
-        let puppet = WebPuppet::builder()
-            .with_all_providers()
-            .headless(self.headless)
-            .with_screening_config(self.screening_config.clone())
-            .build()
-            .await?;
+        *self.intervention_origin.lock().unwrap() = Some(origin.clone());
+        self.intervention_outcome.send_replace(None);
+        let origin_slot = self.intervention_origin.clone();
+        let outcome_slot = self.intervention_outcome.clone();
+        let mut waiting = Box::pin(async move {
+            let outcome = match handler.request_intervention(reason).await {
+                // `cancel` completes unsuccessfully, then marks the handler
+                Ok(_) if handler.state() == InterventionState::Cancelled => {
+                    InterventionOutcome::Cancelled
+                }
+                Ok(complete) => InterventionOutcome::Completed {
+                    success: complete.success,
+                    message: complete.message,
+                },
+                Err(e) => {
+                    tracing::warn!("Intervention ended without completion: {}", e);
+                    match handler.state() {
+                        InterventionState::TimedOut => InterventionOutcome::TimedOut,
+                        _ => InterventionOutcome::Cancelled,
+                    }
+                }
+            };
+            outcome_slot.send_replace(Some(outcome));
+            // Unless a newer intervention has taken its place
+            let mut slot = origin_slot.lock().unwrap();
+            if slot.as_ref() == Some(&origin) {
+                *slot = None;
+            }
+        });
+        // The first poll moves the handler to `WaitingForHuman`, so callers
+        // see the new state as soon as this returns
+        if futures::poll!(&mut waiting).is_pending() {
+            tokio::spawn(waiting);
+        }
+    }
+
+    /// What the pending intervention is blocking, if one is pending.
+    pub fn intervention_origin(&self) -> Option<InterventionOrigin> {
+        self.intervention_origin.lock().unwrap().clone()
+    }
+
+    /// Notified with how each intervention requested through
+    /// [`request_intervention`](Self::request_intervention) ends.
+    pub fn intervention_outcome(&self) -> watch::Receiver<Option<InterventionOutcome>> {
+        self.intervention_outcome.subscribe()
+    }
+
+    /// Record an action in the session history, redacting it first.
+    pub fn record(&self, action: Action) {
+        self.history.record(action, &self.redactor);
+    }
+
+    /// Number of tool calls currently running in this context.
+    pub fn calls_in_flight(&self) -> usize {
+        self.activity.active_calls.load(Ordering::SeqCst)
+    }
+
+    /// Options for launching the browser session.
+    pub fn launch_options(&self) -> LaunchOptions {
+        LaunchOptions {
+            headless: self.is_headless(),
+            default_provider: self.default_provider,
+            browser: self.config.browser,
+            profile: self.profile(),
+            viewport: self.viewport(),
+            proxy: self.config.proxy.clone(),
+            identity: self.identity(),
+            extra_headers: self.extra_headers(),
+            provider_init_concurrency: self.config.provider_init_concurrency,
+            session_dir: self.config.session_dir.clone(),
+        }
+    }
+
+    /// Resolve an optional provider argument, falling back to the default.
+    pub fn provider_or_default(&self, name: Option<&str>) -> Result<Provider> {
+        match name {
+            Some(name) => parse_provider(name),
+            None => Ok(self.default_provider),
+        }
+    }
+
+    /// Whether the browser runs (or will run) headless.
+    pub fn is_headless(&self) -> bool {
+        self.headless.load(Ordering::SeqCst)
+    }
+
+    /// Switch between headless and visible mode.
+    ///
+    /// A running browser in the other mode is closed and relaunched in the
+    /// requested one; if that fails, the mode is left as it was. Returns
+    /// whether a relaunch happened.
+    pub async fn set_headless(&self, headless: bool) -> Result<bool> {
+        let mut guard = self.puppet.write().await;
+        let previous = self.headless.swap(headless, Ordering::SeqCst);
+
+        if previous == headless {
+            return Ok(false);
+        }
+        let Some(old) = guard.take() else {
+            return Ok(false);
+        };
+
+        if let Err(e) = old.close().await {
+            tracing::warn!("Failed to close browser before switching mode: {}", e);
+        }
+        match self.launch().await {
+            Ok(puppet) => *guard = Some(puppet),
+            Err(e) => {
+                self.headless.store(previous, Ordering::SeqCst);
+                return Err(e);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Browser profile the browser runs (or will run) with; `None` is the
+    /// browser's default profile.
+    pub fn profile(&self) -> Option<String> {
+        self.profile.lock().unwrap().clone()
+    }
+
+    /// Viewport pages are rendered at; `None` is the browser's default.
+    pub fn viewport(&self) -> Option<Viewport> {
+        *self.viewport.lock().unwrap()
+    }
+
+    /// Change the viewport for this and future browser sessions.
+    ///
+    /// The viewport is fixed when the browser starts, so a running browser
+    /// is relaunched with the new one and reopens the page it was showing;
+    /// if that launch fails, the viewport is left as it was. Returns
+    /// whether a relaunch happened.
+    pub async fn set_viewport(&self, viewport: Option<Viewport>) -> Result<bool> {
+        let mut guard = self.puppet.write().await;
+        let previous = std::mem::replace(&mut *self.viewport.lock().unwrap(), viewport);
+
+        if previous == viewport {
+            return Ok(false);
+        }
+        let Some(old) = guard.take() else {
+            return Ok(false);
+        };
+
+        let url = old.current_url().await.ok();
+        if let Err(e) = old.close().await {
+            tracing::warn!("Failed to close browser before changing viewport: {}", e);
+        }
+        let puppet = match self.launch().await {
+            Ok(puppet) => puppet,
+            Err(e) => {
+                *self.viewport.lock().unwrap() = previous;
+                return Err(e);
+            }
+        };
+        if let Some(url) = url.filter(|url| url.starts_with("http")) {
+            if let Err(e) = puppet.navigate(&url).await {
+                tracing::warn!("Could not reopen {} after changing viewport: {}", url, e);
+            }
+        }
+        *guard = Some(puppet);
+
+        Ok(true)
+    }
 
+    /// User agent, locale and timezone the browser presents.
+    pub fn identity(&self) -> BrowserIdentity {
+        self.identity.lock().unwrap().clone()
+    }
+
+    /// Present a different user agent or locale from now on.
+    ///
+    /// Both are fixed when the browser starts, so a running browser is
+    /// closed and the next tool call launches it with the new identity.
+    /// Returns whether the browser was closed.
+    pub async fn set_identity(&self, identity: BrowserIdentity) -> Result<bool> {
+        identity.validate()?;
+        let mut guard = self.puppet.write().await;
+        let previous = std::mem::replace(&mut *self.identity.lock().unwrap(), identity.clone());
+
+        if previous == identity {
+            return Ok(false);
+        }
+        let Some(old) = guard.take() else {
+            return Ok(false);
+        };
+        if let Err(e) = old.close().await {
+            tracing::warn!("Failed to close browser before changing identity: {}", e);
+        }
+
+        Ok(true)
+    }
+
+    /// Headers added to the browsing tab's requests.
+    pub fn extra_headers(&self) -> ExtraHeaders {
+        self.extra_headers.lock().unwrap().clone()
+    }
+
+    /// Add `headers` to the browsing tab's requests from now on, replacing
+    /// any set before. A running browser gets them right away; a browser
+    /// launched later gets them at launch.
+    pub async fn set_extra_headers(&self, headers: ExtraHeaders) -> Result<()> {
+        let guard = self.puppet.write().await;
+        *self.extra_headers.lock().unwrap() = headers.clone();
+        if let Some(ref puppet) = *guard {
+            puppet.set_extra_headers(&headers).await?;
+        }
+        Ok(())
+    }
+
+    /// Switch to another profile of the browser, closing the running
+    /// browser (if any) and relaunching it with that profile. If the
+    /// launch fails (e.g. the profile is open in another browser), the
+    /// previous profile is kept for later launches.
+    ///
+    /// Fails with the list of available profiles if `profile` doesn't exist.
+    pub async fn use_profile(&self, profile: &str) -> Result<()> {
+        let available = self.launcher.profiles(self.config.browser)?;
+        if !available.iter().any(|p| p == profile) {
+            return Err(Error::InvalidParams(format!(
+                "unknown browser profile '{}'; available profiles: {}",
+                profile,
+                if available.is_empty() {
+                    "none".to_string()
+                } else {
+                    available.join(", ")
+                }
+            )));
+        }
+
+        let mut guard = self.puppet.write().await;
+        let previous = self.profile.lock().unwrap().replace(profile.to_string());
+
+        if let Some(old) = guard.take() {
+            if let Err(e) = old.close().await {
+                tracing::warn!("Failed to close browser before switching profile: {}", e);
+            }
+        }
+        match self.launch().await {
+            Ok(puppet) => *guard = Some(puppet),
+            Err(e) => {
+                *self.profile.lock().unwrap() = previous;
+                return Err(e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Launch the browser now rather than on the first tool call, so that
+    /// call is fast. Skipped when the permission policy doesn't allow
+    /// navigation; progress is reported by [`prewarm_state`](Self::prewarm_state).
+    pub async fn prewarm(&self) {
+        if self.permissions.require(Operation::Navigate).is_err() {
+            tracing::info!("Not prewarming the browser: the policy doesn't allow navigation");
+            *self.prewarm.lock().unwrap() = Prewarm::Skipped;
+            return;
+        }
+
+        *self.prewarm.lock().unwrap() = Prewarm::Warming;
+        let state = match self.get_puppet().await {
+            Ok(_) => {
+                tracing::info!("Browser prewarmed");
+                Prewarm::Ready
+            }
+            Err(e) => {
+                tracing::warn!("Failed to prewarm the browser: {}", e);
+                Prewarm::Failed(e.to_string())
+            }
+        };
+        *self.prewarm.lock().unwrap() = state;
+    }
+
+    /// Progress of [`prewarm`](Self::prewarm).
+    pub fn prewarm_state(&self) -> Prewarm {
+        self.prewarm.lock().unwrap().clone()
+    }
+
+    /// Remember the account a provider's page shows in use.
+    pub fn record_accounts(&self, provider: Provider, accounts: &ProviderAccounts) {
+        let mut active = self.active_accounts.lock().unwrap();
+        match &accounts.active {
+            Some(account) => active.insert(provider.name().to_string(), account.clone()),
+            None => active.remove(provider.name()),
+        };
+    }
+
+    /// Account each provider was last seen using, by provider name.
+    pub fn active_accounts(&self) -> BTreeMap<String, String> {
+        self.active_accounts.lock().unwrap().clone()
+    }
+
+    /// Adjust results to the quirks of the connected client.
+    pub fn set_client_workarounds(&self, workarounds: ClientWorkarounds) {
+        *self.client_workarounds.lock().unwrap() = workarounds;
+    }
+
+    /// Format of the human-readable text of tool results, after client
+    /// workarounds.
+    pub fn result_format(&self) -> ResultFormat {
+        self.client_workarounds
+            .lock()
+            .unwrap()
+            .result_format(self.config.result_format)
+    }
+
+    /// Size limit of tool results (zero meaning none), after client
+    /// workarounds.
+    pub fn max_result_bytes(&self) -> usize {
+        self.client_workarounds
+            .lock()
+            .unwrap()
+            .max_result_bytes(self.config.max_result_bytes)
+    }
+
+    /// Navigate the browsing tab to `url` once its domain's delay allows.
+    pub async fn navigate(&self, puppet: &Arc<dyn BrowserSession>, url: &str) -> Result<()> {
+        self.domain_delays.acquire(url).await;
+        puppet.navigate(url).await
+    }
+
+    /// Get the browser session, launching it on first use.
+    pub async fn get_puppet(&self) -> Result<Arc<dyn BrowserSession>> {
+        self.activity.touch();
+        if let Some(ref puppet) = *self.puppet.read().await {
+            return Ok(puppet.clone());
+        }
+
+        let mut guard = self.puppet.write().await;
+        // Another call may have launched the session while we waited for the lock
+        if let Some(ref puppet) = *guard {
+            return Ok(puppet.clone());
+        }
+
+        let puppet = self.launch().await?;
+        *guard = Some(puppet.clone());
+
+        Ok(puppet)
+    }
+
+    /// Launch a browser session and start watching it for inactivity.
+    async fn launch(&self) -> Result<Arc<dyn BrowserSession>> {
+        let puppet = self.launcher.launch(&self.launch_options()).await?;
+        self.activity.touch();
+        let generation = self.activity.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        self.watch_idle(generation);
         Ok(puppet)
     }
+
+    /// Close the browser once it has gone unused for the idle timeout.
+    ///
+    /// The watcher exits when the session it was started for is closed or
+    /// replaced. It never closes the browser while a tool call is running or
+    /// a human intervention is pending.
+    fn watch_idle(&self, generation: u64) {
+        let timeout = self.config.browser_idle_timeout;
+        if timeout.is_zero() {
+            return;
+        }
+
+        let puppet = self.puppet.clone();
+        let activity = self.activity.clone();
+        let intervention_handler = self.intervention_handler.clone();
+
+        tokio::spawn(async move {
+            loop {
+                let remaining = timeout.saturating_sub(activity.idle_for());
+                if !remaining.is_zero() {
+                    tokio::time::sleep(remaining).await;
+                    continue;
+                }
+
+                let mut guard = puppet.write().await;
+                if guard.is_none() || activity.generation.load(Ordering::SeqCst) != generation {
+                    return;
+                }
+                if activity.active_calls.load(Ordering::SeqCst) > 0
+                    || intervention_handler.read().await.is_waiting()
+                {
+                    activity.touch();
+                    continue;
+                }
+
+                let Some(session) = guard.take() else {
+                    return;
+                };
+                drop(guard);
+
+                tracing::info!("Closing browser after {}s without use", timeout.as_secs());
+                activity.auto_closed.store(true, Ordering::SeqCst);
+                if let Err(e) = session.close().await {
+                    tracing::warn!("Failed to close idle browser: {}", e);
+                }
+                return;
+            }
+        });
+    }
+
+    /// Whether the browser was closed for inactivity since the last call;
+    /// clears the flag.
+    pub fn take_auto_closed(&self) -> bool {
+        self.activity.auto_closed.swap(false, Ordering::SeqCst)
+    }
+
+    /// Forget a browser session that has crashed, so the next call launches
+    /// a new one. The dead session isn't closed since it can't respond.
+    pub async fn discard_puppet(&self) {
+        self.puppet.write().await.take();
+    }
+
+    /// Close the browser session, if one is running.
+    pub async fn close_puppet(&self) -> Result<()> {
+        let puppet = self.puppet.write().await.take();
+        match puppet {
+            Some(puppet) => puppet.close().await,
+            None => Ok(()),
+        }
+    }
+}
+
+/// Parse an operation name, accepting any [`Operation`] the permission
+/// system knows about (case-insensitive; `SendPrompt`, `send_prompt` and
+/// `send-prompt` are equivalent).
+pub fn parse_operation(name: &str) -> Result<Operation> {
+    let wanted: String = name.chars().filter(|c| *c != '_' && *c != '-').collect();
+    Operation::all()
+        .into_iter()
+        .find(|op| op.to_string().eq_ignore_ascii_case(&wanted))
+        .ok_or_else(|| Error::InvalidParams(format!("unknown operation: {}", name)))
+}
+
+/// Parse a provider name (case-insensitive, with common aliases).
+pub fn parse_provider(name: &str) -> Result<Provider> {
+    match name.to_lowercase().as_str() {
+        "claude" => Ok(Provider::Claude),
+        "grok" => Ok(Provider::Grok),
+        "gemini" => Ok(Provider::Gemini),
+        "chatgpt" | "openai" => Ok(Provider::ChatGpt),
+        "perplexity" => Ok(Provider::Perplexity),
+        "notebooklm" | "notebook" => Ok(Provider::NotebookLm),
+        "kaggle" => Ok(Provider::Kaggle),
+        _ => Err(Error::InvalidParams(format!("unknown provider: {}", name))),
+    }
 }
 
 /// Registry of available tools.
@@ -105,8 +984,13 @@ impl ToolRegistry {
         Self::with_context(ToolContext::with_visible_browser(permissions))
     }
 
-    /// Create a new tool registry with custom context.
-    fn with_context(context: ToolContext) -> Self {
+    /// Create a new tool registry from server configuration.
+    pub fn with_config(permissions: PermissionGuard, config: ServerConfig) -> Self {
+        Self::with_context(ToolContext::with_config(permissions, config))
+    }
+
+    /// Create a new tool registry with the built-in tools and a custom context.
+    pub fn with_context(context: ToolContext) -> Self {
         let context = Arc::new(context);
         let mut tools: HashMap<String, Arc<dyn Tool>> = HashMap::new();
 
@@ -114,12 +998,24 @@ impl ToolRegistry {
         let prompt_tool = Arc::new(PromptTool);
         tools.insert(prompt_tool.definition().name.clone(), prompt_tool);
 
+        let compare_tool = Arc::new(CompareProvidersTool);
+        tools.insert(compare_tool.definition().name.clone(), compare_tool);
+
+        let summarize_tool = Arc::new(SummarizePageTool);
+        tools.insert(summarize_tool.definition().name.clone(), summarize_tool);
+
         let list_providers_tool = Arc::new(ListProvidersTool);
         tools.insert(
             list_providers_tool.definition().name.clone(),
             list_providers_tool,
         );
 
+        let login_status_tool = Arc::new(LoginStatusTool);
+        tools.insert(
+            login_status_tool.definition().name.clone(),
+            login_status_tool,
+        );
+
         let provider_caps_tool = Arc::new(ProviderCapabilitiesTool);
         tools.insert(
             provider_caps_tool.definition().name.clone(),
@@ -135,12 +1031,21 @@ impl ToolRegistry {
         let screenshot_tool = Arc::new(ScreenshotTool);
         tools.insert(screenshot_tool.definition().name.clone(), screenshot_tool);
 
+        let screenshot_element_tool = Arc::new(ScreenshotElementTool);
+        tools.insert(
+            screenshot_element_tool.definition().name.clone(),
+            screenshot_element_tool,
+        );
+
         let check_permission_tool = Arc::new(CheckPermissionTool);
         tools.insert(
             check_permission_tool.definition().name.clone(),
             check_permission_tool,
         );
 
+        let screen_text_tool = Arc::new(ScreenTextTool);
+        tools.insert(screen_text_tool.definition().name.clone(), screen_text_tool);
+
         // Intervention tools
         let intervention_status_tool = Arc::new(InterventionStatusTool);
         tools.insert(
@@ -154,6 +1059,12 @@ impl ToolRegistry {
             intervention_complete_tool,
         );
 
+        let wait_for_intervention_tool = Arc::new(WaitForInterventionTool);
+        tools.insert(
+            wait_for_intervention_tool.definition().name.clone(),
+            wait_for_intervention_tool,
+        );
+
         let intervention_pause_tool = Arc::new(InterventionPauseTool);
         tools.insert(
             intervention_pause_tool.definition().name.clone(),
@@ -170,268 +1081,4846 @@ impl ToolRegistry {
         let navigate_tool = Arc::new(NavigateTool);
         tools.insert(navigate_tool.definition().name.clone(), navigate_tool);
 
+        let back_tool = Arc::new(BackTool);
+        tools.insert(back_tool.definition().name.clone(), back_tool);
+
+        let forward_tool = Arc::new(ForwardTool);
+        tools.insert(forward_tool.definition().name.clone(), forward_tool);
+
+        let reload_tool = Arc::new(ReloadTool);
+        tools.insert(reload_tool.definition().name.clone(), reload_tool);
+
         let browser_status_tool = Arc::new(BrowserStatusTool);
         tools.insert(
             browser_status_tool.definition().name.clone(),
             browser_status_tool,
         );
 
-        Self { tools, context }
-    }
-
-    /// Get tool definitions.
-    pub fn list_tools(&self) -> Vec<ToolDefinition> {
-        self.tools.values().map(|t| t.definition()).collect()
-    }
+        let download_tool = Arc::new(DownloadTool);
+        tools.insert(download_tool.definition().name.clone(), download_tool);
 
-    /// Execute a tool by name.
-    pub async fn execute(
-        &self,
-        name: &str,
-        arguments: serde_json::Value,
-    ) -> Result<ToolCallResult> {
-        let tool = self
-            .tools
-            .get(name)
-            .ok_or_else(|| Error::ToolNotFound(name.to_string()))?;
+        let set_visibility_tool = Arc::new(SetVisibilityTool);
+        tools.insert(
+            set_visibility_tool.definition().name.clone(),
+            set_visibility_tool,
+        );
 
-        tool.execute(arguments, &self.context).await
-    }
+        let use_profile_tool = Arc::new(UseProfileTool);
+        tools.insert(use_profile_tool.definition().name.clone(), use_profile_tool);
 
-    /// Register a custom tool.
-    pub fn register(&mut self, tool: Arc<dyn Tool>) {
-        let name = tool.definition().name.clone();
-        self.tools.insert(name, tool);
-    }
-}
+        let network_log_tool = Arc::new(NetworkLogTool);
+        tools.insert(network_log_tool.definition().name.clone(), network_log_tool);
 
-// ============================================================================
-// Built-in Tools
-// ============================================================================
+        let find_tool = Arc::new(FindTool);
+        tools.insert(find_tool.definition().name.clone(), find_tool);
 
-/// Tool for sending prompts to AI providers.
-pub struct PromptTool;
+        let describe_page_tool = Arc::new(DescribePageTool);
+        tools.insert(
+            describe_page_tool.definition().name.clone(),
+            describe_page_tool,
+        );
 
-#[derive(Debug, Deserialize)]
-struct PromptArgs {
-    /// Provider to use (claude, grok, gemini).
-    provider: String,
-    /// Message to send.
-    message: String,
-    /// Optional context/system prompt.
-    context: Option<String>,
-}
+        let history_tool = Arc::new(HistoryTool);
+        tools.insert(history_tool.definition().name.clone(), history_tool);
+
+        let set_viewport_tool = Arc::new(SetViewportTool);
+        tools.insert(
+            set_viewport_tool.definition().name.clone(),
+            set_viewport_tool,
+        );
+
+        let reset_tool = Arc::new(ResetTool);
+        tools.insert(reset_tool.definition().name.clone(), reset_tool);
+
+        let fill_form_tool = Arc::new(FillFormTool);
+        tools.insert(fill_form_tool.definition().name.clone(), fill_form_tool);
+
+        let clear_storage_tool = Arc::new(ClearStorageTool);
+        tools.insert(
+            clear_storage_tool.definition().name.clone(),
+            clear_storage_tool,
+        );
+
+        let set_headers_tool = Arc::new(SetHeadersTool);
+        tools.insert(set_headers_tool.definition().name.clone(), set_headers_tool);
+
+        let run_sequence_tool = Arc::new(RunSequenceTool);
+        tools.insert(
+            run_sequence_tool.definition().name.clone(),
+            run_sequence_tool,
+        );
+
+        let config_tool = Arc::new(ConfigTool);
+        tools.insert(config_tool.definition().name.clone(), config_tool);
+
+        let provider_url_tool = Arc::new(ProviderUrlTool);
+        tools.insert(
+            provider_url_tool.definition().name.clone(),
+            provider_url_tool,
+        );
+
+        let read_response_tool = Arc::new(ReadResponseTool);
+        tools.insert(
+            read_response_tool.definition().name.clone(),
+            read_response_tool,
+        );
+
+        let get_content_tool = Arc::new(GetContentTool);
+        tools.insert(get_content_tool.definition().name.clone(), get_content_tool);
+
+        let clear_sessions_tool = Arc::new(ClearSessionsTool);
+        tools.insert(
+            clear_sessions_tool.definition().name.clone(),
+            clear_sessions_tool,
+        );
+
+        let list_conversations_tool = Arc::new(ListConversationsTool);
+        tools.insert(
+            list_conversations_tool.definition().name.clone(),
+            list_conversations_tool,
+        );
+
+        let close_conversation_tool = Arc::new(CloseConversationTool);
+        tools.insert(
+            close_conversation_tool.definition().name.clone(),
+            close_conversation_tool,
+        );
+
+        let console_log_tool = Arc::new(ConsoleLogTool);
+        tools.insert(console_log_tool.definition().name.clone(), console_log_tool);
+
+        let export_audit_tool = Arc::new(ExportAuditTool);
+        tools.insert(
+            export_audit_tool.definition().name.clone(),
+            export_audit_tool,
+        );
+
+        let list_accounts_tool = Arc::new(ListAccountsTool);
+        tools.insert(
+            list_accounts_tool.definition().name.clone(),
+            list_accounts_tool,
+        );
+
+        let switch_account_tool = Arc::new(SwitchAccountTool);
+        tools.insert(
+            switch_account_tool.definition().name.clone(),
+            switch_account_tool,
+        );
+
+        Self { tools, context }
+    }
+
+    /// Get the shared tool context.
+    pub fn context(&self) -> &Arc<ToolContext> {
+        &self.context
+    }
+
+    /// Get definitions of the tools available under the permission policy.
+    ///
+    /// Tools whose primary operation the policy denies are hidden unless
+    /// [`ServerConfig::show_all_tools`] is set; calling them still fails
+    /// with a permission error either way.
+    pub fn list_tools(&self) -> Vec<ToolDefinition> {
+        let config = &self.context.config;
+        let show_all = config.show_all_tools;
+        self.tools
+            .iter()
+            .filter(|(name, _)| !config.tool_denied(name))
+            .map(|(_, tool)| tool)
+            .filter(|tool| {
+                show_all
+                    || tool
+                        .primary_operation()
+                        .is_none_or(|op| policy_allows(&self.context.permissions, op))
+            })
+            .map(|t| t.definition())
+            .collect()
+    }
+
+    /// Operations a registered tool checks, or `None` if there is no such tool.
+    pub fn required_operations(&self, name: &str) -> Option<Vec<Operation>> {
+        self.tools.get(name).map(|tool| tool.required_operations())
+    }
+
+    /// Completion candidates for an argument of a tool (or prompt).
+    ///
+    /// `provider` arguments complete to the known provider ids; other
+    /// arguments complete from the `enum` in the tool's input schema, if any.
+    /// Prompt references are looked up by tool name since the server exposes
+    /// no prompt templates of its own. Unknown arguments yield no candidates.
+    pub fn complete(
+        &self,
+        reference: &CompletionReference,
+        argument: &CompletionArgument,
+    ) -> Completion {
+        let candidates: Vec<String> = if argument.name == "provider" {
+            PROVIDER_IDS.iter().map(|id| id.to_string()).collect()
+        } else {
+            match reference {
+                CompletionReference::Tool { name } | CompletionReference::Prompt { name } => self
+                    .tools
+                    .get(name)
+                    .map(|tool| schema_enum(&tool.definition().input_schema, &argument.name))
+                    .unwrap_or_default(),
+                CompletionReference::Resource { .. } => Vec::new(),
+            }
+        };
+
+        let prefix = argument.value.to_lowercase();
+        let matches: Vec<String> = candidates
+            .into_iter()
+            .filter(|candidate| candidate.to_lowercase().starts_with(&prefix))
+            .collect();
+
+        Completion {
+            total: Some(matches.len()),
+            has_more: matches.len() > MAX_COMPLETIONS,
+            values: matches.into_iter().take(MAX_COMPLETIONS).collect(),
+        }
+    }
+
+    /// Execute a tool by name.
+    pub async fn execute(
+        &self,
+        name: &str,
+        arguments: serde_json::Value,
+    ) -> Result<ToolCallResult> {
+        self.execute_in(&self.context, name, arguments).await
+    }
+
+    /// Execute a tool by name against a specific (e.g. per-session) context.
+    pub async fn execute_in(
+        &self,
+        context: &ToolContext,
+        name: &str,
+        arguments: serde_json::Value,
+    ) -> Result<ToolCallResult> {
+        let tool = self
+            .tools
+            .get(name)
+            .ok_or_else(|| Error::ToolNotFound(name.to_string()))?;
+        if context.config.tool_denied(name) {
+            return Err(Error::Disabled(format!("tool {}", name)));
+        }
+
+        let _call = ActiveCall::start(&context.activity);
+        let timeout = context.config.tool_timeout;
+        // A panicking tool fails its call instead of taking the server down
+        let execution = AssertUnwindSafe(async {
+            // Waiting for the page counts towards the timeout
+            let _page = if tool.drives_page() {
+                Some(context.page_lock.lock().await)
+            } else {
+                None
+            };
+            Self::execute_recovering(tool, context, name, arguments).await
+        })
+        .catch_unwind();
+        let result = tokio::time::timeout(timeout, execution)
+            .await
+            .map_err(|_| Error::ToolTimeout {
+                tool: name.to_string(),
+                timeout_secs: timeout.as_secs(),
+            })?
+            .unwrap_or_else(|panic| {
+                let message = panic_message(panic.as_ref());
+                tracing::error!("Tool {} panicked: {}", name, message);
+                Err(Error::Internal(format!(
+                    "tool {} panicked: {}",
+                    name, message
+                )))
+            })?;
+
+        let mut result = result;
+        if context.result_format() == ResultFormat::Plain && !tool.verbatim_output() {
+            plain_result(&mut result);
+        }
+        Ok(match context.max_result_bytes() {
+            0 => result,
+            max_bytes => truncate_result(result, max_bytes),
+        })
+    }
+
+    /// Run a tool, discarding the browser if it crashed and retrying
+    /// idempotent tools once against a fresh one.
+    async fn execute_recovering(
+        tool: &Arc<dyn Tool>,
+        context: &ToolContext,
+        name: &str,
+        arguments: serde_json::Value,
+    ) -> Result<ToolCallResult> {
+        let retry_arguments = tool.idempotent().then(|| arguments.clone());
+
+        match tool.execute(arguments, context).await {
+            Err(Error::BrowserCrashed(reason)) => {
+                context.discard_puppet().await;
+                match retry_arguments {
+                    Some(arguments) => {
+                        tracing::warn!("Browser crashed during {} ({}); relaunching", name, reason);
+                        tool.execute(arguments, context).await
+                    }
+                    None => Err(Error::BrowserCrashed(reason)),
+                }
+            }
+            result => result,
+        }
+    }
+
+    /// Register a custom tool.
+    pub fn register(&mut self, tool: Arc<dyn Tool>) {
+        let name = tool.definition().name.clone();
+        self.tools.insert(name, tool);
+    }
+
+    /// Register the declarative tools of a manifest, returning how many were
+    /// added. Fails without registering anything if a name is already taken.
+    pub fn register_manifest(&mut self, manifest: ToolsManifest) -> Result<usize> {
+        if let Some(spec) = manifest
+            .tools
+            .iter()
+            .find(|spec| self.tools.contains_key(&spec.name))
+        {
+            return Err(Error::InvalidParams(format!(
+                "tool `{}` from the manifest is already registered",
+                spec.name
+            )));
+        }
+
+        let count = manifest.tools.len();
+        for spec in manifest.tools {
+            self.register(Arc::new(ManifestTool::new(spec)));
+        }
+        Ok(count)
+    }
+}
+
+/// The message a panic was raised with, if it is a string.
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    panic
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
+}
+
+/// Longest string kept in the structured content of a truncated result.
+const MAX_TRUNCATED_FIELD_BYTES: usize = 256;
+
+/// Cut a result down to `max_bytes` once serialized, counting every content
+/// item (including base64 image data) and the structured content.
+///
+/// Long strings in the structured content are cut first, since the text
+/// usually repeats them; if it is still more than half the limit, it is
+/// dropped. The sizes, the cut fields (as JSON pointers) and whether the
+/// structured content was dropped are recorded under `truncation` in the
+/// structured content so clients know data is missing. The remaining budget
+/// goes to the content items in order, with room set aside for a
+/// `[Truncated: ...]` notice and for a short placeholder per image: the
+/// first text that doesn't fit is cut and ends with the notice, later text
+/// is dropped, and an image that doesn't fit is replaced by its placeholder.
+fn truncate_result(mut result: ToolCallResult, max_bytes: usize) -> ToolCallResult {
+    let original_bytes = json_bytes(&result);
+    if original_bytes <= max_bytes {
+        return result;
+    }
+    let notice = format!(
+        "\n\n[Truncated: result was {} bytes, limit is {}]",
+        original_bytes, max_bytes
+    );
+
+    let mut cut_fields = Vec::new();
+    if let Some(ref mut structured) = result.structured_content {
+        cut_long_strings(structured, String::new(), &mut cut_fields);
+    }
+    let structured_dropped = structured_bytes(&result) > max_bytes / 2;
+    if structured_dropped {
+        result.structured_content = None;
+    }
+
+    let mut truncation = json!({"originalBytes": original_bytes, "maxBytes": max_bytes});
+    if !cut_fields.is_empty() {
+        truncation["structuredFields"] = json!(cut_fields);
+    }
+    if structured_dropped {
+        truncation["structuredDropped"] = json!(true);
+    }
+    match result.structured_content {
+        Some(serde_json::Value::Object(ref mut object)) => {
+            object.insert("truncation".into(), truncation);
+        }
+        // Keep a non-object result intact alongside the truncation info
+        Some(other) => {
+            result.structured_content = Some(json!({"result": other, "truncation": truncation}));
+        }
+        None => result.structured_content = Some(json!({"truncation": truncation})),
+    }
+
+    // What is left once everything but the content items is paid for; each
+    // item also costs a separating comma
+    let items = std::mem::take(&mut result.content);
+    let mut remaining = max_bytes.saturating_sub(json_bytes(&result));
+    let notice_bytes = json_bytes(&notice) - 2;
+    let mut reserved = notice_bytes
+        + items
+            .iter()
+            .filter_map(image_placeholder)
+            .map(|placeholder| json_bytes(&placeholder) + 1)
+            .sum::<usize>();
+    let mut cut = false;
+    for mut item in items {
+        let bytes = json_bytes(&item) + 1;
+        if let Some(placeholder) = image_placeholder(&item) {
+            let placeholder_bytes = json_bytes(&placeholder) + 1;
+            reserved -= placeholder_bytes;
+            if bytes <= remaining.saturating_sub(reserved) {
+                remaining -= bytes;
+                result.content.push(item);
+            } else if placeholder_bytes <= remaining {
+                remaining -= placeholder_bytes;
+                result.content.push(placeholder);
+            }
+            continue;
+        }
+        if bytes <= remaining.saturating_sub(reserved) {
+            remaining -= bytes;
+            result.content.push(item);
+            continue;
+        }
+        let text = match item {
+            ContentItem::Text { ref mut text, .. }
+            | ContentItem::Resource {
+                text: Some(ref mut text),
+                ..
+            } => text,
+            _ => continue,
+        };
+        if cut {
+            continue;
+        }
+        // What the item costs without its text
+        let envelope = bytes - (json_bytes(&*text) - 2);
+        truncate_json_bytes(text, remaining.saturating_sub(reserved + envelope));
+        text.push_str(&notice);
+        reserved -= notice_bytes;
+        remaining = remaining.saturating_sub(json_bytes(&item) + 1);
+        result.content.push(item);
+        cut = true;
+    }
+    result
+}
+
+/// Bytes of `value` serialized as JSON.
+fn json_bytes(value: &impl Serialize) -> usize {
+    serde_json::to_string(value).map_or(0, |json| json.len())
+}
+
+/// The text item standing in for `item` if it is an image that doesn't fit.
+fn image_placeholder(item: &ContentItem) -> Option<ContentItem> {
+    match item {
+        ContentItem::Image { data, .. } => Some(ContentItem::text(format!(
+            "[Image omitted: {} bytes of data]",
+            data.len()
+        ))),
+        _ => None,
+    }
+}
+
+/// Cut `text` on a character boundary so it takes at most `max_bytes` in a
+/// JSON string, escapes included.
+fn truncate_json_bytes(text: &mut String, max_bytes: usize) {
+    let mut bytes = 0;
+    for (index, c) in text.char_indices() {
+        bytes += match c {
+            '"' | '\\' | '\n' | '\r' | '\t' | '\u{8}' | '\u{c}' => 2,
+            c if (c as u32) < 0x20 => 6,
+            c => c.len_utf8(),
+        };
+        if bytes > max_bytes {
+            text.truncate(index);
+            return;
+        }
+    }
+}
+
+/// Bytes of the serialized structured content of `result`.
+fn structured_bytes(result: &ToolCallResult) -> usize {
+    result
+        .structured_content
+        .as_ref()
+        .map_or(0, |value| value.to_string().len())
+}
+
+/// Cut strings in `value` longer than [`MAX_TRUNCATED_FIELD_BYTES`],
+/// collecting the JSON pointers of those cut.
+fn cut_long_strings(value: &mut serde_json::Value, pointer: String, cut: &mut Vec<String>) {
+    match value {
+        serde_json::Value::String(text) if text.len() > MAX_TRUNCATED_FIELD_BYTES => {
+            let mut end = MAX_TRUNCATED_FIELD_BYTES;
+            while !text.is_char_boundary(end) {
+                end -= 1;
+            }
+            text.truncate(end);
+            cut.push(pointer);
+        }
+        serde_json::Value::Array(items) => {
+            for (index, item) in items.iter_mut().enumerate() {
+                cut_long_strings(item, format!("{}/{}", pointer, index), cut);
+            }
+        }
+        serde_json::Value::Object(object) => {
+            for (key, item) in object.iter_mut() {
+                let key = key.replace('~', "~0").replace('/', "~1");
+                cut_long_strings(item, format!("{}/{}", pointer, key), cut);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Whether the policy could ever allow `operation`, ignoring URL rules.
+///
+/// Mirrors the operation checks in `PermissionGuard::check` without
+/// writing an audit entry, since listing tools isn't an attempt to use them.
+fn policy_allows(permissions: &PermissionGuard, operation: Operation) -> bool {
+    let policy = permissions.policy();
+    if policy.denied_operations.contains(&operation)
+        || operation.risk_level() > policy.max_risk_level
+    {
+        return false;
+    }
+    policy.allowed_operations.contains(&operation) || !policy.default_deny
+}
+
+/// String values of a property's `enum` in a JSON schema.
+fn schema_enum(schema: &serde_json::Value, property: &str) -> Vec<String> {
+    schema["properties"][property]["enum"]
+        .as_array()
+        .map(|values| {
+            values
+                .iter()
+                .filter_map(|v| v.as_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// ============================================================================
+// Built-in Tools
+// ============================================================================
+
+/// Tool for sending prompts to AI providers.
+pub struct PromptTool;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct PromptArgs {
+    /// Provider/tool to use (defaults to the server's default provider)
+    #[schemars(extend("enum" = PROVIDER_IDS))]
+    provider: Option<String>,
+    /// The prompt message to send
+    message: String,
+    /// Optional context or system instructions
+    context: Option<String>,
+    /// Continue this conversation (an id from an earlier response or
+    /// webpuppet_list_conversations) instead of starting a new one
+    conversation_id: Option<String>,
+}
+
+#[async_trait::async_trait]
+impl Tool for PromptTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::from_args::<PromptArgs>(
+            "webpuppet_prompt",
+            "Send a prompt through browser automation (AI providers + select web tools). Uses existing authenticated sessions.",
+        )
+    }
+
+    fn primary_operation(&self) -> Option<Operation> {
+        Some(Operation::SendPrompt)
+    }
+
+    fn verbatim_output(&self) -> bool {
+        true
+    }
+
+    async fn execute(
+        &self,
+        arguments: serde_json::Value,
+        context: &ToolContext,
+    ) -> Result<ToolCallResult> {
+        // Check permission
+        context
+            .permissions
+            .require(Operation::SendPrompt)
+            .map_err(|e| Error::PermissionDenied(e.to_string()))?;
+
+        // Parse arguments
+        let args: PromptArgs =
+            serde_json::from_value(arguments).map_err(|e| Error::InvalidParams(e.to_string()))?;
+
+        let provider = context.provider_or_default(args.provider.as_deref())?;
+
+        // Build request
+        let mut request = PromptRequest::new(args.message);
+        if let Some(ctx) = args.context {
+            request = request.with_context(ctx);
+        }
+        if let Some(id) = args.conversation_id {
+            match context.conversations.provider(&id) {
+                Some(started_with) if started_with != provider.name() => {
+                    return Err(Error::InvalidParams(format!(
+                        "Conversation {} is with {}, not {}",
+                        id, started_with, provider
+                    )));
+                }
+                _ => request = request.with_conversation(id),
+            }
+        }
+
+        // Get the browser session and send prompt, streaming the response if
+        // the client asked for progress
+        let puppet = context.get_puppet().await?;
+        let progress = ProgressReporter::current();
+        let ScreenedResponse {
+            screening,
+            metadata,
+        } = match send_screened(context, &puppet, provider, request, progress.as_ref()).await {
+            Ok(response) => response,
+            Err(Error::InterventionRequired { provider, reason }) => {
+                return Ok(intervention_required_result(&provider, &reason));
+            }
+            Err(e) => return Err(e),
+        };
+        let withheld = withholds(context, &screening);
+        let mut structured = serde_json::to_value(&metadata)?;
+        structured["passed"] = json!(screening.passed);
+        structured["risk_score"] = json!(screening.risk_score);
+        structured["screening"] = screening_report(&screening, withheld);
+
+        // An error page or nothing at all is a failure, not an answer
+        let page = if diagnose::may_be_notice(&screening.sanitized) {
+            prompt_page_state(&puppet, provider).await
+        } else {
+            PageState::default()
+        };
+        if let Some(diagnosis) = diagnose(&screening.sanitized, &page) {
+            tracing::warn!(
+                "Prompt to {} failed ({}): {}",
+                provider,
+                diagnosis.category,
+                diagnosis.cause
+            );
+            structured["diagnosis"] = serde_json::to_value(&diagnosis)?;
+            let response = (!withheld).then_some(screening.sanitized.as_str());
+            return Ok(ToolCallResult {
+                content: vec![
+                    ContentItem::text(failed_prompt_text(provider, &diagnosis, response)),
+                    ContentItem::json(&structured),
+                ],
+                is_error: true,
+                structured_content: Some(structured),
+            });
+        }
+
+        // Make sure the user sees a flagged response
+        Ok(ToolCallResult {
+            content: screened_content(&screening, withheld, screening.sanitized.clone()),
+            is_error: withheld,
+            structured_content: Some(structured),
+        })
+    }
+}
+
+/// What the provider's page shows after a prompt; what can't be read
+/// counts as nothing wrong.
+async fn prompt_page_state(puppet: &Arc<dyn BrowserSession>, provider: Provider) -> PageState {
+    let logged_out = match puppet.login_status(provider).await {
+        Ok(status) => status == LoginStatus::LoggedOut,
+        Err(e) => {
+            tracing::debug!("Could not check {} login state: {}", provider, e);
+            false
+        }
+    };
+    let notices = puppet.page_notices(provider).await.unwrap_or_else(|e| {
+        tracing::debug!("Could not read {} page notices: {}", provider, e);
+        Vec::new()
+    });
+    PageState {
+        logged_out,
+        notices,
+    }
+}
+
+/// Report of a prompt that came back without an answer, quoting what the
+/// provider showed instead, if anything and if it may be shown.
+fn failed_prompt_text(provider: Provider, diagnosis: &Diagnosis, response: Option<&str>) -> String {
+    let shown = match response.map(str::trim) {
+        Some("") => "\n\nThe response was empty.".to_string(),
+        Some(response) => format!(
+            "\n\nThe provider showed:\n\n> {}",
+            response.replace('\n', "\n> ")
+        ),
+        None => String::new(),
+    };
+    format!(
+        "# Prompt Failed\n\n**Provider**: {}\n**Likely cause**: {}\n**Suggestion**: {}{}",
+        provider, diagnosis.cause, diagnosis.suggestion, shown
+    )
+}
+
+/// Result telling the client to have a human finish an intervention, then
+/// retry the call.
+fn intervention_required_result(provider: &str, reason: &str) -> ToolCallResult {
+    let structured = json!({
+        "intervention_required": true,
+        "provider": provider,
+        "reason": reason,
+        "state": intervention_state_name(InterventionState::WaitingForHuman),
+    });
+    let text = format!(
+        "# Human Intervention Required\n\n**Provider**: {}\n**Reason**: {}\n\n⚠️ **Action Required**: Please complete this in the browser window, call `webpuppet_intervention_complete` with success=true, then retry this call. The browser session stays open.",
+        provider, reason
+    );
+
+    ToolCallResult {
+        content: vec![
+            ContentItem::text(text)
+                .with_audience([Role::User, Role::Assistant])
+                .with_priority(1.0),
+            ContentItem::json(&structured).with_audience([Role::Assistant]),
+        ],
+        is_error: true,
+        structured_content: Some(structured),
+    }
+}
+
+/// A screened provider response and what is known about how it was produced.
+struct ScreenedResponse {
+    screening: ScreeningResult,
+    metadata: ResponseMetadata,
+}
+
+/// Provenance of a provider response, for attribution and citation.
+#[derive(Debug, Serialize)]
+struct ResponseMetadata {
+    /// Provider that answered.
+    provider: &'static str,
+    /// Model that answered, if the provider says.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    model: Option<String>,
+    /// Time the provider took to answer.
+    elapsed_ms: u64,
+    /// Sources the provider cited (search-capable providers only).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sources: Option<Vec<serde_json::Value>>,
+    /// Conversation the response belongs to, if the provider keeps one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    conversation_id: Option<String>,
+}
+
+impl ResponseMetadata {
+    /// Read the `model` and `sources` entries of a response's metadata.
+    ///
+    /// `sources` is either a JSON array (of URLs or citation objects) or one
+    /// URL per line.
+    fn new(response: &webpuppet::PromptResponse, elapsed: Duration) -> Self {
+        let sources = response.metadata.get("sources").map(|sources| {
+            serde_json::from_str::<Vec<serde_json::Value>>(sources).unwrap_or_else(|_| {
+                sources
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                    .map(serde_json::Value::from)
+                    .collect()
+            })
+        });
+
+        Self {
+            provider: response.provider.name(),
+            model: response.metadata.get("model").cloned(),
+            elapsed_ms: elapsed.as_millis() as u64,
+            sources: sources.filter(|sources| !sources.is_empty()),
+            conversation_id: response.conversation_id.clone(),
+        }
+    }
+}
+
+/// Send a prompt to a provider and screen the response.
+///
+/// Waits for the provider's rate limit and authenticates first if needed.
+/// Only the sanitized text of the returned result should be shown. With a
+/// `progress` reporter, the response is also reported as it is generated.
+async fn send_screened(
+    context: &ToolContext,
+    puppet: &Arc<dyn BrowserSession>,
+    provider: Provider,
+    request: PromptRequest,
+    progress: Option<&ProgressReporter>,
+) -> Result<ScreenedResponse> {
+    // Space out prompts to the same provider
+    context
+        .rate_limiter
+        .acquire(provider, context.config.tool_timeout)
+        .await?;
+
+    // Surface captchas, 2FA and logins instead of blocking on them
+    if let Some(reason) = puppet.intervention_needed(provider).await? {
+        return Err(prompt_intervention(context, provider, reason.to_string()).await);
+    }
+
+    // A login that doesn't settle in time needs a human too
+    let auth_timeout = context.config.auth_timeout(provider);
+    tracing::debug!(
+        "Authenticating with {} (timeout {:?})",
+        provider,
+        auth_timeout
+    );
+    match tokio::time::timeout(auth_timeout, puppet.authenticate(provider)).await {
+        Ok(result) => result?,
+        Err(_) => {
+            tracing::warn!(
+                "Authentication with {} timed out after {:?}",
+                provider,
+                auth_timeout
+            );
+            let reason = format!("Login did not complete within {:?}", auth_timeout);
+            return Err(prompt_intervention(context, provider, reason).await);
+        }
+    }
+    let action = Action::prompt(provider, &request.message);
+    let message = request.message.clone();
+    let continued = request.conversation_id.clone();
+    let started = Instant::now();
+    let response = match progress {
+        Some(reporter) => prompt_streamed(context, puppet, provider, request, reporter).await?,
+        None => puppet.prompt(provider, request).await?,
+    };
+    let mut metadata = ResponseMetadata::new(&response, started.elapsed());
+    context.record(action);
+    // Providers that don't report the id of a continued conversation keep it
+    metadata.conversation_id = metadata.conversation_id.or(continued);
+    if let Some(id) = &metadata.conversation_id {
+        context
+            .conversations
+            .record(id, provider, &message, &context.redactor);
+    }
+
+    let screening =
+        ContentScreener::with_config(context.screening_config.clone()).screen(&response.text);
+    if !screening.passed {
+        tracing::warn!(
+            "Response from {} flagged with risk score {:.2}",
+            provider,
+            screening.risk_score
+        );
+    }
+    Ok(ScreenedResponse {
+        screening,
+        metadata,
+    })
+}
+
+/// Put the server in the intervention state for a prompt to `provider` and
+/// return the error telling the client to finish it and retry.
+async fn prompt_intervention(context: &ToolContext, provider: Provider, reason: String) -> Error {
+    let reason = InterventionReason::Manual {
+        description: format!("{} on {} ({})", reason, provider, provider.base_url()),
+    };
+    context
+        .request_intervention(
+            reason.clone(),
+            InterventionOrigin::current(Some(provider), Operation::SendPrompt),
+        )
+        .await;
+    Error::InterventionRequired {
+        provider: provider.name().to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+/// Send a prompt, reporting the response to `reporter` as it is generated.
+async fn prompt_streamed(
+    context: &ToolContext,
+    puppet: &Arc<dyn BrowserSession>,
+    provider: Provider,
+    request: PromptRequest,
+    reporter: &ProgressReporter,
+) -> Result<webpuppet::PromptResponse> {
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    let mut stream = ScreenedStream::new(context.screening_config.clone());
+    let prompt = puppet.prompt_streaming(provider, request, sender);
+    tokio::pin!(prompt);
+
+    let response = loop {
+        tokio::select! {
+            biased;
+            Some(chunk) = receiver.recv() => {
+                if let Some(text) = stream.push(&chunk) {
+                    reporter.report(&text);
+                }
+            }
+            response = &mut prompt => break response?,
+        }
+    };
+    while let Ok(chunk) = receiver.try_recv() {
+        if let Some(text) = stream.push(&chunk) {
+            reporter.report(&text);
+        }
+    }
+    Ok(response)
+}
+
+/// Screens a response while it streams in.
+///
+/// Partial text is screened like a full response: only sanitized text that
+/// extends what was already forwarded is passed on, and nothing more is
+/// once the partial response fails screening.
+struct ScreenedStream {
+    screener: ContentScreener,
+    raw: String,
+    forwarded: String,
+    halted: bool,
+}
+
+impl ScreenedStream {
+    fn new(config: ScreeningConfig) -> Self {
+        Self {
+            screener: ContentScreener::with_config(config),
+            raw: String::new(),
+            forwarded: String::new(),
+            halted: false,
+        }
+    }
+
+    /// Add a raw chunk, returning the sanitized text to forward, if any.
+    fn push(&mut self, chunk: &str) -> Option<String> {
+        if self.halted {
+            return None;
+        }
+        self.raw.push_str(chunk);
+
+        let screening = self.screener.screen(&self.raw);
+        if !screening.passed {
+            self.halted = true;
+            return None;
+        }
+        let new = screening
+            .sanitized
+            .strip_prefix(self.forwarded.as_str())
+            .filter(|new| !new.is_empty())?
+            .to_string();
+        self.forwarded = screening.sanitized;
+        Some(new)
+    }
+}
+
+/// Tool for reading a provider's latest response while it is generated.
+pub struct ReadResponseTool;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct ReadResponseArgs {
+    /// Provider whose page to read (defaults to the server's default provider)
+    #[schemars(extend("enum" = PROVIDER_IDS))]
+    provider: Option<String>,
+}
+
+#[async_trait::async_trait]
+impl Tool for ReadResponseTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::from_args::<ReadResponseArgs>(
+            "webpuppet_read_response",
+            "Read the provider's latest response as far as it has been rendered, without waiting for it to finish. Returns the (screened) text and a complete flag; poll it to follow a long generation.",
+        )
+    }
+
+    fn primary_operation(&self) -> Option<Operation> {
+        Some(Operation::ReadResponse)
+    }
+
+    fn verbatim_output(&self) -> bool {
+        true
+    }
+
+    async fn execute(
+        &self,
+        arguments: serde_json::Value,
+        context: &ToolContext,
+    ) -> Result<ToolCallResult> {
+        let args: ReadResponseArgs =
+            serde_json::from_value(arguments).map_err(|e| Error::InvalidParams(e.to_string()))?;
+        let provider = context.provider_or_default(args.provider.as_deref())?;
+        context
+            .permissions
+            .require_with_url(Operation::ReadResponse, provider.base_url())
+            .map_err(|e| Error::PermissionDenied(e.to_string()))?;
+
+        let puppet = context.get_puppet().await?;
+        let Some(response) = puppet.read_response(provider).await? else {
+            let structured = json!({
+                "provider": provider.name(),
+                "text": "",
+                "complete": false,
+                "found": false,
+            });
+            return Ok(ToolCallResult {
+                content: vec![
+                    ContentItem::text(format!("No response on the {} page yet.", provider)),
+                    ContentItem::json(&structured),
+                ],
+                is_error: false,
+                structured_content: Some(structured),
+            });
+        };
+
+        // Partial text is screened like a full response
+        let screening =
+            ContentScreener::with_config(context.screening_config.clone()).screen(&response.text);
+        let withheld = withholds(context, &screening);
+        let structured = json!({
+            "provider": provider.name(),
+            "text": if withheld { "" } else { &screening.sanitized },
+            "complete": response.complete,
+            "found": true,
+            "passed": screening.passed,
+            "risk_score": screening.risk_score,
+            "screening": screening_report(&screening, withheld),
+        });
+
+        let status = if response.complete {
+            "complete"
+        } else {
+            "still generating"
+        };
+        let mut content = screened_content(
+            &screening,
+            withheld,
+            format!("[{}]\n\n{}", status, screening.sanitized),
+        );
+        content.push(ContentItem::json(&structured));
+
+        Ok(ToolCallResult {
+            content,
+            is_error: withheld,
+            structured_content: Some(structured),
+        })
+    }
+}
+
+/// Tool for sending the same prompt to several providers and comparing the
+/// responses.
+pub struct CompareProvidersTool;
+
+/// Characters of each response shown in the comparison table.
+const COMPARE_PREVIEW_CHARS: usize = 120;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct CompareProvidersArgs {
+    /// Providers/tools to compare (at least two)
+    #[schemars(length(min = 2), extend("items" = {"type": "string", "enum": PROVIDER_IDS}))]
+    providers: Vec<String>,
+    /// The prompt message to send to every provider
+    message: String,
+    /// Optional context or system instructions
+    context: Option<String>,
+}
+
+/// One provider's side of a comparison.
+#[derive(Debug, Serialize)]
+struct ProviderAnswer {
+    /// Sanitized response text.
+    text: Option<String>,
+    /// Screening risk score of the response.
+    risk_score: Option<f32>,
+    /// Whether the response passed screening.
+    passed: Option<bool>,
+    /// Word count of the response.
+    words: Option<usize>,
+    /// Why the provider could not answer.
+    error: Option<String>,
+}
+
+#[async_trait::async_trait]
+impl Tool for CompareProvidersTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::from_args::<CompareProvidersArgs>(
+            "webpuppet_compare_providers",
+            "Send the same prompt to two or more providers and compare the screened responses side by side, with per-provider risk scores and pairwise word overlap. A provider that fails is reported without failing the whole comparison.",
+        )
+    }
+
+    fn primary_operation(&self) -> Option<Operation> {
+        Some(Operation::SendPrompt)
+    }
+
+    async fn execute(
+        &self,
+        arguments: serde_json::Value,
+        context: &ToolContext,
+    ) -> Result<ToolCallResult> {
+        context
+            .permissions
+            .require(Operation::SendPrompt)
+            .map_err(|e| Error::PermissionDenied(e.to_string()))?;
+
+        let args: CompareProvidersArgs =
+            serde_json::from_value(arguments).map_err(|e| Error::InvalidParams(e.to_string()))?;
+
+        let mut providers = Vec::new();
+        for name in &args.providers {
+            let provider = parse_provider(name)?;
+            if !providers.contains(&provider) {
+                providers.push(provider);
+            }
+        }
+        if providers.len() < 2 {
+            return Err(Error::InvalidParams(
+                "at least two different providers are needed for a comparison".into(),
+            ));
+        }
+
+        let puppet = context.get_puppet().await?;
+
+        let mut answers = Vec::with_capacity(providers.len());
+        for &provider in &providers {
+            let mut request = PromptRequest::new(args.message.clone());
+            if let Some(ref ctx) = args.context {
+                request = request.with_context(ctx.clone());
+            }
+
+            let answer = match send_screened(context, &puppet, provider, request, None).await {
+                Ok(ScreenedResponse { screening, .. }) if withholds(context, &screening) => {
+                    ProviderAnswer {
+                        text: None,
+                        risk_score: Some(screening.risk_score),
+                        passed: Some(false),
+                        words: None,
+                        error: Some(format!(
+                            "response withheld by screening (risk score {:.2})",
+                            screening.risk_score
+                        )),
+                    }
+                }
+                Ok(ScreenedResponse { screening, .. }) => ProviderAnswer {
+                    words: Some(screening.sanitized.split_whitespace().count()),
+                    risk_score: Some(screening.risk_score),
+                    passed: Some(screening.passed),
+                    text: Some(screening.sanitized),
+                    error: None,
+                },
+                // The browser is gone for every remaining provider too
+                Err(e @ Error::BrowserCrashed(_)) => return Err(e),
+                Err(e) => {
+                    tracing::warn!("Comparison prompt to {} failed: {}", provider, e);
+                    ProviderAnswer {
+                        text: None,
+                        risk_score: None,
+                        passed: None,
+                        words: None,
+                        error: Some(e.to_string()),
+                    }
+                }
+            };
+            answers.push((provider, answer));
+        }
+
+        let mut similarity = Vec::new();
+        for (i, (a, answer_a)) in answers.iter().enumerate() {
+            for (b, answer_b) in &answers[i + 1..] {
+                if let (Some(text_a), Some(text_b)) = (&answer_a.text, &answer_b.text) {
+                    similarity.push(json!({
+                        "providers": [a.name(), b.name()],
+                        "word_overlap": word_overlap(text_a, text_b),
+                    }));
+                }
+            }
+        }
+
+        let rows = answers
+            .iter()
+            .map(|(provider, answer)| match (&answer.text, &answer.error) {
+                (Some(text), _) => format!(
+                    "| {} | {:.2}{} | {} | {} |",
+                    provider.name(),
+                    answer.risk_score.unwrap_or_default(),
+                    if answer.passed == Some(false) {
+                        " ⚠"
+                    } else {
+                        ""
+                    },
+                    answer.words.unwrap_or_default(),
+                    table_cell(text, COMPARE_PREVIEW_CHARS)
+                ),
+                (None, error) => format!(
+                    "| {} | - | - | **failed:** {} |",
+                    provider.name(),
+                    table_cell(
+                        error.as_deref().unwrap_or("unknown error"),
+                        COMPARE_PREVIEW_CHARS
+                    )
+                ),
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let overlap_lines = similarity
+            .iter()
+            .map(|pair| {
+                format!(
+                    "- {} vs {}: {:.0}% word overlap",
+                    pair["providers"][0].as_str().unwrap_or_default(),
+                    pair["providers"][1].as_str().unwrap_or_default(),
+                    pair["word_overlap"].as_f64().unwrap_or_default() * 100.0
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let answered = answers.iter().filter(|(_, a)| a.text.is_some()).count();
+        let mut text = format!(
+            "# Provider Comparison\n\n{} of {} providers answered.\n\n| Provider | Risk | Words | Response |\n|----------|------|-------|----------|\n{}",
+            answered,
+            answers.len(),
+            rows
+        );
+        if !overlap_lines.is_empty() {
+            text.push_str("\n\n## Similarity\n\n");
+            text.push_str(&overlap_lines);
+        }
+
+        let structured = json!({
+            "responses": answers
+                .iter()
+                .map(|(provider, answer)| (provider.name().to_string(), json!(answer)))
+                .collect::<serde_json::Map<_, _>>(),
+            "similarity": similarity,
+        });
+
+        Ok(ToolCallResult {
+            content: vec![ContentItem::text(text), ContentItem::json(&structured)],
+            is_error: false,
+            structured_content: Some(structured),
+        })
+    }
+}
+
+/// Share of distinct words two texts have in common (Jaccard index, 0–1).
+fn word_overlap(a: &str, b: &str) -> f64 {
+    fn words(text: &str) -> std::collections::HashSet<String> {
+        text.split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .map(str::to_lowercase)
+            .collect()
+    }
+
+    let (a, b) = (words(a), words(b));
+    let union = a.union(&b).count();
+    if union == 0 {
+        return 1.0;
+    }
+    let overlap = a.intersection(&b).count() as f64 / union as f64;
+    (overlap * 100.0).round() / 100.0
+}
+
+/// Text shortened and escaped to fit in one markdown table cell.
+fn table_cell(text: &str, max_chars: usize) -> String {
+    let flat = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut cell: String = flat.chars().take(max_chars).collect();
+    if flat.chars().count() > max_chars {
+        cell.push('…');
+    }
+    cell.replace('|', "\\|")
+}
+
+/// Tool for summarizing a web page with a provider.
+pub struct SummarizePageTool;
+
+/// Characters of page text sent to the provider; longer pages are cut off.
+const SUMMARIZE_MAX_CHARS: usize = 20_000;
+
+/// Instructions used when the caller gives none.
+const DEFAULT_SUMMARY_INSTRUCTIONS: &str =
+    "Summarize the following web page in a few paragraphs, keeping the key facts.";
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct SummarizePageArgs {
+    /// URL of the page to summarize
+    url: String,
+    /// Provider/tool to summarize with (defaults to the server's default provider)
+    #[schemars(extend("enum" = PROVIDER_IDS))]
+    provider: Option<String>,
+    /// What to do with the page (default: summarize it in a few paragraphs)
+    instructions: Option<String>,
+    /// How the page is given to the provider (default: markdown)
+    format: Option<PageFormat>,
+}
+
+#[async_trait::async_trait]
+impl Tool for SummarizePageTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::from_args::<SummarizePageArgs>(
+            "webpuppet_summarize_page",
+            "Open a URL (allowed domains only), extract the page's readable text and send it to a provider to summarize, returning the screened response. Optional instructions replace the default summary request.",
+        )
+    }
+
+    fn primary_operation(&self) -> Option<Operation> {
+        Some(Operation::SendPrompt)
+    }
+
+    fn required_operations(&self) -> Vec<Operation> {
+        vec![
+            Operation::Navigate,
+            Operation::ReadContent,
+            Operation::SendPrompt,
+        ]
+    }
+
+    fn drives_page(&self) -> bool {
+        true
+    }
+
+    fn verbatim_output(&self) -> bool {
+        true
+    }
+
+    async fn execute(
+        &self,
+        arguments: serde_json::Value,
+        context: &ToolContext,
+    ) -> Result<ToolCallResult> {
+        let args: SummarizePageArgs =
+            serde_json::from_value(arguments).map_err(|e| Error::InvalidParams(e.to_string()))?;
+        let url = normalize_and_validate_url(&args.url)?;
+        let provider = context.provider_or_default(args.provider.as_deref())?;
+
+        // Check everything up front rather than failing halfway
+        context
+            .permissions
+            .require_with_url(Operation::Navigate, &url)
+            .map_err(|e| Error::PermissionDenied(e.to_string()))?;
+        context.config.blocklist.check_url(&url)?;
+        confirm(context, ConfirmAction::Navigate(&url)).await?;
+        for operation in [Operation::ReadContent, Operation::SendPrompt] {
+            context
+                .permissions
+                .require(operation)
+                .map_err(|e| Error::PermissionDenied(e.to_string()))?;
+        }
+
+        let puppet = context.get_puppet().await?;
+        context.navigate(&puppet, &url).await?;
+        let page_url = puppet.current_url().await.unwrap_or_else(|_| url.clone());
+        context.record(Action::Navigate {
+            url: page_url.clone(),
+        });
+
+        let page = render_page(puppet.as_ref(), args.format.unwrap_or_default()).await?;
+        if page.content.trim().is_empty() {
+            return Err(Error::InvalidParams(format!(
+                "the page at {} has no readable text",
+                page_url
+            )));
+        }
+        let truncated = page.content.chars().count() > SUMMARIZE_MAX_CHARS;
+        let page_text: String = page.content.chars().take(SUMMARIZE_MAX_CHARS).collect();
+
+        let instructions = args
+            .instructions
+            .as_deref()
+            .unwrap_or(DEFAULT_SUMMARY_INSTRUCTIONS);
+        let message = format!(
+            "{}\n\nTitle: {}\nURL: {}\n\n---\n{}{}",
+            instructions,
+            page.title,
+            page_url,
+            page_text,
+            if truncated {
+                "\n[page text truncated]"
+            } else {
+                ""
+            }
+        );
+
+        let ScreenedResponse {
+            screening,
+            metadata,
+        } = match send_screened(
+            context,
+            &puppet,
+            provider,
+            PromptRequest::new(message),
+            ProgressReporter::current().as_ref(),
+        )
+        .await
+        {
+            Ok(response) => response,
+            Err(Error::InterventionRequired { provider, reason }) => {
+                return Ok(intervention_required_result(&provider, &reason));
+            }
+            Err(e) => return Err(e),
+        };
+
+        let mut structured = serde_json::to_value(&metadata)?;
+        structured["url"] = json!(page_url);
+        structured["title"] = json!(page.title);
+        structured["format"] = json!(page.format);
+        structured["page_chars"] = json!(page_text.chars().count());
+        structured["truncated"] = json!(truncated);
+        structured["passed"] = json!(screening.passed);
+        structured["risk_score"] = json!(screening.risk_score);
+        let withheld = withholds(context, &screening);
+        structured["screening"] = screening_report(&screening, withheld);
+
+        let mut content = screened_content(
+            &screening,
+            withheld,
+            format!("# Summary of {}\n\n{}", page_url, screening.sanitized),
+        );
+        content.push(ContentItem::json(&structured));
+
+        Ok(ToolCallResult {
+            content,
+            is_error: withheld,
+            structured_content: Some(structured),
+        })
+    }
+}
+
+/// Tool for listing available AI providers.
+pub struct ListProvidersTool;
+
+#[async_trait::async_trait]
+impl Tool for ListProvidersTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "webpuppet_list_providers".into(),
+            description: "List available AI providers and their status.".into(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {},
+                "required": []
+            }),
+        }
+    }
+
+    async fn execute(
+        &self,
+        _arguments: serde_json::Value,
+        context: &ToolContext,
+    ) -> Result<ToolCallResult> {
+        let key = RequestCoalescer::key("webpuppet_list_providers", &json!({}));
+        context
+            .coalescer
+            .run(key, context.config.read_cache_ttl, async {
+                list_providers_result(context)
+            })
+            .await
+    }
+}
+
+/// The `webpuppet_list_providers` answer.
+fn list_providers_result(context: &ToolContext) -> Result<ToolCallResult> {
+    let default = context.default_provider.name();
+    let text = PROVIDERS
+        .iter()
+        .map(|info| {
+            let marker = if info.id() == default {
+                " — default"
+            } else {
+                ""
+            };
+            format!(
+                "- **{}** (`{}`){}: [{}]({})\n  _{}_",
+                info.name,
+                info.id(),
+                marker,
+                info.url(),
+                info.url(),
+                info.features
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let structured = json!({
+        "default": default,
+        "providers": PROVIDERS
+            .iter()
+            .map(|info| json!({
+                "id": info.id(),
+                "name": info.name,
+                "url": info.url(),
+                "features": info.features,
+            }))
+            .collect::<Vec<_>>(),
+    });
+
+    Ok(ToolCallResult {
+            content: vec![
+                ContentItem::text(format!(
+                    "# Available Providers\n\n**Default provider**: `{}`\n\n{}\n\n*Note: Uses browser sessions; some providers require login.*",
+                    default, text
+                )),
+                ContentItem::json(&structured),
+            ],
+            is_error: false,
+            structured_content: Some(structured),
+        })
+}
+
+/// Tool for resolving the page a provider is reached at.
+pub struct ProviderUrlTool;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct ProviderUrlArgs {
+    /// Provider to resolve (defaults to the server's default provider)
+    #[schemars(extend("enum" = PROVIDER_IDS))]
+    provider: Option<String>,
+}
+
+#[async_trait::async_trait]
+impl Tool for ProviderUrlTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::from_args::<ProviderUrlArgs>(
+            "webpuppet_provider_url",
+            "Get the canonical URL the browser opens for a provider, to open it yourself or check where automation will go. No browser needed.",
+        )
+    }
+
+    fn idempotent(&self) -> bool {
+        true
+    }
+
+    async fn execute(
+        &self,
+        arguments: serde_json::Value,
+        context: &ToolContext,
+    ) -> Result<ToolCallResult> {
+        let args: ProviderUrlArgs =
+            serde_json::from_value(arguments).map_err(|e| Error::InvalidParams(e.to_string()))?;
+        let info = provider_info(context.provider_or_default(args.provider.as_deref())?);
+
+        let structured = json!({
+            "provider": info.id(),
+            "name": info.name,
+            "url": info.url(),
+            "default": info.provider == context.default_provider,
+        });
+
+        Ok(ToolCallResult {
+            content: vec![
+                ContentItem::text(format!("**{}**: {}", info.name, info.url())),
+                ContentItem::json(&structured),
+            ],
+            is_error: false,
+            structured_content: Some(structured),
+        })
+    }
+}
+
+/// Tool for checking whether the browser profile is logged in to a provider.
+pub struct LoginStatusTool;
+
+#[derive(Debug, Deserialize)]
+struct LoginStatusArgs {
+    /// Provider to check (defaults to the configured provider).
+    provider: Option<String>,
+}
+
+#[async_trait::async_trait]
+impl Tool for LoginStatusTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "webpuppet_login_status".into(),
+            description: "Check whether the browser profile has an authenticated session for a provider (logged_in, logged_out, or unknown) without sending a prompt or starting a login flow.".into(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "provider": {
+                        "type": "string",
+                        "enum": PROVIDER_IDS,
+                        "description": "Provider/tool to check (defaults to the server's default provider)"
+                    }
+                },
+                "required": []
+            }),
+        }
+    }
+
+    fn primary_operation(&self) -> Option<Operation> {
+        Some(Operation::ReadContent)
+    }
+
+    fn idempotent(&self) -> bool {
+        true
+    }
+
+    async fn execute(
+        &self,
+        arguments: serde_json::Value,
+        context: &ToolContext,
+    ) -> Result<ToolCallResult> {
+        context
+            .permissions
+            .require(Operation::ReadContent)
+            .map_err(|e| Error::PermissionDenied(e.to_string()))?;
+
+        let args: LoginStatusArgs =
+            serde_json::from_value(arguments).map_err(|e| Error::InvalidParams(e.to_string()))?;
+
+        let provider = context.provider_or_default(args.provider.as_deref())?;
+
+        let puppet = context.get_puppet().await?;
+        let status = puppet.login_status(provider).await?;
+
+        let summary = match status {
+            LoginStatus::LoggedIn => "🟢 Logged in",
+            LoginStatus::LoggedOut => {
+                "🔴 Logged out\n\n⚠️ Prompts to this provider will need a login; expect a human intervention."
+            }
+            LoginStatus::Unknown => "⚪ Unknown (session state could not be determined)",
+        };
+
+        let structured = json!({
+            "provider": provider.name(),
+            "status": status.as_str(),
+        });
+
+        Ok(ToolCallResult {
+            content: vec![
+                ContentItem::text(format!(
+                    "# Login Status\n\n**Provider**: {}\n**Status**: {}",
+                    provider, summary
+                )),
+                ContentItem::json(&structured),
+            ],
+            is_error: false,
+            structured_content: Some(structured),
+        })
+    }
+}
+
+/// Markdown list of a provider's accounts, marking the active one.
+fn accounts_list(accounts: &ProviderAccounts) -> String {
+    if accounts.accounts.is_empty() {
+        return "No accounts found on the provider's page.".to_string();
+    }
+    accounts
+        .accounts
+        .iter()
+        .map(|account| {
+            if accounts.active.as_ref() == Some(account) {
+                format!("- **{}** (active)", account)
+            } else {
+                format!("- {}", account)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Tool listing the accounts signed in to a provider.
+pub struct ListAccountsTool;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct ListAccountsArgs {
+    /// Provider whose accounts to list (defaults to the server's default provider)
+    provider: Option<String>,
+}
+
+#[async_trait::async_trait]
+impl Tool for ListAccountsTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::from_args::<ListAccountsArgs>(
+            "webpuppet_list_accounts",
+            "List the accounts signed in to a provider in the browser profile (e.g. several Google accounts for Gemini) and which one is active, as the provider's page shows them. Switch with webpuppet_switch_account.",
+        )
+    }
+
+    fn primary_operation(&self) -> Option<Operation> {
+        Some(Operation::ReadContent)
+    }
+
+    fn idempotent(&self) -> bool {
+        true
+    }
+
+    async fn execute(
+        &self,
+        arguments: serde_json::Value,
+        context: &ToolContext,
+    ) -> Result<ToolCallResult> {
+        context
+            .permissions
+            .require(Operation::ReadContent)
+            .map_err(|e| Error::PermissionDenied(e.to_string()))?;
+
+        let args: ListAccountsArgs =
+            serde_json::from_value(arguments).map_err(|e| Error::InvalidParams(e.to_string()))?;
+        let provider = context.provider_or_default(args.provider.as_deref())?;
+
+        let puppet = context.get_puppet().await?;
+        let accounts = puppet.accounts(provider).await?;
+        context.record_accounts(provider, &accounts);
+
+        let structured = json!({
+            "provider": provider.name(),
+            "accounts": accounts.accounts,
+            "active": accounts.active,
+        });
+
+        Ok(ToolCallResult {
+            content: vec![
+                ContentItem::text(format!(
+                    "# Accounts\n\n**Provider**: {}\n\n{}",
+                    provider,
+                    accounts_list(&accounts)
+                )),
+                ContentItem::json(&structured),
+            ],
+            is_error: false,
+            structured_content: Some(structured),
+        })
+    }
+}
+
+/// Tool switching the account a provider's session uses.
+pub struct SwitchAccountTool;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct SwitchAccountArgs {
+    /// Provider whose account to switch (defaults to the server's default provider)
+    provider: Option<String>,
+    /// Account to use, as listed by webpuppet_list_accounts
+    account: String,
+}
+
+#[async_trait::async_trait]
+impl Tool for SwitchAccountTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::from_args::<SwitchAccountArgs>(
+            "webpuppet_switch_account",
+            "Switch the account a provider's session uses to another one signed in to it (as listed by webpuppet_list_accounts), keeping the browser session. This selects an account within the provider; to use another browser profile, see webpuppet_use_profile.",
+        )
+    }
+
+    fn primary_operation(&self) -> Option<Operation> {
+        Some(Operation::Click)
+    }
+
+    async fn execute(
+        &self,
+        arguments: serde_json::Value,
+        context: &ToolContext,
+    ) -> Result<ToolCallResult> {
+        context
+            .permissions
+            .require(Operation::Click)
+            .map_err(|e| Error::PermissionDenied(e.to_string()))?;
+
+        let args: SwitchAccountArgs =
+            serde_json::from_value(arguments).map_err(|e| Error::InvalidParams(e.to_string()))?;
+        let provider = context.provider_or_default(args.provider.as_deref())?;
+        let account = args.account.trim();
+        if account.is_empty() {
+            return Err(Error::InvalidParams("account must not be empty".into()));
+        }
+
+        let puppet = context.get_puppet().await?;
+        let accounts = puppet.switch_account(provider, account).await?;
+        context.record_accounts(provider, &accounts);
+
+        let structured = json!({
+            "provider": provider.name(),
+            "accounts": accounts.accounts,
+            "active": accounts.active,
+        });
+
+        Ok(ToolCallResult {
+            content: vec![
+                ContentItem::text(format!(
+                    "# Account Switched\n\n**Provider**: {}\n**Active account**: {}\n\n{}",
+                    provider,
+                    accounts.active.as_deref().unwrap_or(account),
+                    accounts_list(&accounts)
+                )),
+                ContentItem::json(&structured),
+            ],
+            is_error: false,
+            structured_content: Some(structured),
+        })
+    }
+}
+
+/// Tool for retrieving declared provider capabilities.
+pub struct ProviderCapabilitiesTool;
+
+#[derive(Debug, Deserialize)]
+struct ProviderCapabilitiesArgs {
+    /// Provider/tool to inspect (defaults to the configured provider).
+    provider: Option<String>,
+    /// Also load the provider's page and check its UI is ready.
+    #[serde(default)]
+    probe: bool,
+}
+
+#[async_trait::async_trait]
+impl Tool for ProviderCapabilitiesTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "webpuppet_provider_capabilities".into(),
+            description: "Get declared capabilities for a provider/tool (conversation, vision, file upload, web search, etc). With probe=true, also load the provider's page and report whether it is reachable and its prompt input is present.".into(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "provider": {
+                        "type": "string",
+                        "enum": PROVIDER_IDS,
+                        "description": "Provider/tool to inspect (defaults to the server's default provider)"
+                    },
+                    "probe": {
+                        "type": "boolean",
+                        "description": "Launch the browser and check the provider's UI right now (default false)"
+                    }
+                },
+                "required": []
+            }),
+        }
+    }
+
+    fn primary_operation(&self) -> Option<Operation> {
+        Some(Operation::ReadContent)
+    }
+
+    fn required_operations(&self) -> Vec<Operation> {
+        // Navigate only with `probe`
+        vec![Operation::ReadContent, Operation::Navigate]
+    }
+
+    fn idempotent(&self) -> bool {
+        true
+    }
+
+    async fn execute(
+        &self,
+        arguments: serde_json::Value,
+        context: &ToolContext,
+    ) -> Result<ToolCallResult> {
+        context
+            .permissions
+            .require(Operation::ReadContent)
+            .map_err(|e| Error::PermissionDenied(e.to_string()))?;
+
+        let args: ProviderCapabilitiesArgs =
+            serde_json::from_value(arguments).map_err(|e| Error::InvalidParams(e.to_string()))?;
+
+        let provider = context.provider_or_default(args.provider.as_deref())?;
+
+        // Declared capabilities are static; no browser needed
+        let caps = declared_capabilities(provider);
+
+        let probe = if args.probe {
+            context
+                .permissions
+                .require_with_url(Operation::Navigate, provider.base_url())
+                .map_err(|e| Error::PermissionDenied(e.to_string()))?;
+            let puppet = context.get_puppet().await?;
+            Some(puppet.probe(provider).await?)
+        } else {
+            None
+        };
+
+        // Only declared capabilities are cached; a probe always looks again
+        let key = RequestCoalescer::key(
+            "webpuppet_provider_capabilities",
+            &json!({ "provider": provider.name(), "probe": args.probe }),
+        );
+        let ttl = if args.probe {
+            Duration::ZERO
+        } else {
+            context.config.read_cache_ttl
+        };
+        context
+            .coalescer
+            .run(key, ttl, async move {
+                let mut structured = json!({
+                    "provider": provider.to_string(),
+                    "capabilities": {
+                        "conversation": caps.conversation,
+                        "vision": caps.vision,
+                        "file_upload": caps.file_upload,
+                        "code_execution": caps.code_execution,
+                        "web_search": caps.web_search,
+                        "max_context": caps.max_context,
+                        "models": caps.models,
+                        "note": "Declared capabilities (not runtime UI detection)."
+                    }
+                });
+                if let Some(probe) = probe {
+                    structured["probe"] = serde_json::to_value(probe)?;
+                }
+
+                Ok(ToolCallResult {
+                    content: vec![ContentItem::text(
+                        serde_json::to_string_pretty(&structured)
+                            .map_err(|e| Error::Internal(e.to_string()))?,
+                    )],
+                    is_error: false,
+                    structured_content: Some(structured),
+                })
+            })
+            .await
+    }
+}
+
+/// Tool for detecting installed browsers.
+pub struct DetectBrowsersTool;
+
+#[async_trait::async_trait]
+impl Tool for DetectBrowsersTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "webpuppet_detect_browsers".into(),
+            description: "Detect installed browsers that can be used for automation.".into(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {},
+                "required": []
+            }),
+        }
+    }
+
+    async fn execute(
+        &self,
+        _arguments: serde_json::Value,
+        context: &ToolContext,
+    ) -> Result<ToolCallResult> {
+        // Installed browsers rarely change; skip re-scanning the disk
+        let key = RequestCoalescer::key("webpuppet_detect_browsers", &json!({}));
+        context
+            .coalescer
+            .run(key, context.config.read_cache_ttl, async {
+                detect_browsers_result(context)
+            })
+            .await
+    }
+}
+
+/// The `webpuppet_detect_browsers` answer.
+fn detect_browsers_result(context: &ToolContext) -> Result<ToolCallResult> {
+    let browsers = context.launcher.detect_browsers();
+
+    if browsers.is_empty() {
+        return Ok(ToolCallResult {
+            content: vec![ContentItem::text(
+                "No supported browsers detected. Please install Brave, Chrome, or Chromium.",
+            )],
+            is_error: true,
+            structured_content: None,
+        });
+    }
+
+    // The one launched: --browser, else the system default, else by priority
+    let system_default = context.launcher.system_default_browser();
+    let default =
+        choose_browser(&browsers, context.config.browser, system_default).map(|b| b.browser_type);
+    let reason = if context.config.browser.is_some() {
+        "set with --browser"
+    } else if default.is_some() && default == system_default {
+        "system default browser"
+    } else {
+        "first installed of Brave, Chrome, Chromium, Edge, Opera, Vivaldi"
+    };
+
+    let profiles: Vec<Vec<String>> = browsers
+        .iter()
+        .map(|b| b.list_profiles().unwrap_or_default())
+        .collect();
+
+    let text = browsers
+        .iter()
+        .zip(&profiles)
+        .map(|(b, profiles)| {
+            let version = b.version.as_deref().unwrap_or("unknown");
+            format!(
+                "- **{}** ({}){}\n  - Path: `{}`\n  - Data: `{}`\n  - Profiles: {}",
+                b.browser_type,
+                version,
+                if Some(b.browser_type) == default {
+                    format!(" ⭐ default ({})", reason)
+                } else {
+                    String::new()
+                },
+                b.executable_path.display(),
+                b.user_data_dir.display(),
+                if profiles.is_empty() {
+                    "none".to_string()
+                } else {
+                    profiles.join(", ")
+                }
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    let structured = json!({
+        "browsers": browsers
+            .iter()
+            .zip(&profiles)
+            .map(|(b, profiles)| json!({
+                "browser_type": b.browser_type.name(),
+                "version": b.version,
+                "executable_path": b.executable_path,
+                "user_data_dir": b.user_data_dir,
+                "profiles": profiles,
+                "is_default": Some(b.browser_type) == default,
+            }))
+            .collect::<Vec<_>>(),
+        "default": default.map(|b| b.name()),
+        "system_default": system_default.map(|b| b.name()),
+    });
+
+    Ok(ToolCallResult {
+        content: vec![
+            ContentItem::text(format!("# Detected Browsers\n\n{}", text)),
+            ContentItem::json(&structured),
+        ],
+        is_error: false,
+        structured_content: Some(structured),
+    })
+}
+
+/// Tool for taking screenshots.
+pub struct ScreenshotTool;
+
+#[derive(Debug, Deserialize)]
+struct ScreenshotArgs {
+    /// URL to screenshot.
+    url: String,
+    /// Readiness to wait for before capturing (default: network idle).
+    #[serde(default)]
+    wait_until: WaitUntil,
+    /// Longest time to wait for it, in milliseconds.
+    timeout_ms: Option<u64>,
+}
+
+/// How long a screenshot waits for the page to be ready by default.
+const DEFAULT_READY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longest a screenshot may wait for the page to be ready.
+const MAX_READY_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How often readiness is checked.
+const READY_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Time without a finished request after which the network counts as idle.
+const NETWORK_IDLE_TIME: Duration = Duration::from_millis(500);
+
+/// Reports how far the page has loaded: `{ state, idle_ms }`, with
+/// `document.readyState` and the time since the network was last busy.
+///
+/// The session has no access to the DevTools network domain, so this is a
+/// heuristic. On its first run in a document it wraps `fetch` and
+/// `XMLHttpRequest` to count requests in flight (`idle_ms` is zero while
+/// any are) and observes Resource Timing entries as they arrive, so none
+/// are missed once the 250-entry buffer is full. Other requests, and
+/// `fetch`/XHR requests started before the first check, only count once
+/// they finish. [`RESTORE_NETWORK_SCRIPT`] undoes the wrapping.
+const READY_STATE_SCRIPT: &str = r#"(() => {
+    let net = window.__webpuppetNetwork;
+    if (!net) {
+        net = window.__webpuppetNetwork = { pending: 0, lastEnd: 0 };
+        const finish = (e) => { net.lastEnd = Math.max(net.lastEnd, e.responseEnd || e.startTime); };
+        performance.getEntriesByType('resource').forEach(finish);
+        net.observer = new PerformanceObserver((list) => list.getEntries().forEach(finish));
+        net.observer.observe({ type: 'resource' });
+        const settle = () => {
+            net.pending--;
+            net.lastEnd = performance.now();
+        };
+
+        const fetch = net.fetch = window.fetch;
+        window.fetch = net.wrappedFetch = function (...args) {
+            net.pending++;
+            try {
+                return fetch.apply(this, args).finally(settle);
+            } catch (e) {
+                settle();
+                throw e;
+            }
+        };
+
+        const send = net.send = XMLHttpRequest.prototype.send;
+        XMLHttpRequest.prototype.send = net.wrappedSend = function (...args) {
+            net.pending++;
+            this.addEventListener('loadend', settle, { once: true });
+            try {
+                return send.apply(this, args);
+            } catch (e) {
+                this.removeEventListener('loadend', settle);
+                settle();
+                throw e;
+            }
+        };
+    }
+    const idle = net.pending > 0 ? 0 : Math.max(0, performance.now() - net.lastEnd);
+    return { state: document.readyState, idle_ms: Math.round(idle) };
+})()"#;
+
+/// Puts back the `fetch` and `XMLHttpRequest` that [`READY_STATE_SCRIPT`]
+/// wrapped (unless the page has replaced them since) and stops observing
+/// Resource Timing, so the page is left as it was found.
+const RESTORE_NETWORK_SCRIPT: &str = r#"(() => {
+    const net = window.__webpuppetNetwork;
+    if (!net) {
+        return;
+    }
+    if (window.fetch === net.wrappedFetch) {
+        window.fetch = net.fetch;
+    }
+    if (XMLHttpRequest.prototype.send === net.wrappedSend) {
+        XMLHttpRequest.prototype.send = net.send;
+    }
+    net.observer.disconnect();
+    delete window.__webpuppetNetwork;
+})()"#;
+
+/// Page readiness a screenshot waits for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum WaitUntil {
+    /// The document is parsed (`interactive`).
+    DomContentLoaded,
+    /// The document and its resources are loaded (`complete`).
+    Load,
+    /// Loaded, no `fetch`/XHR request in flight and none finished for
+    /// [`NETWORK_IDLE_TIME`] (see [`READY_STATE_SCRIPT`]).
+    #[default]
+    NetworkIdle,
+    /// A fixed delay.
+    Delay(Duration),
+}
+
+impl WaitUntil {
+    fn describe(&self) -> String {
+        match self {
+            Self::DomContentLoaded => "domcontentloaded".into(),
+            Self::Load => "load".into(),
+            Self::NetworkIdle => "networkidle".into(),
+            Self::Delay(delay) => format!("{}ms", delay.as_millis()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for WaitUntil {
+    /// A condition name, or a delay in milliseconds.
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        use serde::de::Error as _;
+        match serde_json::Value::deserialize(deserializer)? {
+            serde_json::Value::String(name) => match name.to_ascii_lowercase().as_str() {
+                "domcontentloaded" => Ok(Self::DomContentLoaded),
+                "load" => Ok(Self::Load),
+                "networkidle" => Ok(Self::NetworkIdle),
+                other => Err(D::Error::custom(format!(
+                    "unknown wait_until '{}' (expected load, domcontentloaded, networkidle or a delay in milliseconds)",
+                    other
+                ))),
+            },
+            serde_json::Value::Number(ms) => ms
+                .as_u64()
+                .map(|ms| Self::Delay(Duration::from_millis(ms)))
+                .ok_or_else(|| D::Error::custom("wait_until delay must be a whole number of milliseconds")),
+            _ => Err(D::Error::custom(
+                "wait_until must be a condition name or a delay in milliseconds",
+            )),
+        }
+    }
+}
+
+/// What [`READY_STATE_SCRIPT`] reports.
+#[derive(Debug, Deserialize)]
+struct ReadyState {
+    state: String,
+    idle_ms: u64,
+}
+
+/// Wait until the page in the browsing tab meets `condition`, for at most
+/// `timeout`. Returns whether it did; a page that can't report its state
+/// counts as ready.
+async fn wait_until_ready(
+    puppet: &Arc<dyn BrowserSession>,
+    condition: WaitUntil,
+    timeout: Duration,
+) -> Result<bool> {
+    if let WaitUntil::Delay(delay) = condition {
+        tokio::time::sleep(delay.min(timeout)).await;
+        return Ok(delay <= timeout);
+    }
+
+    let ready = poll_ready_state(puppet, condition, timeout).await;
+    if let Err(e) = puppet.evaluate(RESTORE_NETWORK_SCRIPT).await {
+        tracing::debug!("Could not restore the page's fetch and XHR: {}", e);
+    }
+    ready
+}
+
+/// Check [`READY_STATE_SCRIPT`] until the page meets `condition` or
+/// `timeout` runs out.
+async fn poll_ready_state(
+    puppet: &Arc<dyn BrowserSession>,
+    condition: WaitUntil,
+    timeout: Duration,
+) -> Result<bool> {
+    let started = Instant::now();
+    loop {
+        let Ok(page) =
+            serde_json::from_value::<ReadyState>(puppet.evaluate(READY_STATE_SCRIPT).await?)
+        else {
+            return Ok(true);
+        };
+        let ready = match condition {
+            WaitUntil::DomContentLoaded => page.state != "loading",
+            WaitUntil::Load => page.state == "complete",
+            _ => page.state == "complete" && page.idle_ms >= NETWORK_IDLE_TIME.as_millis() as u64,
+        };
+        if ready {
+            return Ok(true);
+        }
+        if started.elapsed() + READY_POLL_INTERVAL > timeout {
+            return Ok(false);
+        }
+        tokio::time::sleep(READY_POLL_INTERVAL).await;
+    }
+}
+
+#[async_trait::async_trait]
+impl Tool for ScreenshotTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "webpuppet_screenshot".into(),
+            description: "Open a web page and capture a PNG screenshot at the current viewport (see webpuppet_set_viewport). Only allowed domains can be accessed.".into(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "url": {
+                        "type": "string",
+                        "description": "URL to take a screenshot of"
+                    },
+                    "wait_until": {
+                        "oneOf": [
+                            {"type": "string", "enum": ["load", "domcontentloaded", "networkidle"]},
+                            {"type": "integer", "minimum": 0, "description": "Fixed delay in milliseconds"}
+                        ],
+                        "description": "Capture once the page is loaded, parsed, idle on the network (default), or after a fixed delay in milliseconds. networkidle is a heuristic: loaded, no fetch/XHR request in flight and none finished for 500ms; requests started before the first check only count once they finish"
+                    },
+                    "timeout_ms": {
+                        "type": "integer",
+                        "minimum": 0,
+                        "maximum": MAX_READY_TIMEOUT.as_millis() as u64,
+                        "description": "Longest time to wait for wait_until (default 10000); the page is captured as rendered if it runs out"
+                    }
+                },
+                "required": ["url"]
+            }),
+        }
+    }
+
+    fn primary_operation(&self) -> Option<Operation> {
+        Some(Operation::Screenshot)
+    }
+
+    fn required_operations(&self) -> Vec<Operation> {
+        vec![Operation::Navigate, Operation::Screenshot]
+    }
+
+    fn idempotent(&self) -> bool {
+        true
+    }
+
+    fn drives_page(&self) -> bool {
+        true
+    }
+
+    async fn execute(
+        &self,
+        arguments: serde_json::Value,
+        context: &ToolContext,
+    ) -> Result<ToolCallResult> {
+        let args: ScreenshotArgs =
+            serde_json::from_value(arguments).map_err(|e| Error::InvalidParams(e.to_string()))?;
+        let url = normalize_and_validate_url(&args.url)?;
+        let wait_until = args.wait_until;
+        let ready_timeout = args
+            .timeout_ms
+            .map_or(DEFAULT_READY_TIMEOUT, Duration::from_millis)
+            .min(MAX_READY_TIMEOUT);
+        let key = RequestCoalescer::key(
+            "webpuppet_screenshot",
+            &json!({ "url": url, "wait_until": wait_until.describe(), "timeout_ms": ready_timeout.as_millis() as u64 }),
+        );
+
+        // Check permissions for this URL
+        context
+            .permissions
+            .require_with_url(Operation::Navigate, &url)
+            .map_err(|e| Error::PermissionDenied(e.to_string()))?;
+        context.config.blocklist.check_url(&url)?;
+        confirm(context, ConfirmAction::Navigate(&url)).await?;
+
+        context
+            .permissions
+            .require(Operation::Screenshot)
+            .map_err(|e| Error::PermissionDenied(e.to_string()))?;
+
+        context
+            .coalescer
+            .run(key, COALESCE_TTL, async {
+                let puppet = context.get_puppet().await?;
+                context.navigate(&puppet, &url).await?;
+                let waited = Instant::now();
+                let ready = wait_until_ready(&puppet, wait_until, ready_timeout).await?;
+                let waited_ms = waited.elapsed().as_millis() as u64;
+                if !ready {
+                    tracing::debug!(
+                        "{} not ready ({}) after {}ms; capturing as rendered",
+                        url,
+                        wait_until.describe(),
+                        waited_ms
+                    );
+                }
+                let png = puppet.screenshot().await?;
+                context.record(Action::Screenshot { url: url.clone() });
+
+                let (width, height) = png_dimensions(&png).ok_or_else(|| {
+                    Error::Internal("browser returned a screenshot that is not a PNG".into())
+                })?;
+                let viewport = context.viewport();
+
+                let structured = json!({
+                    "url": url,
+                    "width": width,
+                    "height": height,
+                    "bytes": png.len(),
+                    "viewport": viewport,
+                    "wait_until": wait_until.describe(),
+                    "ready": ready,
+                    "waited_ms": waited_ms,
+                });
+
+                let readiness = if ready {
+                    format!("{} after {}ms", wait_until.describe(), waited_ms)
+                } else {
+                    format!(
+                        "not {} after {}ms; captured as rendered",
+                        wait_until.describe(),
+                        waited_ms
+                    )
+                };
+                Ok(ToolCallResult {
+                    content: vec![
+                        ContentItem::text(format!(
+                            "# Screenshot\n\n- **URL**: {}\n- **Size**: {}×{} px\n- **Viewport**: {}\n- **Ready**: {}",
+                            url,
+                            width,
+                            height,
+                            viewport.map_or_else(|| "browser default".to_string(), |v| v.to_string()),
+                            readiness
+                        )),
+                        ContentItem::image(BASE64.encode(&png), "image/png"),
+                        ContentItem::json(&structured),
+                    ],
+                    is_error: false,
+                    structured_content: Some(structured),
+                })
+            })
+            .await
+    }
+}
+
+/// Scrolls the first element matching a selector into view and measures
+/// it. Returns `{ tag, width, height }` in CSS pixels or `{ error }`.
+const ELEMENT_BOX_SCRIPT: &str = r#"((selector) => {
+    let el;
+    try {
+        el = document.querySelector(selector);
+    } catch (e) {
+        return { error: 'invalid selector: ' + String(e.message || e) };
+    }
+    if (!el) return { error: 'no element matches ' + selector };
+    el.scrollIntoView({ block: 'center', inline: 'center' });
+    const rect = el.getBoundingClientRect();
+    return { tag: el.tagName.toLowerCase(), width: rect.width, height: rect.height };
+})"#;
+
+/// An element measured by [`ELEMENT_BOX_SCRIPT`].
+#[derive(Debug, Deserialize)]
+struct ElementBox {
+    #[serde(default)]
+    error: Option<String>,
+    #[serde(default)]
+    tag: String,
+    #[serde(default)]
+    width: f64,
+    #[serde(default)]
+    height: f64,
+}
+
+/// Tool for capturing a single element of the current page.
+pub struct ScreenshotElementTool;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct ScreenshotElementArgs {
+    /// CSS selector of the element to capture (the first match is used)
+    selector: String,
+}
+
+#[async_trait::async_trait]
+impl Tool for ScreenshotElementTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::from_args::<ScreenshotElementArgs>(
+            "webpuppet_screenshot_element",
+            "Capture a PNG screenshot of a single element of the current page (e.g. a chart or a provider's answer block), scrolling it into view first. Fails if the selector matches nothing or the element has no size.",
+        )
+    }
+
+    fn primary_operation(&self) -> Option<Operation> {
+        Some(Operation::Screenshot)
+    }
+
+    fn drives_page(&self) -> bool {
+        true
+    }
+
+    async fn execute(
+        &self,
+        arguments: serde_json::Value,
+        context: &ToolContext,
+    ) -> Result<ToolCallResult> {
+        let args: ScreenshotElementArgs =
+            serde_json::from_value(arguments).map_err(|e| Error::InvalidParams(e.to_string()))?;
+
+        context
+            .permissions
+            .require(Operation::Screenshot)
+            .map_err(|e| Error::PermissionDenied(e.to_string()))?;
+
+        let puppet = context.get_puppet().await?;
+        let script = format!(
+            "{}({})",
+            ELEMENT_BOX_SCRIPT,
+            serde_json::to_string(&args.selector)?
+        );
+        let element: ElementBox = serde_json::from_value(puppet.evaluate(&script).await?)
+            .map_err(|e| Error::Internal(format!("unexpected element measurement: {}", e)))?;
+        if let Some(error) = element.error {
+            return Err(Error::InvalidParams(error));
+        }
+        if element.width <= 0.0 || element.height <= 0.0 {
+            return Err(Error::InvalidParams(format!(
+                "the <{}> matching {} has zero size ({}×{} px); it may be hidden or empty",
+                element.tag, args.selector, element.width, element.height
+            )));
+        }
+
+        let png = puppet.screenshot_element(&args.selector).await?;
+        let (width, height) = png_dimensions(&png).ok_or_else(|| {
+            Error::Internal("browser returned a screenshot that is not a PNG".into())
+        })?;
+        let url = puppet.current_url().await?;
+        context.record(Action::Screenshot { url: url.clone() });
+
+        let structured = json!({
+            "url": url,
+            "selector": args.selector,
+            "tag": element.tag,
+            "width": width,
+            "height": height,
+            "bytes": png.len(),
+        });
+
+        Ok(ToolCallResult {
+            content: vec![
+                ContentItem::text(format!(
+                    "# Element Screenshot\n\n- **URL**: {}\n- **Element**: <{}> matching {}\n- **Size**: {}×{} px",
+                    url, element.tag, args.selector, width, height
+                )),
+                ContentItem::image(BASE64.encode(&png), "image/png"),
+                ContentItem::json(&structured),
+            ],
+            is_error: false,
+            structured_content: Some(structured),
+        })
+    }
+}
+
+/// Fetches a URL from the current page, so the browser's cookies apply.
+/// Called with the URL and the size limit as JSON literals. Returns
+/// `{ status, contentType, disposition, size, data }` with the body
+/// base64-encoded, `{ tooLarge, size }` once the limit is passed, or
+/// `{ error }`.
+const DOWNLOAD_SCRIPT: &str = r#"(async (url, limit) => {
+    let response;
+    try {
+        response = await fetch(url, { credentials: 'include' });
+    } catch (e) {
+        return { error: String(e.message || e) };
+    }
+    const declared = Number(response.headers.get('content-length'));
+    if (declared > limit) {
+        return { tooLarge: true, size: declared };
+    }
+    const reader = response.body.getReader();
+    const chunks = [];
+    let size = 0;
+    for (;;) {
+        const { done, value } = await reader.read();
+        if (done) break;
+        size += value.length;
+        if (size > limit) {
+            reader.cancel();
+            return { tooLarge: true, size };
+        }
+        chunks.push(value);
+    }
+    let binary = '';
+    for (const chunk of chunks) {
+        for (let i = 0; i < chunk.length; i += 0x8000) {
+            binary += String.fromCharCode.apply(null, chunk.subarray(i, i + 0x8000));
+        }
+    }
+    return {
+        status: response.status,
+        contentType: response.headers.get('content-type'),
+        disposition: response.headers.get('content-disposition'),
+        size,
+        data: btoa(binary)
+    };
+})"#;
+
+/// Resolves the link target of the first element matching a selector
+/// (`href`, `src` or `data-href`) to an absolute URL. Returns `{ url }` or
+/// `{ error }`.
+const LINK_TARGET_SCRIPT: &str = r#"((selector) => {
+    let el;
+    try {
+        el = document.querySelector(selector);
+    } catch (e) {
+        return { error: 'invalid selector: ' + String(e.message || e) };
+    }
+    if (!el) return { error: 'no element matches ' + selector };
+    const target = el.getAttribute('href') || el.getAttribute('src') || el.getAttribute('data-href');
+    if (!target) return { error: 'the element has no href or src' };
+    return { url: new URL(target, location.href).href };
+})"#;
+
+/// A file fetched by [`DOWNLOAD_SCRIPT`].
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FetchedFile {
+    #[serde(default)]
+    error: Option<String>,
+    #[serde(default)]
+    too_large: bool,
+    #[serde(default)]
+    status: u16,
+    content_type: Option<String>,
+    disposition: Option<String>,
+    #[serde(default)]
+    size: u64,
+    #[serde(default)]
+    data: String,
+}
+
+/// Tool for downloading a linked file into the download directory.
+pub struct DownloadTool;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct DownloadArgs {
+    /// URL of the file (give this or selector)
+    url: Option<String>,
+    /// CSS selector of a link or element whose href/src to download (give this or url)
+    selector: Option<String>,
+    /// File name to save as (default: from the server or the URL)
+    filename: Option<String>,
+    /// Directory to save into, inside one of the client's roots (default: the server's download directory)
+    directory: Option<String>,
+}
+
+#[async_trait::async_trait]
+impl Tool for DownloadTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::from_args::<DownloadArgs>(
+            "webpuppet_download",
+            "Download a file (e.g. a dataset or PDF) by URL or by the selector of a link on the current page, using the browser's session. Saves it to the server's download directory and returns the local path, content type and size. Only allowed domains can be accessed, and files over the size limit are refused.",
+        )
+    }
+
+    fn primary_operation(&self) -> Option<Operation> {
+        Some(Operation::ReadContent)
+    }
+
+    fn drives_page(&self) -> bool {
+        true
+    }
+
+    async fn execute(
+        &self,
+        arguments: serde_json::Value,
+        context: &ToolContext,
+    ) -> Result<ToolCallResult> {
+        let args: DownloadArgs =
+            serde_json::from_value(arguments).map_err(|e| Error::InvalidParams(e.to_string()))?;
+
+        context
+            .permissions
+            .require(Operation::ReadContent)
+            .map_err(|e| Error::PermissionDenied(e.to_string()))?;
+
+        // Only the client's roots widen where files may be written
+        let dir = match args.directory {
+            Some(ref directory) if !context.roots.is_declared() => {
+                return Err(Error::InvalidParams(format!(
+                    "cannot save to {}: the client declares no roots to save into",
+                    directory
+                )));
+            }
+            Some(directory) => std::path::PathBuf::from(directory),
+            None => context.config.download_dir.clone(),
+        };
+        context.roots.check(&dir)?;
+
+        let puppet = context.get_puppet().await?;
+        let target = match (args.url, args.selector) {
+            (Some(url), None) => url,
+            (None, Some(selector)) => {
+                let script = format!("{}({})", LINK_TARGET_SCRIPT, json!(selector));
+                let found = puppet.evaluate(&script).await?;
+                if let Some(message) = found.get("error").and_then(|e| e.as_str()) {
+                    return Err(Error::InvalidParams(message.to_string()));
+                }
+                found
+                    .get("url")
+                    .and_then(|u| u.as_str())
+                    .ok_or_else(|| Error::Internal("unexpected link lookup result".into()))?
+                    .to_string()
+            }
+            _ => {
+                return Err(Error::InvalidParams(
+                    "give exactly one of url or selector".into(),
+                ))
+            }
+        };
+        let url = normalize_and_validate_url(&target)?;
+        context
+            .permissions
+            .require_with_url(Operation::ReadContent, &url)
+            .map_err(|e| Error::PermissionDenied(e.to_string()))?;
+        context.config.blocklist.check_url(&url)?;
+
+        let limit = context.config.max_download_bytes;
+        let script = format!("{}({}, {})", DOWNLOAD_SCRIPT, json!(url), limit);
+        let fetched: FetchedFile = serde_json::from_value(puppet.evaluate(&script).await?)
+            .map_err(|e| Error::Internal(format!("unexpected download result format: {}", e)))?;
+        if let Some(error) = fetched.error {
+            return Err(Error::InvalidParams(format!(
+                "could not fetch {} from the current page ({}); cross-site files may need webpuppet_navigate to their site first",
+                url, error
+            )));
+        }
+        if fetched.too_large {
+            return Err(Error::InvalidParams(format!(
+                "{} is at least {} bytes, over the {} byte download limit",
+                url, fetched.size, limit
+            )));
+        }
+        if !(200..300).contains(&fetched.status) {
+            return Err(Error::InvalidParams(format!(
+                "{} returned HTTP {}",
+                url, fetched.status
+            )));
+        }
+        let bytes = BASE64
+            .decode(&fetched.data)
+            .map_err(|e| Error::Internal(format!("download was not valid base64: {}", e)))?;
+
+        let name = args
+            .filename
+            .as_deref()
+            .or_else(|| {
+                fetched
+                    .disposition
+                    .as_deref()
+                    .and_then(disposition_filename)
+            })
+            .map(sanitize_filename)
+            .unwrap_or_else(|| url_filename(&url));
+        tokio::fs::create_dir_all(&dir).await?;
+        let path = unique_path(&dir, &name);
+        tokio::fs::write(&path, &bytes).await?;
+
+        let content_type = fetched
+            .content_type
+            .unwrap_or_else(|| "application/octet-stream".into());
+        let structured = json!({
+            "url": url,
+            "path": path,
+            "content_type": content_type,
+            "size": bytes.len(),
+        });
+
+        Ok(ToolCallResult {
+            content: vec![
+                ContentItem::text(format!(
+                    "# File Downloaded\n\n- **URL**: {}\n- **Saved to**: {}\n- **Content type**: {}\n- **Size**: {} bytes",
+                    url,
+                    path.display(),
+                    content_type,
+                    bytes.len()
+                )),
+                ContentItem::json(&structured),
+            ],
+            is_error: false,
+            structured_content: Some(structured),
+        })
+    }
+}
+
+/// File name from a `Content-Disposition` header, if it has one.
+fn disposition_filename(disposition: &str) -> Option<&str> {
+    disposition.split(';').find_map(|part| {
+        let (key, value) = part.trim().split_once('=')?;
+        (key.eq_ignore_ascii_case("filename")).then(|| value.trim().trim_matches('"'))
+    })
+}
+
+/// Last path segment of a URL as a file name, or `download`.
+fn url_filename(url: &str) -> String {
+    let name = url::Url::parse(url)
+        .ok()
+        .and_then(|url| {
+            url.path_segments()
+                .and_then(|mut segments| segments.next_back().map(str::to_string))
+        })
+        .unwrap_or_default();
+    sanitize_filename(&name)
+}
+
+/// Keep a file name inside the download directory: path separators and
+/// unusual characters become `_`, and leading dots are dropped.
+fn sanitize_filename(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let name = name.trim_start_matches('.');
+    if name.is_empty() {
+        "download".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// `dir/name`, or `dir/name-1.ext`, `dir/name-2.ext`, ... if taken.
+fn unique_path(dir: &std::path::Path, name: &str) -> std::path::PathBuf {
+    let candidate = dir.join(name);
+    if !candidate.exists() {
+        return candidate;
+    }
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem, format!(".{}", extension)),
+        _ => (name, String::new()),
+    };
+    (1..)
+        .map(|n| dir.join(format!("{}-{}{}", stem, n, extension)))
+        .find(|path| !path.exists())
+        .expect("some numbered file name is free")
+}
+
+/// Pixel dimensions from the header of a PNG image.
+fn png_dimensions(png: &[u8]) -> Option<(u32, u32)> {
+    const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+    if png.len() < 24 || !png.starts_with(SIGNATURE) || &png[12..16] != b"IHDR" {
+        return None;
+    }
+    let width = u32::from_be_bytes(png[16..20].try_into().ok()?);
+    let height = u32::from_be_bytes(png[20..24].try_into().ok()?);
+    Some((width, height))
+}
+
+/// Tool for checking permissions.
+pub struct CheckPermissionTool;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct CheckPermissionArgs {
+    /// Operation to check (e.g., Navigate, SendPrompt, DeleteAccount)
+    operation: String,
+    /// Optional URL context for navigation checks
+    url: Option<String>,
+}
+
+#[async_trait::async_trait]
+impl Tool for CheckPermissionTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::from_args::<CheckPermissionArgs>(
+            "webpuppet_check_permission",
+            "Check if an operation is allowed by the security policy.",
+        )
+    }
+
+    async fn execute(
+        &self,
+        arguments: serde_json::Value,
+        context: &ToolContext,
+    ) -> Result<ToolCallResult> {
+        let args: CheckPermissionArgs =
+            serde_json::from_value(arguments).map_err(|e| Error::InvalidParams(e.to_string()))?;
+
+        let operation = match parse_operation(&args.operation) {
+            Ok(operation) => operation,
+            Err(_) => {
+                let valid: Vec<String> = Operation::all().iter().map(|op| op.to_string()).collect();
+                return Ok(ToolCallResult {
+                    content: vec![ContentItem::text(format!(
+                        "Unknown operation: `{}`\n\nValid operations: {}",
+                        args.operation,
+                        valid.join(", ")
+                    ))],
+                    is_error: true,
+                    structured_content: None,
+                });
+            }
+        };
+
+        let decision = if let Some(url) = args.url {
+            context.permissions.check_with_url(operation, &url)
+        } else {
+            context.permissions.check(operation)
+        };
+
+        let status = if decision.allowed {
+            "✅ ALLOWED"
+        } else {
+            "❌ DENIED"
+        };
+        let text = format!(
+            "# Permission Check\n\n**Operation**: `{}`\n**Status**: {}\n**Reason**: {}\n**Risk Level**: {}/10",
+            operation, status, decision.reason, decision.risk_level
+        );
+
+        let structured = json!({
+            "operation": operation.to_string(),
+            "allowed": decision.allowed,
+            "reason": decision.reason,
+            "risk_level": decision.risk_level,
+            "url": decision.url,
+        });
+
+        Ok(ToolCallResult {
+            content: vec![ContentItem::text(text), ContentItem::json(&structured)],
+            is_error: false,
+            structured_content: Some(structured),
+        })
+    }
+}
+
+/// Tool for running arbitrary text through the response screener.
+pub struct ScreenTextTool;
+
+#[derive(Debug, Deserialize)]
+struct ScreenTextArgs {
+    /// Text to screen.
+    text: String,
+}
+
+#[async_trait::async_trait]
+impl Tool for ScreenTextTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "webpuppet_screen_text".into(),
+            description: "Screen text for prompt injections and hidden/malicious content using the same screener applied to provider responses. No browser needed.".into(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "text": {
+                        "type": "string",
+                        "description": "Text to screen"
+                    }
+                },
+                "required": ["text"]
+            }),
+        }
+    }
+
+    async fn execute(
+        &self,
+        arguments: serde_json::Value,
+        context: &ToolContext,
+    ) -> Result<ToolCallResult> {
+        let args: ScreenTextArgs =
+            serde_json::from_value(arguments).map_err(|e| Error::InvalidParams(e.to_string()))?;
+
+        let screener = ContentScreener::with_config(context.screening_config.clone());
+        let result = screener.screen(&args.text);
+
+        let structured = screening_summary(&result);
+
+        Ok(ToolCallResult {
+            content: vec![ContentItem::text(
+                serde_json::to_string_pretty(&structured)
+                    .map_err(|e| Error::Internal(e.to_string()))?,
+            )],
+            is_error: false,
+            structured_content: Some(structured),
+        })
+    }
+}
+
+/// Structured summary of a screening result.
+fn screening_summary(result: &ScreeningResult) -> serde_json::Value {
+    let mut categories: Vec<&str> = result.issues.iter().map(issue_category).collect();
+    categories.sort_unstable();
+    categories.dedup();
+
+    json!({
+        "passed": result.passed,
+        "risk_score": result.risk_score,
+        "categories": categories,
+        "issues": result
+            .issues
+            .iter()
+            .map(|issue| json!({
+                "category": issue_category(issue),
+                "severity": issue.severity(),
+            }))
+            .collect::<Vec<_>>(),
+    })
+}
+
+/// Whether a screened provider response is withheld from the result, as
+/// `--screening-mode block` does with flagged responses.
+fn withholds(context: &ToolContext, screening: &ScreeningResult) -> bool {
+    !screening.passed && context.config.screening_mode == ScreeningMode::Block
+}
+
+/// The `screening` object of a result carrying a provider response: the
+/// [`screening_summary`], whether sanitizing changed the text and whether it
+/// was withheld.
+fn screening_report(screening: &ScreeningResult, withheld: bool) -> serde_json::Value {
+    let mut report = screening_summary(screening);
+    report["redacted"] = json!(screening.sanitized != screening.original);
+    report["withheld"] = json!(withheld);
+    report
+}
+
+/// Content showing a screened provider response as `text`. A flagged
+/// response is preceded by a warning for the user, or replaced by it when
+/// `withheld`.
+fn screened_content(screening: &ScreeningResult, withheld: bool, text: String) -> Vec<ContentItem> {
+    if screening.passed {
+        return vec![ContentItem::text(text)];
+    }
+    let mut categories: Vec<&str> = screening.issues.iter().map(issue_category).collect();
+    categories.sort_unstable();
+    categories.dedup();
+    let mut warning = format!(
+        "[SECURITY WARNING: Response had risk score {:.2}",
+        screening.risk_score
+    );
+    if !categories.is_empty() {
+        warning.push_str(&format!(" ({})", categories.join(", ")));
+    }
+    warning.push(']');
+    if withheld {
+        warning.push_str(" The response was withheld (--screening-mode block).");
+    }
+
+    let warning = ContentItem::text(warning)
+        .with_audience([Role::User, Role::Assistant])
+        .with_priority(1.0);
+    if withheld {
+        vec![warning]
+    } else {
+        vec![warning, ContentItem::text(text)]
+    }
+}
+
+/// Stable category name for a screening issue.
+fn issue_category(issue: &SecurityIssue) -> &'static str {
+    match issue {
+        SecurityIssue::InvisibleText { .. } => "invisible_text",
+        SecurityIssue::BackgroundMatchingText { .. } => "background_matching_text",
+        SecurityIssue::ZeroWidthCharacters { .. } => "zero_width_characters",
+        SecurityIssue::HomoglyphAttack { .. } => "homoglyph_attack",
+        SecurityIssue::PromptInjection { .. } => "prompt_injection",
+        SecurityIssue::EncodedPayload { .. } => "encoded_payload",
+        SecurityIssue::HiddenElement { .. } => "hidden_element",
+        SecurityIssue::OverflowHidden { .. } => "overflow_hidden",
+        SecurityIssue::CodeInjection { .. } => "code_injection",
+    }
+}
+
+// ============================================================================
+// Intervention Tools
+// ============================================================================
+
+/// Tool for checking intervention status.
+pub struct InterventionStatusTool;
+
+#[async_trait::async_trait]
+impl Tool for InterventionStatusTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "webpuppet_intervention_status".into(),
+            description: "Check if human intervention is needed (captcha, 2FA, etc.). Returns current automation state and any pending intervention reason.".into(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {},
+                "required": []
+            }),
+        }
+    }
+
+    async fn execute(
+        &self,
+        _arguments: serde_json::Value,
+        context: &ToolContext,
+    ) -> Result<ToolCallResult> {
+        let handler = context.intervention_handler.read().await;
+        let state = handler.state();
+        let reason = handler.current_reason();
+        let origin = context
+            .intervention_origin()
+            .filter(|_| handler.is_waiting());
+
+        let state_str = match state {
+            InterventionState::Running => "🟢 Running",
+            InterventionState::WaitingForHuman => "🟡 Waiting for human",
+            InterventionState::Resuming => "🔵 Resuming",
+            InterventionState::TimedOut => "🔴 Timed out",
+            InterventionState::Cancelled => "⚫ Cancelled",
+        };
+
+        let structured = json!({
+            "state": intervention_state_name(state),
+            "reason": reason.as_ref().map(|r| r.to_string()),
+            "action_required": reason.is_some(),
+            "blocking": origin,
+        });
+
+        let text = if let Some(reason) = reason {
+            let blocking = origin
+                .map(|origin| format!("\n**Blocking**: {}", origin))
+                .unwrap_or_default();
+            format!(
+                "# Intervention Status\n\n**State**: {}\n**Reason**: {}{}\n\n⚠️ **Action Required**: Please complete the intervention in the browser, then call `webpuppet_intervention_complete` with success=true.",
+                state_str, reason, blocking
+            )
+        } else {
+            format!(
+                "# Intervention Status\n\n**State**: {}\n\nNo intervention currently required. Automation is running normally.",
+                state_str
+            )
+        };
+
+        Ok(ToolCallResult {
+            content: vec![ContentItem::text(text), ContentItem::json(&structured)],
+            is_error: false,
+            structured_content: Some(structured),
+        })
+    }
+}
+
+/// Stable machine-readable name for an intervention state.
+fn intervention_state_name(state: InterventionState) -> &'static str {
+    match state {
+        InterventionState::Running => "running",
+        InterventionState::WaitingForHuman => "waiting_for_human",
+        InterventionState::Resuming => "resuming",
+        InterventionState::TimedOut => "timed_out",
+        InterventionState::Cancelled => "cancelled",
+    }
+}
+
+/// Tool for signaling intervention completion.
+pub struct InterventionCompleteTool;
+
+#[derive(Debug, Deserialize)]
+struct InterventionCompleteArgs {
+    /// Whether the intervention was successful.
+    success: bool,
+    /// Optional message about the intervention.
+    message: Option<String>,
+    /// Request the intervention must be blocking; refuses to complete any other.
+    request_id: Option<JsonRpcId>,
+}
+
+#[async_trait::async_trait]
+impl Tool for InterventionCompleteTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "webpuppet_intervention_complete".into(),
+            description: "Signal that a human intervention (captcha, 2FA, etc.) has been completed. Call this after manually handling the intervention in the browser.".into(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "success": {
+                        "type": "boolean",
+                        "description": "Whether the intervention was completed successfully"
+                    },
+                    "message": {
+                        "type": "string",
+                        "description": "Optional message about what was done"
+                    },
+                    "request_id": {
+                        "type": ["string", "integer"],
+                        "description": "Id of the blocked request (from webpuppet_intervention_status); the call fails if the pending intervention blocks a different one"
+                    }
+                },
+                "required": ["success"]
+            }),
+        }
+    }
+
+    async fn execute(
+        &self,
+        arguments: serde_json::Value,
+        context: &ToolContext,
+    ) -> Result<ToolCallResult> {
+        let args: InterventionCompleteArgs =
+            serde_json::from_value(arguments).map_err(|e| Error::InvalidParams(e.to_string()))?;
+
+        let handler = context.intervention_handler.read().await;
+        let origin = context
+            .intervention_origin()
+            .filter(|_| handler.is_waiting());
+        if let Some(ref request_id) = args.request_id {
+            let blocking = origin
+                .as_ref()
+                .and_then(|origin| origin.request_id.as_ref());
+            match blocking {
+                Some(blocking) if blocking == request_id => {}
+                Some(blocking) => {
+                    return Err(Error::InvalidParams(format!(
+                        "the pending intervention blocks request {}, not request {}",
+                        blocking, request_id
+                    )))
+                }
+                None => {
+                    return Err(Error::InvalidParams(format!(
+                        "no pending intervention is blocking request {}",
+                        request_id
+                    )))
+                }
+            }
+        }
+        handler.complete(args.success, args.message.clone());
+
+        let status = if args.success {
+            "✅ SUCCESS"
+        } else {
+            "❌ FAILED"
+        };
+        let unblocked = origin
+            .map(|origin| format!("\n**Unblocks**: {} (retry it now)", origin))
+            .unwrap_or_default();
+        let text = format!(
+            "# Intervention Complete\n\n**Status**: {}\n**Message**: {}{}\n\nAutomation will now resume.",
+            status,
+            args.message.unwrap_or_else(|| "None".into()),
+            unblocked
+        );
+
+        Ok(ToolCallResult {
+            content: vec![ContentItem::text(text)],
+            is_error: false,
+            structured_content: None,
+        })
+    }
+}
+
+/// Tool for blocking until a human finishes an intervention.
+pub struct WaitForInterventionTool;
+
+/// How long `webpuppet_wait_for_intervention` waits by default.
+const DEFAULT_INTERVENTION_WAIT: Duration = Duration::from_secs(120);
+
+/// How often the wait checks the browser and reports progress.
+const INTERVENTION_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct WaitForInterventionArgs {
+    /// Longest time to wait, in seconds (default: 120; capped below the tool timeout)
+    timeout_secs: Option<u64>,
+}
+
+#[async_trait::async_trait]
+impl Tool for WaitForInterventionTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::from_args::<WaitForInterventionArgs>(
+            "webpuppet_wait_for_intervention",
+            "Wait until the pending human intervention (captcha, 2FA, login) is finished, instead of polling webpuppet_intervention_status. Returns the outcome: completed (with success), timed_out or cancelled, or still_waiting if the wait ran out first. An intervention the human resolves in the browser without calling webpuppet_intervention_complete is detected too.",
+        )
+    }
+
+    async fn execute(
+        &self,
+        arguments: serde_json::Value,
+        context: &ToolContext,
+    ) -> Result<ToolCallResult> {
+        let args: WaitForInterventionArgs =
+            serde_json::from_value(arguments).map_err(|e| Error::InvalidParams(e.to_string()))?;
+        // Return before the call itself times out
+        let limit = context
+            .config
+            .tool_timeout
+            .saturating_sub(Duration::from_secs(1));
+        let timeout = args
+            .timeout_secs
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_INTERVENTION_WAIT)
+            .min(limit);
+
+        let handler = context.intervention_handler.read().await.clone();
+        if !handler.is_waiting() {
+            let structured = json!({ "outcome": "none_pending" });
+            return Ok(ToolCallResult {
+                content: vec![
+                    ContentItem::text(
+                        "# No Intervention Pending\n\nNothing is waiting for a human.",
+                    ),
+                    ContentItem::json(&structured),
+                ],
+                is_error: false,
+                structured_content: Some(structured),
+            });
+        }
+
+        let reason = handler.current_reason().map(|r| r.to_string());
+        // Only interventions with an origin report an outcome; a pause doesn't
+        let origin = context.intervention_origin();
+        let provider = origin
+            .as_ref()
+            .and_then(|origin| origin.provider.as_deref())
+            .and_then(|name| parse_provider(name).ok());
+        let mut outcomes = context.intervention_outcome();
+        outcomes.mark_unchanged();
+
+        let progress = ProgressReporter::current();
+        let started = Instant::now();
+        let deadline = tokio::time::Instant::now() + timeout;
+        let mut ticker = tokio::time::interval(INTERVENTION_POLL_INTERVAL);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        ticker.tick().await;
+
+        let outcome = loop {
+            tokio::select! {
+                Ok(()) = outcomes.changed() => {
+                    if let Some(outcome) = outcomes.borrow_and_update().clone() {
+                        break Some(outcome);
+                    }
+                }
+                _ = ticker.tick() => {
+                    // `webpuppet_reset` replaces the handler
+                    let current = context.intervention_handler.read().await.clone();
+                    if !Arc::ptr_eq(&current, &handler) {
+                        break Some(InterventionOutcome::Cancelled);
+                    }
+                    if !handler.is_waiting() {
+                        let recorded = outcomes.borrow().clone().filter(|_| origin.is_some());
+                        break Some(recorded.unwrap_or(match handler.state() {
+                            InterventionState::TimedOut => InterventionOutcome::TimedOut,
+                            InterventionState::Cancelled => InterventionOutcome::Cancelled,
+                            _ => InterventionOutcome::Completed { success: true, message: None },
+                        }));
+                    }
+
+                    // The human may have dealt with it without telling us
+                    let puppet = context.puppet.read().await.clone();
+                    if let (Some(provider), Some(puppet)) = (provider, puppet) {
+                        if let Ok(None) = puppet.intervention_needed(provider).await {
+                            tracing::info!("Intervention on {} resolved in the browser", provider);
+                            handler.complete(true, Some("Resolved in the browser".into()));
+                        }
+                    }
+
+                    if let Some(ref reporter) = progress {
+                        reporter.report(&format!(
+                            "Waiting for a human ({}s)",
+                            started.elapsed().as_secs()
+                        ));
+                    }
+                }
+                _ = tokio::time::sleep_until(deadline) => break None,
+            }
+        };
+
+        let mut structured = match outcome {
+            Some(ref outcome) => serde_json::to_value(outcome)?,
+            None => json!({ "outcome": "still_waiting" }),
+        };
+        structured["reason"] = json!(reason);
+        structured["waited_ms"] = json!(started.elapsed().as_millis() as u64);
+
+        let (status, next) = match outcome {
+            Some(InterventionOutcome::Completed { success: true, .. }) => {
+                ("✅ Completed", "Retry the call the intervention blocked.")
+            }
+            Some(InterventionOutcome::Completed { success: false, .. }) => (
+                "❌ Completed unsuccessfully",
+                "The human could not finish it; the blocked call will fail again.",
+            ),
+            Some(InterventionOutcome::TimedOut) => (
+                "🔴 Timed out",
+                "Nobody finished it in time; retry the blocked call to start over.",
+            ),
+            Some(InterventionOutcome::Cancelled) => {
+                ("⚫ Cancelled", "The intervention was cancelled.")
+            }
+            None => (
+                "🟡 Still waiting",
+                "The human has not finished yet; call this tool again to keep waiting.",
+            ),
+        };
+        let failed = matches!(
+            outcome,
+            Some(InterventionOutcome::Completed { success: false, .. })
+                | Some(InterventionOutcome::TimedOut)
+                | Some(InterventionOutcome::Cancelled)
+        );
+
+        Ok(ToolCallResult {
+            content: vec![
+                ContentItem::text(format!(
+                    "# Intervention Wait\n\n**Outcome**: {}\n**Reason**: {}\n\n{}",
+                    status,
+                    reason.as_deref().unwrap_or("unknown"),
+                    next
+                )),
+                ContentItem::json(&structured),
+            ],
+            is_error: failed,
+            structured_content: Some(structured),
+        })
+    }
+}
+
+/// Tool for pausing automation.
+pub struct InterventionPauseTool;
+
+#[async_trait::async_trait]
+impl Tool for InterventionPauseTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "webpuppet_pause".into(),
+            description: "Pause browser automation. Use this when you need to manually interact with the browser.".into(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {},
+                "required": []
+            }),
+        }
+    }
+
+    async fn execute(
+        &self,
+        _arguments: serde_json::Value,
+        context: &ToolContext,
+    ) -> Result<ToolCallResult> {
+        let handler = context.intervention_handler.read().await;
+        handler.pause();
+
+        Ok(ToolCallResult {
+            content: vec![ContentItem::text(
+                "# Automation Paused\n\n⏸️ Automation is now paused. The browser is available for manual interaction.\n\nCall `webpuppet_resume` when ready to continue."
+            )],
+            is_error: false,
+            structured_content: None,
+        })
+    }
+}
+
+/// Tool for resuming automation.
+pub struct InterventionResumeTool;
+
+#[async_trait::async_trait]
+impl Tool for InterventionResumeTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "webpuppet_resume".into(),
+            description: "Resume browser automation after a pause or manual intervention.".into(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {},
+                "required": []
+            }),
+        }
+    }
+
+    async fn execute(
+        &self,
+        _arguments: serde_json::Value,
+        context: &ToolContext,
+    ) -> Result<ToolCallResult> {
+        let handler = context.intervention_handler.read().await;
+        handler.resume();
+
+        Ok(ToolCallResult {
+            content: vec![ContentItem::text(
+                "# Automation Resumed\n\n▶️ Automation has been resumed. Browser operations will continue."
+            )],
+            is_error: false,
+            structured_content: None,
+        })
+    }
+}
+
+/// Tool for navigating to a URL (for testing).
+pub struct NavigateTool;
+
+#[derive(Debug, Deserialize)]
+struct NavigateArgs {
+    /// URL to navigate to.
+    url: String,
+    /// User agent to present from now on.
+    user_agent: Option<String>,
+    /// Locale to present from now on.
+    locale: Option<String>,
+}
+
+#[async_trait::async_trait]
+impl Tool for NavigateTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "webpuppet_navigate".into(),
+            description: "Navigate browser to a URL. Opens a browser window if not already open. Only allowed domains can be accessed. Optionally switch the user agent or locale first (the browser is relaunched, and later pages keep them)."
+                .into(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "url": {
+                        "type": "string",
+                        "description": "URL to navigate to"
+                    },
+                    "user_agent": {
+                        "type": "string",
+                        "description": "User agent to present from now on"
+                    },
+                    "locale": {
+                        "type": "string",
+                        "description": "Locale to present from now on, e.g. en-US"
+                    }
+                },
+                "required": ["url"]
+            }),
+        }
+    }
+
+    fn primary_operation(&self) -> Option<Operation> {
+        Some(Operation::Navigate)
+    }
+
+    fn idempotent(&self) -> bool {
+        true
+    }
+
+    fn drives_page(&self) -> bool {
+        true
+    }
+
+    async fn execute(
+        &self,
+        arguments: serde_json::Value,
+        context: &ToolContext,
+    ) -> Result<ToolCallResult> {
+        // Parse arguments
+        let args: NavigateArgs =
+            serde_json::from_value(arguments).map_err(|e| Error::InvalidParams(e.to_string()))?;
+        let url = normalize_and_validate_url(&args.url)?;
+
+        // Check permission for this URL
+        context
+            .permissions
+            .require_with_url(Operation::Navigate, &url)
+            .map_err(|e| Error::PermissionDenied(e.to_string()))?;
+        context.config.blocklist.check_url(&url)?;
+        confirm(context, ConfirmAction::Navigate(&url)).await?;
+
+        if args.user_agent.is_some() || args.locale.is_some() {
+            let identity = context.identity().merged(&BrowserIdentity {
+                user_agent: args.user_agent,
+                locale: args.locale,
+                timezone: None,
+            });
+            context.set_identity(identity).await?;
+        }
+
+        let key = RequestCoalescer::key("webpuppet_navigate", &json!({ "url": url }));
+
+        context
+            .coalescer
+            .run(key, COALESCE_TTL, async {
+                // Get the browser session and navigate
+                let puppet = context.get_puppet().await?;
+                context.navigate(&puppet, &url).await?;
+
+                // Get current URL and title
+                let current_url = puppet.current_url().await.unwrap_or_else(|_| url.clone());
+                let title = puppet.title().await.unwrap_or_else(|_| "Unknown".into());
+                context.record(Action::Navigate {
+                    url: current_url.clone(),
+                });
+
+                Ok(ToolCallResult {
+                    content: vec![ContentItem::text(format!(
+                        "# Browser Navigated\n\n✅ Successfully navigated to URL.\n\n- **URL**: {}\n- **Title**: {}",
+                        current_url, title
+                    ))],
+                    is_error: false,
+                    structured_content: None,
+                })
+            })
+            .await
+    }
+}
+
+/// Go back, forward or reload in the browsing tab and report the resulting
+/// page.
+async fn traverse_history(context: &ToolContext, step: HistoryStep) -> Result<ToolCallResult> {
+    context
+        .permissions
+        .require(Operation::Navigate)
+        .map_err(|e| Error::PermissionDenied(e.to_string()))?;
+
+    let puppet = context.get_puppet().await?;
+    let previous_url = puppet.current_url().await.ok();
+    puppet.traverse(step).await?;
+
+    let url = puppet.current_url().await?;
+    let title = puppet.title().await.unwrap_or_else(|_| "Unknown".into());
+    let moved = previous_url.as_deref() != Some(url.as_str());
+    if moved {
+        context.record(Action::Navigate { url: url.clone() });
+    }
+
+    let (action, heading, unchanged) = match step {
+        HistoryStep::Back => (
+            "back",
+            "Browser Went Back",
+            "There is no earlier page in this tab's history.",
+        ),
+        HistoryStep::Forward => (
+            "forward",
+            "Browser Went Forward",
+            "There is no later page in this tab's history.",
+        ),
+        HistoryStep::Reload { .. } => ("reload", "Page Reloaded", ""),
+    };
+    let note = if moved || unchanged.is_empty() {
+        String::new()
+    } else {
+        format!("\n\n{}", unchanged)
+    };
+
+    let mut structured = json!({
+        "action": action,
+        "url": url,
+        "title": title,
+    });
+    match step {
+        HistoryStep::Reload { ignore_cache } => structured["ignore_cache"] = json!(ignore_cache),
+        _ => structured["moved"] = json!(moved),
+    }
+
+    Ok(ToolCallResult {
+        content: vec![
+            ContentItem::text(format!(
+                "# {}\n\n- **URL**: {}\n- **Title**: {}{}",
+                heading, url, title, note
+            )),
+            ContentItem::json(&structured),
+        ],
+        is_error: false,
+        structured_content: Some(structured),
+    })
+}
+
+/// Tool for going back in the browsing tab's history.
+pub struct BackTool;
+
+/// Arguments of `webpuppet_back` and `webpuppet_forward`.
+#[derive(Debug, Deserialize, JsonSchema)]
+struct TraverseArgs {}
+
+#[async_trait::async_trait]
+impl Tool for BackTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::from_args::<TraverseArgs>(
+            "webpuppet_back",
+            "Go back to the previous page in the browser, like the Back button. Returns the resulting URL and title.",
+        )
+    }
+
+    fn primary_operation(&self) -> Option<Operation> {
+        Some(Operation::Navigate)
+    }
+
+    fn drives_page(&self) -> bool {
+        true
+    }
+
+    async fn execute(
+        &self,
+        _arguments: serde_json::Value,
+        context: &ToolContext,
+    ) -> Result<ToolCallResult> {
+        traverse_history(context, HistoryStep::Back).await
+    }
+}
+
+/// Tool for going forward in the browsing tab's history.
+pub struct ForwardTool;
+
+#[async_trait::async_trait]
+impl Tool for ForwardTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::from_args::<TraverseArgs>(
+            "webpuppet_forward",
+            "Go forward to the next page in the browser, undoing webpuppet_back. Returns the resulting URL and title.",
+        )
+    }
+
+    fn primary_operation(&self) -> Option<Operation> {
+        Some(Operation::Navigate)
+    }
+
+    fn drives_page(&self) -> bool {
+        true
+    }
+
+    async fn execute(
+        &self,
+        _arguments: serde_json::Value,
+        context: &ToolContext,
+    ) -> Result<ToolCallResult> {
+        traverse_history(context, HistoryStep::Forward).await
+    }
+}
+
+/// Tool for reloading the current page.
+pub struct ReloadTool;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct ReloadArgs {
+    /// Fetch the page from the network instead of the browser cache
+    #[serde(default)]
+    ignore_cache: bool,
+}
+
+#[async_trait::async_trait]
+impl Tool for ReloadTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::from_args::<ReloadArgs>(
+            "webpuppet_reload",
+            "Reload the current page in the browser, optionally bypassing the cache. Returns the resulting URL and title.",
+        )
+    }
+
+    fn primary_operation(&self) -> Option<Operation> {
+        Some(Operation::Navigate)
+    }
+
+    fn drives_page(&self) -> bool {
+        true
+    }
+
+    async fn execute(
+        &self,
+        arguments: serde_json::Value,
+        context: &ToolContext,
+    ) -> Result<ToolCallResult> {
+        let args: ReloadArgs =
+            serde_json::from_value(arguments).map_err(|e| Error::InvalidParams(e.to_string()))?;
+        traverse_history(
+            context,
+            HistoryStep::Reload {
+                ignore_cache: args.ignore_cache,
+            },
+        )
+        .await
+    }
+}
+
+/// Tool for getting browser status.
+pub struct BrowserStatusTool;
+
+#[async_trait::async_trait]
+impl Tool for BrowserStatusTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "webpuppet_browser_status".into(),
+            description: "Get current browser status including URL, title, and visibility.".into(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {},
+                "required": []
+            }),
+        }
+    }
+
+    fn idempotent(&self) -> bool {
+        true
+    }
+
+    async fn execute(
+        &self,
+        _arguments: serde_json::Value,
+        context: &ToolContext,
+    ) -> Result<ToolCallResult> {
+        let (active, failed_providers) = match context.puppet.read().await.as_ref() {
+            Some(puppet) => (true, puppet.failed_providers()),
+            None => (false, Vec::new()),
+        };
+        let visibility = if context.is_headless() {
+            "Headless"
+        } else {
+            "Visible"
+        };
+
+        let profile = context.profile();
+        let viewport = context.viewport();
+        let identity = context.identity();
+        let extra_headers = context.extra_headers();
+        let auto_closed = context.take_auto_closed();
+        let prewarm = context.prewarm_state();
+        let active_accounts = context.active_accounts();
+
+        let structured = json!({
+            "active": active,
+            "prewarm": prewarm,
+            "mode": visibility.to_lowercase(),
+            "default_provider": context.default_provider.name(),
+            "profile": profile,
+            "viewport": viewport,
+            "auto_closed": auto_closed,
+            "proxy": context.config.proxy.as_ref().map(ProxyConfig::host),
+            "user_agent": identity.user_agent,
+            "locale": identity.locale,
+            "timezone": identity.timezone,
+            "extra_headers": extra_headers.names(),
+            "accounts": active_accounts,
+            "failed_providers": failed_providers
+                .iter()
+                .map(|(provider, reason)| json!({"provider": provider.name(), "reason": reason}))
+                .collect::<Vec<_>>(),
+        });
+        let profile = profile.as_deref().unwrap_or("default");
+        let viewport = viewport.map_or_else(|| "browser default".to_string(), |v| v.to_string());
+        let proxy = context
+            .config
+            .proxy
+            .as_ref()
+            .map_or_else(|| "none".to_string(), ProxyConfig::host);
+        let identity = format!(
+            "- **User agent**: {}\n- **Locale**: {}\n- **Timezone**: {}",
+            identity.user_agent.as_deref().unwrap_or("browser default"),
+            identity.locale.as_deref().unwrap_or("browser default"),
+            identity.timezone.as_deref().unwrap_or("system default")
+        );
+        // Only names: values may be credentials
+        let extra_headers = if extra_headers.is_empty() {
+            "none".to_string()
+        } else {
+            extra_headers.names().join(", ")
+        };
+        let prewarm = if prewarm == Prewarm::Off {
+            String::new()
+        } else {
+            format!("\n- **Prewarm**: {}", prewarm)
+        };
+
+        let accounts = if active_accounts.is_empty() {
+            String::new()
+        } else {
+            let lines: Vec<String> = active_accounts
+                .iter()
+                .map(|(provider, account)| format!("\n  - {}: {}", provider, account))
+                .collect();
+            format!("\n- **Active accounts**:{}", lines.concat())
+        };
+
+        let failed = if failed_providers.is_empty() {
+            String::new()
+        } else {
+            let lines: Vec<String> = failed_providers
+                .iter()
+                .map(|(provider, reason)| format!("\n  - {}: {}", provider, reason))
+                .collect();
+            format!("\n- **Unavailable providers**:{}", lines.concat())
+        };
+
+        if !active {
+            return Ok(ToolCallResult {
+                content: vec![
+                    ContentItem::text(format!(
+                        "# Browser Status\n\n⚪ No browser session is currently active.{}\n\n- **Mode on launch**: {}\n- **Profile**: {}\n- **Viewport**: {}\n- **Proxy**: {}\n{}\n- **Extra headers**: {}{}\n\nA browser will be launched when you use `webpuppet_navigate` or `webpuppet_prompt`.",
+                        if auto_closed {
+                            " The session was auto-closed due to inactivity."
+                        } else {
+                            ""
+                        },
+                        visibility,
+                        profile,
+                        viewport,
+                        proxy,
+                        identity,
+                        extra_headers,
+                        prewarm
+                    )),
+                    ContentItem::json(&structured),
+                ],
+                is_error: false,
+                structured_content: Some(structured),
+            });
+        }
+
+        Ok(ToolCallResult {
+            content: vec![
+                ContentItem::text(format!(
+                    "# Browser Status\n\n🟢 Browser session is active.\n\n- **Mode**: {}\n- **Profile**: {}\n- **Viewport**: {}\n- **Proxy**: {}\n{}\n- **Extra headers**: {}\n- **Default provider**: {}{}{}{}",
+                    visibility, profile, viewport, proxy, identity, extra_headers, context.default_provider, accounts, prewarm, failed
+                )),
+                ContentItem::json(&structured),
+            ],
+            is_error: false,
+            structured_content: Some(structured),
+        })
+    }
+}
+
+/// Tool for switching the browser between headless and visible mode.
+pub struct SetVisibilityTool;
+
+#[derive(Debug, Deserialize)]
+struct SetVisibilityArgs {
+    /// Whether the browser window should be visible.
+    visible: bool,
+}
+
+#[async_trait::async_trait]
+impl Tool for SetVisibilityTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "webpuppet_set_visibility".into(),
+            description: "Switch the browser between visible and headless mode, relaunching it if a session is running. Useful for debugging or handling an intervention by hand.".into(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "visible": {
+                        "type": "boolean",
+                        "description": "true to show the browser window, false to run headless"
+                    }
+                },
+                "required": ["visible"]
+            }),
+        }
+    }
+
+    async fn execute(
+        &self,
+        arguments: serde_json::Value,
+        context: &ToolContext,
+    ) -> Result<ToolCallResult> {
+        let args: SetVisibilityArgs =
+            serde_json::from_value(arguments).map_err(|e| Error::InvalidParams(e.to_string()))?;
+
+        let relaunched = context.set_headless(!args.visible).await?;
+
+        let mode = if args.visible { "Visible" } else { "Headless" };
+        let detail = if relaunched {
+            "The browser was relaunched in this mode. Pages and unsaved state from the old window are gone."
+        } else {
+            "The next browser launch will use this mode."
+        };
+
+        let structured = json!({
+            "mode": mode.to_lowercase(),
+            "relaunched": relaunched,
+        });
+
+        Ok(ToolCallResult {
+            content: vec![
+                ContentItem::text(format!(
+                    "# Browser Visibility\n\n**Mode**: {}\n\n{}",
+                    mode, detail
+                )),
+                ContentItem::json(&structured),
+            ],
+            is_error: false,
+            structured_content: Some(structured),
+        })
+    }
+}
+
+/// Smallest viewport edge accepted by `webpuppet_set_viewport`.
+const MIN_VIEWPORT_EDGE: u32 = 200;
+
+/// Largest viewport width accepted by `webpuppet_set_viewport` (8K).
+const MAX_VIEWPORT_WIDTH: u32 = 7680;
+
+/// Largest viewport height accepted by `webpuppet_set_viewport` (8K).
+const MAX_VIEWPORT_HEIGHT: u32 = 4320;
+
+/// Tool for changing the rendering dimensions of the browser.
+pub struct SetViewportTool;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct SetViewportArgs {
+    /// Viewport width in CSS pixels
+    #[schemars(range(min = 200, max = 7680))]
+    width: u32,
+    /// Viewport height in CSS pixels
+    #[schemars(range(min = 200, max = 4320))]
+    height: u32,
+    /// Device pixel ratio, e.g. 2 or 3 for high-density phone screens
+    #[schemars(range(min = 0.5, max = 4.0))]
+    device_scale_factor: Option<f64>,
+    /// Present as a mobile device (mobile user agent, touch events)
+    #[serde(default)]
+    mobile: bool,
+}
 
 #[async_trait::async_trait]
-impl Tool for PromptTool {
+impl Tool for SetViewportTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::from_args::<SetViewportArgs>(
+            "webpuppet_set_viewport",
+            "Set the browser viewport (width, height, optional device scale factor and mobile emulation) used for pages and screenshots from now on. A running browser is relaunched and reopens its current page.",
+        )
+    }
+
+    async fn execute(
+        &self,
+        arguments: serde_json::Value,
+        context: &ToolContext,
+    ) -> Result<ToolCallResult> {
+        let args: SetViewportArgs =
+            serde_json::from_value(arguments).map_err(|e| Error::InvalidParams(e.to_string()))?;
+
+        if !(MIN_VIEWPORT_EDGE..=MAX_VIEWPORT_WIDTH).contains(&args.width)
+            || !(MIN_VIEWPORT_EDGE..=MAX_VIEWPORT_HEIGHT).contains(&args.height)
+        {
+            return Err(Error::InvalidParams(format!(
+                "viewport must be between {min}×{min} and {}×{} pixels, got {}×{}",
+                MAX_VIEWPORT_WIDTH,
+                MAX_VIEWPORT_HEIGHT,
+                args.width,
+                args.height,
+                min = MIN_VIEWPORT_EDGE
+            )));
+        }
+        if let Some(scale) = args.device_scale_factor {
+            if !(0.5..=4.0).contains(&scale) {
+                return Err(Error::InvalidParams(format!(
+                    "device_scale_factor must be between 0.5 and 4, got {}",
+                    scale
+                )));
+            }
+        }
+
+        let viewport = Viewport {
+            width: args.width,
+            height: args.height,
+            device_scale_factor: args.device_scale_factor,
+            mobile: args.mobile,
+        };
+        let relaunched = context.set_viewport(Some(viewport)).await?;
+
+        let detail = if relaunched {
+            "The browser was relaunched with this viewport and reopened its page."
+        } else {
+            "Pages and screenshots will use this viewport."
+        };
+
+        let structured = json!({
+            "viewport": viewport,
+            "relaunched": relaunched,
+        });
+
+        Ok(ToolCallResult {
+            content: vec![
+                ContentItem::text(format!(
+                    "# Browser Viewport\n\n**Viewport**: {}\n\n{}",
+                    viewport, detail
+                )),
+                ContentItem::json(&structured),
+            ],
+            is_error: false,
+            structured_content: Some(structured),
+        })
+    }
+}
+
+/// Tool for switching the browser to another profile.
+pub struct UseProfileTool;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct UseProfileArgs {
+    /// Profile name, e.g. "Default" or "Profile 1"
+    profile: String,
+}
+
+#[async_trait::async_trait]
+impl Tool for UseProfileTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::from_args::<UseProfileArgs>(
+            "webpuppet_use_profile",
+            "Switch the browser to another of its profiles (e.g. one logged in to a different account), closing and relaunching it. Profiles are listed by webpuppet_detect_browsers.",
+        )
+    }
+
+    async fn execute(
+        &self,
+        arguments: serde_json::Value,
+        context: &ToolContext,
+    ) -> Result<ToolCallResult> {
+        let args: UseProfileArgs =
+            serde_json::from_value(arguments).map_err(|e| Error::InvalidParams(e.to_string()))?;
+
+        context.use_profile(&args.profile).await?;
+
+        let structured = json!({ "profile": args.profile });
+
+        Ok(ToolCallResult {
+            content: vec![
+                ContentItem::text(format!(
+                    "# Browser Profile\n\n**Profile**: {}\n\nThe browser was relaunched with this profile. Pages from the old window are gone.",
+                    args.profile
+                )),
+                ContentItem::json(&structured),
+            ],
+            is_error: false,
+            structured_content: Some(structured),
+        })
+    }
+}
+
+/// Default number of entries returned by `webpuppet_network_log`.
+const DEFAULT_NETWORK_LOG_LIMIT: usize = 100;
+
+/// Upper bound on entries returned by `webpuppet_network_log`.
+const MAX_NETWORK_LOG_LIMIT: usize = 1000;
+
+/// Collects the page's own request log from the Resource Timing API. The
+/// buffer starts empty on every document load, so it covers requests made
+/// since the last navigation.
+const NETWORK_LOG_SCRIPT: &str = r#"(() => performance.getEntriesByType('navigation')
+    .concat(performance.getEntriesByType('resource'))
+    .map(e => ({
+        url: e.name,
+        type: e.entryType === 'navigation' ? 'document' : e.initiatorType,
+        status: e.responseStatus || null,
+        content_type: e.contentType || null,
+        duration_ms: Math.round(e.duration),
+        transfer_size: e.transferSize ?? null
+    })))()"#;
+
+/// Tool for listing the network requests made by the current page.
+pub struct NetworkLogTool;
+
+#[derive(Debug, Deserialize)]
+struct NetworkLogArgs {
+    /// Only include these resource types (e.g. script, css, img, fetch).
+    #[serde(default)]
+    types: Vec<String>,
+    /// Maximum number of entries to return.
+    limit: Option<usize>,
+}
+
+/// A request recorded by the page.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct NetworkEntry {
+    url: String,
+    #[serde(rename = "type")]
+    resource_type: String,
+    status: Option<u16>,
+    content_type: Option<String>,
+    duration_ms: Option<u64>,
+    transfer_size: Option<u64>,
+}
+
+#[async_trait::async_trait]
+impl Tool for NetworkLogTool {
     fn definition(&self) -> ToolDefinition {
         ToolDefinition {
-            name: "webpuppet_prompt".into(),
-            description: "Send a prompt through browser automation (AI providers + select web tools). Uses existing authenticated sessions.".into(),
+            name: "webpuppet_network_log".into(),
+            description: "List the network requests (URL, resource type, status, content type) made by the current page since the last navigation. Useful for diagnosing pages that don't load.".into(),
             input_schema: json!({
                 "type": "object",
                 "properties": {
-                    "provider": {
-                        "type": "string",
-                        "enum": ["claude", "grok", "gemini", "chatgpt", "perplexity", "notebooklm", "kaggle"],
-                        "description": "Provider/tool to use"
-                    },
-                    "message": {
-                        "type": "string",
-                        "description": "The prompt message to send"
+                    "types": {
+                        "type": "array",
+                        "items": {"type": "string"},
+                        "description": "Only include these resource types (document, script, css, img, fetch, xmlhttprequest, ...)"
                     },
-                    "context": {
-                        "type": "string",
-                        "description": "Optional context or system instructions"
+                    "limit": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "maximum": MAX_NETWORK_LOG_LIMIT,
+                        "description": "Maximum number of entries to return (default 100)"
                     }
                 },
-                "required": ["provider", "message"]
+                "required": []
             }),
         }
     }
 
+    fn primary_operation(&self) -> Option<Operation> {
+        Some(Operation::ReadContent)
+    }
+
+    async fn execute(
+        &self,
+        arguments: serde_json::Value,
+        context: &ToolContext,
+    ) -> Result<ToolCallResult> {
+        context
+            .permissions
+            .require(Operation::ReadContent)
+            .map_err(|e| Error::PermissionDenied(e.to_string()))?;
+
+        let args: NetworkLogArgs =
+            serde_json::from_value(arguments).map_err(|e| Error::InvalidParams(e.to_string()))?;
+        let limit = args
+            .limit
+            .unwrap_or(DEFAULT_NETWORK_LOG_LIMIT)
+            .clamp(1, MAX_NETWORK_LOG_LIMIT);
+
+        let puppet = context.get_puppet().await?;
+        let raw = puppet.evaluate(NETWORK_LOG_SCRIPT).await?;
+        let entries: Vec<NetworkEntry> = serde_json::from_value(raw)
+            .map_err(|e| Error::Internal(format!("unexpected network log format: {}", e)))?;
+
+        let matching: Vec<NetworkEntry> = entries
+            .into_iter()
+            .filter(|entry| {
+                args.types.is_empty()
+                    || args
+                        .types
+                        .iter()
+                        .any(|t| t.eq_ignore_ascii_case(&entry.resource_type))
+            })
+            .map(|entry| NetworkEntry {
+                url: context.redactor.redact_text(&entry.url),
+                ..entry
+            })
+            .collect();
+        let total = matching.len();
+        let shown: Vec<NetworkEntry> = matching.into_iter().take(limit).collect();
+
+        let lines = shown
+            .iter()
+            .map(|entry| {
+                format!(
+                    "- `{}` {} — {}{}",
+                    entry.resource_type,
+                    entry.url,
+                    entry
+                        .status
+                        .map(|s| s.to_string())
+                        .unwrap_or_else(|| "?".into()),
+                    entry
+                        .content_type
+                        .as_deref()
+                        .map(|ct| format!(" ({})", ct))
+                        .unwrap_or_default()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let text = if shown.is_empty() {
+            "# Network Log\n\nNo matching requests recorded since the last navigation.".to_string()
+        } else {
+            format!(
+                "# Network Log\n\nShowing {} of {} requests since the last navigation.\n\n{}",
+                shown.len(),
+                total,
+                lines
+            )
+        };
+
+        let structured = json!({
+            "entries": shown,
+            "total": total,
+            "truncated": total > shown.len(),
+        });
+
+        Ok(ToolCallResult {
+            content: vec![ContentItem::text(text), ContentItem::json(&structured)],
+            is_error: false,
+            structured_content: Some(structured),
+        })
+    }
+}
+
+/// Default number of messages returned by `webpuppet_console_log`.
+const DEFAULT_CONSOLE_LOG_LIMIT: usize = 100;
+
+/// Tool for reading the console messages of the current page.
+pub struct ConsoleLogTool;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct ConsoleLogArgs {
+    /// Only include these levels (log, info, warn, error, debug)
+    #[serde(default)]
+    levels: Vec<String>,
+    /// Maximum number of messages to return, most recent last (default 100)
+    #[schemars(range(min = 1, max = MAX_CONSOLE_MESSAGES))]
+    limit: Option<usize>,
+}
+
+#[async_trait::async_trait]
+impl Tool for ConsoleLogTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::from_args::<ConsoleLogArgs>(
+            "webpuppet_console_log",
+            "List the console messages (level, text, timestamp) and uncaught errors of the current page since the last navigation. Useful for diagnosing provider pages that misbehave. Messages logged while the page was loading may be missing.",
+        )
+    }
+
+    fn primary_operation(&self) -> Option<Operation> {
+        Some(Operation::ReadContent)
+    }
+
+    async fn execute(
+        &self,
+        arguments: serde_json::Value,
+        context: &ToolContext,
+    ) -> Result<ToolCallResult> {
+        context
+            .permissions
+            .require(Operation::ReadContent)
+            .map_err(|e| Error::PermissionDenied(e.to_string()))?;
+
+        let args: ConsoleLogArgs =
+            serde_json::from_value(arguments).map_err(|e| Error::InvalidParams(e.to_string()))?;
+        let limit = args
+            .limit
+            .unwrap_or(DEFAULT_CONSOLE_LOG_LIMIT)
+            .clamp(1, MAX_CONSOLE_MESSAGES);
+
+        // Also starts capturing on a page loaded before it was installed
+        let puppet = context.get_puppet().await?;
+        let raw = puppet.evaluate(&console::capture_script()).await?;
+        let log: ConsoleLog = serde_json::from_value(raw)
+            .map_err(|e| Error::Internal(format!("unexpected console log format: {}", e)))?;
+
+        let matching: Vec<ConsoleMessage> = log
+            .messages
+            .into_iter()
+            .filter(|message| {
+                args.levels.is_empty()
+                    || args
+                        .levels
+                        .iter()
+                        .any(|level| level.eq_ignore_ascii_case(&message.level))
+            })
+            .map(|message| ConsoleMessage {
+                text: context.redactor.redact_text(&message.text),
+                ..message
+            })
+            .collect();
+        let total = matching.len();
+        let shown = &matching[total.saturating_sub(limit)..];
+
+        let text = if shown.is_empty() {
+            "# Console Log\n\nNo matching console messages since the last navigation.".to_string()
+        } else {
+            let lines = shown
+                .iter()
+                .map(|message| {
+                    format!(
+                        "- {} `{}` {}",
+                        message.timestamp.format("%H:%M:%S%.3f"),
+                        message.level,
+                        message.text
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            format!(
+                "# Console Log\n\nShowing the last {} of {} messages since the last navigation.\n\n{}",
+                shown.len(),
+                total,
+                lines
+            )
+        };
+
+        let structured = json!({
+            "messages": shown,
+            "total": total,
+            "truncated": total > shown.len(),
+            "dropped": log.dropped,
+        });
+
+        Ok(ToolCallResult {
+            content: vec![ContentItem::text(text), ContentItem::json(&structured)],
+            is_error: false,
+            structured_content: Some(structured),
+        })
+    }
+}
+
+/// Default number of elements returned by `webpuppet_find`.
+const DEFAULT_FIND_LIMIT: usize = 20;
+
+/// Upper bound on `webpuppet_find`'s `limit` argument.
+const MAX_FIND_LIMIT: usize = 200;
+
+/// Finds elements by CSS selector or by text and describes each with a
+/// selector that can be passed back to other tools. Called with the selector
+/// and text query as JSON literals (one of them `null`) and the limit. For
+/// text queries only the innermost elements containing the text are
+/// returned, so a match doesn't also report every ancestor. Only the first
+/// `limit` matches are described, since building a stable selector queries
+/// the whole document; `total` counts all of them.
+const FIND_SCRIPT: &str = r#"((selector, query, limit) => {
+    const unique = (sel) => document.querySelectorAll(sel).length === 1;
+    const stableSelector = (el) => {
+        const parts = [];
+        for (let node = el; node && node !== document.documentElement; node = node.parentElement) {
+            if (node.id && unique('#' + CSS.escape(node.id))) {
+                parts.unshift('#' + CSS.escape(node.id));
+                return parts.join(' > ');
+            }
+            let index = 1;
+            for (let sib = node.previousElementSibling; sib; sib = sib.previousElementSibling) {
+                if (sib.tagName === node.tagName) index++;
+            }
+            parts.unshift(node.tagName.toLowerCase() + ':nth-of-type(' + index + ')');
+        }
+        return ['html'].concat(parts).join(' > ');
+    };
+    let elements;
+    try {
+        if (selector !== null) {
+            elements = Array.from(document.querySelectorAll(selector));
+        } else {
+            const needle = query.toLowerCase();
+            const contains = (el) => (el.textContent || '').toLowerCase().includes(needle);
+            elements = Array.from(document.body.querySelectorAll('*'))
+                .filter(el => contains(el) && !Array.from(el.children).some(contains));
+        }
+    } catch (e) {
+        return { error: String(e.message || e) };
+    }
+    const names = ['id', 'class', 'name', 'type', 'role', 'href', 'aria-label', 'placeholder', 'title'];
+    return {
+        total: elements.length,
+        elements: elements.slice(0, limit).map(el => ({
+            tag: el.tagName.toLowerCase(),
+            text: (el.innerText || el.textContent || '').trim().replace(/\s+/g, ' ').slice(0, 200),
+            selector: stableSelector(el),
+            attributes: Object.fromEntries(names
+                .filter(name => el.hasAttribute(name))
+                .map(name => [name, el.getAttribute(name)]))
+        }))
+    };
+})"#;
+
+/// Tool for discovering elements (and selectors for them) on the current page.
+pub struct FindTool;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct FindArgs {
+    /// CSS selector to match (give either this or text)
+    selector: Option<String>,
+    /// Case-insensitive text the element contains (give either this or selector)
+    text: Option<String>,
+    /// Maximum number of elements to return (default 20)
+    #[schemars(range(min = 1, max = MAX_FIND_LIMIT))]
+    limit: Option<usize>,
+}
+
+/// What [`FIND_SCRIPT`] returns.
+#[derive(Debug, Deserialize)]
+struct FindResult {
+    /// Number of matching elements.
+    total: usize,
+    /// The first `limit` of them.
+    elements: Vec<FoundElement>,
+}
+
+/// An element matched on the page.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct FoundElement {
+    tag: String,
+    text: String,
+    selector: String,
+    #[serde(default)]
+    attributes: BTreeMap<String, String>,
+}
+
+#[async_trait::async_trait]
+impl Tool for FindTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::from_args::<FindArgs>(
+            "webpuppet_find",
+            "Find elements on the current page by CSS selector or by visible text. Returns each element's tag, text, key attributes and a stable selector to use with other tools.",
+        )
+    }
+
+    fn primary_operation(&self) -> Option<Operation> {
+        Some(Operation::ReadContent)
+    }
+
+    async fn execute(
+        &self,
+        arguments: serde_json::Value,
+        context: &ToolContext,
+    ) -> Result<ToolCallResult> {
+        context
+            .permissions
+            .require(Operation::ReadContent)
+            .map_err(|e| Error::PermissionDenied(e.to_string()))?;
+
+        let args: FindArgs =
+            serde_json::from_value(arguments).map_err(|e| Error::InvalidParams(e.to_string()))?;
+        let query = match (&args.selector, &args.text) {
+            (Some(selector), None) => format!("`{}`", selector),
+            (None, Some(text)) => format!("text \"{}\"", text),
+            _ => {
+                return Err(Error::InvalidParams(
+                    "exactly one of `selector` or `text` is required".into(),
+                ))
+            }
+        };
+        let limit = args
+            .limit
+            .unwrap_or(DEFAULT_FIND_LIMIT)
+            .clamp(1, MAX_FIND_LIMIT);
+
+        let script = format!(
+            "{}({}, {}, {})",
+            FIND_SCRIPT,
+            json!(args.selector),
+            json!(args.text),
+            limit
+        );
+        let puppet = context.get_puppet().await?;
+        let raw = puppet.evaluate(&script).await?;
+        if let Some(message) = raw.get("error").and_then(|e| e.as_str()) {
+            return Err(Error::InvalidParams(format!(
+                "invalid selector: {}",
+                message
+            )));
+        }
+        let FindResult { total, elements } = serde_json::from_value(raw)
+            .map_err(|e| Error::Internal(format!("unexpected find result format: {}", e)))?;
+
+        let shown: Vec<FoundElement> = elements
+            .into_iter()
+            .take(limit)
+            .map(|mut element| {
+                if let Some(href) = element.attributes.get_mut("href") {
+                    *href = context.redactor.redact_text(href);
+                }
+                element
+            })
+            .collect();
+
+        let lines = shown
+            .iter()
+            .map(|element| {
+                format!(
+                    "- `{}` <{}> {}",
+                    element.selector,
+                    element.tag,
+                    if element.text.is_empty() {
+                        "(no text)"
+                    } else {
+                        &element.text
+                    }
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let text = if shown.is_empty() {
+            format!("# Find Results\n\nNo elements match {}.", query)
+        } else {
+            format!(
+                "# Find Results\n\nShowing {} of {} elements matching {}.\n\n{}",
+                shown.len(),
+                total,
+                query,
+                lines
+            )
+        };
+
+        let structured = json!({
+            "elements": shown,
+            "total": total,
+            "truncated": total > shown.len(),
+        });
+
+        Ok(ToolCallResult {
+            content: vec![ContentItem::text(text), ContentItem::json(&structured)],
+            is_error: false,
+            structured_content: Some(structured),
+        })
+    }
+}
+
+/// Default depth of the tree returned by `webpuppet_describe_page`.
+const DEFAULT_DESCRIBE_DEPTH: usize = 10;
+
+/// Upper bound on `webpuppet_describe_page`'s `max_depth` argument.
+const MAX_DESCRIBE_DEPTH: usize = 32;
+
+/// Default number of nodes returned by `webpuppet_describe_page`.
+const DEFAULT_DESCRIBE_NODES: usize = 300;
+
+/// Upper bound on `webpuppet_describe_page`'s `max_nodes` argument.
+const MAX_DESCRIBE_NODES: usize = 2000;
+
+/// Builds a compact accessibility tree of the page: elements with an
+/// explicit or implicit ARIA role, their accessible name and states.
+/// Elements without a role are skipped and their children hoisted, and
+/// hidden elements are left out. Called with the depth and node limits.
+const DESCRIBE_PAGE_SCRIPT: &str = r#"((maxDepth, maxNodes) => {
+    const implicit = {
+        a: el => el.hasAttribute('href') ? 'link' : null,
+        button: () => 'button',
+        select: () => 'combobox',
+        textarea: () => 'textbox',
+        img: el => el.getAttribute('alt') === '' ? null : 'img',
+        nav: () => 'navigation',
+        main: () => 'main',
+        header: () => 'banner',
+        footer: () => 'contentinfo',
+        form: () => 'form',
+        dialog: () => 'dialog',
+        ul: () => 'list',
+        ol: () => 'list',
+        li: () => 'listitem',
+        table: () => 'table',
+        h1: () => 'heading', h2: () => 'heading', h3: () => 'heading',
+        h4: () => 'heading', h5: () => 'heading', h6: () => 'heading',
+        input: el => ({
+            checkbox: 'checkbox', radio: 'radio', button: 'button', submit: 'button',
+            reset: 'button', range: 'slider', search: 'searchbox', hidden: null
+        })[(el.getAttribute('type') || 'text').toLowerCase()] ?? 'textbox'
+    };
+    const roleOf = el => el.getAttribute('role')
+        || (implicit[el.tagName.toLowerCase()] || (() => null))(el);
+    const text = s => (s || '').trim().replace(/\s+/g, ' ').slice(0, 120);
+    const nameOf = el => {
+        if (el.getAttribute('aria-label')) return text(el.getAttribute('aria-label'));
+        const labelledBy = el.getAttribute('aria-labelledby');
+        if (labelledBy) {
+            return text(labelledBy.split(/\s+/)
+                .map(id => document.getElementById(id)?.textContent || '').join(' '));
+        }
+        if (el.labels && el.labels.length) return text(el.labels[0].textContent);
+        return text(el.getAttribute('alt') || el.getAttribute('title')
+            || el.getAttribute('placeholder') || el.innerText || el.textContent);
+    };
+    const statesOf = el => {
+        const states = [];
+        if (el.disabled || el.getAttribute('aria-disabled') === 'true') states.push('disabled');
+        if (el.checked || el.getAttribute('aria-checked') === 'true') states.push('checked');
+        if (el.getAttribute('aria-expanded') === 'true') states.push('expanded');
+        if (el.getAttribute('aria-expanded') === 'false') states.push('collapsed');
+        if (el.getAttribute('aria-selected') === 'true') states.push('selected');
+        if (el.required) states.push('required');
+        if (el === document.activeElement) states.push('focused');
+        return states;
+    };
+    const hidden = el => el.hidden || el.getAttribute('aria-hidden') === 'true'
+        || getComputedStyle(el).display === 'none' || getComputedStyle(el).visibility === 'hidden';
+    let count = 0;
+    let truncated = false;
+    const walk = (el, depth) => {
+        const nodes = [];
+        for (const child of el.children) {
+            if (hidden(child)) continue;
+            const role = roleOf(child);
+            if (!role) {
+                nodes.push(...walk(child, depth));
+                continue;
+            }
+            if (depth >= maxDepth || count >= maxNodes) {
+                truncated = true;
+                continue;
+            }
+            count++;
+            const node = { role, name: nameOf(child), states: statesOf(child) };
+            const children = walk(child, depth + 1);
+            if (children.length) node.children = children;
+            nodes.push(node);
+        }
+        return nodes;
+    };
+    const tree = walk(document.body, 0);
+    return { title: document.title, tree, truncated };
+})"#;
+
+/// Tool for describing the current page as an accessibility tree.
+pub struct DescribePageTool;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct DescribePageArgs {
+    /// Maximum nesting depth of the tree (default 10)
+    #[schemars(range(min = 1, max = MAX_DESCRIBE_DEPTH))]
+    max_depth: Option<usize>,
+    /// Maximum number of nodes to return (default 300)
+    #[schemars(range(min = 1, max = MAX_DESCRIBE_NODES))]
+    max_nodes: Option<usize>,
+}
+
+/// A node of the accessibility tree.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct AxNode {
+    role: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    states: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    children: Vec<AxNode>,
+}
+
+/// Page description returned by [`DESCRIBE_PAGE_SCRIPT`].
+#[derive(Debug, Deserialize)]
+struct PageDescription {
+    #[serde(default)]
+    title: String,
+    tree: Vec<AxNode>,
+    #[serde(default)]
+    truncated: bool,
+}
+
+/// Trim `nodes` to `max_depth` levels and at most `budget` nodes (depth
+/// first). Returns whether anything was cut.
+fn prune_tree(nodes: &mut Vec<AxNode>, max_depth: usize, budget: &mut usize) -> bool {
+    if max_depth == 0 {
+        let cut = !nodes.is_empty();
+        nodes.clear();
+        return cut;
+    }
+
+    let mut cut = false;
+    let mut kept = 0;
+    for node in nodes.iter_mut() {
+        if *budget == 0 {
+            break;
+        }
+        *budget -= 1;
+        kept += 1;
+        cut |= prune_tree(&mut node.children, max_depth - 1, budget);
+    }
+    if kept < nodes.len() {
+        nodes.truncate(kept);
+        cut = true;
+    }
+    cut
+}
+
+/// Render the tree as an indented markdown list.
+fn render_tree(nodes: &[AxNode], indent: usize, out: &mut String) {
+    for node in nodes {
+        out.push_str(&"  ".repeat(indent));
+        out.push_str("- ");
+        out.push_str(&node.role);
+        if !node.name.is_empty() {
+            out.push_str(&format!(" \"{}\"", node.name));
+        }
+        if !node.states.is_empty() {
+            out.push_str(&format!(" [{}]", node.states.join(", ")));
+        }
+        out.push('\n');
+        render_tree(&node.children, indent + 1, out);
+    }
+}
+
+#[async_trait::async_trait]
+impl Tool for DescribePageTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::from_args::<DescribePageArgs>(
+            "webpuppet_describe_page",
+            "Describe the current page as a compact accessibility tree (roles, names and states of landmarks, headings and interactive elements). Far cheaper than raw HTML for deciding what to click.",
+        )
+    }
+
+    fn primary_operation(&self) -> Option<Operation> {
+        Some(Operation::ReadContent)
+    }
+
     async fn execute(
         &self,
         arguments: serde_json::Value,
         context: &ToolContext,
     ) -> Result<ToolCallResult> {
-        // Check permission
         context
             .permissions
-            .require(Operation::SendPrompt)
+            .require(Operation::ReadContent)
             .map_err(|e| Error::PermissionDenied(e.to_string()))?;
 
-        // Parse arguments
-        let args: PromptArgs =
+        let args: DescribePageArgs =
             serde_json::from_value(arguments).map_err(|e| Error::InvalidParams(e.to_string()))?;
+        let max_depth = args
+            .max_depth
+            .unwrap_or(DEFAULT_DESCRIBE_DEPTH)
+            .clamp(1, MAX_DESCRIBE_DEPTH);
+        let max_nodes = args
+            .max_nodes
+            .unwrap_or(DEFAULT_DESCRIBE_NODES)
+            .clamp(1, MAX_DESCRIBE_NODES);
 
-        // Parse provider
-        let provider = match args.provider.to_lowercase().as_str() {
-            "claude" => Provider::Claude,
-            "grok" => Provider::Grok,
-            "gemini" => Provider::Gemini,
-            "chatgpt" | "openai" => Provider::ChatGpt,
-            "perplexity" => Provider::Perplexity,
-            "notebooklm" | "notebook" => Provider::NotebookLm,
-            "kaggle" => Provider::Kaggle,
-            _ => {
-                return Err(Error::InvalidParams(format!(
-                    "unknown provider: {}",
-                    args.provider
-                )))
-            }
-        };
-
-        // Build request
-        let mut request = PromptRequest::new(args.message);
-        if let Some(ctx) = args.context {
-            request = request.with_context(ctx);
-        }
-
-        // Get puppet and send prompt
         let puppet = context.get_puppet().await?;
+        let raw = puppet
+            .evaluate(&format!(
+                "{}({}, {})",
+                DESCRIBE_PAGE_SCRIPT, max_depth, max_nodes
+            ))
+            .await?;
+        let mut page: PageDescription = serde_json::from_value(raw)
+            .map_err(|e| Error::Internal(format!("unexpected page description format: {}", e)))?;
 
-        // Authenticate if needed
-        puppet.authenticate(provider).await?;
-
-        // Send with screening
-        let (response, screening) = puppet.prompt_screened(provider, request).await?;
-
-        // Close puppet
-        puppet.close().await.ok();
+        // The script applies the same limits; enforce them here regardless
+        let mut budget = max_nodes;
+        let truncated = prune_tree(&mut page.tree, max_depth, &mut budget) || page.truncated;
+        let nodes = max_nodes - budget;
 
-        // Format result
-        let result_text = if screening.passed {
-            response.text
+        let mut outline = String::new();
+        render_tree(&page.tree, 0, &mut outline);
+        let text = if page.tree.is_empty() {
+            format!(
+                "# Page Description\n\n**Title**: {}\n\nNo accessible elements found.",
+                page.title
+            )
         } else {
             format!(
-                "[SECURITY WARNING: Response had risk score {:.2}]\n\n{}",
-                screening.risk_score, response.text
+                "# Page Description\n\n**Title**: {}\n**Nodes**: {}{}\n\n{}",
+                page.title,
+                nodes,
+                if truncated { " (truncated)" } else { "" },
+                outline.trim_end()
             )
         };
 
+        let structured = json!({
+            "title": page.title,
+            "tree": page.tree,
+            "nodes": nodes,
+            "truncated": truncated,
+        });
+
         Ok(ToolCallResult {
-            content: vec![ContentItem::text(result_text)],
+            content: vec![ContentItem::text(text), ContentItem::json(&structured)],
             is_error: false,
+            structured_content: Some(structured),
         })
     }
 }
 
-/// Tool for listing available AI providers.
-pub struct ListProvidersTool;
+/// Characters of content `webpuppet_get_content` returns; longer pages are
+/// cut off.
+const GET_CONTENT_MAX_CHARS: usize = 100_000;
+
+/// Tool returning the current page's main content as text, markdown or HTML.
+pub struct GetContentTool;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct GetContentArgs {
+    /// Representation to return: text, markdown (default) or html
+    format: Option<PageFormat>,
+}
 
 #[async_trait::async_trait]
-impl Tool for ListProvidersTool {
+impl Tool for GetContentTool {
     fn definition(&self) -> ToolDefinition {
-        ToolDefinition {
-            name: "webpuppet_list_providers".into(),
-            description: "List available AI providers and their status.".into(),
-            input_schema: json!({
-                "type": "object",
-                "properties": {},
-                "required": []
-            }),
-        }
+        ToolDefinition::from_args::<GetContentArgs>(
+            "webpuppet_get_content",
+            "Return the current page's main content (<main>, <article> or the body) as plain text, markdown keeping headings and links, or raw HTML.",
+        )
+    }
+
+    fn primary_operation(&self) -> Option<Operation> {
+        Some(Operation::ReadContent)
+    }
+
+    fn verbatim_output(&self) -> bool {
+        true
     }
 
     async fn execute(
         &self,
-        _arguments: serde_json::Value,
-        _context: &ToolContext,
-    ) -> Result<ToolCallResult> {
-        let providers = [
-            (
-                "claude",
-                "Claude (Anthropic)",
-                "https://claude.ai",
-                "Large context, artifacts, code",
-            ),
-            (
-                "grok",
-                "Grok (X/xAI)",
-                "https://x.com/i/grok",
-                "Real-time info, integrated with X",
-            ),
-            (
-                "gemini",
-                "Gemini (Google)",
-                "https://gemini.google.com",
-                "Google integration, large context",
-            ),
-            (
-                "chatgpt",
-                "ChatGPT (OpenAI)",
-                "https://chat.openai.com",
-                "GPT-4o, vision, code, web search",
-            ),
-            (
-                "perplexity",
-                "Perplexity AI",
-                "https://www.perplexity.ai",
-                "Search-focused, sources cited",
-            ),
-            (
-                "notebooklm",
-                "NotebookLM (Google)",
-                "https://notebooklm.google.com",
-                "Research assistant, 500k context",
-            ),
-            (
-                "kaggle",
-                "Kaggle (Datasets)",
-                "https://www.kaggle.com/datasets",
-                "Dataset search/catalog; returns dataset page links",
-            ),
-        ];
+        arguments: serde_json::Value,
+        context: &ToolContext,
+    ) -> Result<ToolCallResult> {
+        context
+            .permissions
+            .require(Operation::ReadContent)
+            .map_err(|e| Error::PermissionDenied(e.to_string()))?;
 
-        let text = providers
-            .iter()
-            .map(|(id, name, url, features)| {
-                format!(
-                    "- **{}** (`{}`): [{}]({})\n  _{}_",
-                    name, id, url, url, features
-                )
-            })
-            .collect::<Vec<_>>()
-            .join("\n");
+        let args: GetContentArgs =
+            serde_json::from_value(arguments).map_err(|e| Error::InvalidParams(e.to_string()))?;
+
+        let puppet = context.get_puppet().await?;
+        let page = render_page(puppet.as_ref(), args.format.unwrap_or_default()).await?;
+        let url = puppet.current_url().await.ok();
+
+        let truncated = page.content.chars().count() > GET_CONTENT_MAX_CHARS;
+        let mut content: String = page.content.chars().take(GET_CONTENT_MAX_CHARS).collect();
+        let chars = content.chars().count();
+        if truncated {
+            content.push_str("\n[content truncated]");
+        }
+
+        let structured = json!({
+            "url": url,
+            "title": page.title,
+            "format": page.format,
+            "chars": chars,
+            "truncated": truncated,
+        });
 
         Ok(ToolCallResult {
-            content: vec![ContentItem::text(format!(
-                "# Available Providers\n\n{}\n\n*Note: Uses browser sessions; some providers require login.*",
-                text
-            ))],
+            content: vec![ContentItem::text(content), ContentItem::json(&structured)],
             is_error: false,
+            structured_content: Some(structured),
         })
     }
 }
 
-/// Tool for retrieving declared provider capabilities.
-pub struct ProviderCapabilitiesTool;
+/// Default number of entries returned by `webpuppet_history`.
+const DEFAULT_HISTORY_LIMIT: usize = 20;
 
-#[derive(Debug, Deserialize)]
-struct ProviderCapabilitiesArgs {
-    /// Provider/tool to inspect.
-    provider: String,
+/// Tool listing the session's recent browser actions.
+pub struct HistoryTool;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct HistoryArgs {
+    /// Maximum number of most recent actions to return (default 20)
+    #[schemars(range(min = 1, max = 100))]
+    limit: Option<usize>,
 }
 
 #[async_trait::async_trait]
-impl Tool for ProviderCapabilitiesTool {
+impl Tool for HistoryTool {
     fn definition(&self) -> ToolDefinition {
-        ToolDefinition {
-            name: "webpuppet_provider_capabilities".into(),
-            description: "Get declared capabilities for a provider/tool (conversation, vision, file upload, web search, etc).".into(),
-            input_schema: json!({
-                "type": "object",
-                "properties": {
-                    "provider": {
-                        "type": "string",
-                        "enum": ["claude", "grok", "gemini", "chatgpt", "perplexity", "notebooklm", "kaggle"],
-                        "description": "Provider/tool to inspect"
-                    }
-                },
-                "required": ["provider"]
-            }),
-        }
+        ToolDefinition::from_args::<HistoryArgs>(
+            "webpuppet_history",
+            "List recent browser actions of this session (navigations, prompts, screenshots), oldest first. Use it to avoid repeating steps. Secrets in URLs and prompts are redacted.",
+        )
     }
 
     async fn execute(
@@ -439,152 +5928,266 @@ impl Tool for ProviderCapabilitiesTool {
         arguments: serde_json::Value,
         context: &ToolContext,
     ) -> Result<ToolCallResult> {
-        context
-            .permissions
-            .require(Operation::ReadContent)
-            .map_err(|e| Error::PermissionDenied(e.to_string()))?;
+        // No permission check: this only reports what was already allowed
 
-        let args: ProviderCapabilitiesArgs =
+        let args: HistoryArgs =
             serde_json::from_value(arguments).map_err(|e| Error::InvalidParams(e.to_string()))?;
+        let limit = args
+            .limit
+            .unwrap_or(DEFAULT_HISTORY_LIMIT)
+            .clamp(1, DEFAULT_HISTORY_CAPACITY);
 
-        let provider = match args.provider.to_lowercase().as_str() {
-            "claude" => Provider::Claude,
-            "grok" => Provider::Grok,
-            "gemini" => Provider::Gemini,
-            "chatgpt" | "openai" => Provider::ChatGpt,
-            "perplexity" => Provider::Perplexity,
-            "notebooklm" | "notebook" => Provider::NotebookLm,
-            "kaggle" => Provider::Kaggle,
-            _ => {
-                return Err(Error::InvalidParams(format!(
-                    "unknown provider: {}",
-                    args.provider
-                )))
-            }
-        };
-
-        // Build a puppet (no auth needed just to query static capabilities).
-        let puppet = context.get_puppet().await?;
+        let entries = context.history.recent(limit);
+        let total = context.history.len();
 
-        let caps = puppet
-            .provider_capabilities(provider)
-            .ok_or_else(|| Error::InvalidParams(format!("provider not available: {}", provider)))?;
+        let text = if entries.is_empty() {
+            "# Session History\n\nNo actions recorded yet.".to_string()
+        } else {
+            let lines = entries
+                .iter()
+                .map(|entry| {
+                    format!(
+                        "- {} {}",
+                        entry.at.format("%H:%M:%S"),
+                        entry.action.describe()
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            format!(
+                "# Session History\n\nLast {} of {} recorded actions, oldest first.\n\n{}",
+                entries.len(),
+                total,
+                lines
+            )
+        };
 
-        puppet.close().await.ok();
+        let structured = json!({
+            "entries": entries,
+            "total": total,
+        });
 
         Ok(ToolCallResult {
-            content: vec![ContentItem::text(
-                serde_json::to_string_pretty(&json!({
-                    "provider": provider.to_string(),
-                    "capabilities": {
-                        "conversation": caps.conversation,
-                        "vision": caps.vision,
-                        "file_upload": caps.file_upload,
-                        "code_execution": caps.code_execution,
-                        "web_search": caps.web_search,
-                        "max_context": caps.max_context,
-                        "models": caps.models,
-                        "note": "Declared capabilities (not runtime UI detection)."
-                    }
-                }))
-                .map_err(|e| Error::Internal(e.to_string()))?,
-            )],
+            content: vec![ContentItem::text(text), ContentItem::json(&structured)],
             is_error: false,
+            structured_content: Some(structured),
         })
     }
 }
 
-/// Tool for detecting installed browsers.
-pub struct DetectBrowsersTool;
+/// Default number of decisions returned by `webpuppet_export_audit`.
+const DEFAULT_AUDIT_EXPORT_LIMIT: usize = 100;
+
+/// Most decisions `webpuppet_export_audit` returns per page.
+const MAX_AUDIT_EXPORT_LIMIT: usize = 500;
+
+/// Tool exporting the permission audit log.
+pub struct ExportAuditTool;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct ExportAuditArgs {
+    /// Only decisions made at or after this RFC 3339 time (default: since the server started)
+    since: Option<String>,
+    /// Position to continue from, as returned in `next_cursor` (default 0)
+    cursor: Option<usize>,
+    /// Maximum number of decisions to return (default 100)
+    #[schemars(range(min = 1, max = 500))]
+    limit: Option<usize>,
+}
 
 #[async_trait::async_trait]
-impl Tool for DetectBrowsersTool {
+impl Tool for ExportAuditTool {
     fn definition(&self) -> ToolDefinition {
-        ToolDefinition {
-            name: "webpuppet_detect_browsers".into(),
-            description: "Detect installed browsers that can be used for automation.".into(),
-            input_schema: json!({
-                "type": "object",
-                "properties": {},
-                "required": []
-            }),
-        }
+        ToolDefinition::from_args::<ExportAuditArgs>(
+            "webpuppet_export_audit",
+            "Export the permission audit log (every decision: operation, allowed, reason, URL, risk level), oldest first, optionally since a time. Large logs are paged: pass next_cursor back as cursor. URLs are redacted.",
+        )
     }
 
     async fn execute(
         &self,
-        _arguments: serde_json::Value,
-        _context: &ToolContext,
+        arguments: serde_json::Value,
+        context: &ToolContext,
     ) -> Result<ToolCallResult> {
-        let browsers = BrowserDetector::detect_all();
+        // No permission check: it would add a decision to the log it reads
 
-        if browsers.is_empty() {
-            return Ok(ToolCallResult {
-                content: vec![ContentItem::text(
-                    "No supported browsers detected. Please install Brave, Chrome, or Chromium.",
-                )],
-                is_error: true,
-            });
-        }
+        let args: ExportAuditArgs =
+            serde_json::from_value(arguments).map_err(|e| Error::InvalidParams(e.to_string()))?;
+        let since = args
+            .since
+            .as_deref()
+            .map(|since| {
+                chrono::DateTime::parse_from_rfc3339(since)
+                    .map(|since| since.with_timezone(&chrono::Utc))
+                    .map_err(|e| Error::InvalidParams(format!("invalid since `{}`: {}", since, e)))
+            })
+            .transpose()?;
+        let cursor = args.cursor.unwrap_or(0);
+        let limit = args
+            .limit
+            .unwrap_or(DEFAULT_AUDIT_EXPORT_LIMIT)
+            .clamp(1, MAX_AUDIT_EXPORT_LIMIT);
 
-        let text = browsers
+        let log: Vec<_> = context
+            .permissions
+            .audit_log()
+            .into_iter()
+            .filter(|entry| since.is_none_or(|since| entry.timestamp >= since))
+            .collect();
+        let total = log.len();
+        let entries: Vec<serde_json::Value> = log
             .iter()
-            .map(|b| {
-                let version = b.version.as_deref().unwrap_or("unknown");
-                let profiles = b.list_profiles().unwrap_or_default();
-                format!(
-                    "- **{}** ({})\n  - Path: `{}`\n  - Data: `{}`\n  - Profiles: {}",
-                    b.browser_type,
-                    version,
-                    b.executable_path.display(),
-                    b.user_data_dir.display(),
-                    if profiles.is_empty() {
-                        "none".to_string()
-                    } else {
-                        profiles.join(", ")
-                    }
-                )
-            })
-            .collect::<Vec<_>>()
-            .join("\n\n");
+            .skip(cursor)
+            .take(limit)
+            .map(|entry| crate::audit::decision_json(entry, &context.redactor))
+            .collect();
+        let next_cursor = (cursor + entries.len() < total).then_some(cursor + entries.len());
+
+        let text = if entries.is_empty() {
+            "# Audit Export\n\nNo permission decisions recorded.".to_string()
+        } else {
+            let denied = entries
+                .iter()
+                .filter(|entry| entry["allowed"] == false)
+                .count();
+            format!(
+                "# Audit Export\n\nDecisions {}–{} of {}, oldest first ({} denied on this page).{}",
+                cursor + 1,
+                cursor + entries.len(),
+                total,
+                denied,
+                next_cursor.map_or_else(String::new, |next| format!(
+                    " Call again with `cursor: {}` for more.",
+                    next
+                ))
+            )
+        };
+
+        let structured = json!({
+            "entries": entries,
+            "total": total,
+            "next_cursor": next_cursor,
+        });
 
         Ok(ToolCallResult {
-            content: vec![ContentItem::text(format!(
-                "# Detected Browsers\n\n{}",
-                text
-            ))],
+            content: vec![ContentItem::text(text), ContentItem::json(&structured)],
             is_error: false,
+            structured_content: Some(structured),
         })
     }
 }
 
-/// Tool for taking screenshots.
-pub struct ScreenshotTool;
+/// Fills form fields on the current page. Called with an array of
+/// `[selector, value]` pairs and the selector of a submit button (or
+/// `null`) as JSON literals. Returns `{ fields: [{ selector, ok, error? }],
+/// submitted, submitError? }`.
+///
+/// Checkboxes and radios are checked when the value is `true`, `on`, `yes`,
+/// `1` or (for radios) their own value; selects take the value or label of
+/// an option. Password, payment card and one-time-code fields are refused so
+/// a human fills them. The form is only submitted if every field was filled.
+const FILL_FORM_SCRIPT: &str = r#"((fields, submit) => {
+    const sensitive = (el) => el.type === 'password'
+        || /^(cc-|one-time-code|current-password|new-password)/.test(el.autocomplete || '');
+    const truthy = (value) => ['true', 'on', 'yes', '1'].includes(String(value).toLowerCase());
+    const results = fields.map(([selector, value]) => {
+        let el;
+        try {
+            el = document.querySelector(selector);
+        } catch (e) {
+            return { selector, ok: false, error: 'invalid selector: ' + String(e.message || e) };
+        }
+        if (!el) return { selector, ok: false, error: 'no element matches ' + selector };
+        if (sensitive(el)) {
+            return { selector, ok: false, error: 'sensitive field; a human has to fill it in' };
+        }
+        if (el.disabled || el.readOnly) {
+            return { selector, ok: false, error: 'field is disabled or read-only' };
+        }
+        el.focus();
+        if (el.type === 'checkbox') {
+            el.checked = truthy(value);
+        } else if (el.type === 'radio') {
+            el.checked = truthy(value) || el.value === value;
+        } else if (el.tagName === 'SELECT') {
+            const option = Array.from(el.options)
+                .find(o => o.value === value || o.label === value || o.text.trim() === value);
+            if (!option) return { selector, ok: false, error: 'no option ' + JSON.stringify(value) };
+            el.value = option.value;
+        } else if ('value' in el) {
+            el.value = value;
+        } else if (el.isContentEditable) {
+            el.textContent = value;
+        } else {
+            return { selector, ok: false, error: 'element is not a form field' };
+        }
+        el.dispatchEvent(new Event('input', { bubbles: true }));
+        el.dispatchEvent(new Event('change', { bubbles: true }));
+        return { selector, ok: true };
+    });
+    const outcome = { fields: results, submitted: false };
+    if (submit) {
+        if (results.some(r => !r.ok)) {
+            outcome.submitError = 'not submitted: some fields could not be filled';
+        } else {
+            const button = document.querySelector(submit);
+            if (button) {
+                button.click();
+                outcome.submitted = true;
+            } else {
+                outcome.submitError = 'no element matches ' + submit;
+            }
+        }
+    }
+    return outcome;
+})"#;
+
+/// Outcome of filling one field.
+#[derive(Debug, Deserialize, Serialize)]
+struct FilledField {
+    selector: String,
+    ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
 
+/// What [`FILL_FORM_SCRIPT`] did.
 #[derive(Debug, Deserialize)]
-struct ScreenshotArgs {
-    /// URL to screenshot.
-    url: String,
+#[serde(rename_all = "camelCase")]
+struct FilledForm {
+    fields: Vec<FilledField>,
+    #[serde(default)]
+    submitted: bool,
+    #[serde(default)]
+    submit_error: Option<String>,
+}
+
+/// Tool for filling several form fields in one call.
+pub struct FillFormTool;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct FillFormArgs {
+    /// Values to fill in, keyed by the CSS selector of each field
+    fields: BTreeMap<String, String>,
+    /// CSS selector of a button to click once every field is filled
+    submit_selector: Option<String>,
 }
 
 #[async_trait::async_trait]
-impl Tool for ScreenshotTool {
+impl Tool for FillFormTool {
     fn definition(&self) -> ToolDefinition {
-        ToolDefinition {
-            name: "webpuppet_screenshot".into(),
-            description: "Take a screenshot of a web page. Only allowed domains can be accessed."
-                .into(),
-            input_schema: json!({
-                "type": "object",
-                "properties": {
-                    "url": {
-                        "type": "string",
-                        "description": "URL to take a screenshot of"
-                    }
-                },
-                "required": ["url"]
-            }),
-        }
+        ToolDefinition::from_args::<FillFormArgs>(
+            "webpuppet_fill_form",
+            "Fill several fields of a form on the current page in one call (text inputs, textareas, selects, checkboxes, radios), optionally clicking a submit button afterwards. Reports success or failure per field. Password, payment and one-time-code fields are refused and left to a human.",
+        )
+    }
+
+    fn primary_operation(&self) -> Option<Operation> {
+        Some(Operation::TypeText)
+    }
+
+    fn drives_page(&self) -> bool {
+        true
     }
 
     async fn execute(
@@ -592,63 +6195,283 @@ impl Tool for ScreenshotTool {
         arguments: serde_json::Value,
         context: &ToolContext,
     ) -> Result<ToolCallResult> {
-        let args: ScreenshotArgs =
+        let args: FillFormArgs =
             serde_json::from_value(arguments).map_err(|e| Error::InvalidParams(e.to_string()))?;
+        if args.fields.is_empty() {
+            return Err(Error::InvalidParams("no fields to fill".into()));
+        }
 
-        // Check permissions for this URL
         context
             .permissions
-            .require_with_url(Operation::Navigate, &args.url)
+            .require(Operation::TypeText)
             .map_err(|e| Error::PermissionDenied(e.to_string()))?;
+        if args.submit_selector.is_some() {
+            context
+                .permissions
+                .require(Operation::Click)
+                .map_err(|e| Error::PermissionDenied(e.to_string()))?;
+        }
+        for selector in args.fields.keys().chain(&args.submit_selector) {
+            context.config.blocklist.check_selector(selector)?;
+        }
+        if let Some(ref submit) = args.submit_selector {
+            confirm(context, ConfirmAction::Click(submit)).await?;
+        }
+
+        let puppet = context.get_puppet().await?;
+        let fields: Vec<(&String, &String)> = args.fields.iter().collect();
+        let script = format!(
+            "{}({}, {})",
+            FILL_FORM_SCRIPT,
+            serde_json::to_string(&fields)?,
+            serde_json::to_string(&args.submit_selector)?
+        );
+        let form: FilledForm = serde_json::from_value(puppet.evaluate(&script).await?)
+            .map_err(|e| Error::Internal(format!("unexpected form fill result: {}", e)))?;
+
+        let filled = form.fields.iter().filter(|field| field.ok).count();
+        let mut text = format!(
+            "# Form Filled\n\n**Filled**: {} of {} fields\n",
+            filled,
+            form.fields.len()
+        );
+        for field in &form.fields {
+            match field.error {
+                None => text.push_str(&format!("\n- ✅ {}", field.selector)),
+                Some(ref error) => text.push_str(&format!("\n- ❌ {}: {}", field.selector, error)),
+            }
+        }
+        if let Some(ref submit) = args.submit_selector {
+            match form.submit_error {
+                Some(ref error) => text.push_str(&format!("\n\n**Submit**: {}", error)),
+                None => text.push_str(&format!("\n\n**Submitted** via {}", submit)),
+            }
+        }
+
+        let structured = json!({
+            "fields": form.fields,
+            "filled": filled,
+            "submitted": form.submitted,
+            "submit_error": form.submit_error,
+        });
+
+        Ok(ToolCallResult {
+            content: vec![ContentItem::text(text), ContentItem::json(&structured)],
+            is_error: filled < form.fields.len(),
+            structured_content: Some(structured),
+        })
+    }
+}
+
+/// Clears the current origin's cookies, web storage and Cache Storage.
+/// Called with `{ cookies, storage, cache }` flags. Returns
+/// `{ origin, cookies, localStorage, sessionStorage, caches }` with the
+/// number of entries cleared for each requested kind, or `{ error }`.
+///
+/// Cookies are expired for every path of the current URL and every parent
+/// domain; HttpOnly cookies are invisible to the page and stay.
+const CLEAR_STORAGE_SCRIPT: &str = r#"(async (opts) => {
+    const cleared = { origin: location.origin };
+    try {
+        if (opts.cookies) {
+            const names = document.cookie.split(';')
+                .map(c => c.split('=')[0].trim())
+                .filter(Boolean);
+            const labels = location.hostname.split('.');
+            const domains = [''];
+            for (let i = 0; i < labels.length - 1; i++) {
+                domains.push('; domain=.' + labels.slice(i).join('.'));
+            }
+            const paths = ['/'];
+            let path = '';
+            for (const segment of location.pathname.split('/').filter(Boolean)) {
+                path += '/' + segment;
+                paths.push(path);
+            }
+            for (const name of names) {
+                for (const domain of domains) {
+                    for (const p of paths) {
+                        document.cookie = name + '=; expires=Thu, 01 Jan 1970 00:00:00 GMT; path=' + p + domain;
+                    }
+                }
+            }
+            cleared.cookies = names.length;
+        }
+        if (opts.storage) {
+            cleared.localStorage = localStorage.length;
+            localStorage.clear();
+            cleared.sessionStorage = sessionStorage.length;
+            sessionStorage.clear();
+        }
+        if (opts.cache) {
+            let count = 0;
+            if (self.caches) {
+                for (const key of await caches.keys()) {
+                    if (await caches.delete(key)) count++;
+                }
+            }
+            cleared.caches = count;
+        }
+    } catch (e) {
+        return { error: String(e.message || e) };
+    }
+    return cleared;
+})"#;
+
+/// What [`CLEAR_STORAGE_SCRIPT`] cleared.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ClearedStorage {
+    #[serde(default, skip_serializing)]
+    error: Option<String>,
+    #[serde(default)]
+    origin: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cookies: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    local_storage: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    session_storage: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    caches: Option<u64>,
+}
+
+/// Serde default for flags that are on unless turned off.
+fn default_true() -> bool {
+    true
+}
+
+/// Tool for wiping the current site's storage.
+pub struct ClearStorageTool;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct ClearStorageArgs {
+    /// Clear the site's cookies (default: true; HttpOnly cookies are out of reach)
+    #[serde(default = "default_true")]
+    cookies: bool,
+    /// Clear localStorage and sessionStorage (default: true)
+    #[serde(default = "default_true")]
+    storage: bool,
+    /// Clear the site's Cache Storage (default: true)
+    #[serde(default = "default_true")]
+    cache: bool,
+}
+
+#[async_trait::async_trait]
+impl Tool for ClearStorageTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::from_args::<ClearStorageArgs>(
+            "webpuppet_clear_storage",
+            "Clear the current site's cookies, localStorage/sessionStorage and Cache Storage to get a provider session out of a bad state. Logs the site out. Requires the permissive policy.",
+        )
+    }
+
+    fn primary_operation(&self) -> Option<Operation> {
+        Some(Operation::ExecuteScript)
+    }
+
+    fn drives_page(&self) -> bool {
+        true
+    }
+
+    async fn execute(
+        &self,
+        arguments: serde_json::Value,
+        context: &ToolContext,
+    ) -> Result<ToolCallResult> {
+        let args: ClearStorageArgs =
+            serde_json::from_value(arguments).map_err(|e| Error::InvalidParams(e.to_string()))?;
+        if !(args.cookies || args.storage || args.cache) {
+            return Err(Error::InvalidParams(
+                "nothing to clear: enable at least one of cookies, storage and cache".into(),
+            ));
+        }
 
+        // Wiping a site's data is done by a script in the page
         context
             .permissions
-            .require(Operation::Screenshot)
-            .map_err(|e| Error::PermissionDenied(e.to_string()))?;
+            .require(Operation::ExecuteScript)
+            .map_err(|e| {
+                Error::PermissionDenied(format!(
+                    "{}; webpuppet_clear_storage destroys session data and needs the permissive policy (--policy permissive)",
+                    e
+                ))
+            })?;
+
+        let puppet = context.get_puppet().await?;
+        let script = format!(
+            "{}({})",
+            CLEAR_STORAGE_SCRIPT,
+            json!({"cookies": args.cookies, "storage": args.storage, "cache": args.cache})
+        );
+        let cleared: ClearedStorage = serde_json::from_value(puppet.evaluate(&script).await?)
+            .map_err(|e| Error::Internal(format!("unexpected clear storage result: {}", e)))?;
+        if let Some(error) = cleared.error {
+            return Err(Error::Internal(format!(
+                "could not clear storage: {}",
+                error
+            )));
+        }
+
+        let mut lines = Vec::new();
+        if let Some(count) = cleared.cookies {
+            lines.push(format!(
+                "- **Cookies**: {} (HttpOnly cookies are kept)",
+                count
+            ));
+        }
+        if let (Some(local), Some(session)) = (cleared.local_storage, cleared.session_storage) {
+            lines.push(format!("- **localStorage**: {} entries", local));
+            lines.push(format!("- **sessionStorage**: {} entries", session));
+        }
+        if let Some(count) = cleared.caches {
+            lines.push(format!("- **Cache Storage**: {} caches", count));
+        }
+        let structured = serde_json::to_value(&cleared)?;
 
-        // For now, return a placeholder since actual screenshot requires full browser impl
         Ok(ToolCallResult {
-            content: vec![ContentItem::text(format!(
-                "Screenshot of `{}` would be captured here.\n\n*Note: Full browser implementation required for actual screenshots.*",
-                args.url
-            ))],
+            content: vec![
+                ContentItem::text(format!(
+                    "# Storage Cleared\n\n**Origin**: {}\n\n{}",
+                    cleared.origin,
+                    lines.join("\n")
+                )),
+                ContentItem::json(&structured),
+            ],
             is_error: false,
+            structured_content: Some(structured),
         })
     }
 }
 
-/// Tool for checking permissions.
-pub struct CheckPermissionTool;
+/// Tool for adding HTTP headers to the browsing tab's requests.
+pub struct SetHeadersTool;
 
-#[derive(Debug, Deserialize)]
-struct CheckPermissionArgs {
-    /// Operation to check.
-    operation: String,
-    /// Optional URL context.
-    url: Option<String>,
+#[derive(Debug, Deserialize, JsonSchema)]
+struct SetHeadersArgs {
+    /// Headers to add, by name (e.g. {"X-Feature-Flag": "beta"}); replace headers of the same name
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    /// Remove the headers set before, before adding `headers` (default: false)
+    #[serde(default)]
+    clear: bool,
 }
 
 #[async_trait::async_trait]
-impl Tool for CheckPermissionTool {
+impl Tool for SetHeadersTool {
     fn definition(&self) -> ToolDefinition {
-        ToolDefinition {
-            name: "webpuppet_check_permission".into(),
-            description: "Check if an operation is allowed by the security policy.".into(),
-            input_schema: json!({
-                "type": "object",
-                "properties": {
-                    "operation": {
-                        "type": "string",
-                        "description": "Operation to check (e.g., Navigate, SendPrompt, DeleteAccount)"
-                    },
-                    "url": {
-                        "type": "string",
-                        "description": "Optional URL context for navigation checks"
-                    }
-                },
-                "required": ["operation"]
-            }),
-        }
+        ToolDefinition::from_args::<SetHeadersArgs>(
+            "webpuppet_set_headers",
+            "Add HTTP headers (e.g. Authorization or feature flags) to the page's same-origin fetch/XHR requests from now on, or clear them with clear: true. Values are never echoed back. Requires the permissive policy.",
+        )
+    }
+
+    fn primary_operation(&self) -> Option<Operation> {
+        Some(Operation::ExecuteScript)
+    }
+
+    fn drives_page(&self) -> bool {
+        true
     }
 
     async fn execute(
@@ -656,182 +6479,289 @@ impl Tool for CheckPermissionTool {
         arguments: serde_json::Value,
         context: &ToolContext,
     ) -> Result<ToolCallResult> {
-        let args: CheckPermissionArgs =
+        let args: SetHeadersArgs =
             serde_json::from_value(arguments).map_err(|e| Error::InvalidParams(e.to_string()))?;
+        if args.headers.is_empty() && !args.clear {
+            return Err(Error::InvalidParams(
+                "nothing to do: pass headers to add, or clear: true to remove them".into(),
+            ));
+        }
+        let added = ExtraHeaders::new(args.headers)?;
 
-        // Map string to Operation
-        let operation = match args.operation.to_lowercase().as_str() {
-            "navigate" => Operation::Navigate,
-            "sendprompt" | "send_prompt" => Operation::SendPrompt,
-            "readresponse" | "read_response" => Operation::ReadResponse,
-            "screenshot" => Operation::Screenshot,
-            "click" => Operation::Click,
-            "typetext" | "type_text" => Operation::TypeText,
-            "deleteaccount" | "delete_account" => Operation::DeleteAccount,
-            "changepassword" | "change_password" => Operation::ChangePassword,
-            _ => {
-                return Ok(ToolCallResult {
-                    content: vec![ContentItem::text(format!(
-                        "Unknown operation: `{}`\n\nValid operations: Navigate, SendPrompt, ReadResponse, Screenshot, Click, TypeText, DeleteAccount, ChangePassword",
-                        args.operation
-                    ))],
-                    is_error: true,
-                });
-            }
-        };
+        // Headers are added by a script in the page and can carry credentials
+        context
+            .permissions
+            .require(Operation::ExecuteScript)
+            .map_err(|e| {
+                Error::PermissionDenied(format!(
+                    "{}; webpuppet_set_headers can send credentials and needs the permissive policy (--policy permissive)",
+                    e
+                ))
+            })?;
 
-        let decision = if let Some(url) = args.url {
-            context.permissions.check_with_url(operation, &url)
+        let headers = if args.clear {
+            added
         } else {
-            context.permissions.check(operation)
+            context.extra_headers().merged(&added)
         };
+        context.set_extra_headers(headers.clone()).await?;
+        tracing::info!("Extra headers set: {:?}", headers);
 
-        let status = if decision.allowed {
-            "✅ ALLOWED"
+        let names = headers.names();
+        let structured = json!({ "headers": names, "cleared": args.clear });
+        let text = if names.is_empty() {
+            "# Extra Headers\n\nNo extra headers are sent.".to_string()
         } else {
-            "❌ DENIED"
+            format!(
+                "# Extra Headers\n\n{}\n\nSent with the page's same-origin fetch/XHR requests; values are hidden.",
+                names
+                    .iter()
+                    .map(|name| format!("- `{}`: {}", name, REDACTED))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            )
         };
-        let text = format!(
-            "# Permission Check\n\n**Operation**: `{}`\n**Status**: {}\n**Reason**: {}\n**Risk Level**: {}/10",
-            operation, status, decision.reason, decision.risk_level
-        );
 
         Ok(ToolCallResult {
-            content: vec![ContentItem::text(text)],
+            content: vec![ContentItem::text(text), ContentItem::json(&structured)],
             is_error: false,
+            structured_content: Some(structured),
         })
     }
 }
 
-// ============================================================================
-// Intervention Tools
-// ============================================================================
+/// Most steps a sequence may have.
+const MAX_SEQUENCE_STEPS: usize = 50;
 
-/// Tool for checking intervention status.
-pub struct InterventionStatusTool;
+/// Tool for running several primitive actions in one call.
+pub struct RunSequenceTool;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct RunSequenceArgs {
+    /// Steps to run in order, each an object with an `action`: navigate {url}, wait_for {selector, timeout_ms}, click {selector}, type {selector, text}, scroll {selector} or {y}, extract {selector, as, all}, screenshot
+    steps: Vec<Step>,
+    /// Keep going after a step fails (default: stop at the first failure)
+    #[serde(default)]
+    continue_on_error: bool,
+}
 
 #[async_trait::async_trait]
-impl Tool for InterventionStatusTool {
+impl Tool for RunSequenceTool {
     fn definition(&self) -> ToolDefinition {
-        ToolDefinition {
-            name: "webpuppet_intervention_status".into(),
-            description: "Check if human intervention is needed (captcha, 2FA, etc.). Returns current automation state and any pending intervention reason.".into(),
-            input_schema: json!({
-                "type": "object",
-                "properties": {},
-                "required": []
-            }),
-        }
+        ToolDefinition::from_args::<RunSequenceArgs>(
+            "webpuppet_run_sequence",
+            "Run a list of browser steps (navigate, wait_for, click, type, scroll, extract, screenshot) in the current session in one call and return each step's result. Stops at the first failed step unless continue_on_error is set. Each step is permission-checked like the matching tool.",
+        )
+    }
+
+    fn required_operations(&self) -> Vec<Operation> {
+        vec![
+            Operation::Navigate,
+            Operation::Click,
+            Operation::TypeText,
+            Operation::ReadContent,
+            Operation::Screenshot,
+        ]
+    }
+
+    fn drives_page(&self) -> bool {
+        true
     }
 
     async fn execute(
         &self,
-        _arguments: serde_json::Value,
+        arguments: serde_json::Value,
         context: &ToolContext,
     ) -> Result<ToolCallResult> {
-        let handler = context.intervention_handler.read().await;
-        let state = handler.state();
-        let reason = handler.current_reason();
+        let args: RunSequenceArgs =
+            serde_json::from_value(arguments).map_err(|e| Error::InvalidParams(e.to_string()))?;
+        if args.steps.is_empty() || args.steps.len() > MAX_SEQUENCE_STEPS {
+            return Err(Error::InvalidParams(format!(
+                "steps must have 1-{} entries",
+                MAX_SEQUENCE_STEPS
+            )));
+        }
 
-        let state_str = match state {
-            InterventionState::Running => "🟢 Running",
-            InterventionState::WaitingForHuman => "🟡 Waiting for human",
-            InterventionState::Resuming => "🔵 Resuming",
-            InterventionState::TimedOut => "🔴 Timed out",
-            InterventionState::Cancelled => "⚫ Cancelled",
-        };
+        let mut results = Vec::new();
+        let mut lines = Vec::new();
+        let mut screenshots = Vec::new();
+        let mut failed = 0;
+        for (index, step) in args.steps.iter().enumerate() {
+            let action = step.action();
+            let mut result = json!({ "step": index + 1, "action": action });
+            match step.run(context).await {
+                Ok(output) => {
+                    result["ok"] = json!(true);
+                    let detail = match output {
+                        StepOutput::Extracted { key, value } => {
+                            let detail = match value {
+                                serde_json::Value::String(ref text) => text.clone(),
+                                ref other => other.to_string(),
+                            };
+                            result["key"] = json!(key);
+                            result["value"] = value;
+                            format!(": **{}** = {}", key, detail)
+                        }
+                        StepOutput::Screenshot(png) => {
+                            result["bytes"] = json!(png.len());
+                            screenshots.push(screenshot_content(&png));
+                            format!(": {} byte PNG", png.len())
+                        }
+                        StepOutput::None => String::new(),
+                    };
+                    lines.push(format!("{}. `{}` ok{}", index + 1, action, detail));
+                }
+                Err(e) => {
+                    failed += 1;
+                    let message = context.redactor.redact_text(&e.to_string());
+                    lines.push(format!("{}. `{}` failed: {}", index + 1, action, message));
+                    result["ok"] = json!(false);
+                    result["error"] = json!(message);
+                }
+            }
+            results.push(result);
+            if failed > 0 && !args.continue_on_error {
+                break;
+            }
+        }
 
-        let text = if let Some(reason) = reason {
-            format!(
-                "# Intervention Status\n\n**State**: {}\n**Reason**: {}\n\n⚠️ **Action Required**: Please complete the intervention in the browser, then call `webpuppet_intervention_complete` with success=true.",
-                state_str, reason
-            )
-        } else {
-            format!(
-                "# Intervention Status\n\n**State**: {}\n\nNo intervention currently required. Automation is running normally.",
-                state_str
-            )
-        };
+        let skipped = args.steps.len() - results.len();
+        let mut text = format!("# Sequence\n\n{}", lines.join("\n"));
+        if skipped > 0 {
+            text.push_str(&format!(
+                "\n\nStopped after the failed step; {} step(s) not run.",
+                skipped
+            ));
+        }
+        let structured = json!({
+            "steps": results,
+            "failed": failed,
+            "skipped": skipped,
+        });
 
+        let mut content = vec![ContentItem::text(text), ContentItem::json(&structured)];
+        content.extend(screenshots);
         Ok(ToolCallResult {
-            content: vec![ContentItem::text(text)],
-            is_error: false,
+            content,
+            is_error: failed > 0,
+            structured_content: Some(structured),
         })
     }
 }
 
-/// Tool for signaling intervention completion.
-pub struct InterventionCompleteTool;
+/// Tool for reporting the configuration the server runs with.
+pub struct ConfigTool;
 
-#[derive(Debug, Deserialize)]
-struct InterventionCompleteArgs {
-    /// Whether the intervention was successful.
-    success: bool,
-    /// Optional message about the intervention.
-    message: Option<String>,
-}
+#[derive(Debug, Deserialize, JsonSchema)]
+struct ConfigArgs {}
 
 #[async_trait::async_trait]
-impl Tool for InterventionCompleteTool {
+impl Tool for ConfigTool {
     fn definition(&self) -> ToolDefinition {
-        ToolDefinition {
-            name: "webpuppet_intervention_complete".into(),
-            description: "Signal that a human intervention (captcha, 2FA, etc.) has been completed. Call this after manually handling the intervention in the browser.".into(),
-            input_schema: json!({
-                "type": "object",
-                "properties": {
-                    "success": {
-                        "type": "boolean",
-                        "description": "Whether the intervention was completed successfully"
-                    },
-                    "message": {
-                        "type": "string",
-                        "description": "Optional message about what was done"
-                    }
-                },
-                "required": ["success"]
-            }),
-        }
+        ToolDefinition::from_args::<ConfigArgs>(
+            "webpuppet_config",
+            "Report the configuration in effect: permission policy, allowed operations and domains, providers, timeouts, browser mode and screening threshold. Secrets are redacted. No browser needed.",
+        )
+    }
+
+    fn idempotent(&self) -> bool {
+        true
     }
 
     async fn execute(
         &self,
-        arguments: serde_json::Value,
+        _arguments: serde_json::Value,
         context: &ToolContext,
     ) -> Result<ToolCallResult> {
-        let args: InterventionCompleteArgs =
-            serde_json::from_value(arguments).map_err(|e| Error::InvalidParams(e.to_string()))?;
+        let config = &context.config;
+        let policy = context.permissions.policy();
 
-        let handler = context.intervention_handler.read().await;
-        handler.complete(args.success, args.message.clone());
+        let allowed_operations: Vec<String> = Operation::all()
+            .into_iter()
+            .filter(|&op| policy_allows(&context.permissions, op))
+            .map(|op| op.to_string())
+            .collect();
+        let mut allowed_domains: Vec<&str> =
+            policy.allowed_domains.iter().map(String::as_str).collect();
+        allowed_domains.sort_unstable();
+        let auth_timeouts: BTreeMap<&str, u64> = PROVIDER_IDS
+            .iter()
+            .filter_map(|id| parse_provider(id).ok().map(|p| (*id, p)))
+            .map(|(id, provider)| (id, config.auth_timeout(provider).as_secs()))
+            .collect();
 
-        let status = if args.success {
-            "✅ SUCCESS"
+        let structured = context.redactor.redact_value(&json!({
+            "policy": config.policy,
+            "allowed_operations": allowed_operations,
+            "allowed_domains": allowed_domains,
+            "providers": PROVIDER_IDS,
+            "default_provider": context.default_provider.name(),
+            "browser": config.browser.map(|b| b.name()),
+            "headless": context.is_headless(),
+            "timeouts": {
+                "tool_secs": config.tool_timeout.as_secs(),
+                "browser_idle_secs": config.browser_idle_timeout.as_secs(),
+                "provider_min_interval_ms": config.provider_min_interval.as_millis() as u64,
+                "domain_delay_ms": config
+                    .domain_delays
+                    .iter()
+                    .map(|(domain, delay)| (domain.as_str(), delay.as_millis() as u64))
+                    .collect::<BTreeMap<_, _>>(),
+                "heartbeat_secs": config.heartbeat_interval.as_secs(),
+                "read_cache_secs": config.read_cache_ttl.as_secs(),
+                "auth_secs": auth_timeouts,
+            },
+            "screening_threshold": context.screening_config.risk_threshold,
+            "proxy": config.proxy.as_ref().map(ProxyConfig::host),
+            "allowed_methods": config.allowed_methods,
+            "denied_tools": config.denied_tools,
+            "result_format": context.result_format(),
+            "max_result_bytes": context.max_result_bytes(),
+            "max_message_bytes": config.max_message_bytes,
+            "max_download_bytes": config.max_download_bytes,
+            "prewarm": config.prewarm,
+            "provider_init_concurrency": config.provider_init_concurrency,
+            "confirm_destructive": config.confirm_destructive,
+            "screening_mode": config.screening_mode,
+            "humanize": config.humanize.enabled,
+            "session_dir": config.session_dir,
+        }));
+
+        let domains = if allowed_domains.is_empty() {
+            "any".to_string()
         } else {
-            "❌ FAILED"
+            allowed_domains.join(", ")
         };
         let text = format!(
-            "# Intervention Complete\n\n**Status**: {}\n**Message**: {}\n\nAutomation will now resume.",
-            status,
-            args.message.unwrap_or_else(|| "None".into())
+            "# Configuration\n\n- **Policy**: {}\n- **Allowed operations**: {}\n- **Allowed domains**: {}\n- **Default provider**: {}\n- **Browser**: {} ({})\n- **Tool timeout**: {}s\n- **Browser idle timeout**: {}s\n- **Screening threshold**: {}",
+            config.policy,
+            allowed_operations.join(", "),
+            domains,
+            context.default_provider,
+            config.browser.map_or("auto", |b| b.name()),
+            if context.is_headless() { "headless" } else { "visible" },
+            config.tool_timeout.as_secs(),
+            config.browser_idle_timeout.as_secs(),
+            context.screening_config.risk_threshold
         );
 
         Ok(ToolCallResult {
-            content: vec![ContentItem::text(text)],
+            content: vec![ContentItem::text(text), ContentItem::json(&structured)],
             is_error: false,
+            structured_content: Some(structured),
         })
     }
 }
 
-/// Tool for pausing automation.
-pub struct InterventionPauseTool;
+/// Tool for tearing down the browser session and intervention state.
+pub struct ResetTool;
 
 #[async_trait::async_trait]
-impl Tool for InterventionPauseTool {
+impl Tool for ResetTool {
     fn definition(&self) -> ToolDefinition {
         ToolDefinition {
-            name: "webpuppet_pause".into(),
-            description: "Pause browser automation. Use this when you need to manually interact with the browser.".into(),
+            name: "webpuppet_reset".into(),
+            description: "Reset the server to a clean state: close the browser session, clear any pending human intervention, and drop cached results. Use after errors or a timed-out intervention.".into(),
             input_schema: json!({
                 "type": "object",
                 "properties": {},
@@ -845,27 +6775,65 @@ impl Tool for InterventionPauseTool {
         _arguments: serde_json::Value,
         context: &ToolContext,
     ) -> Result<ToolCallResult> {
-        let handler = context.intervention_handler.read().await;
-        handler.pause();
+        // No permission check: this only tears down local state
+
+        let browser_was_open = context.puppet.read().await.is_some();
+        let close_error = context.close_puppet().await.err();
+        if let Some(ref e) = close_error {
+            tracing::warn!("Browser did not close cleanly during reset: {}", e);
+        }
+
+        let previous_state = {
+            let mut handler = context.intervention_handler.write().await;
+            let state = handler.state();
+            // Release anything waiting on the old handler
+            handler.cancel();
+            *handler = Arc::new(InterventionHandler::new());
+            *context.intervention_origin.lock().unwrap() = None;
+            state
+        };
+
+        let cached_results = context.coalescer.clear();
+
+        let browser_line = match (browser_was_open, &close_error) {
+            (false, _) => "⚪ No browser session was running".to_string(),
+            (true, None) => "✅ Browser session closed".to_string(),
+            (true, Some(e)) => format!("⚠️ Browser session dropped (close failed: {})", e),
+        };
+
+        let structured = json!({
+            "browser_closed": browser_was_open,
+            "close_error": close_error.map(|e| e.to_string()),
+            "previous_intervention_state": intervention_state_name(previous_state),
+            "intervention_state": intervention_state_name(InterventionState::Running),
+            "cached_results_cleared": cached_results,
+        });
 
         Ok(ToolCallResult {
-            content: vec![ContentItem::text(
-                "# Automation Paused\n\n⏸️ Automation is now paused. The browser is available for manual interaction.\n\nCall `webpuppet_resume` when ready to continue."
-            )],
+            content: vec![
+                ContentItem::text(format!(
+                    "# Reset Complete\n\n- **Browser**: {}\n- **Intervention**: {} → running\n- **Cached results cleared**: {}",
+                    browser_line,
+                    intervention_state_name(previous_state),
+                    cached_results
+                )),
+                ContentItem::json(&structured),
+            ],
             is_error: false,
+            structured_content: Some(structured),
         })
     }
 }
 
-/// Tool for resuming automation.
-pub struct InterventionResumeTool;
+/// Tool wiping the browser data persisted in `--session-dir`.
+pub struct ClearSessionsTool;
 
 #[async_trait::async_trait]
-impl Tool for InterventionResumeTool {
+impl Tool for ClearSessionsTool {
     fn definition(&self) -> ToolDefinition {
         ToolDefinition {
-            name: "webpuppet_resume".into(),
-            description: "Resume browser automation after a pause or manual intervention.".into(),
+            name: "webpuppet_clear_sessions".into(),
+            description: "Close the browser and delete the browser data (cookies, storage, logins) persisted in --session-dir, so every provider needs a fresh login.".into(),
             input_schema: json!({
                 "type": "object",
                 "properties": {},
@@ -874,142 +6842,162 @@ impl Tool for InterventionResumeTool {
         }
     }
 
+    fn drives_page(&self) -> bool {
+        true
+    }
+
     async fn execute(
         &self,
         _arguments: serde_json::Value,
         context: &ToolContext,
     ) -> Result<ToolCallResult> {
-        let handler = context.intervention_handler.read().await;
-        handler.resume();
+        // No permission check: this only deletes data the server stored
+        let Some(ref dir) = context.config.session_dir else {
+            return Err(Error::InvalidParams(
+                "no session directory configured (see --session-dir)".into(),
+            ));
+        };
+
+        // The browser holds its data directory open
+        let browser_was_open = context.puppet.read().await.is_some();
+        if let Err(e) = context.close_puppet().await {
+            tracing::warn!(
+                "Browser did not close cleanly before clearing sessions: {}",
+                e
+            );
+        }
+
+        let existed = tokio::fs::try_exists(dir).await?;
+        if existed {
+            tokio::fs::remove_dir_all(dir).await?;
+        }
+        tracing::info!("Cleared persisted sessions in {}", dir.display());
 
+        let structured = json!({
+            "session_dir": dir,
+            "cleared": existed,
+            "browser_closed": browser_was_open,
+        });
         Ok(ToolCallResult {
-            content: vec![ContentItem::text(
-                "# Automation Resumed\n\n▶️ Automation has been resumed. Browser operations will continue."
-            )],
+            content: vec![
+                ContentItem::text(format!(
+                    "# Sessions Cleared\n\n- **Directory**: {}\n- **Data**: {}\n- **Browser**: {}",
+                    dir.display(),
+                    if existed {
+                        "deleted"
+                    } else {
+                        "none stored yet"
+                    },
+                    if browser_was_open {
+                        "closed"
+                    } else {
+                        "was not running"
+                    }
+                )),
+                ContentItem::json(&structured),
+            ],
             is_error: false,
+            structured_content: Some(structured),
         })
     }
 }
 
-/// Tool for navigating to a URL (for testing).
-pub struct NavigateTool;
+/// Tool listing the conversations this session has open with providers.
+pub struct ListConversationsTool;
 
-#[derive(Debug, Deserialize)]
-struct NavigateArgs {
-    /// URL to navigate to.
-    url: String,
-}
+#[derive(Debug, Deserialize, JsonSchema)]
+struct ListConversationsArgs {}
 
 #[async_trait::async_trait]
-impl Tool for NavigateTool {
+impl Tool for ListConversationsTool {
     fn definition(&self) -> ToolDefinition {
-        ToolDefinition {
-            name: "webpuppet_navigate".into(),
-            description: "Navigate browser to a URL. Opens a browser window if not already open."
-                .into(),
-            input_schema: json!({
-                "type": "object",
-                "properties": {
-                    "url": {
-                        "type": "string",
-                        "description": "URL to navigate to"
-                    }
-                },
-                "required": ["url"]
-            }),
-        }
+        ToolDefinition::from_args::<ListConversationsArgs>(
+            "webpuppet_list_conversations",
+            "List the provider conversations this session has open, most recently active first, with their provider, a snippet of the first message (secrets redacted) and last activity. Continue one by passing its id to webpuppet_prompt as conversation_id.",
+        )
     }
 
     async fn execute(
         &self,
-        arguments: serde_json::Value,
+        _arguments: serde_json::Value,
         context: &ToolContext,
     ) -> Result<ToolCallResult> {
-        // Check permission
-        context
-            .permissions
-            .require(Operation::Navigate)
-            .map_err(|e| Error::PermissionDenied(e.to_string()))?;
-
-        // Parse arguments
-        let args: NavigateArgs =
-            serde_json::from_value(arguments).map_err(|e| Error::InvalidParams(e.to_string()))?;
-
-        // Get puppet and navigate
-        let puppet = context.get_puppet().await?;
+        // No permission check: this only reports what was already allowed
 
-        // Get session (using Grok as default provider for navigation)
-        let session = puppet.get_session(Provider::Grok).await?;
-
-        // Navigate
-        session.navigate(&args.url).await?;
-
-        // Get current URL and title
-        let current_url = session
-            .current_url()
-            .await
-            .unwrap_or_else(|_| args.url.clone());
-        let title = session
-            .get_title()
-            .await
-            .unwrap_or_else(|_| "Unknown".into());
+        let conversations = context.conversations.list();
+        let text = if conversations.is_empty() {
+            "# Conversations\n\nNo conversations open.".to_string()
+        } else {
+            let lines = conversations
+                .iter()
+                .map(|conversation| {
+                    format!(
+                        "- `{}` with {} ({} turns, last active {}): \"{}\"",
+                        conversation.id,
+                        conversation.provider,
+                        conversation.turns,
+                        conversation.last_activity.format("%H:%M:%S"),
+                        conversation.snippet
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            format!("# Conversations\n\n{}", lines)
+        };
 
+        let structured = json!({ "conversations": conversations });
         Ok(ToolCallResult {
-            content: vec![ContentItem::text(format!(
-                "# Browser Navigated\n\n✅ Successfully navigated to URL.\n\n- **URL**: {}\n- **Title**: {}",
-                current_url, title
-            ))],
+            content: vec![ContentItem::text(text), ContentItem::json(&structured)],
             is_error: false,
+            structured_content: Some(structured),
         })
     }
 }
 
-/// Tool for getting browser status.
-pub struct BrowserStatusTool;
+/// Tool forgetting a conversation listed by `webpuppet_list_conversations`.
+pub struct CloseConversationTool;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct CloseConversationArgs {
+    /// Id of the conversation to close
+    id: String,
+}
 
 #[async_trait::async_trait]
-impl Tool for BrowserStatusTool {
+impl Tool for CloseConversationTool {
     fn definition(&self) -> ToolDefinition {
-        ToolDefinition {
-            name: "webpuppet_browser_status".into(),
-            description: "Get current browser status including URL, title, and visibility.".into(),
-            input_schema: json!({
-                "type": "object",
-                "properties": {},
-                "required": []
-            }),
-        }
+        ToolDefinition::from_args::<CloseConversationArgs>(
+            "webpuppet_close_conversation",
+            "Drop a conversation from this session's list once you are done with it. The thread stays in the provider's own history.",
+        )
     }
 
     async fn execute(
         &self,
-        _arguments: serde_json::Value,
+        arguments: serde_json::Value,
         context: &ToolContext,
     ) -> Result<ToolCallResult> {
-        let guard = context.puppet.read().await;
-
-        if guard.is_none() {
-            return Ok(ToolCallResult {
-                content: vec![ContentItem::text(
-                    "# Browser Status\n\n⚪ No browser session is currently active.\n\nA browser will be launched when you use `webpuppet_navigate` or `webpuppet_prompt`."
-                )],
-                is_error: false,
-            });
+        let args: CloseConversationArgs =
+            serde_json::from_value(arguments).map_err(|e| Error::InvalidParams(e.to_string()))?;
+        if !context.conversations.close(&args.id) {
+            return Err(Error::InvalidParams(format!(
+                "No open conversation {}",
+                args.id
+            )));
         }
 
-        // Return basic status
-        let visibility = if context.headless {
-            "Headless"
-        } else {
-            "Visible"
-        };
-
+        let structured = json!({
+            "id": args.id,
+            "closed": true,
+            "remaining": context.conversations.list().len(),
+        });
         Ok(ToolCallResult {
-            content: vec![ContentItem::text(format!(
-                "# Browser Status\n\n🟢 Browser session is active.\n\n- **Mode**: {}\n- **Providers**: Grok, Claude, Gemini",
-                visibility
-            ))],
+            content: vec![
+                ContentItem::text(format!("Closed conversation `{}`.", args.id)),
+                ContentItem::json(&structured),
+            ],
             is_error: false,
+            structured_content: Some(structured),
         })
     }
 }
@@ -0,0 +1,466 @@
+//! Shared test doubles for in-process tests.
+
+#![allow(dead_code)]
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde_json::Value;
+use tokio::sync::mpsc;
+use webpuppet::providers::ProviderCapabilities;
+use webpuppet::{
+    BrowserInstallation, BrowserType, InterventionReason, PromptRequest, PromptResponse, Provider,
+};
+use webpuppet_mcp::browser::{
+    init_providers, BrowserLauncher, BrowserSession, ExtraHeaders, HistoryStep, LaunchOptions,
+    LoginStatus, PartialResponse, ProviderAccounts, ProviderProbe, Viewport,
+};
+use webpuppet_mcp::{Error, Result};
+
+/// Browser session that records calls instead of driving a real browser.
+#[derive(Default)]
+pub struct StubBrowser {
+    /// Login state per provider (defaults to logged in).
+    pub login: Mutex<HashMap<Provider, LoginStatus>>,
+    /// URLs navigated to, in order.
+    pub navigations: Mutex<Vec<String>>,
+    /// Pages left by going back, most recent last.
+    pub forward_pages: Mutex<Vec<String>>,
+    /// Reloads performed, with whether each ignored the cache.
+    pub reloads: Mutex<Vec<bool>>,
+    /// Prompts sent, in order.
+    pub prompts: Mutex<Vec<(Provider, String)>>,
+    /// Text returned for every prompt.
+    pub response_text: Mutex<String>,
+    /// Text returned for prompts to specific providers, overriding `response_text`.
+    pub provider_responses: Mutex<HashMap<Provider, String>>,
+    /// Metadata attached to every prompt response.
+    pub response_metadata: Mutex<HashMap<String, String>>,
+    /// Providers whose prompts fail with a provider error.
+    pub failing_providers: Mutex<Vec<Provider>>,
+    /// Number of times the session was closed.
+    pub closes: AtomicUsize,
+    /// Number of conversations started by prompts.
+    pub conversations_started: AtomicUsize,
+    /// Scripts evaluated, in order.
+    pub scripts: Mutex<Vec<String>>,
+    /// Produces the result of `evaluate` (defaults to `null`).
+    pub eval_handler: Mutex<Option<EvalHandler>>,
+    /// Make the next navigation or prompt fail as if the browser had died.
+    pub crash_next: AtomicBool,
+    /// Whether probed provider pages show their prompt input (default: no).
+    pub provider_ui_ready: AtomicBool,
+    /// Viewport of the most recent launch, used to size screenshots.
+    pub viewport: Mutex<Option<Viewport>>,
+    /// Chunks a streaming prompt sends before returning their concatenation
+    /// (default: none, i.e. no streaming).
+    pub stream_chunks: Mutex<Vec<String>>,
+    /// Keep streaming prompts generating after their last chunk until the
+    /// call is dropped.
+    pub stream_stalls: AtomicBool,
+    /// What a human has to do before prompts go through (default: nothing).
+    pub intervention: Mutex<Option<InterventionReason>>,
+    /// Selectors of elements captured by `screenshot_element`, in order.
+    pub element_screenshots: Mutex<Vec<String>>,
+    /// How long `authenticate` takes (default: no time).
+    pub auth_delay: Mutex<Duration>,
+    /// Extra headers added to requests, from the launch or set since.
+    pub extra_headers: Mutex<ExtraHeaders>,
+    /// Navigations currently in progress.
+    pub navigations_in_flight: AtomicUsize,
+    /// Most navigations ever in progress at once.
+    pub max_navigations_in_flight: AtomicUsize,
+    /// What successive `read_response` calls see; the last one stays.
+    pub partial_responses: Mutex<VecDeque<PartialResponse>>,
+    /// Providers that failed to initialize at the most recent launch.
+    pub failed_providers: Mutex<Vec<(Provider, String)>>,
+    /// Accounts signed in per provider (default: none).
+    pub accounts: Mutex<HashMap<Provider, ProviderAccounts>>,
+    /// Error notices shown on every provider's page (default: none).
+    pub page_notices: Mutex<Vec<String>>,
+}
+
+/// Computes the result of a script evaluated in the stub browser.
+pub type EvalHandler = Box<dyn Fn(&str) -> Value + Send + Sync>;
+
+impl StubBrowser {
+    /// Set the login state reported for a provider.
+    pub fn set_login(&self, provider: Provider, status: LoginStatus) {
+        self.login.lock().unwrap().insert(provider, status);
+    }
+
+    /// Set how `evaluate` responds.
+    pub fn on_evaluate(&self, handler: impl Fn(&str) -> Value + Send + Sync + 'static) {
+        *self.eval_handler.lock().unwrap() = Some(Box::new(handler));
+    }
+
+    /// The conversation a prompt continues, or a new one (`conv-1`, ...).
+    fn conversation_for(&self, request: &PromptRequest) -> String {
+        request.conversation_id.clone().unwrap_or_else(|| {
+            let number = self.conversations_started.fetch_add(1, Ordering::SeqCst) + 1;
+            format!("conv-{}", number)
+        })
+    }
+
+    /// Fail with a DevTools disconnect if a crash was requested.
+    fn check_crash(&self) -> Result<()> {
+        if self.crash_next.swap(false, Ordering::SeqCst) {
+            return Err(webpuppet::Error::Browser(
+                "Navigation failed: send failed because receiver is gone".into(),
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Number of navigations performed.
+    pub fn navigation_count(&self) -> usize {
+        self.navigations.lock().unwrap().len()
+    }
+}
+
+#[async_trait::async_trait]
+impl BrowserSession for StubBrowser {
+    async fn page_notices(&self, _provider: Provider) -> Result<Vec<String>> {
+        Ok(self.page_notices.lock().unwrap().clone())
+    }
+
+    async fn accounts(&self, provider: Provider) -> Result<ProviderAccounts> {
+        Ok(self
+            .accounts
+            .lock()
+            .unwrap()
+            .get(&provider)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn switch_account(&self, provider: Provider, account: &str) -> Result<ProviderAccounts> {
+        let mut accounts = self.accounts.lock().unwrap();
+        let accounts = accounts.entry(provider).or_default();
+        if !accounts.accounts.iter().any(|a| a == account) {
+            return Err(Error::InvalidParams(format!(
+                "{} shows no account {}",
+                provider, account
+            )));
+        }
+        accounts.active = Some(account.to_string());
+        Ok(accounts.clone())
+    }
+
+    async fn login_status(&self, provider: Provider) -> Result<LoginStatus> {
+        Ok(self
+            .login
+            .lock()
+            .unwrap()
+            .get(&provider)
+            .copied()
+            .unwrap_or(LoginStatus::LoggedIn))
+    }
+
+    async fn authenticate(&self, _provider: Provider) -> Result<()> {
+        let delay = *self.auth_delay.lock().unwrap();
+        tokio::time::sleep(delay).await;
+        Ok(())
+    }
+
+    async fn intervention_needed(&self, _provider: Provider) -> Result<Option<InterventionReason>> {
+        Ok(self.intervention.lock().unwrap().clone())
+    }
+
+    async fn prompt(&self, provider: Provider, request: PromptRequest) -> Result<PromptResponse> {
+        self.check_crash()?;
+        self.prompts
+            .lock()
+            .unwrap()
+            .push((provider, request.message.clone()));
+
+        if self.failing_providers.lock().unwrap().contains(&provider) {
+            return Err(webpuppet::Error::ProviderError {
+                provider: provider.name().into(),
+                message: "something went wrong".into(),
+            }
+            .into());
+        }
+
+        let text = match self.provider_responses.lock().unwrap().get(&provider) {
+            Some(text) => text.clone(),
+            None => self.response_text.lock().unwrap().clone(),
+        };
+
+        Ok(PromptResponse {
+            text,
+            provider,
+            conversation_id: Some(self.conversation_for(&request)),
+            timestamp: chrono::Utc::now(),
+            tokens_used: None,
+            metadata: self.response_metadata.lock().unwrap().clone(),
+        })
+    }
+
+    async fn prompt_streaming(
+        &self,
+        provider: Provider,
+        request: PromptRequest,
+        chunks: mpsc::UnboundedSender<String>,
+    ) -> Result<PromptResponse> {
+        let parts = self.stream_chunks.lock().unwrap().clone();
+        if parts.is_empty() {
+            return self.prompt(provider, request).await;
+        }
+
+        self.prompts
+            .lock()
+            .unwrap()
+            .push((provider, request.message.clone()));
+        for part in &parts {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            let _ = chunks.send(part.clone());
+        }
+        if self.stream_stalls.load(Ordering::SeqCst) {
+            std::future::pending::<()>().await;
+        }
+
+        Ok(PromptResponse {
+            text: parts.concat(),
+            provider,
+            conversation_id: Some(self.conversation_for(&request)),
+            timestamp: chrono::Utc::now(),
+            tokens_used: None,
+            metadata: HashMap::new(),
+        })
+    }
+
+    async fn read_response(&self, _provider: Provider) -> Result<Option<PartialResponse>> {
+        self.check_crash()?;
+        let mut responses = self.partial_responses.lock().unwrap();
+        Ok(match responses.len() {
+            0 | 1 => responses.front().cloned(),
+            _ => responses.pop_front(),
+        })
+    }
+
+    async fn navigate(&self, url: &str) -> Result<()> {
+        let in_flight = self.navigations_in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.max_navigations_in_flight
+            .fetch_max(in_flight, Ordering::SeqCst);
+        // Yield so concurrent callers actually overlap
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        self.navigations_in_flight.fetch_sub(1, Ordering::SeqCst);
+        self.check_crash()?;
+        self.navigations.lock().unwrap().push(url.to_string());
+        self.forward_pages.lock().unwrap().clear();
+        Ok(())
+    }
+
+    async fn traverse(&self, step: HistoryStep) -> Result<()> {
+        self.check_crash()?;
+        let mut navigations = self.navigations.lock().unwrap();
+        let mut forward_pages = self.forward_pages.lock().unwrap();
+        match step {
+            // Like a browser, moving past either end of the history does nothing
+            HistoryStep::Back => {
+                if navigations.len() > 1 {
+                    forward_pages.extend(navigations.pop());
+                }
+            }
+            HistoryStep::Forward => navigations.extend(forward_pages.pop()),
+            HistoryStep::Reload { ignore_cache } => self.reloads.lock().unwrap().push(ignore_cache),
+        }
+        Ok(())
+    }
+
+    async fn set_extra_headers(&self, headers: &ExtraHeaders) -> Result<()> {
+        *self.extra_headers.lock().unwrap() = headers.clone();
+        Ok(())
+    }
+
+    async fn current_url(&self) -> Result<String> {
+        Ok(self
+            .navigations
+            .lock()
+            .unwrap()
+            .last()
+            .cloned()
+            .unwrap_or_else(|| "about:blank".into()))
+    }
+
+    async fn title(&self) -> Result<String> {
+        Ok("Stub Page".into())
+    }
+
+    async fn evaluate(&self, script: &str) -> Result<Value> {
+        self.check_crash()?;
+        self.scripts.lock().unwrap().push(script.to_string());
+        Ok(match &*self.eval_handler.lock().unwrap() {
+            Some(handler) => handler(script),
+            None => Value::Null,
+        })
+    }
+
+    async fn screenshot(&self) -> Result<Vec<u8>> {
+        // Just the PNG signature and header, sized like a real capture
+        let (width, height) = match *self.viewport.lock().unwrap() {
+            Some(viewport) => {
+                let scale = viewport.device_scale_factor.unwrap_or(1.0);
+                (
+                    (viewport.width as f64 * scale) as u32,
+                    (viewport.height as f64 * scale) as u32,
+                )
+            }
+            None => (1280, 720),
+        };
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend_from_slice(&width.to_be_bytes());
+        png.extend_from_slice(&height.to_be_bytes());
+        png.extend_from_slice(&[8, 6, 0, 0, 0]);
+        Ok(png)
+    }
+
+    async fn screenshot_element(&self, selector: &str) -> Result<Vec<u8>> {
+        self.element_screenshots
+            .lock()
+            .unwrap()
+            .push(selector.to_string());
+        // A 320×200 capture of the element
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend_from_slice(&320u32.to_be_bytes());
+        png.extend_from_slice(&200u32.to_be_bytes());
+        png.extend_from_slice(&[8, 6, 0, 0, 0]);
+        Ok(png)
+    }
+
+    fn provider_capabilities(&self, _provider: Provider) -> Option<ProviderCapabilities> {
+        Some(ProviderCapabilities::default())
+    }
+
+    async fn probe(&self, provider: Provider) -> Result<ProviderProbe> {
+        self.navigations
+            .lock()
+            .unwrap()
+            .push(provider.base_url().to_string());
+        Ok(ProviderProbe {
+            reachable: true,
+            url: provider.base_url().to_string(),
+            title: Some("Stub Page".into()),
+            input_ready: self.provider_ui_ready.load(Ordering::SeqCst),
+            error: None,
+        })
+    }
+
+    async fn close(&self) -> Result<()> {
+        self.closes.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn failed_providers(&self) -> Vec<(Provider, String)> {
+        self.failed_providers.lock().unwrap().clone()
+    }
+}
+
+/// Launcher handing out a shared [`StubBrowser`] and counting launches.
+#[derive(Default)]
+pub struct StubLauncher {
+    /// The browser returned by every launch.
+    pub browser: Arc<StubBrowser>,
+    /// Number of launches.
+    pub launches: AtomicUsize,
+    /// Options of the most recent launch.
+    pub last_options: Mutex<Option<LaunchOptions>>,
+    /// Profiles reported by the launcher's browser.
+    pub profiles: Mutex<Vec<String>>,
+    /// Number of times installed browsers were detected.
+    pub detections: AtomicUsize,
+    /// The operating system's default browser (default: unknown).
+    pub system_default: Mutex<Option<BrowserType>>,
+    /// Providers whose initialization fails at launch.
+    pub failing_inits: Mutex<Vec<Provider>>,
+    /// Provider initializations currently in progress.
+    pub inits_in_flight: AtomicUsize,
+    /// Most provider initializations ever in progress at once.
+    pub max_inits_in_flight: AtomicUsize,
+    /// Make the next launch fail.
+    pub fail_next_launch: AtomicBool,
+}
+
+impl StubLauncher {
+    /// Number of launches so far.
+    pub fn launch_count(&self) -> usize {
+        self.launches.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait::async_trait]
+impl BrowserLauncher for StubLauncher {
+    async fn launch(&self, options: &LaunchOptions) -> Result<Arc<dyn BrowserSession>> {
+        self.launches.fetch_add(1, Ordering::SeqCst);
+        *self.last_options.lock().unwrap() = Some(options.clone());
+        if self.fail_next_launch.swap(false, Ordering::SeqCst) {
+            return Err(Error::LaunchFailed("browser exited with 1".into()));
+        }
+        *self.browser.viewport.lock().unwrap() = options.viewport;
+        *self.browser.extra_headers.lock().unwrap() = options.extra_headers.clone();
+        if options.provider_init_concurrency > 0 {
+            // Like the real launcher: open every provider, keep what opened
+            let init = init_providers(
+                Provider::all(),
+                options.provider_init_concurrency,
+                |provider| async move {
+                    let in_flight = self.inits_in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    self.max_inits_in_flight
+                        .fetch_max(in_flight, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    self.inits_in_flight.fetch_sub(1, Ordering::SeqCst);
+                    if self.failing_inits.lock().unwrap().contains(&provider) {
+                        return Err(Error::LaunchFailed(format!("{} is down", provider)));
+                    }
+                    Ok(())
+                },
+            )
+            .await;
+            *self.browser.failed_providers.lock().unwrap() = init.failed;
+        }
+        Ok(self.browser.clone())
+    }
+
+    fn profiles(&self, _requested: Option<BrowserType>) -> Result<Vec<String>> {
+        Ok(self.profiles.lock().unwrap().clone())
+    }
+
+    fn detect_browsers(&self) -> Vec<BrowserInstallation> {
+        self.detections.fetch_add(1, Ordering::SeqCst);
+        vec![
+            BrowserInstallation {
+                browser_type: BrowserType::Chrome,
+                executable_path: "/usr/bin/google-chrome".into(),
+                user_data_dir: "/nonexistent/chrome".into(),
+                version: Some("131.0.6778.85".into()),
+            },
+            BrowserInstallation {
+                browser_type: BrowserType::Brave,
+                executable_path: "/usr/bin/brave-browser".into(),
+                user_data_dir: "/nonexistent/brave".into(),
+                version: Some("1.70.117".into()),
+            },
+        ]
+    }
+
+    fn system_default_browser(&self) -> Option<BrowserType> {
+        *self.system_default.lock().unwrap()
+    }
+}
+
+/// Launcher that always fails with the webpuppet error built by `error`.
+pub struct FailingLauncher {
+    /// Builds the error returned by every launch.
+    pub error: fn() -> webpuppet::Error,
+}
+
+#[async_trait::async_trait]
+impl BrowserLauncher for FailingLauncher {
+    async fn launch(&self, _options: &LaunchOptions) -> Result<Arc<dyn BrowserSession>> {
+        Err((self.error)().into())
+    }
+}
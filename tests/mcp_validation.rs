@@ -622,3 +622,81 @@ async fn test_pause_resume_workflow() {
 
     client.close().await;
 }
+
+// ============================================================================
+// Once Mode Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_once_mode_lists_tools_and_exits() {
+    let output = timeout(
+        Duration::from_secs(30),
+        Command::new(env!("CARGO_BIN_EXE_webpuppet-mcp"))
+            .args([
+                "--once",
+                r#"{"method":"tools/list"}"#,
+                "--denied-tools",
+                "webpuppet_prompt",
+            ])
+            .output(),
+    )
+    .await
+    .expect("once mode should exit on its own")
+    .unwrap();
+    assert!(output.status.success());
+
+    let response: JsonRpcResponse = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(response.jsonrpc, "2.0");
+    assert_eq!(response.id, Some(1));
+    let names: Vec<String> = response.result.unwrap()["tools"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|tool| tool["name"].as_str().unwrap().to_string())
+        .collect();
+    assert!(names.iter().any(|name| name == "webpuppet_navigate"));
+    // Policy flags still apply
+    assert!(!names.iter().any(|name| name == "webpuppet_prompt"));
+}
+
+#[tokio::test]
+async fn test_once_mode_prints_plain_results_unless_asked_for_markdown() {
+    let call = r#"{"method":"tools/call","params":{"name":"webpuppet_check_permission","arguments":{"operation":"Navigate"}}}"#;
+    for (flags, heading) in [
+        (&[][..], "Permission Check\n"),
+        (&["--result-format", "markdown"][..], "# Permission Check"),
+    ] {
+        let output = Command::new(env!("CARGO_BIN_EXE_webpuppet-mcp"))
+            .arg("--once")
+            .arg(call)
+            .args(flags)
+            .output()
+            .await
+            .unwrap();
+        assert!(output.status.success());
+
+        let response: JsonRpcResponse = serde_json::from_slice(&output.stdout).unwrap();
+        let text = response.result.unwrap()["content"][0]["text"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        assert!(text.starts_with(heading), "got {}", text);
+    }
+}
+
+#[tokio::test]
+async fn test_once_mode_fails_on_error_response() {
+    let output = Command::new(env!("CARGO_BIN_EXE_webpuppet-mcp"))
+        .args([
+            "--once",
+            r#"{"jsonrpc":"2.0","id":9,"method":"no/such/method"}"#,
+        ])
+        .output()
+        .await
+        .unwrap();
+    assert!(!output.status.success());
+
+    let response: JsonRpcResponse = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(response.id, Some(9));
+    assert_eq!(response.error.unwrap().code, -32601);
+}
@@ -0,0 +1,2175 @@
+//! In-process server tests.
+//!
+//! Drives `McpServer::handle_message` directly, without spawning the binary.
+
+mod common;
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+
+use webpuppet::{
+    BrowserDetector, InterventionReason, Operation, PermissionGuard, PermissionPolicy,
+};
+use webpuppet_mcp::harness::InProcessClient;
+use webpuppet_mcp::logging::{self, LogFormat};
+use webpuppet_mcp::protocol::{ContentItem, ToolCallResult, ToolDefinition};
+use webpuppet_mcp::protolog::ProtocolLog;
+use webpuppet_mcp::redact::{RedactingMakeWriter, Redactor};
+use webpuppet_mcp::tools::{Tool, ToolContext, ToolRegistry};
+use webpuppet_mcp::{Error, McpServer, ServerConfig};
+
+use common::StubLauncher;
+
+/// Log sink capturing formatted tracing output in memory.
+#[derive(Clone, Default)]
+struct LogBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl LogBuffer {
+    fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.0.lock().unwrap()).into_owned()
+    }
+}
+
+impl Write for LogBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for LogBuffer {
+    type Writer = LogBuffer;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+fn request(id: u64, method: &str, params: Value) -> String {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "method": method,
+        "params": params
+    })
+    .to_string()
+}
+
+async fn initialize(server: &McpServer) -> Value {
+    let response = server
+        .handle_message(&request(
+            1,
+            "initialize",
+            json!({
+                "protocolVersion": "2024-11-05",
+                "capabilities": {},
+                "clientInfo": {"name": "test", "version": "1.0"}
+            }),
+        ))
+        .await
+        .expect("initialize should respond");
+    assert!(response.error.is_none());
+    response.result.expect("initialize should succeed")
+}
+
+// ============================================================================
+// Redaction Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_cookie_values_never_logged() {
+    let config = ServerConfig::default();
+    let logs = LogBuffer::default();
+    let redactor = Arc::new(Redactor::new(&config.redaction));
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::DEBUG)
+        .with_ansi(false)
+        .with_writer(RedactingMakeWriter::new(logs.clone(), redactor))
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let server = McpServer::with_config(PermissionGuard::secure(), config);
+    initialize(&server).await;
+
+    let cookie_value = "s3cr3t-cookie-value-1234";
+    server
+        .handle_message(&request(
+            2,
+            "tools/call",
+            json!({
+                "name": "webpuppet_check_permission",
+                "arguments": {
+                    "operation": "Navigate",
+                    "url": format!("https://claude.ai/?session_id={}", cookie_value),
+                    "cookie": format!("sessionKey={}", cookie_value),
+                    "message": format!("my cookie is sessionKey={}", cookie_value)
+                }
+            }),
+        ))
+        .await;
+
+    let output = logs.contents();
+    assert!(output.contains("Received"), "expected debug traffic logs");
+    assert!(
+        !output.contains(cookie_value),
+        "cookie value leaked into logs:\n{}",
+        output
+    );
+}
+
+#[tokio::test]
+async fn test_prompt_bodies_logged_when_opted_in() {
+    let logs = LogBuffer::default();
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::DEBUG)
+        .with_ansi(false)
+        .with_writer(logs.clone())
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let mut config = ServerConfig::default();
+    config.redaction.log_prompts = true;
+    let server = McpServer::with_config(PermissionGuard::secure(), config);
+    initialize(&server).await;
+
+    server
+        .handle_message(&request(
+            2,
+            "tools/call",
+            json!({
+                "name": "webpuppet_check_permission",
+                "arguments": {"operation": "Navigate", "message": "explain io_uring"}
+            }),
+        ))
+        .await;
+
+    assert!(logs.contents().contains("explain io_uring"));
+}
+
+#[test]
+fn test_authorization_credentials_redacted_but_not_prose() {
+    let redactor = Redactor::default();
+
+    assert_eq!(
+        redactor.redact_text("Authorization: Bearer abc123"),
+        "Authorization: Bearer [REDACTED]"
+    );
+    assert_eq!(
+        redactor.redact_text("token sent as bearer dGhpc2lzYWxvbmdiZWFyZXJ0b2tlbg=="),
+        "token sent as bearer [REDACTED]"
+    );
+    for prose in [
+        "See the basic usage section",
+        "Basic auth is configured per host",
+        "Bearer tokens expire after an hour",
+    ] {
+        assert_eq!(redactor.redact_text(prose), prose);
+    }
+}
+
+#[tokio::test]
+async fn test_json_log_lines_carry_request_and_tool() {
+    let logs = LogBuffer::default();
+    let subscriber =
+        tracing_subscriber::registry().with(logging::fmt_layer(LogFormat::Json, logs.clone()));
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let server = McpServer::new();
+    initialize(&server).await;
+
+    // Not on the secure policy's domain allowlist, so the call fails and is logged
+    server
+        .handle_message(&request(
+            4,
+            "tools/call",
+            json!({"name": "webpuppet_navigate", "arguments": {"url": "https://example.com"}}),
+        ))
+        .await;
+
+    let output = logs.contents();
+    let line = output
+        .lines()
+        .find(|line| line.contains("webpuppet_navigate failed"))
+        .unwrap_or_else(|| panic!("no failure logged:\n{}", output));
+    let entry: Value = serde_json::from_str(line).expect("log line should be JSON");
+    assert_eq!(entry["level"], "ERROR");
+    assert_eq!(entry["span"]["name"], "tool_call");
+    assert_eq!(entry["span"]["request_id"], "4");
+    assert_eq!(entry["span"]["tool"], "webpuppet_navigate");
+}
+
+// ============================================================================
+// Capability Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_experimental_capabilities_list_features() {
+    let config = ServerConfig {
+        headless: false,
+        ..ServerConfig::default()
+    };
+    let server = McpServer::with_config(PermissionGuard::secure(), config);
+    let result = initialize(&server).await;
+
+    let capabilities = &result["capabilities"];
+    assert_eq!(capabilities["tools"]["listChanged"], false);
+
+    let webpuppet = &capabilities["experimental"]["webpuppet"];
+    assert_eq!(
+        webpuppet["features"],
+        json!({
+            "realScreenshots": true,
+            "jsEval": false,
+            "httpTransport": cfg!(feature = "http"),
+            "stdioTransport": cfg!(feature = "stdio"),
+            "visibleBrowser": true,
+        })
+    );
+    assert_eq!(webpuppet["defaultProvider"], "grok");
+}
+
+#[tokio::test]
+async fn test_initialize_reports_self_check() {
+    let server = McpServer::new();
+    let result = initialize(&server).await;
+
+    let check = &result["capabilities"]["experimental"]["webpuppet"]["selfCheck"];
+    let detected = BrowserDetector::detect_all();
+    assert_eq!(check["browserCount"], detected.len());
+    let browsers = check["browsers"].as_array().unwrap();
+    assert_eq!(browsers.len(), detected.len());
+    for (listed, browser) in browsers.iter().zip(&detected) {
+        assert_eq!(listed["type"], browser.browser_type.to_string());
+    }
+
+    let warnings = check["warnings"].as_array().unwrap();
+    assert_eq!(
+        detected.is_empty(),
+        warnings
+            .iter()
+            .any(|w| w.as_str().unwrap().contains("no supported browser"))
+    );
+    assert_eq!(check["profile"]["valid"], true);
+    assert_eq!(check["defaultProvider"]["name"], "grok");
+}
+
+#[tokio::test]
+async fn test_second_initialize_is_rejected() {
+    let server = McpServer::new();
+    initialize(&server).await;
+
+    let again = server
+        .handle_message(&request(
+            2,
+            "initialize",
+            json!({
+                "protocolVersion": "2024-11-05",
+                "capabilities": {},
+                "clientInfo": {"name": "other", "version": "2.0"}
+            }),
+        ))
+        .await
+        .unwrap();
+    let error = again.error.expect("a second initialize should fail");
+    assert_eq!(error.code, -32600);
+    assert!(error.message.contains("already initialized"));
+
+    // The first initialization still stands
+    let listed = server
+        .handle_message(&request(3, "tools/list", json!({})))
+        .await
+        .unwrap();
+    assert!(listed.error.is_none());
+}
+
+/// Initialize `server` as the client named `name`, then return the text of
+/// a `webpuppet_check_permission` call.
+async fn permission_report_for_client(server: &McpServer, name: &str) -> String {
+    let response = server
+        .handle_message(&request(
+            1,
+            "initialize",
+            json!({
+                "protocolVersion": "2024-11-05",
+                "capabilities": {},
+                "clientInfo": {"name": name, "version": "1.0"}
+            }),
+        ))
+        .await
+        .unwrap();
+    assert!(response.error.is_none());
+
+    let response = server
+        .handle_message(&request(
+            2,
+            "tools/call",
+            json!({"name": "webpuppet_check_permission", "arguments": {"operation": "Navigate"}}),
+        ))
+        .await
+        .unwrap();
+    response.result.unwrap()["content"][0]["text"]
+        .as_str()
+        .unwrap()
+        .to_string()
+}
+
+#[tokio::test]
+async fn test_configured_client_gets_its_result_format() {
+    let config = ServerConfig {
+        client_workarounds: HashMap::from([(
+            "Acme Desktop".to_string(),
+            "plain,max-result-bytes:300".parse().unwrap(),
+        )]),
+        ..ServerConfig::default()
+    };
+
+    // Matched ignoring case
+    let server = McpServer::with_config(PermissionGuard::secure(), config.clone());
+    let report = permission_report_for_client(&server, "acme desktop").await;
+    assert!(report.starts_with("Permission Check\n"), "got {}", report);
+    assert!(report.contains("[Truncated"), "got {}", report);
+
+    // Other clients keep the configured markdown
+    let server = McpServer::with_config(PermissionGuard::secure(), config);
+    let report = permission_report_for_client(&server, "other-host").await;
+    assert!(report.starts_with("# Permission Check"), "got {}", report);
+}
+
+#[tokio::test]
+async fn test_known_client_gets_its_result_format() {
+    // Built in, with no configuration
+    let server = McpServer::with_config(PermissionGuard::secure(), ServerConfig::default());
+    let report = permission_report_for_client(&server, "mcp-inspector").await;
+    assert!(report.starts_with("Permission Check\n"), "got {}", report);
+
+    // Configuration overrides the built-in entry
+    let config = ServerConfig {
+        client_workarounds: HashMap::from([(
+            "MCP-Inspector".to_string(),
+            "markdown".parse().unwrap(),
+        )]),
+        ..ServerConfig::default()
+    };
+    let server = McpServer::with_config(PermissionGuard::secure(), config);
+    let report = permission_report_for_client(&server, "mcp-inspector").await;
+    assert!(report.starts_with("# Permission Check"), "got {}", report);
+}
+
+// ============================================================================
+// Completion Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_completion_for_provider_argument() {
+    let server = McpServer::new();
+    initialize(&server).await;
+
+    let response = server
+        .handle_message(&request(
+            2,
+            "completion/complete",
+            json!({
+                "ref": {"type": "ref/prompt", "name": "webpuppet_prompt"},
+                "argument": {"name": "provider", "value": "g"}
+            }),
+        ))
+        .await
+        .unwrap();
+
+    let completion = &response.result.unwrap()["completion"];
+    assert_eq!(completion["values"], json!(["grok", "gemini"]));
+    assert_eq!(completion["total"], 2);
+    assert_eq!(completion["hasMore"], false);
+
+    // Arguments without known values complete to nothing rather than erroring
+    let response = server
+        .handle_message(&request(
+            3,
+            "completion/complete",
+            json!({
+                "ref": {"type": "ref/prompt", "name": "webpuppet_prompt"},
+                "argument": {"name": "message", "value": "hel"}
+            }),
+        ))
+        .await
+        .unwrap();
+
+    assert!(response.error.is_none());
+    assert_eq!(response.result.unwrap()["completion"]["values"], json!([]));
+}
+
+// ============================================================================
+// Backpressure Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_slow_client_bounds_notifications_but_not_responses() {
+    let config = ServerConfig {
+        notification_capacity: 16,
+        ..ServerConfig::default()
+    };
+    let server = Arc::new(McpServer::with_config(PermissionGuard::secure(), config));
+
+    let (mut client_in, server_in) = tokio::io::duplex(4096);
+    // Tiny pipe the client doesn't read from yet, so the writer stalls quickly
+    let (server_out, client_out) = tokio::io::duplex(64);
+
+    let serving = {
+        let server = server.clone();
+        tokio::spawn(async move { server.serve(BufReader::new(server_in), server_out).await })
+    };
+
+    for i in 0..10_000 {
+        server.notify("notifications/message", json!({"level": "info", "data": i}));
+    }
+
+    // Queued notifications plus at most one message held by the stalled writer
+    assert!(server.outbound().len() <= 16);
+    assert!(server.outbound().dropped() >= 10_000 - 16 - 1);
+
+    client_in
+        .write_all(format!("{}\n", request(7, "ping", json!({}))).as_bytes())
+        .await
+        .unwrap();
+
+    // Start reading: the response still gets through after the backlog
+    let mut lines = BufReader::new(client_out).lines();
+    let response = loop {
+        let line = lines
+            .next_line()
+            .await
+            .unwrap()
+            .expect("server output ended");
+        let message: Value = serde_json::from_str(&line).unwrap();
+        if message.get("id").is_some() {
+            break message;
+        }
+    };
+    assert_eq!(response["id"], 7);
+    assert_eq!(response["result"], json!({}));
+
+    drop(client_in);
+    serving.await.unwrap().unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_senders_never_interleave_output_lines() {
+    const SENDERS: usize = 16;
+    const PER_SENDER: usize = 200;
+    const PINGS: u64 = 50;
+
+    let config = ServerConfig {
+        notification_capacity: SENDERS * PER_SENDER,
+        ..ServerConfig::default()
+    };
+    let server = Arc::new(McpServer::with_config(PermissionGuard::secure(), config));
+
+    let (mut client_in, server_in) = tokio::io::duplex(4096);
+    // A small pipe splits every message across several writes
+    let (server_out, client_out) = tokio::io::duplex(256);
+    let serving = {
+        let server = server.clone();
+        tokio::spawn(async move { server.serve(BufReader::new(server_in), server_out).await })
+    };
+
+    let senders: Vec<_> = (0..SENDERS)
+        .map(|sender| {
+            let server = server.clone();
+            tokio::spawn(async move {
+                for i in 0..PER_SENDER {
+                    let data = json!({"sender": sender, "i": i, "padding": "é\"\n".repeat(40)});
+                    server.notify(
+                        "notifications/message",
+                        json!({"level": "info", "data": data}),
+                    );
+                    tokio::task::yield_now().await;
+                }
+            })
+        })
+        .collect();
+    let pings = tokio::spawn(async move {
+        for id in 0..PINGS {
+            client_in
+                .write_all(format!("{}\n", request(id, "ping", json!({}))).as_bytes())
+                .await
+                .unwrap();
+        }
+        client_in
+    });
+
+    let mut lines = BufReader::new(client_out).lines();
+    let (mut notifications, mut responses) = (0, 0);
+    while notifications < SENDERS * PER_SENDER || responses < PINGS {
+        let line = lines
+            .next_line()
+            .await
+            .unwrap()
+            .expect("server output ended");
+        let message: Value = serde_json::from_str(&line)
+            .unwrap_or_else(|e| panic!("corrupted line {:?}: {}", line, e));
+        if message.get("id").is_some() {
+            responses += 1;
+        } else {
+            notifications += 1;
+        }
+    }
+    for sender in senders {
+        sender.await.unwrap();
+    }
+    assert_eq!(server.outbound().dropped(), 0);
+
+    drop(pings.await.unwrap());
+    serving.await.unwrap().unwrap();
+}
+
+// ============================================================================
+// Session Isolation Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_pausing_one_session_does_not_affect_another() {
+    let server = McpServer::new();
+    initialize(&server).await;
+
+    let call = |id, name: &str| request(id, "tools/call", json!({"name": name, "arguments": {}}));
+    let state = |response: Option<webpuppet_mcp::JsonRpcResponse>| -> Value {
+        let result = response.unwrap().result.unwrap();
+        let structured = result["content"][1]["text"].as_str().unwrap().to_string();
+        serde_json::from_str::<Value>(&structured).unwrap()["state"].clone()
+    };
+
+    server
+        .handle_session_message("a", &call(2, "webpuppet_pause"))
+        .await;
+
+    let a = server
+        .handle_session_message("a", &call(3, "webpuppet_intervention_status"))
+        .await;
+    let b = server
+        .handle_session_message("b", &call(4, "webpuppet_intervention_status"))
+        .await;
+    let default = server
+        .handle_message(&call(5, "webpuppet_intervention_status"))
+        .await;
+
+    assert_eq!(state(a), "waiting_for_human");
+    assert_eq!(state(b), "running");
+    assert_eq!(state(default), "running");
+
+    server.close_session("a").await.unwrap();
+    let a = server
+        .handle_session_message("a", &call(6, "webpuppet_intervention_status"))
+        .await;
+    assert_eq!(state(a), "running");
+}
+
+#[tokio::test]
+async fn test_held_click_is_confirmed_over_the_wire() {
+    let launcher = Arc::new(StubLauncher::default());
+    launcher.browser.on_evaluate(|script| {
+        if script.contains("formAction") {
+            json!({"label": "Delete this repository", "href": null, "formAction": null, "formMethod": null})
+        } else {
+            json!({})
+        }
+    });
+    let config = ServerConfig {
+        confirm_destructive: true,
+        ..ServerConfig::default()
+    };
+    let context =
+        ToolContext::with_config(PermissionGuard::new(PermissionPolicy::permissive()), config)
+            .with_launcher(launcher.clone());
+    let (mut client_in, mut lines) = serve_initialized(McpServer::with_registry(
+        ToolRegistry::with_context(context),
+    ))
+    .await;
+
+    let click = request(
+        2,
+        "tools/call",
+        json!({
+            "name": "webpuppet_run_sequence",
+            "arguments": {"steps": [{"action": "click", "selector": "#delete-repo"}]}
+        }),
+    );
+    client_in
+        .write_all(format!("{}\n", click).as_bytes())
+        .await
+        .unwrap();
+
+    // The status is answered while the click is held
+    let mut id = 3;
+    loop {
+        let status = request(
+            id,
+            "tools/call",
+            json!({"name": "webpuppet_intervention_status", "arguments": {}}),
+        );
+        client_in
+            .write_all(format!("{}\n", status).as_bytes())
+            .await
+            .unwrap();
+        let response: Value =
+            serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert_eq!(response["id"], id);
+        if response["result"]["content"][0]["text"]
+            .as_str()
+            .unwrap()
+            .contains("Confirm clicking `#delete-repo`")
+        {
+            break;
+        }
+        id += 1;
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+
+    let complete = request(
+        100,
+        "tools/call",
+        json!({"name": "webpuppet_intervention_complete", "arguments": {"success": true}}),
+    );
+    client_in
+        .write_all(format!("{}\n", complete).as_bytes())
+        .await
+        .unwrap();
+
+    let completed: Value =
+        serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+    assert_eq!(completed["id"], 100);
+    assert!(completed["error"].is_null());
+    let clicked: Value = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+    assert_eq!(clicked["id"], 2);
+    assert_eq!(clicked["result"]["structuredContent"]["failed"], 0);
+    assert!(launcher
+        .browser
+        .scripts
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|script| script.contains(r##""#delete-repo", "click""##)));
+}
+
+// ============================================================================
+// Shutdown Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_client_going_away_is_a_clean_shutdown() {
+    let server = Arc::new(McpServer::new());
+
+    let (mut client_in, server_in) = tokio::io::duplex(4096);
+    let (server_out, client_out) = tokio::io::duplex(4096);
+
+    let serving = {
+        let server = server.clone();
+        tokio::spawn(async move { server.serve(BufReader::new(server_in), server_out).await })
+    };
+
+    client_in
+        .write_all(format!("{}\n", request(1, "ping", json!({}))).as_bytes())
+        .await
+        .unwrap();
+    let mut lines = BufReader::new(client_out).lines();
+    assert!(lines.next_line().await.unwrap().is_some());
+
+    // The host stops reading mid-session; the next response hits a broken pipe
+    drop(lines);
+    client_in
+        .write_all(format!("{}\n", request(2, "ping", json!({}))).as_bytes())
+        .await
+        .unwrap();
+
+    let result = tokio::time::timeout(std::time::Duration::from_secs(5), serving)
+        .await
+        .expect("server should stop once the client is gone")
+        .unwrap();
+    assert!(result.is_ok(), "expected clean shutdown, got {:?}", result);
+}
+
+#[tokio::test]
+async fn test_input_eof_is_a_clean_shutdown() {
+    let server = McpServer::new();
+    let (client_in, server_in) = tokio::io::duplex(64);
+    drop(client_in);
+
+    let result = server
+        .serve(BufReader::new(server_in), tokio::io::sink())
+        .await;
+    assert!(result.is_ok());
+}
+
+// ============================================================================
+// Locked-Down Mode Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_denied_tool_is_disabled_even_if_policy_allows_it() {
+    let config = ServerConfig {
+        denied_tools: vec!["webpuppet_list_providers".into()],
+        ..ServerConfig::default()
+    };
+    let server =
+        McpServer::with_config(PermissionGuard::new(PermissionPolicy::permissive()), config);
+    let client = InProcessClient::new(&server);
+    client.initialize().await.unwrap();
+
+    let tools = client.list_tools().await.unwrap();
+    assert!(!tools.iter().any(|t| t.name == "webpuppet_list_providers"));
+    assert!(tools.iter().any(|t| t.name == "webpuppet_screen_text"));
+
+    let err = client
+        .call_tool("webpuppet_list_providers", json!({}))
+        .await
+        .unwrap_err();
+    assert_eq!(err.code, -32014);
+    assert_eq!(
+        err.message,
+        "tool webpuppet_list_providers disabled by policy"
+    );
+    assert_eq!(err.data.unwrap()["category"], "disabled");
+}
+
+#[tokio::test]
+async fn test_allowed_methods_disable_everything_else() {
+    let config = ServerConfig {
+        allowed_methods: Some(vec!["tools/list".into(), "ping".into()]),
+        ..ServerConfig::default()
+    };
+    let server = McpServer::with_config(PermissionGuard::secure(), config);
+    let client = InProcessClient::new(&server);
+    client.initialize().await.unwrap();
+
+    assert!(!client.list_tools().await.unwrap().is_empty());
+    client.request("ping", json!({})).await.unwrap();
+
+    let err = client
+        .call_tool("webpuppet_screen_text", json!({"text": "hello"}))
+        .await
+        .unwrap_err();
+    assert_eq!(err.code, -32014);
+    assert_eq!(err.message, "method tools/call disabled by policy");
+}
+
+// ============================================================================
+// Heartbeat Tests
+// ============================================================================
+
+/// Tool that takes a while to finish.
+struct SlowTool;
+
+#[async_trait::async_trait]
+impl Tool for SlowTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "slow".into(),
+            description: "Sleeps for a while".into(),
+            input_schema: json!({"type": "object"}),
+        }
+    }
+
+    async fn execute(
+        &self,
+        _arguments: Value,
+        _context: &ToolContext,
+    ) -> webpuppet_mcp::Result<ToolCallResult> {
+        tokio::time::sleep(std::time::Duration::from_millis(550)).await;
+        Ok(ToolCallResult {
+            content: vec![ContentItem::text("done")],
+            is_error: false,
+            structured_content: None,
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_heartbeats_during_long_tool_call() {
+    let config = ServerConfig {
+        heartbeat_interval: std::time::Duration::from_millis(100),
+        ..ServerConfig::default()
+    };
+    let mut registry =
+        ToolRegistry::with_context(ToolContext::with_config(PermissionGuard::secure(), config));
+    registry.register(Arc::new(SlowTool));
+    let server = Arc::new(McpServer::with_registry(registry));
+
+    let (mut client_in, server_in) = tokio::io::duplex(4096);
+    let (server_out, client_out) = tokio::io::duplex(4096);
+    let serving = {
+        let server = server.clone();
+        tokio::spawn(async move { server.serve(BufReader::new(server_in), server_out).await })
+    };
+    let mut lines = BufReader::new(client_out).lines();
+
+    let initialize = request(
+        1,
+        "initialize",
+        json!({
+            "protocolVersion": "2024-11-05",
+            "capabilities": {},
+            "clientInfo": {"name": "test", "version": "1.0"}
+        }),
+    );
+    client_in
+        .write_all(format!("{}\n", initialize).as_bytes())
+        .await
+        .unwrap();
+    lines.next_line().await.unwrap().unwrap();
+
+    // Idle: no heartbeats
+    tokio::time::sleep(std::time::Duration::from_millis(250)).await;
+    client_in
+        .write_all(
+            format!(
+                "{}\n",
+                request(2, "tools/call", json!({"name": "slow", "arguments": {}}))
+            )
+            .as_bytes(),
+        )
+        .await
+        .unwrap();
+
+    let mut heartbeats = Vec::new();
+    let response = loop {
+        let line = lines.next_line().await.unwrap().unwrap();
+        // Every line is a complete message
+        let message: Value = serde_json::from_str(&line).unwrap();
+        if message.get("id").is_some() {
+            break message;
+        }
+        assert_eq!(message["method"], "notifications/message");
+        heartbeats.push(message["params"]["data"].clone());
+    };
+
+    assert_eq!(response["id"], 2);
+    assert!(response["result"].is_object());
+    // 550 ms at a 100 ms interval; allow for scheduling jitter
+    assert!(
+        (3..=6).contains(&heartbeats.len()),
+        "got {} heartbeats",
+        heartbeats.len()
+    );
+    assert_eq!(heartbeats[0]["heartbeat"], 1);
+    assert!(heartbeats.iter().all(|h| h["reason"] == "tool_call"));
+
+    drop(client_in);
+    serving.await.unwrap().unwrap();
+}
+
+// ============================================================================
+// Protocol Log Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_protocol_log_records_both_directions() {
+    let buffer = LogBuffer::default();
+    let server = McpServer::new().with_protocol_log(ProtocolLog::new(buffer.clone()));
+
+    let initialize = request(
+        1,
+        "initialize",
+        json!({
+            "protocolVersion": "2024-11-05",
+            "capabilities": {},
+            "clientInfo": {"name": "test", "version": "1.0"}
+        }),
+    );
+    let initialized = json!({"jsonrpc": "2.0", "method": "notifications/initialized"}).to_string();
+    let list = request(2, "tools/list", json!({}));
+    let input = format!("{}\n{}\n{}\n{{not json\n", initialize, initialized, list);
+
+    server
+        .serve(BufReader::new(input.as_bytes()), tokio::io::sink())
+        .await
+        .unwrap();
+
+    let records: Vec<Value> = buffer
+        .contents()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let messages = |direction: &str| -> Vec<String> {
+        records
+            .iter()
+            .filter(|r| r["direction"] == direction)
+            .map(|r| r["message"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    // Inbound lines are kept verbatim, including the one that failed to parse
+    assert_eq!(
+        messages("in"),
+        vec![initialize, initialized, list, "{not json".to_string()]
+    );
+
+    let out: Vec<Value> = messages("out")
+        .iter()
+        .map(|m| serde_json::from_str(m).unwrap())
+        .collect();
+    assert_eq!(out.len(), 3);
+    assert_eq!(out[0]["id"], 1);
+    assert!(out[1]["result"]["tools"].is_array());
+    assert_eq!(out[2]["error"]["code"], -32700);
+    assert!(records.iter().all(|r| r["ts"].is_string()));
+}
+
+// ============================================================================
+// Custom Registry Tests
+// ============================================================================
+
+/// Tool that echoes its arguments back.
+struct EchoTool;
+
+#[async_trait::async_trait]
+impl Tool for EchoTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "echo".into(),
+            description: "Echo the arguments back.".into(),
+            input_schema: json!({"type": "object", "properties": {}, "required": []}),
+        }
+    }
+
+    async fn execute(
+        &self,
+        arguments: Value,
+        _context: &ToolContext,
+    ) -> webpuppet_mcp::Result<ToolCallResult> {
+        Ok(ToolCallResult {
+            content: vec![ContentItem::text(arguments.to_string())],
+            is_error: false,
+            structured_content: Some(arguments),
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_custom_tool_is_callable_through_handle_message() {
+    let mut registry = ToolRegistry::with_context(ToolContext::new(PermissionGuard::secure()));
+    registry.register(Arc::new(EchoTool));
+    let server = McpServer::with_registry(registry);
+    initialize(&server).await;
+
+    let listed = server
+        .handle_message(&request(2, "tools/list", json!({})))
+        .await
+        .unwrap()
+        .result
+        .unwrap();
+    assert!(listed["tools"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|tool| tool["name"] == "echo"));
+    let echo = listed["tools"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|tool| tool["name"] == "echo")
+        .unwrap();
+    assert_eq!(echo["_meta"]["webpuppet/requiredOperations"], json!([]));
+
+    let response = server
+        .handle_message(&request(
+            3,
+            "tools/call",
+            json!({"name": "echo", "arguments": {"say": "hello"}}),
+        ))
+        .await
+        .unwrap();
+    let result = response.result.expect("echo should succeed");
+    assert_eq!(result["isError"], false);
+    assert_eq!(result["structuredContent"]["say"], "hello");
+}
+
+#[tokio::test]
+async fn test_tools_list_by_name_returns_one_schema() {
+    let server = McpServer::new();
+    initialize(&server).await;
+
+    let listed = server
+        .handle_message(&request(
+            2,
+            "tools/list",
+            json!({"name": "webpuppet_prompt"}),
+        ))
+        .await
+        .unwrap()
+        .result
+        .unwrap();
+    let tools = listed["tools"].as_array().unwrap();
+    assert_eq!(tools.len(), 1);
+    let prompt = &tools[0];
+    assert_eq!(prompt["name"], "webpuppet_prompt");
+    assert!(!prompt["description"].as_str().unwrap().is_empty());
+    let schema = &prompt["inputSchema"];
+    assert_eq!(schema["required"], json!(["message"]));
+    // Optional since --default-provider, but still offered with its choices
+    assert!(schema["properties"]["provider"]["enum"]
+        .as_array()
+        .unwrap()
+        .contains(&json!("claude")));
+    assert_eq!(
+        prompt["_meta"]["webpuppet/requiredOperations"],
+        json!(["SendPrompt"])
+    );
+
+    let error = server
+        .handle_message(&request(3, "tools/list", json!({"name": "webpuppet_nope"})))
+        .await
+        .unwrap()
+        .error
+        .unwrap();
+    assert_eq!(error.code, -32602);
+    assert!(error.message.contains("webpuppet_nope"));
+}
+
+/// Tool with a bug.
+struct PanicTool;
+
+#[async_trait::async_trait]
+impl Tool for PanicTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "panic".into(),
+            description: "Panics.".into(),
+            input_schema: json!({"type": "object"}),
+        }
+    }
+
+    async fn execute(
+        &self,
+        _arguments: Value,
+        _context: &ToolContext,
+    ) -> webpuppet_mcp::Result<ToolCallResult> {
+        panic!("index out of bounds");
+    }
+}
+
+#[tokio::test]
+async fn test_panicking_tool_fails_its_call_only() {
+    let mut registry = ToolRegistry::with_context(ToolContext::new(PermissionGuard::secure()));
+    registry.register(Arc::new(PanicTool));
+    let server = McpServer::with_registry(registry);
+    initialize(&server).await;
+
+    let response = server
+        .handle_message(&request(
+            2,
+            "tools/call",
+            json!({"name": "panic", "arguments": {}}),
+        ))
+        .await
+        .unwrap();
+    let error = response.error.expect("a panic should fail the call");
+    assert_eq!(error.code, -32603);
+    assert!(error.message.contains("panicked: index out of bounds"));
+
+    let response = server
+        .handle_message(&request(3, "ping", json!({})))
+        .await
+        .unwrap();
+    assert!(response.error.is_none());
+    assert_eq!(response.result, Some(json!({})));
+}
+
+#[tokio::test]
+async fn test_tools_list_advertises_required_operations() {
+    let server = McpServer::with_config(
+        PermissionGuard::new(PermissionPolicy::permissive()),
+        ServerConfig::default(),
+    );
+    initialize(&server).await;
+
+    let listed = server
+        .handle_message(&request(2, "tools/list", json!({})))
+        .await
+        .unwrap()
+        .result
+        .unwrap();
+    let operations = |name: &str| {
+        listed["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|tool| tool["name"] == name)
+            .unwrap()["_meta"]["webpuppet/requiredOperations"]
+            .clone()
+    };
+    assert_eq!(operations("webpuppet_prompt"), json!(["SendPrompt"]));
+    assert_eq!(operations("webpuppet_navigate"), json!(["Navigate"]));
+    assert_eq!(operations("webpuppet_list_providers"), json!([]));
+}
+
+// ============================================================================
+// In-Process Harness Tests
+// ============================================================================
+
+/// Tool that always fails with a permission error.
+struct DeniedTool;
+
+#[async_trait::async_trait]
+impl Tool for DeniedTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "denied".into(),
+            description: "Always denied.".into(),
+            input_schema: json!({"type": "object", "properties": {}, "required": []}),
+        }
+    }
+
+    async fn execute(
+        &self,
+        _arguments: Value,
+        _context: &ToolContext,
+    ) -> webpuppet_mcp::Result<ToolCallResult> {
+        Err(Error::PermissionDenied("not today".into()))
+    }
+}
+
+fn mock_server() -> McpServer {
+    let mut registry = ToolRegistry::with_context(ToolContext::new(PermissionGuard::secure()));
+    registry.register(Arc::new(EchoTool));
+    registry.register(Arc::new(DeniedTool));
+    McpServer::with_registry(registry)
+}
+
+#[tokio::test]
+async fn test_harness_runs_initialize_list_call() {
+    let server = mock_server();
+    let client = InProcessClient::new(&server);
+
+    let init = client.initialize().await.unwrap();
+    assert_eq!(init.server_info.name, "webpuppet-mcp");
+
+    let names: Vec<String> = client
+        .list_tools()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|tool| tool.name)
+        .collect();
+    assert!(names.contains(&"echo".to_string()));
+    assert!(names.contains(&"webpuppet_check_permission".to_string()));
+
+    let result = client.call_tool("echo", json!({"n": 1})).await.unwrap();
+    assert!(!result.is_error);
+    assert_eq!(result.structured_content, Some(json!({"n": 1})));
+
+    // Built-in tools that don't need a browser work too
+    let result = client
+        .call_tool(
+            "webpuppet_check_permission",
+            json!({"operation": "Navigate"}),
+        )
+        .await
+        .unwrap();
+    assert!(!result.is_error);
+}
+
+#[tokio::test]
+async fn test_harness_requires_initialize_first() {
+    let server = mock_server();
+    let client = InProcessClient::new(&server);
+
+    let err = client.list_tools().await.unwrap_err();
+    assert!(err.message.contains("not initialized"), "got {:?}", err);
+
+    let err = client.call_tool("echo", json!({})).await.unwrap_err();
+    assert!(err.message.contains("not initialized"), "got {:?}", err);
+}
+
+#[tokio::test]
+async fn test_harness_surfaces_tool_errors() {
+    let server = mock_server();
+    let client = InProcessClient::new(&server);
+    client.initialize().await.unwrap();
+
+    let err = client.call_tool("missing", json!({})).await.unwrap_err();
+    assert_eq!(err.code, -32601);
+
+    let err = client.call_tool("denied", json!({})).await.unwrap_err();
+    assert_eq!(err.code, -32000);
+    assert!(err.message.contains("not today"));
+    assert_eq!(err.data.unwrap()["category"], "permission_denied");
+
+    let err = client
+        .request("no/such/method", json!({}))
+        .await
+        .unwrap_err();
+    assert_eq!(err.code, -32601);
+}
+
+// ============================================================================
+// Streaming Tests
+// ============================================================================
+
+/// Serve a server backed by `launcher` over in-memory pipes, after
+/// initializing it. Returns the client's input and output.
+async fn serve_stub(
+    launcher: Arc<StubLauncher>,
+) -> (
+    tokio::io::DuplexStream,
+    tokio::io::Lines<BufReader<tokio::io::DuplexStream>>,
+) {
+    let context = ToolContext::new(PermissionGuard::secure()).with_launcher(launcher);
+    serve_initialized(McpServer::with_registry(ToolRegistry::with_context(
+        context,
+    )))
+    .await
+}
+
+/// Serve `server` over in-memory pipes, after initializing it. Returns the
+/// client's input and output.
+async fn serve_initialized(
+    server: McpServer,
+) -> (
+    tokio::io::DuplexStream,
+    tokio::io::Lines<BufReader<tokio::io::DuplexStream>>,
+) {
+    let (mut client_in, server_in) = tokio::io::duplex(4096);
+    let (server_out, client_out) = tokio::io::duplex(4096);
+    tokio::spawn(async move { server.serve(BufReader::new(server_in), server_out).await });
+
+    let init = request(
+        1,
+        "initialize",
+        json!({
+            "protocolVersion": "2024-11-05",
+            "capabilities": {},
+            "clientInfo": {"name": "test", "version": "1.0"}
+        }),
+    );
+    client_in
+        .write_all(format!("{}\n", init).as_bytes())
+        .await
+        .unwrap();
+    let mut lines = BufReader::new(client_out).lines();
+    lines
+        .next_line()
+        .await
+        .unwrap()
+        .expect("initialize response");
+    (client_in, lines)
+}
+
+fn streaming_prompt(id: u64) -> String {
+    request(
+        id,
+        "tools/call",
+        json!({
+            "name": "webpuppet_prompt",
+            "arguments": {"provider": "claude", "message": "Say hello"},
+            "_meta": {"progressToken": "stream-1"}
+        }),
+    )
+}
+
+#[tokio::test]
+async fn test_prompt_streams_chunks_before_result() {
+    let launcher = Arc::new(StubLauncher::default());
+    *launcher.browser.stream_chunks.lock().unwrap() =
+        vec!["Hello".into(), ", streaming".into(), " world".into()];
+    let (mut client_in, mut lines) = serve_stub(launcher).await;
+
+    client_in
+        .write_all(format!("{}\n", streaming_prompt(2)).as_bytes())
+        .await
+        .unwrap();
+
+    let mut chunks = Vec::new();
+    let response = loop {
+        let line = lines
+            .next_line()
+            .await
+            .unwrap()
+            .expect("server output ended");
+        let message: Value = serde_json::from_str(&line).unwrap();
+        if message.get("id").is_some() {
+            break message;
+        }
+        assert_eq!(message["method"], "notifications/progress");
+        assert_eq!(message["params"]["progressToken"], "stream-1");
+        assert_eq!(message["params"]["progress"], chunks.len() + 1);
+        chunks.push(message["params"]["message"].as_str().unwrap().to_string());
+    };
+
+    assert!(
+        chunks.len() >= 2,
+        "expected several chunks, got {:?}",
+        chunks
+    );
+    assert_eq!(chunks.concat(), "Hello, streaming world");
+    assert_eq!(response["id"], 2);
+    assert_eq!(
+        response["result"]["content"][0]["text"],
+        "Hello, streaming world"
+    );
+}
+
+#[tokio::test]
+async fn test_cancelling_a_streaming_prompt_closes_the_browser() {
+    let launcher = Arc::new(StubLauncher::default());
+    *launcher.browser.stream_chunks.lock().unwrap() = vec!["Partial".into()];
+    launcher.browser.stream_stalls.store(true, Ordering::SeqCst);
+    let (mut client_in, mut lines) = serve_stub(launcher.clone()).await;
+
+    client_in
+        .write_all(format!("{}\n", streaming_prompt(2)).as_bytes())
+        .await
+        .unwrap();
+    let chunk: Value = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+    assert_eq!(chunk["params"]["message"], "Partial");
+
+    let cancel = json!({
+        "jsonrpc": "2.0",
+        "method": "notifications/cancelled",
+        "params": {"requestId": 2, "reason": "user aborted"}
+    });
+    client_in
+        .write_all(format!("{}\n{}\n", cancel, request(3, "ping", json!({}))).as_bytes())
+        .await
+        .unwrap();
+
+    // The cancelled call gets no response; the server moves on
+    let next: Value = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+    assert_eq!(next["id"], 3);
+    assert_eq!(launcher.browser.closes.load(Ordering::SeqCst), 1);
+}
+
+// ============================================================================
+// Result Size Cap Tests
+// ============================================================================
+
+/// Tool returning a lot of text, repeated in its structured content, an
+/// image and more text needing escapes.
+struct VerboseTool;
+
+#[async_trait::async_trait]
+impl Tool for VerboseTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "verbose".into(),
+            description: "Returns 10 kB of text, a 2 kB image and 5 kB more text".into(),
+            input_schema: json!({"type": "object"}),
+        }
+    }
+
+    async fn execute(
+        &self,
+        _arguments: Value,
+        _context: &ToolContext,
+    ) -> webpuppet_mcp::Result<ToolCallResult> {
+        Ok(ToolCallResult {
+            content: vec![
+                ContentItem::text("é".repeat(5_000)),
+                ContentItem::image("A".repeat(2_000), "image/png"),
+                ContentItem::text("\"quoted\"\n".repeat(500)),
+            ],
+            is_error: false,
+            structured_content: Some(json!({"kind": "verbose", "text": "é".repeat(5_000)})),
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_oversized_results_are_truncated_with_metadata() {
+    let config = ServerConfig {
+        max_result_bytes: 1_001,
+        ..ServerConfig::default()
+    };
+    let mut registry =
+        ToolRegistry::with_context(ToolContext::with_config(PermissionGuard::secure(), config));
+    registry.register(Arc::new(VerboseTool));
+    let server = McpServer::with_registry(registry);
+    initialize(&server).await;
+
+    let result = server
+        .handle_message(&request(
+            2,
+            "tools/call",
+            json!({"name": "verbose", "arguments": {}}),
+        ))
+        .await
+        .unwrap()
+        .result
+        .expect("verbose should succeed");
+
+    // The whole serialized result counts: 15 kB of text, 2 kB of image
+    // data and the structured content
+    let original = serde_json::to_string(
+        &VerboseTool
+            .execute(json!({}), &ToolContext::new(PermissionGuard::secure()))
+            .await
+            .unwrap(),
+    )
+    .unwrap()
+    .len();
+    let notice = format!("[Truncated: result was {} bytes, limit is 1001]", original);
+    assert!(serde_json::to_string(&result).unwrap().len() <= 1_001);
+
+    // Long structured fields are cut and named
+    let structured = &result["structuredContent"];
+    assert_eq!(structured["kind"], "verbose");
+    assert_eq!(structured["text"], "é".repeat(128));
+    assert_eq!(
+        structured["truncation"],
+        json!({"originalBytes": original, "maxBytes": 1_001, "structuredFields": ["/text"]})
+    );
+
+    // The text gets what is left, cut on a character boundary, and carries
+    // the notice once
+    let text = result["content"][0]["text"].as_str().unwrap();
+    let (kept, cut_notice) = text.split_once("\n\n").unwrap();
+    assert_eq!(cut_notice, notice);
+    assert!(!kept.is_empty());
+    assert!(kept.chars().all(|c| c == 'é'));
+
+    // Room was kept for the image's placeholder; the text after it is
+    // dropped rather than given a second notice
+    assert_eq!(result["content"].as_array().unwrap().len(), 2);
+    assert_eq!(result["content"][1]["type"], "text");
+    assert_eq!(
+        result["content"][1]["text"],
+        "[Image omitted: 2000 bytes of data]"
+    );
+}
+
+// ============================================================================
+// Audit Resource Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_audit_subscription_reports_denied_operations() {
+    let server = McpServer::new();
+    let capabilities = initialize(&server).await["capabilities"].clone();
+    assert_eq!(capabilities["resources"]["subscribe"], true);
+
+    let listed = server
+        .handle_message(&request(2, "resources/list", json!({})))
+        .await
+        .unwrap()
+        .result
+        .unwrap();
+    assert_eq!(listed["resources"][0]["uri"], "webpuppet://audit");
+
+    let subscribed = server
+        .handle_message(&request(
+            3,
+            "resources/subscribe",
+            json!({"uri": "webpuppet://audit"}),
+        ))
+        .await
+        .unwrap();
+    assert!(subscribed.error.is_none());
+
+    // Not on the secure policy's domain allowlist
+    let denied = server
+        .handle_message(&request(
+            4,
+            "tools/call",
+            json!({
+                "name": "webpuppet_navigate",
+                "arguments": {"url": "https://example.com/?token=hunter2"}
+            }),
+        ))
+        .await
+        .unwrap();
+    assert!(denied.error.is_some());
+
+    let update: Value = serde_json::from_str(&server.outbound().next().await.unwrap()).unwrap();
+    assert_eq!(update["method"], "notifications/resources/updated");
+    assert_eq!(update["params"]["uri"], "webpuppet://audit");
+    let decision = &update["params"]["decision"];
+    assert_eq!(decision["operation"], "Navigate");
+    assert_eq!(decision["allowed"], false);
+    assert!(!decision["url"].as_str().unwrap().contains("hunter2"));
+
+    // Reading the resource returns the whole log
+    let read = server
+        .handle_message(&request(
+            5,
+            "resources/read",
+            json!({"uri": "webpuppet://audit"}),
+        ))
+        .await
+        .unwrap()
+        .result
+        .unwrap();
+    let log: Value = serde_json::from_str(read["contents"][0]["text"].as_str().unwrap()).unwrap();
+    assert_eq!(log.as_array().unwrap().last().unwrap()["allowed"], false);
+
+    server
+        .handle_message(&request(
+            6,
+            "resources/unsubscribe",
+            json!({"uri": "webpuppet://audit"}),
+        ))
+        .await;
+    server
+        .handle_message(&request(
+            7,
+            "tools/call",
+            json!({"name": "webpuppet_navigate", "arguments": {"url": "https://example.com"}}),
+        ))
+        .await;
+    assert!(server.outbound().is_empty());
+}
+
+// ============================================================================
+// Intervention Correlation Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_intervention_names_the_request_it_blocks() {
+    let launcher = Arc::new(StubLauncher::default());
+    *launcher.browser.intervention.lock().unwrap() =
+        Some(InterventionReason::Captcha { captcha_type: None });
+    let context = ToolContext::new(PermissionGuard::secure()).with_launcher(launcher.clone());
+    let server = McpServer::with_registry(ToolRegistry::with_context(context));
+    initialize(&server).await;
+
+    let blocked = server
+        .handle_message(&request(
+            7,
+            "tools/call",
+            json!({
+                "name": "webpuppet_prompt",
+                "arguments": {"provider": "claude", "message": "Hi"}
+            }),
+        ))
+        .await
+        .unwrap()
+        .result
+        .unwrap();
+    assert_eq!(blocked["isError"], true);
+
+    let status = server
+        .handle_message(&request(
+            8,
+            "tools/call",
+            json!({"name": "webpuppet_intervention_status", "arguments": {}}),
+        ))
+        .await
+        .unwrap()
+        .result
+        .unwrap();
+    let blocking = &status["structuredContent"]["blocking"];
+    assert_eq!(blocking["request_id"], 7);
+    assert_eq!(blocking["tool"], "webpuppet_prompt");
+    assert_eq!(blocking["provider"], "claude");
+    assert!(status["content"][0]["text"]
+        .as_str()
+        .unwrap()
+        .contains("request 7"));
+
+    // Completing on behalf of another request is refused
+    let wrong = server
+        .handle_message(&request(
+            9,
+            "tools/call",
+            json!({
+                "name": "webpuppet_intervention_complete",
+                "arguments": {"success": true, "request_id": 8}
+            }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(wrong.error.unwrap().code, -32602);
+
+    *launcher.browser.intervention.lock().unwrap() = None;
+    let done = server
+        .handle_message(&request(
+            10,
+            "tools/call",
+            json!({
+                "name": "webpuppet_intervention_complete",
+                "arguments": {"success": true, "request_id": 7}
+            }),
+        ))
+        .await
+        .unwrap()
+        .result
+        .unwrap();
+    assert!(done["content"][0]["text"]
+        .as_str()
+        .unwrap()
+        .contains("request 7"));
+}
+
+// ============================================================================
+// Shutdown Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_request_buffered_before_exit_is_answered() {
+    let (mut client_in, mut lines) = serve_stub(Arc::new(StubLauncher::default())).await;
+
+    // Both messages arrive in a single write
+    let call = request(
+        2,
+        "tools/call",
+        json!({"name": "webpuppet_check_permission", "arguments": {"operation": "Navigate"}}),
+    );
+    let exit = json!({"jsonrpc": "2.0", "method": "exit"});
+    client_in
+        .write_all(format!("{}\n{}\n", call, exit).as_bytes())
+        .await
+        .unwrap();
+
+    let response: Value =
+        serde_json::from_str(&lines.next_line().await.unwrap().expect("tool response")).unwrap();
+    assert_eq!(response["id"], 2);
+    assert!(response["result"]["content"].is_array());
+
+    // Then the server exits and closes its output
+    let end = tokio::time::timeout(std::time::Duration::from_secs(5), lines.next_line())
+        .await
+        .expect("server should exit");
+    assert!(end.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_shutdown_then_exit_is_a_clean_exit() {
+    let server = Arc::new(McpServer::new());
+    initialize(&server).await;
+
+    let (mut client_in, server_in) = tokio::io::duplex(4096);
+    let (server_out, client_out) = tokio::io::duplex(4096);
+    let serving = {
+        let server = server.clone();
+        tokio::spawn(async move { server.serve(BufReader::new(server_in), server_out).await })
+    };
+    let mut lines = BufReader::new(client_out).lines();
+
+    client_in
+        .write_all(format!("{}\n", request(1, "shutdown", json!({}))).as_bytes())
+        .await
+        .unwrap();
+    let response: Value = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+    assert_eq!(response["id"], 1);
+    assert_eq!(response["result"], json!({}));
+
+    // Still reading, but refusing anything other than exit
+    client_in
+        .write_all(format!("{}\n", request(2, "ping", json!({}))).as_bytes())
+        .await
+        .unwrap();
+    let response: Value = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+    assert_eq!(response["id"], 2);
+    assert_eq!(response["error"]["code"], -32600);
+    assert!(!serving.is_finished());
+
+    let exit = json!({"jsonrpc": "2.0", "method": "exit"});
+    client_in
+        .write_all(format!("{}\n", exit).as_bytes())
+        .await
+        .unwrap();
+
+    let result = tokio::time::timeout(std::time::Duration::from_secs(5), serving)
+        .await
+        .expect("server should stop on exit")
+        .unwrap();
+    assert!(result.is_ok(), "expected clean exit, got {:?}", result);
+    assert!(lines.next_line().await.unwrap().is_none());
+}
+
+// ============================================================================
+// Notification Sink Tests
+// ============================================================================
+
+/// Tool logging to the client through the context's notification sink.
+struct NotifyingTool;
+
+#[async_trait::async_trait]
+impl Tool for NotifyingTool {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: "notifying".into(),
+            description: "Logs a message to the client, then returns".into(),
+            input_schema: json!({"type": "object"}),
+        }
+    }
+
+    async fn execute(
+        &self,
+        _arguments: Value,
+        context: &ToolContext,
+    ) -> webpuppet_mcp::Result<ToolCallResult> {
+        context
+            .notifications
+            .log("info", "notifying", json!({"step": "halfway"}));
+        Ok(ToolCallResult {
+            content: vec![ContentItem::text("done")],
+            is_error: false,
+            structured_content: None,
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_tool_notifications_reach_the_client() {
+    let mut registry = ToolRegistry::with_context(ToolContext::new(PermissionGuard::secure()));
+    registry.register(Arc::new(NotifyingTool));
+    assert!(!registry.context().notifications.is_connected());
+    let (mut client_in, mut lines) = serve_initialized(McpServer::with_registry(registry)).await;
+
+    client_in
+        .write_all(
+            format!(
+                "{}\n",
+                request(
+                    2,
+                    "tools/call",
+                    json!({"name": "notifying", "arguments": {}})
+                )
+            )
+            .as_bytes(),
+        )
+        .await
+        .unwrap();
+
+    let notification: Value =
+        serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+    assert_eq!(notification["method"], "notifications/message");
+    assert!(notification.get("id").is_none());
+    assert_eq!(notification["params"]["level"], "info");
+    assert_eq!(notification["params"]["logger"], "notifying");
+    assert_eq!(notification["params"]["data"]["step"], "halfway");
+
+    let response: Value = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+    assert_eq!(response["id"], 2);
+    assert_eq!(response["result"]["content"][0]["text"], "done");
+}
+
+#[tokio::test]
+async fn test_unconnected_sink_discards_notifications() {
+    let context = ToolContext::new(PermissionGuard::secure());
+    assert!(!context.notifications.is_connected());
+    // Nothing to send to; must not panic or block
+    context
+        .notifications
+        .resource_updated("webpuppet://audit", json!({"decision": {}}));
+
+    let result = NotifyingTool.execute(json!({}), &context).await.unwrap();
+    assert!(!result.is_error);
+}
+
+// ============================================================================
+// Message Framing Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_oversized_message_rejected_and_server_stays_responsive() {
+    let config = ServerConfig {
+        max_message_bytes: 1_024,
+        ..ServerConfig::default()
+    };
+    let (mut client_in, mut lines) =
+        serve_initialized(McpServer::with_config(PermissionGuard::secure(), config)).await;
+
+    let oversized = request(5, "ping", json!({"padding": "x".repeat(100_000)}));
+    client_in
+        .write_all(format!("{}\n{}\n", oversized, request(6, "ping", json!({}))).as_bytes())
+        .await
+        .unwrap();
+
+    let rejected: Value = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+    assert_eq!(rejected["id"], 5);
+    assert_eq!(rejected["error"]["code"], -32600);
+    assert!(rejected["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("1024-byte limit"));
+
+    let pong: Value = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+    assert_eq!(pong["id"], 6);
+    assert!(pong["error"].is_null());
+}
+
+#[tokio::test]
+async fn test_bom_and_crlf_framed_requests_are_parsed() {
+    let server = McpServer::new();
+    let (mut client_in, server_in) = tokio::io::duplex(4096);
+    let (server_out, client_out) = tokio::io::duplex(4096);
+    tokio::spawn(async move { server.serve(BufReader::new(server_in), server_out).await });
+
+    let init = request(
+        1,
+        "initialize",
+        json!({
+            "protocolVersion": "2024-11-05",
+            "capabilities": {},
+            "clientInfo": {"name": "test", "version": "1.0"}
+        }),
+    );
+    client_in
+        .write_all(
+            format!(
+                "\u{feff}{}\r\n  \r\n{} \t\r\n",
+                init,
+                request(2, "ping", json!({}))
+            )
+            .as_bytes(),
+        )
+        .await
+        .unwrap();
+
+    let mut lines = BufReader::new(client_out).lines();
+    let initialized: Value =
+        serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+    assert_eq!(initialized["id"], 1);
+    assert!(initialized["error"].is_null());
+
+    let pong: Value = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+    assert_eq!(pong["id"], 2);
+    assert!(pong["error"].is_null());
+}
+
+// ============================================================================
+// Liveness Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_unanswered_ping_ends_serving() {
+    let config = ServerConfig {
+        ping_interval: std::time::Duration::from_millis(50),
+        ping_timeout: std::time::Duration::from_millis(100),
+        ..ServerConfig::default()
+    };
+    let server = McpServer::with_config(PermissionGuard::secure(), config);
+    let (mut client_in, server_in) = tokio::io::duplex(4096);
+    let (server_out, client_out) = tokio::io::duplex(4096);
+    let serving =
+        tokio::spawn(async move { server.serve(BufReader::new(server_in), server_out).await });
+
+    let init = request(
+        1,
+        "initialize",
+        json!({
+            "protocolVersion": "2024-11-05",
+            "capabilities": {},
+            "clientInfo": {"name": "test", "version": "1.0"}
+        }),
+    );
+    client_in
+        .write_all(format!("{}\n", init).as_bytes())
+        .await
+        .unwrap();
+
+    let mut lines = BufReader::new(client_out).lines();
+    let mut pings = 0;
+    while let Some(line) = lines.next_line().await.unwrap() {
+        let message: Value = serde_json::from_str(&line).unwrap();
+        if message["method"] == "ping" {
+            assert!(message["id"].as_str().unwrap().starts_with("webpuppet-"));
+            pings += 1;
+        }
+    }
+    assert_eq!(pings, 1, "the first unanswered ping ends serving");
+
+    // The client never answered, yet input is still open
+    tokio::time::timeout(std::time::Duration::from_secs(5), serving)
+        .await
+        .expect("server should stop on its own")
+        .unwrap()
+        .unwrap();
+    drop(client_in);
+}
+
+#[tokio::test]
+async fn test_answered_pings_keep_serving() {
+    let config = ServerConfig {
+        ping_interval: std::time::Duration::from_millis(20),
+        ping_timeout: std::time::Duration::from_millis(200),
+        ..ServerConfig::default()
+    };
+    let (mut client_in, mut lines) =
+        serve_initialized(McpServer::with_config(PermissionGuard::secure(), config)).await;
+
+    for _ in 0..3 {
+        let ping: Value = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert_eq!(ping["method"], "ping");
+        let pong = json!({"jsonrpc": "2.0", "id": ping["id"], "result": {}});
+        client_in
+            .write_all(format!("{}\n", pong).as_bytes())
+            .await
+            .unwrap();
+    }
+
+    client_in
+        .write_all(format!("{}\n", request(9, "ping", json!({}))).as_bytes())
+        .await
+        .unwrap();
+    loop {
+        let message: Value =
+            serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        if message["id"] == 9 {
+            assert_eq!(message["result"], json!({}));
+            break;
+        }
+    }
+}
+
+// ============================================================================
+// Content Fallback Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_text_only_client_gets_images_as_resources() {
+    let launcher = Arc::new(StubLauncher::default());
+    let context = ToolContext::new(PermissionGuard::secure()).with_launcher(launcher);
+    let server = McpServer::with_registry(ToolRegistry::with_context(context));
+    server
+        .handle_message(&request(
+            1,
+            "initialize",
+            json!({
+                "protocolVersion": "2024-11-05",
+                "capabilities": {"experimental": {"contentTypes": ["text"]}},
+                "clientInfo": {"name": "text-only", "version": "1.0"}
+            }),
+        ))
+        .await
+        .unwrap();
+
+    let response = server
+        .handle_message(&request(
+            2,
+            "tools/call",
+            json!({"name": "webpuppet_screenshot", "arguments": {"url": "https://claude.ai"}}),
+        ))
+        .await
+        .unwrap();
+    let result: ToolCallResult = serde_json::from_value(response.result.unwrap()).unwrap();
+    assert!(!result.is_error);
+    assert!(!result
+        .content
+        .iter()
+        .any(|item| matches!(item, ContentItem::Image { .. })));
+    let note = result
+        .content
+        .iter()
+        .find_map(|item| match item {
+            ContentItem::Text { text, .. } if text.contains("webpuppet://capture/1") => Some(text),
+            _ => None,
+        })
+        .expect("a note pointing to the image");
+    assert!(note.contains("image/png"));
+
+    let listed = server
+        .handle_message(&request(3, "resources/list", json!({})))
+        .await
+        .unwrap();
+    assert!(listed.result.unwrap()["resources"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|r| r["uri"] == "webpuppet://capture/1"));
+
+    let read = server
+        .handle_message(&request(
+            4,
+            "resources/read",
+            json!({"uri": "webpuppet://capture/1"}),
+        ))
+        .await
+        .unwrap();
+    let contents = &read.result.unwrap()["contents"][0];
+    assert_eq!(contents["mimeType"], "image/png");
+    assert!(contents.get("text").is_none());
+    let png = BASE64.decode(contents["blob"].as_str().unwrap()).unwrap();
+    assert!(png.starts_with(b"\x89PNG"));
+}
+
+#[tokio::test]
+async fn test_resource_templates_list_the_screenshot_capture_template() {
+    let server = McpServer::new();
+    initialize(&server).await;
+
+    let listed = server
+        .handle_message(&request(2, "resources/templates/list", json!({})))
+        .await
+        .unwrap()
+        .result
+        .unwrap();
+    let templates = listed["resourceTemplates"].as_array().unwrap();
+    let capture = templates
+        .iter()
+        .find(|t| t["uriTemplate"] == "webpuppet://capture/{id}")
+        .expect("the capture template");
+    assert!(capture["description"]
+        .as_str()
+        .unwrap()
+        .contains("screenshot"));
+}
+
+// ============================================================================
+// Prewarm Tests
+// ============================================================================
+
+/// Serve a server with `prewarm` set, backed by `launcher`, after
+/// initializing it.
+async fn serve_prewarmed(
+    permissions: PermissionGuard,
+    launcher: Arc<StubLauncher>,
+) -> (
+    tokio::io::DuplexStream,
+    tokio::io::Lines<BufReader<tokio::io::DuplexStream>>,
+) {
+    let config = ServerConfig {
+        prewarm: true,
+        ..ServerConfig::default()
+    };
+    let context = ToolContext::with_config(permissions, config).with_launcher(launcher);
+    serve_initialized(McpServer::with_registry(ToolRegistry::with_context(
+        context,
+    )))
+    .await
+}
+
+async fn browser_status(
+    client_in: &mut tokio::io::DuplexStream,
+    lines: &mut tokio::io::Lines<BufReader<tokio::io::DuplexStream>>,
+    id: u64,
+) -> Value {
+    let call = request(
+        id,
+        "tools/call",
+        json!({"name": "webpuppet_browser_status", "arguments": {}}),
+    );
+    client_in
+        .write_all(format!("{}\n", call).as_bytes())
+        .await
+        .unwrap();
+    let response: Value = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+    response["result"]["structuredContent"].clone()
+}
+
+#[tokio::test]
+async fn test_prewarm_launches_the_browser_before_the_first_call() {
+    let launcher = Arc::new(StubLauncher::default());
+    let (mut client_in, mut lines) =
+        serve_prewarmed(PermissionGuard::secure(), launcher.clone()).await;
+
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+    while launcher.launch_count() == 0 {
+        assert!(
+            std::time::Instant::now() < deadline,
+            "browser never prewarmed"
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    let status = browser_status(&mut client_in, &mut lines, 2).await;
+    assert_eq!(status["active"], true);
+    assert_eq!(status["prewarm"]["state"], "ready");
+
+    // The first navigation reuses the prewarmed browser
+    let navigate = request(
+        3,
+        "tools/call",
+        json!({"name": "webpuppet_navigate", "arguments": {"url": "https://claude.ai"}}),
+    );
+    client_in
+        .write_all(format!("{}\n", navigate).as_bytes())
+        .await
+        .unwrap();
+    let response: Value = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+    assert_eq!(response["result"]["isError"], false);
+    assert_eq!(launcher.launch_count(), 1);
+}
+
+#[tokio::test]
+async fn test_prewarm_is_skipped_when_navigation_is_denied() {
+    let launcher = Arc::new(StubLauncher::default());
+    let policy = PermissionPolicy::builder()
+        .deny_operation(Operation::Navigate)
+        .build();
+    let (mut client_in, mut lines) =
+        serve_prewarmed(PermissionGuard::new(policy), launcher.clone()).await;
+
+    let status = browser_status(&mut client_in, &mut lines, 2).await;
+    assert_eq!(status["active"], false);
+    assert_eq!(status["prewarm"]["state"], "skipped");
+    assert_eq!(launcher.launch_count(), 0);
+}
+
+// ============================================================================
+// Roots Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_download_outside_client_roots_is_rejected() {
+    let root = std::env::temp_dir().join(format!("webpuppet-mcp-roots-{}", std::process::id()));
+    let context = ToolContext::new(PermissionGuard::secure())
+        .with_launcher(Arc::new(StubLauncher::default()));
+    let server = McpServer::with_registry(ToolRegistry::with_context(context));
+
+    let (mut client_in, server_in) = tokio::io::duplex(4096);
+    let (server_out, client_out) = tokio::io::duplex(4096);
+    tokio::spawn(async move { server.serve(BufReader::new(server_in), server_out).await });
+    let mut lines = BufReader::new(client_out).lines();
+
+    let init = request(
+        1,
+        "initialize",
+        json!({
+            "protocolVersion": "2024-11-05",
+            "capabilities": {"roots": {"listChanged": true}},
+            "clientInfo": {"name": "test", "version": "1.0"}
+        }),
+    );
+    let initialized = json!({"jsonrpc": "2.0", "method": "notifications/initialized"});
+    client_in
+        .write_all(format!("{}\n{}\n", init, initialized).as_bytes())
+        .await
+        .unwrap();
+    lines
+        .next_line()
+        .await
+        .unwrap()
+        .expect("initialize response");
+
+    // The server asks for the roots once initialized
+    let roots_request: Value =
+        serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+    assert_eq!(roots_request["method"], "roots/list");
+    let answer = json!({
+        "jsonrpc": "2.0",
+        "id": roots_request["id"],
+        "result": {"roots": [{"uri": url::Url::from_directory_path(&root).unwrap().as_str(), "name": "project"}]}
+    });
+    let outside = request(
+        2,
+        "tools/call",
+        json!({
+            "name": "webpuppet_download",
+            "arguments": {"url": "https://www.kaggle.com/data.csv", "directory": std::env::temp_dir()}
+        }),
+    );
+    client_in
+        .write_all(format!("{}\n{}\n", answer, outside).as_bytes())
+        .await
+        .unwrap();
+
+    let response = lines.next_line().await.unwrap().unwrap();
+    assert!(
+        response.contains("outside the client's roots"),
+        "unexpected response: {}",
+        response
+    );
+    assert!(response.contains("webpuppet-mcp-roots-"));
+}